@@ -1,25 +1,94 @@
-use crate::ast::{BeamScript, Scene, Value};
-use crate::{gpu_renderer, renderer};
+use crate::ast::{Animation, BeamScript, Property, Scene, Timeline, Value};
+use crate::export::ExportOptions;
+use crate::output::StagedOutput;
+use crate::sink::{self, FrameSink, VideoInfo};
+use crate::{color, gpu_renderer, layout, path, renderer};
 use image::RgbaImage;
 use indicatif::{ProgressBar, ProgressStyle};
 use rayon::prelude::*;
-use std::fs;
-use std::process::Command;
+use std::error::Error;
+use std::ops::Range;
+use std::path::Path;
 use std::time::Duration;
 
-const FRAME_RATE: u64 = 60;
+/// Properties that jump between whole values instead of interpolating smoothly.
+const STEPPED_PROPERTIES: &[&str] = &["z_index"];
 
-pub fn animate_script(script: &BeamScript, output_base: &str, gpu: bool) {
+/// Frames rendered at once per thread before they are handed to the output.
+const FRAMES_IN_FLIGHT_PER_THREAD: usize = 2;
+
+pub fn animate_script(
+    script: &BeamScript,
+    output: &Path,
+    gpu: bool,
+    options: &ExportOptions,
+    selection: &Selection,
+) {
     if script.scenes.is_empty() {
-        println!("No scenes to render.");
+        eprintln!("No scenes to render.");
         return;
     }
 
-    let temp_dir = "temp_frames";
-    if fs::metadata(temp_dir).is_ok() {
-        fs::remove_dir_all(temp_dir).expect("Failed to remove old temp directory");
+    if renderer::background_color(&script.camera)[3] < 255 && !options.format.has_alpha() {
+        eprintln!(
+            "Warning: {} cannot keep the transparent background, use png, qoi, apng, webp, webm or prores",
+            format!("{:?}", options.format).to_lowercase()
+        );
+    }
+
+    let to_stdout = output == Path::new("-");
+    match export(script, output, gpu, options, selection) {
+        Ok(()) if to_stdout => eprintln!("✅ Written to standard output"),
+        Ok(()) => eprintln!("✅ Saved to {}", output.display()),
+        Err(e) => eprintln!("Error writing {}: {}", output.display(), e),
+    }
+}
+
+/// Renders the frames of `script` chosen by `selection` to `output`, which
+/// only appears once it is complete, or `-` for standard output.
+pub fn export(
+    script: &BeamScript,
+    output: &Path,
+    gpu: bool,
+    options: &ExportOptions,
+    selection: &Selection,
+) -> Result<(), Box<dyn Error>> {
+    // Standard output is written as frames come, everything else is staged.
+    let staged = if output == Path::new("-") {
+        None
+    } else {
+        Some(StagedOutput::new(output)?)
+    };
+    let sink_path = staged.as_ref().map_or(output, |staged| staged.path());
+    let mut sink = sink::create(options, sink_path)?;
+    render_script(script, gpu, selection, sink.as_mut())?;
+    if let Some(staged) = staged {
+        staged.commit()?;
     }
-    fs::create_dir_all(temp_dir).expect("Failed to create temp directory");
+    Ok(())
+}
+
+/// The part of a script to render.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Selection {
+    /// The only scene to render, by name.
+    pub scene: Option<String>,
+    /// The frames to render, counted from the start of the video, or of the
+    /// scene when one is chosen.
+    pub frames: Option<Range<u64>>,
+}
+
+/// Renders the frames of `script` chosen by `selection` in order into
+/// `sink`, from `begin` to `finish`.
+pub fn render_script(
+    script: &BeamScript,
+    gpu: bool,
+    selection: &Selection,
+    sink: &mut dyn FrameSink,
+) -> Result<(), Box<dyn Error>> {
+    let fps = script.fps();
+    let plan = plan_frames(script, selection)?;
+    let total_frames: u64 = plan.iter().map(|(_, frames)| frames.end - frames.start).sum();
 
     let mut gpu_state = if gpu {
         Some(pollster::block_on(gpu_renderer::GpuRendererState::new()))
@@ -27,21 +96,22 @@ pub fn animate_script(script: &BeamScript, output_base: &str, gpu: bool) {
         None
     };
 
-    let total_frames: u64 = script
-        .scenes
-        .iter()
-        .map(|scene| {
-            let duration = scene
-                .duration
-                .unwrap_or_else(|| Duration::from_secs(2));
-            (duration.as_secs_f64() * FRAME_RATE as f64).ceil() as u64
-        })
-        .sum();
-
-    println!(
+    let (width, height) = if gpu {
+        gpu_renderer::frame_size(&script.camera)
+    } else {
+        renderer::frame_size(&script.camera)
+    };
+    sink.begin(&VideoInfo {
+        width,
+        height,
+        fps,
+        frames: total_frames,
+    })?;
+
+    eprintln!(
         "Rendering a total of {} frames from {} scene(s)...",
         total_frames,
-        script.scenes.len()
+        plan.len()
     );
     let bar = ProgressBar::new(total_frames);
     bar.set_style(
@@ -50,132 +120,207 @@ pub fn animate_script(script: &BeamScript, output_base: &str, gpu: bool) {
             .unwrap(),
     );
 
-    let mut frame_offset = 0;
-    for scene in &script.scenes {
-        let duration = scene
-            .duration
-            .unwrap_or_else(|| Duration::from_secs(2));
-        let num_frames_for_scene = (duration.as_secs_f64() * FRAME_RATE as f64).ceil() as u64;
-
-        if let Some(timeline) = &scene.timeline {
+    for (scene, frames) in plan {
+        let animated = scene.timeline.as_ref().filter(|t| !t.animations.is_empty());
+        let rendered = if let Some(timeline) = animated {
             // Animated scene
-            if gpu {
-                for i in 0..num_frames_for_scene {
-                    let current_time = Duration::from_secs_f64(i as f64 / FRAME_RATE as f64);
-                    let mut frame_scene = scene.clone();
-                    apply_animations(&mut frame_scene, timeline, current_time);
+            if let Some(gpu_state) = gpu_state.as_mut() {
+                frames.clone().try_for_each(|i| {
+                    let current_time = Duration::from_secs_f64(i as f64 / fps);
+                    let frame_scene = scene_at(scene, timeline, current_time);
 
                     let image: RgbaImage = pollster::block_on(gpu_renderer::render_scene_gpu(
-                        gpu_state.as_mut().unwrap(),
-                        &frame_scene.items,
+                        gpu_state,
+                        &frame_scene,
                         &script.camera,
                     ));
-                    let frame_path = format!("{}/frame_{:05}.png", temp_dir, frame_offset + i);
-                    image.save(frame_path).expect("Failed to save frame");
-                }
+                    bar.inc(1);
+                    sink.push_frame(&image)
+                })
             } else {
-                (0..num_frames_for_scene)
-                    .into_par_iter()
-                    .for_each(|i| {
-                        let current_time = Duration::from_secs_f64(i as f64 / FRAME_RATE as f64);
-                        let mut frame_scene = scene.clone();
-                        apply_animations(&mut frame_scene, timeline, current_time);
-
-                        let image: RgbaImage = renderer::render_scene(&frame_scene, &script.camera);
-                        let frame_path = format!("{}/frame_{:05}.png", temp_dir, frame_offset + i);
-                        image.save(frame_path).expect("Failed to save frame");
-                    });
+                // Frames render in parallel a batch at a time, and are handed
+                // on in order once the whole batch is done.
+                let batch = (rayon::current_num_threads() * FRAMES_IN_FLIGHT_PER_THREAD) as u64;
+                frames.clone().step_by(batch as usize).try_for_each(|start| {
+                    let end = (start + batch).min(frames.end);
+                    let images: Vec<RgbaImage> = (start..end)
+                        .into_par_iter()
+                        .map(|i| {
+                            let current_time = Duration::from_secs_f64(i as f64 / fps);
+                            let frame_scene = scene_at(scene, timeline, current_time);
+                            renderer::render_scene(&frame_scene, &script.camera)
+                        })
+                        .collect();
+                    bar.inc(end - start);
+                    images.iter().try_for_each(|image| sink.push_frame(image))
+                })
             }
         } else {
             // Static scene
-            if gpu {
-                let image: RgbaImage = pollster::block_on(gpu_renderer::render_scene_gpu(
-                    gpu_state.as_mut().unwrap(),
-                    &scene.items,
+            let mut frame_scene = scene.clone();
+            layout::resolve_layout(&mut frame_scene.items);
+            let image: RgbaImage = if let Some(gpu_state) = gpu_state.as_mut() {
+                pollster::block_on(gpu_renderer::render_scene_gpu(
+                    gpu_state,
+                    &frame_scene,
                     &script.camera,
-                ));
-                for i in 0..num_frames_for_scene {
-                    let frame_path = format!("{}/frame_{:05}.png", temp_dir, frame_offset + i);
-                    image.save(&frame_path).expect("Failed to save frame");
-                }
+                ))
             } else {
-                let image: RgbaImage = renderer::render_scene(scene, &script.camera);
-                (0..num_frames_for_scene)
-                    .into_par_iter()
-                    .for_each(|i| {
-                        let frame_path = format!("{}/frame_{:05}.png", temp_dir, frame_offset + i);
-                        image.save(&frame_path).expect("Failed to save frame");
-                    });
-            }
-        }
+                renderer::render_scene(&frame_scene, &script.camera)
+            };
+            bar.inc(frames.end - frames.start);
+            frames.clone().try_for_each(|_| sink.push_frame(&image))
+        };
 
-        bar.inc(num_frames_for_scene);
-        frame_offset += num_frames_for_scene;
+        if let Err(e) = rendered {
+            bar.abandon();
+            return Err(e);
+        }
     }
     bar.finish_with_message("All frames rendered");
 
-    println!("🎬 Assembling video with ffmpeg...");
-
-    let output_path = format!("{}.mp4", output_base);
-    let output = Command::new("ffmpeg")
-        .arg("-r")
-        .arg(FRAME_RATE.to_string())
-        .arg("-s")
-        .arg(format!(
-            "{}x{}",
-            script
-                .camera
-                .as_ref()
-                .and_then(|c| c
-                    .properties
-                    .iter()
-                    .find(|p| p.name == "width")
-                    .and_then(|p| match p.value {
-                        Value::Number(n) => Some(n as u32),
-                        _ => None,
-                    }))
-                .unwrap_or(1920),
-            script
-                .camera
-                .as_ref()
-                .and_then(|c| c
-                    .properties
-                    .iter()
-                    .find(|p| p.name == "height")
-                    .and_then(|p| match p.value {
-                        Value::Number(n) => Some(n as u32),
-                        _ => None,
-                    }))
-                .unwrap_or(1080)
-        ))
-        .arg("-i")
-        .arg(format!("{}/frame_%05d.png", temp_dir))
-        .arg("-c:v")
-        .arg("libx264")
-        .arg("-pix_fmt")
-        .arg("yuv420p")
-        .arg("-y") // Overwrite output file if it exists
-        .arg(&output_path)
-        .output()
-        .expect("Failed to execute ffmpeg");
-
-    if !output.status.success() {
-        eprintln!("ffmpeg error: {}", String::from_utf8_lossy(&output.stderr));
-    } else {
-        println!("✅ Video saved to {}", output_path);
+    sink.finish()
+}
+
+// The scenes to render with the frames of each, counted from the start of
+// the scene.
+fn plan_frames<'a>(
+    script: &'a BeamScript,
+    selection: &Selection,
+) -> Result<Vec<(&'a Scene, Range<u64>)>, String> {
+    let fps = script.fps();
+    let scenes: Vec<&Scene> = match &selection.scene {
+        Some(name) => vec![script
+            .scenes
+            .iter()
+            .find(|scene| scene.name == *name)
+            .ok_or_else(|| format!("No scene named '{}'", name))?],
+        None => script.scenes.iter().collect(),
+    };
+    let lengths: Vec<u64> = scenes.iter().map(|scene| scene_frames(scene, fps)).collect();
+    let total: u64 = lengths.iter().sum();
+
+    let wanted = selection.frames.clone().unwrap_or(0..total);
+    if wanted.start >= wanted.end.min(total) {
+        return Err(format!(
+            "No frames to render in {}..{}, out of {} frames",
+            wanted.start, wanted.end, total
+        ));
     }
+    let mut plan = Vec::new();
+    let mut offset = 0;
+    for (scene, length) in scenes.into_iter().zip(lengths) {
+        let start = wanted.start.max(offset) - offset;
+        let end = wanted.end.min(offset + length).saturating_sub(offset);
+        if start < end {
+            plan.push((scene, start..end));
+        }
+        offset += length;
+    }
+    Ok(plan)
+}
 
-    fs::remove_dir_all(temp_dir).expect("Failed to remove temp directory");
+/// Number of frames `scene` is shown for.
+pub fn scene_frames(scene: &Scene, fps: f64) -> u64 {
+    let duration = scene
+        .duration
+        .unwrap_or_else(|| Duration::from_secs(2));
+    frame_count(duration, fps)
 }
 
-fn apply_animations(scene: &mut Scene, timeline: &crate::ast::Timeline, current_time: Duration) {
+/// Number of frames needed to show `duration` at `fps` frames per second,
+/// which is also the first frame shown at or after `duration`.
+pub fn frame_count(duration: Duration, fps: f64) -> u64 {
+    // Round away float noise first, so that a scene lasting `48f` takes 48
+    // frames and not 49.
+    let frames = (duration.as_secs_f64() * fps * 1e6).round() / 1e6;
+    frames.ceil() as u64
+}
+
+/// The frame on screen at `time`, shown at `fps` frames per second.
+pub fn frame_at(time: Duration, fps: f64) -> u64 {
+    let frames = (time.as_secs_f64() * fps * 1e6).round() / 1e6;
+    frames.floor() as u64
+}
+
+/// The scene as it is drawn `time` after it starts.
+pub fn frame_scene(scene: &Scene, time: Duration) -> Scene {
+    match &scene.timeline {
+        Some(timeline) => scene_at(scene, timeline, time),
+        None => {
+            let mut frame_scene = scene.clone();
+            layout::resolve_layout(&mut frame_scene.items);
+            frame_scene
+        }
+    }
+}
+
+// The scene as it is drawn at `current_time`: animated, then laid out.
+//
+// Anchors such as `"box".top_right` in the targets of animations, and in the
+// properties they animate, are first resolved against where the objects are
+// at `current_time`, so that an object moving to a moving anchor catches it.
+fn scene_at(scene: &Scene, timeline: &Timeline, current_time: Duration) -> Scene {
+    let mut frame_scene = scene.clone();
+    let anchored = |a: &Animation| {
+        layout::has_anchors(&a.to)
+            || scene
+                .items
+                .iter()
+                .filter(|o| is_member(&o.name, &a.target_object))
+                .flat_map(|o| &o.properties)
+                .any(|p| p.name == a.property && layout::has_anchors(&p.value))
+    };
+    if !timeline.animations.iter().any(anchored) {
+        apply_animations(&mut frame_scene, timeline, current_time);
+        layout::resolve_layout(&mut frame_scene.items);
+        return frame_scene;
+    }
+
+    let mut current = scene.clone();
+    let unanchored = Timeline {
+        animations: timeline
+            .animations
+            .iter()
+            .filter(|a| !layout::has_anchors(&a.to))
+            .cloned()
+            .collect(),
+        markers: vec![],
+    };
+    apply_animations(&mut current, &unanchored, current_time);
+    layout::resolve_layout(&mut current.items);
+
+    let mut timeline = timeline.clone();
+    for animation in &mut timeline.animations {
+        layout::resolve_anchors(&current.items, &mut animation.to);
+        for object in frame_scene
+            .items
+            .iter_mut()
+            .filter(|o| is_member(&o.name, &animation.target_object))
+        {
+            for property in object
+                .properties
+                .iter_mut()
+                .filter(|p| p.name == animation.property)
+            {
+                layout::resolve_anchors(&current.items, &mut property.value);
+            }
+        }
+    }
+    apply_animations(&mut frame_scene, &timeline, current_time);
+    layout::resolve_layout(&mut frame_scene.items);
+    frame_scene
+}
+
+fn apply_animations(scene: &mut Scene, timeline: &Timeline, current_time: Duration) {
     // Create a list of all unique properties that are animated in this timeline.
-    let mut animated_properties = std::collections::HashMap::new();
+    // Sorting by name applies a group's animations before those of its members.
+    let mut animated_properties = std::collections::BTreeSet::new();
     for anim in &timeline.animations {
-        animated_properties.insert((anim.target_object.clone(), anim.property.clone()), ());
+        animated_properties.insert((anim.target_object.clone(), anim.property.clone()));
     }
 
-    for (object_name, property_name) in animated_properties.keys() {
+    for (object_name, property_name) in &animated_properties {
         // For each unique property, find its state at `current_time`.
         // First, find all animations for this property, sorted by start time.
         let mut relevant_animations: Vec<_> = timeline
@@ -185,60 +330,223 @@ fn apply_animations(scene: &mut Scene, timeline: &crate::ast::Timeline, current_
             .collect();
         relevant_animations.sort_by_key(|a| a.start);
 
-        // Find the initial value from the scene definition to start with.
-        let initial_value = scene
+        // An animation targets the named object, or every object in the group it
+        // names, such as all of the shapes imported from an SVG file.
+        for object in scene
             .items
-            .iter()
-            .find(|o| &o.name == object_name)
-            .and_then(|o| o.properties.iter().find(|p| &p.name == property_name))
-            .map(|p| p.value.clone())
-            .expect("Animated property not found in scene object");
-
-        let mut final_value = initial_value;
-
-        // Chronologically apply animations to find the value at `current_time`.
-        for anim in relevant_animations {
-            if current_time >= anim.start {
-                let start_value = final_value.clone();
-                let end_value = anim.to.clone();
-
-                // Check if the animation is currently active and interpolating.
-                if anim.end.is_some() && current_time < anim.end.unwrap() {
-                    let animation_duration = anim.end.unwrap() - anim.start;
-                    let elapsed = current_time - anim.start;
-
-                    // Avoid division by zero for zero-duration animations.
-                    let mut factor = if animation_duration.as_secs_f64() > 0.0 {
-                        elapsed.as_secs_f64() / animation_duration.as_secs_f64()
-                    } else {
-                        1.0
-                    };
-
-                    if let Some(easing) = &anim.easing {
-                        factor = apply_easing(factor, easing);
+            .iter_mut()
+            .filter(|o| is_member(&o.name, object_name))
+        {
+            // Find the initial value from the scene definition to start with.
+            // Members without the property, such as an unfilled path in an
+            // imported SVG when the group's fill is animated, are left alone;
+            // `check_animations` warns when no member has it.
+            let Some(initial_value) = object
+                .properties
+                .iter()
+                .find(|p| &p.name == property_name)
+                .map(|p| p.value.clone())
+                .or_else(|| default_property_value(property_name))
+            else {
+                continue;
+            };
+
+            // Code blocks diff the two versions of their source themselves, so
+            // they are handed the one being left and how far along it is.
+            if object.r#type == "code" && property_name == "source" {
+                let state = state_at(initial_value, &relevant_animations, current_time);
+                let source = match state {
+                    State::Settled(source) => source,
+                    State::Running { from, to, factor } => {
+                        set_property(&mut object.properties, "previous_source", from);
+                        let progress = Value::Number(factor);
+                        set_property(&mut object.properties, "source_progress", progress);
+                        to
                     }
+                };
+                set_property(&mut object.properties, property_name, source);
+                continue;
+            }
 
-                    final_value = lerp(&start_value, &end_value, factor);
-                    // This is the dominant state, so we're done with this property for this frame.
-                    break;
+            let final_value =
+                value_at(property_name, initial_value, &relevant_animations, current_time);
+            set_property(&mut object.properties, property_name, final_value);
+        }
+    }
+}
+
+/// Warns about animations of a property that their target does not have, or
+/// that no member of the group they target has, such as a misspelled one.
+/// Members of a group without the property are skipped when animating it.
+pub fn check_animations(scene: &Scene) {
+    for problem in missing_properties(scene) {
+        eprintln!("Warning: {}", problem);
+    }
+}
+
+fn missing_properties(scene: &Scene) -> Vec<String> {
+    let Some(timeline) = &scene.timeline else {
+        return Vec::new();
+    };
+    let targets: std::collections::BTreeSet<_> = timeline
+        .animations
+        .iter()
+        .map(|a| (a.target_object.as_str(), a.property.as_str()))
+        .collect();
+    let mut problems = Vec::new();
+    for (target, property) in targets {
+        let members: Vec<_> = scene
+            .items
+            .iter()
+            .filter(|o| is_member(&o.name, target))
+            .collect();
+        let animatable = members.iter().any(|o| {
+            o.properties.iter().any(|p| p.name == property)
+                || default_property_value(property).is_some()
+        });
+        match members.as_slice() {
+            _ if animatable => {}
+            [] => {}
+            [object] if object.name == target => problems.push(format!(
+                "'{}' has no property '{}' to animate",
+                target, property
+            )),
+            _ => problems.push(format!(
+                "No object in '{}' has a property '{}' to animate",
+                target, property
+            )),
+        }
+    }
+    problems
+}
+
+// Sets `name` in `properties`, adding it if it is not there yet.
+fn set_property(properties: &mut Vec<Property>, name: &str, value: Value) {
+    if let Some(property) = properties.iter_mut().find(|p| p.name == name) {
+        property.value = value;
+    } else {
+        properties.push(Property {
+            name: name.to_string(),
+            value,
+        });
+    }
+}
+
+/// Whether an object belongs to the group named by an animation's target: the
+/// object itself, its parts like `"group.part"` or its cells like `"table[1][2]"`.
+/// An index of `[*]` in the target stands for any index, as in `"table[*][2]"`.
+pub fn is_member(name: &str, target: &str) -> bool {
+    let mut rest = name;
+    let mut pattern = target;
+    while let Some(wildcard) = pattern.find("[*]") {
+        let Some(after) = rest.strip_prefix(&pattern[..wildcard]) else {
+            return false;
+        };
+        let Some(index) = after.strip_prefix('[') else {
+            return false;
+        };
+        let Some(close) = index.find(']') else {
+            return false;
+        };
+        rest = &index[close + 1..];
+        pattern = &pattern[wildcard + 3..];
+    }
+    match rest.strip_prefix(pattern) {
+        Some(after) => after.is_empty() || after.starts_with('.') || after.starts_with('['),
+        None => false,
+    }
+}
+
+// Chronologically applies animations to find a property's value at `current_time`.
+fn value_at(
+    property_name: &str,
+    initial_value: Value,
+    animations: &[&Animation],
+    current_time: Duration,
+) -> Value {
+    match state_at(initial_value, animations, current_time) {
+        State::Settled(value) => value,
+        State::Running { from, to, factor } => interpolate(property_name, &from, &to, factor),
+    }
+}
+
+// Where a property stands at some time: at rest, or part way through an
+// animation by an eased factor.
+enum State {
+    Settled(Value),
+    Running { from: Value, to: Value, factor: f64 },
+}
+
+fn state_at(initial_value: Value, animations: &[&Animation], current_time: Duration) -> State {
+    let mut final_value = initial_value;
+
+    for anim in animations {
+        if current_time >= anim.start {
+            let start_value = final_value.clone();
+            let end_value = anim.to.clone();
+
+            // Check if the animation is currently active and interpolating.
+            if anim.end.is_some() && current_time < anim.end.unwrap() {
+                let animation_duration = anim.end.unwrap() - anim.start;
+                let elapsed = current_time - anim.start;
+
+                // Avoid division by zero for zero-duration animations.
+                let mut factor = if animation_duration.as_secs_f64() > 0.0 {
+                    elapsed.as_secs_f64() / animation_duration.as_secs_f64()
                 } else {
-                    // This is either a finished animation or an instant `at X` animation.
-                    // Its end value becomes the new base state for subsequent animations.
-                    final_value = end_value;
+                    1.0
+                };
+
+                if let Some(easing) = &anim.easing {
+                    factor = apply_easing(factor, easing);
                 }
+
+                // This is the dominant state, so we're done with this property for this frame.
+                return State::Running {
+                    from: start_value,
+                    to: end_value,
+                    factor,
+                };
             } else {
-                // This animation (and all subsequent ones) are in the future, so we can stop.
-                break;
+                // This is either a finished animation or an instant `at X` animation.
+                // Its end value becomes the new base state for subsequent animations.
+                final_value = end_value;
             }
+        } else {
+            // This animation (and all subsequent ones) are in the future, so we can stop.
+            break;
         }
+    }
 
-        // Find the property in the scene and update it with the final calculated value.
-        if let Some(object) = scene.items.iter_mut().find(|o| &o.name == object_name) {
-            if let Some(property) = object.properties.iter_mut().find(|p| &p.name == property_name)
-            {
-                property.value = final_value;
-            }
-        }
+    State::Settled(final_value)
+}
+
+// Values used for animated properties that an object leaves unset.
+fn default_property_value(property_name: &str) -> Option<Value> {
+    match property_name {
+        "z_index" | "rotation" => Some(Value::Number(0.0)),
+        "opacity" | "scale" | "typed" | "draw" => Some(Value::Number(1.0)),
+        "highlight" => Some(Value::Tuple(0.0, 0.0)),
+        _ => None,
+    }
+}
+
+// Snap an interpolated value to the nearest whole step.
+fn step(value: &Value) -> Value {
+    match value {
+        Value::Number(n) => Value::Number(n.round()),
+        _ => value.clone(),
+    }
+}
+
+// Interpolates a property value, honouring properties with their own blending rules.
+fn interpolate(property_name: &str, start: &Value, end: &Value, factor: f64) -> Value {
+    match (property_name, start, end) {
+        ("d", Value::String(s), Value::String(e)) => path::interpolate(s, e, factor)
+            .map(Value::String)
+            .unwrap_or_else(|| end.clone()),
+        _ if STEPPED_PROPERTIES.contains(&property_name) => step(&lerp(start, end, factor)),
+        _ => lerp(start, end, factor),
     }
 }
 
@@ -250,13 +558,29 @@ fn lerp(start: &Value, end: &Value, factor: f64) -> Value {
             Value::Tuple(sx + (ex - sx) * factor, sy + (ey - sy) * factor)
         }
         (Value::Color(s_hex), Value::Color(e_hex)) => {
-            let s_rgb = hex_to_rgb(s_hex);
-            let e_rgb = hex_to_rgb(e_hex);
-            let r = s_rgb[0] as f64 + (e_rgb[0] as f64 - s_rgb[0] as f64) * factor;
-            let g = s_rgb[1] as f64 + (e_rgb[1] as f64 - s_rgb[1] as f64) * factor;
-            let b = s_rgb[2] as f64 + (e_rgb[2] as f64 - s_rgb[2] as f64) * factor;
-            Value::Color(format!("#{:02x}{:02x}{:02x}", r as u8, g as u8, b as u8))
+            let (Some(mut s_rgba), Some(mut e_rgba)) = (color::parse(s_hex), color::parse(e_hex))
+            else {
+                return end.clone();
+            };
+            // A fully transparent end has no color of its own, so fade the
+            // other end's color in or out rather than passing through black.
+            if s_rgba[3] == 0 {
+                s_rgba = [e_rgba[0], e_rgba[1], e_rgba[2], 0];
+            } else if e_rgba[3] == 0 {
+                e_rgba = [s_rgba[0], s_rgba[1], s_rgba[2], 0];
+            }
+            let mut rgba = [0; 4];
+            for (c, (s, e)) in rgba.iter_mut().zip(s_rgba.iter().zip(e_rgba)) {
+                *c = (*s as f64 + (e as f64 - *s as f64) * factor) as u8;
+            }
+            Value::Color(color::format(rgba))
         }
+        (Value::List(s), Value::List(e)) if s.len() == e.len() => Value::List(
+            s.iter()
+                .zip(e)
+                .map(|(s, e)| lerp(s, e, factor))
+                .collect(),
+        ),
         _ => end.clone(), // No interpolation for mismatched or unsupported types
     }
 }
@@ -276,22 +600,10 @@ fn apply_easing(t: f64, easing_type: &str) -> f64 {
     }
 }
 
-fn hex_to_rgb(hex: &str) -> [u8; 3] {
-    let hex = hex.trim_start_matches('#');
-    if hex.len() == 6 {
-        let r = u8::from_str_radix(&hex[0..2], 16).unwrap_or(0);
-        let g = u8::from_str_radix(&hex[2..4], 16).unwrap_or(0);
-        let b = u8::from_str_radix(&hex[4..6], 16).unwrap_or(0);
-        [r, g, b]
-    } else {
-        [0, 0, 0]
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::ast::{Animation, Object, Property, Timeline};
+    use crate::ast::{Object, Timeline};
 
     #[test]
     fn test_ease_in_animation() {
@@ -307,6 +619,7 @@ mod tests {
             }],
             timeline: None,
             duration: Some(Duration::from_secs(1)),
+            layers: vec![],
         };
 
         let timeline = Timeline {
@@ -318,6 +631,7 @@ mod tests {
                 to: Value::Tuple(100.0, 0.0),
                 easing: Some("ease_in".to_string()),
             }],
+            markers: vec![],
         };
 
         let current_time = Duration::from_millis(500); // 0.5s
@@ -348,6 +662,7 @@ mod tests {
             }],
             timeline: None,
             duration: Some(Duration::from_secs(1)),
+            layers: vec![],
         };
 
         let timeline = Timeline {
@@ -359,6 +674,7 @@ mod tests {
                 to: Value::Tuple(100.0, 0.0),
                 easing: Some("ease_out".to_string()),
             }],
+            markers: vec![],
         };
 
         let current_time = Duration::from_millis(500); // 0.5s
@@ -389,6 +705,7 @@ mod tests {
             }],
             timeline: None,
             duration: Some(Duration::from_secs(1)),
+            layers: vec![],
         };
 
         let timeline = Timeline {
@@ -400,6 +717,7 @@ mod tests {
                 to: Value::Tuple(100.0, 0.0),
                 easing: Some("ease_in_out".to_string()),
             }],
+            markers: vec![],
         };
 
         let current_time = Duration::from_millis(500); // 0.5s
@@ -440,6 +758,26 @@ mod tests {
         assert_eq!(result, Value::Color("#7f7f7f".to_string()));
     }
 
+    #[test]
+    fn test_lerp_list() {
+        let start = Value::List(vec![Value::Tuple(0.0, 0.0), Value::Number(10.0)]);
+        let end = Value::List(vec![Value::Tuple(10.0, 20.0), Value::Number(20.0)]);
+        let result = lerp(&start, &end, 0.5);
+        assert_eq!(
+            result,
+            Value::List(vec![Value::Tuple(5.0, 10.0), Value::Number(15.0)])
+        );
+    }
+
+    #[test]
+    fn test_interpolate_path_data() {
+        // Lines are drawn as cubic curves with control points at thirds.
+        let start = Value::String("M0 0 L30 0".to_string());
+        let end = Value::String("M0 30 L30 60".to_string());
+        let result = interpolate("d", &start, &end, 0.5);
+        assert_eq!(result, Value::String("M 0 15 C 10 20 20 25 30 30".to_string()));
+    }
+
     #[test]
     fn test_lerp_unsupported_types() {
         let start = Value::String("start".to_string());
@@ -449,13 +787,14 @@ mod tests {
     }
 
     #[test]
-    fn test_hex_to_rgb() {
-        assert_eq!(hex_to_rgb("#FF0000"), [255, 0, 0]);
-        assert_eq!(hex_to_rgb("#00FF00"), [0, 255, 0]);
-        assert_eq!(hex_to_rgb("#0000FF"), [0, 0, 255]);
-        assert_eq!(hex_to_rgb("#FFFFFF"), [255, 255, 255]);
-        assert_eq!(hex_to_rgb("#000000"), [0, 0, 0]);
-        assert_eq!(hex_to_rgb("invalid"), [0, 0, 0]);
+    fn test_lerp_fades_to_transparent() {
+        let red = Value::Color("#FF0000".to_string());
+        let transparent = Value::Color("transparent".to_string());
+        assert_eq!(lerp(&red, &transparent, 0.5), Value::Color("#ff00007f".to_string()));
+        assert_eq!(lerp(&transparent, &red, 0.25), Value::Color("#ff00003f".to_string()));
+        assert_eq!(lerp(&red, &transparent, 1.0), Value::Color("#ff000000".to_string()));
+        let invalid = Value::Color("invalid".to_string());
+        assert_eq!(lerp(&red, &invalid, 0.5), invalid);
     }
 
     #[test]
@@ -500,6 +839,7 @@ mod tests {
             }],
             timeline: None,
             duration: Some(Duration::from_secs(3)),
+            layers: vec![],
         };
 
         let timeline = Timeline {
@@ -521,6 +861,7 @@ mod tests {
                     easing: None,
                 },
             ],
+            markers: vec![],
         };
 
         let current_time = Duration::from_millis(1500);
@@ -551,6 +892,7 @@ mod tests {
             }],
             timeline: None,
             duration: Some(Duration::from_secs(2)),
+            layers: vec![],
         };
 
         let timeline = Timeline {
@@ -562,6 +904,7 @@ mod tests {
                 to: Value::Number(50.0),
                 easing: None,
             }],
+            markers: vec![],
         };
 
         let current_time = Duration::from_millis(1500);
@@ -592,6 +935,7 @@ mod tests {
             }],
             timeline: None,
             duration: Some(Duration::from_secs(2)),
+            layers: vec![],
         };
 
         let timeline = Timeline {
@@ -603,6 +947,7 @@ mod tests {
                 to: Value::Number(200.0),
                 easing: None,
             }],
+            markers: vec![],
         };
 
         let current_time = Duration::from_millis(500);
@@ -618,4 +963,340 @@ mod tests {
 
         assert_eq!(final_size, Value::Number(100.0));
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_z_index_animation_is_stepped() {
+        let mut scene = Scene {
+            name: "TestScene".to_string(),
+            items: vec![Object {
+                r#type: "square".to_string(),
+                name: "test_square".to_string(),
+                properties: vec![],
+            }],
+            timeline: None,
+            duration: Some(Duration::from_secs(1)),
+            layers: vec![],
+        };
+
+        let timeline = Timeline {
+            animations: vec![Animation {
+                start: Duration::from_secs(0),
+                end: Some(Duration::from_secs(1)),
+                target_object: "test_square".to_string(),
+                property: "z_index".to_string(),
+                to: Value::Number(3.0),
+                easing: None,
+            }],
+            markers: vec![],
+        };
+
+        apply_animations(&mut scene, &timeline, Duration::from_millis(400));
+
+        assert_eq!(scene.items[0].z_index(), 1.0);
+    }
+
+    #[test]
+    fn test_missing_properties_are_reported() {
+        let object = |name: &str, property: &str| Object {
+            r#type: "path".to_string(),
+            name: name.to_string(),
+            properties: vec![Property {
+                name: property.to_string(),
+                value: Value::Color("#FF0000".to_string()),
+            }],
+        };
+        let animation = |target: &str, property: &str| Animation {
+            start: Duration::ZERO,
+            end: None,
+            target_object: target.to_string(),
+            property: property.to_string(),
+            to: Value::Color("#0000FF".to_string()),
+            easing: None,
+        };
+        let scene = Scene {
+            name: "TestScene".to_string(),
+            items: vec![
+                object("box", "fill"),
+                object("diagram.rect", "fill"),
+                object("diagram.outline", "border_color"),
+            ],
+            timeline: Some(Timeline {
+                animations: vec![
+                    animation("box", "fill"),
+                    animation("box", "opcity"),
+                    animation("diagram", "fill"),
+                    animation("diagram", "stroke"),
+                    animation("box", "opacity"),
+                ],
+                markers: vec![],
+            }),
+            duration: None,
+            layers: vec![],
+        };
+        assert_eq!(
+            missing_properties(&scene),
+            [
+                "'box' has no property 'opcity' to animate",
+                "No object in 'diagram' has a property 'stroke' to animate",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_group_animation_skips_members_without_the_property() {
+        let mut scene = Scene {
+            name: "TestScene".to_string(),
+            items: vec![
+                Object {
+                    r#type: "path".to_string(),
+                    name: "diagram.rect".to_string(),
+                    properties: vec![Property {
+                        name: "fill".to_string(),
+                        value: Value::Color("#FF0000".to_string()),
+                    }],
+                },
+                // An SVG path with `fill="none"` is imported without a fill.
+                Object {
+                    r#type: "path".to_string(),
+                    name: "diagram.outline".to_string(),
+                    properties: vec![Property {
+                        name: "border_color".to_string(),
+                        value: Value::Color("#000000".to_string()),
+                    }],
+                },
+            ],
+            timeline: None,
+            duration: Some(Duration::from_secs(1)),
+            layers: vec![],
+        };
+        let timeline = Timeline {
+            animations: vec![Animation {
+                start: Duration::from_secs(0),
+                end: None,
+                target_object: "diagram".to_string(),
+                property: "fill".to_string(),
+                to: Value::Color("#0000FF".to_string()),
+                easing: None,
+            }],
+            markers: vec![],
+        };
+
+        apply_animations(&mut scene, &timeline, Duration::from_millis(500));
+
+        assert_eq!(scene.items[0].properties[0].value, Value::Color("#0000FF".to_string()));
+        assert!(scene.items[1].properties.iter().all(|p| p.name != "fill"));
+    }
+
+    #[test]
+    fn test_code_source_animation_keeps_the_previous_source() {
+        let code = |source: &str| Scene {
+            name: "TestScene".to_string(),
+            items: vec![Object {
+                r#type: "code".to_string(),
+                name: "snippet".to_string(),
+                properties: vec![Property {
+                    name: "source".to_string(),
+                    value: Value::String(source.to_string()),
+                }],
+            }],
+            timeline: None,
+            duration: Some(Duration::from_secs(2)),
+            layers: vec![],
+        };
+        let timeline = Timeline {
+            animations: vec![Animation {
+                start: Duration::from_secs(0),
+                end: Some(Duration::from_secs(1)),
+                target_object: "snippet".to_string(),
+                property: "source".to_string(),
+                to: Value::String("b".to_string()),
+                easing: None,
+            }],
+            markers: vec![],
+        };
+        let value = |scene: &Scene, name: &str| {
+            let properties = &scene.items[0].properties;
+            properties.iter().find(|p| p.name == name).map(|p| p.value.clone())
+        };
+
+        let mut running = code("a");
+        apply_animations(&mut running, &timeline, Duration::from_millis(250));
+        assert_eq!(value(&running, "source"), Some(Value::String("b".to_string())));
+        assert_eq!(value(&running, "previous_source"), Some(Value::String("a".to_string())));
+        assert_eq!(value(&running, "source_progress"), Some(Value::Number(0.25)));
+
+        let mut finished = code("a");
+        apply_animations(&mut finished, &timeline, Duration::from_millis(1500));
+        assert_eq!(value(&finished, "source"), Some(Value::String("b".to_string())));
+        assert_eq!(value(&finished, "previous_source"), None);
+    }
+
+    #[test]
+    fn test_group_animation_applies_to_members() {
+        let member = |name: &str| Object {
+            r#type: "path".to_string(),
+            name: name.to_string(),
+            properties: vec![Property {
+                name: "position".to_string(),
+                value: Value::Tuple(0.0, 0.0),
+            }],
+        };
+        let mut scene = Scene {
+            name: "TestScene".to_string(),
+            items: vec![member("diagram.a"), member("diagram.b"), member("diagrams")],
+            timeline: None,
+            duration: Some(Duration::from_secs(1)),
+            layers: vec![],
+        };
+
+        let timeline = Timeline {
+            animations: vec![
+                Animation {
+                    start: Duration::from_secs(0),
+                    end: None,
+                    target_object: "diagram".to_string(),
+                    property: "position".to_string(),
+                    to: Value::Tuple(10.0, 10.0),
+                    easing: None,
+                },
+                Animation {
+                    start: Duration::from_secs(0),
+                    end: None,
+                    target_object: "diagram.b".to_string(),
+                    property: "opacity".to_string(),
+                    to: Value::Number(0.5),
+                    easing: None,
+                },
+            ],
+            markers: vec![],
+        };
+
+        apply_animations(&mut scene, &timeline, Duration::from_millis(500));
+
+        let value = |object: &Object, name: &str| {
+            object.properties.iter().find(|p| p.name == name).map(|p| p.value.clone())
+        };
+        assert_eq!(value(&scene.items[0], "position"), Some(Value::Tuple(10.0, 10.0)));
+        assert_eq!(value(&scene.items[1], "position"), Some(Value::Tuple(10.0, 10.0)));
+        assert_eq!(value(&scene.items[1], "opacity"), Some(Value::Number(0.5)));
+        assert_eq!(value(&scene.items[2], "position"), Some(Value::Tuple(0.0, 0.0)));
+    }
+
+    #[test]
+    fn test_animation_to_moving_anchor() {
+        let object = |r#type: &str, name: &str, position: (f64, f64)| Object {
+            r#type: r#type.to_string(),
+            name: name.to_string(),
+            properties: vec![Property {
+                name: "position".to_string(),
+                value: Value::Tuple(position.0, position.1),
+            }],
+        };
+        let scene = Scene {
+            name: "TestScene".to_string(),
+            items: vec![
+                object("circle", "dot", (0.0, 0.0)),
+                object("square", "box", (100.0, 0.0)),
+            ],
+            timeline: None,
+            duration: Some(Duration::from_secs(2)),
+            layers: vec![],
+        };
+        let animation = |target: &str, to: Value| Animation {
+            start: Duration::from_secs(0),
+            end: Some(Duration::from_secs(2)),
+            target_object: target.to_string(),
+            property: "position".to_string(),
+            to,
+            easing: None,
+        };
+        let timeline = Timeline {
+            animations: vec![
+                animation("box", Value::Tuple(300.0, 0.0)),
+                animation(
+                    "dot",
+                    Value::Reference(crate::ast::Reference::Anchor {
+                        object: "box".to_string(),
+                        anchor: "top".to_string(),
+                    }),
+                ),
+            ],
+            markers: vec![],
+        };
+
+        // Halfway to the top of the box, which has moved to (200, 0) by now.
+        let frame = scene_at(&scene, &timeline, Duration::from_secs(1));
+        let dot = frame.items[0].properties[0].value.clone();
+        assert_eq!(dot, Value::Tuple(100.0, -25.0));
+    }
+
+    #[test]
+    fn test_frame_count() {
+        assert_eq!(frame_count(Duration::from_secs(2), 60.0), 120);
+        assert_eq!(frame_count(Duration::from_millis(2500), 24.0), 60);
+        assert_eq!(frame_count(Duration::from_secs(1), 29.97), 30);
+        assert_eq!(frame_count(Duration::from_secs_f64(48.0 / 23.976), 23.976), 48);
+    }
+
+    #[test]
+    fn test_frame_at() {
+        assert_eq!(frame_at(Duration::from_millis(2500), 24.0), 60);
+        assert_eq!(frame_at(Duration::from_millis(2510), 24.0), 60);
+        assert_eq!(frame_at(Duration::from_secs_f64(48.0 / 23.976), 23.976), 48);
+    }
+
+    #[test]
+    fn test_plan_frames() {
+        let script = crate::parser::parse_str(
+            r#"
+            camera { fps: 10, }
+            scene "A" { duration: 1s }
+            scene "B" { duration: 2s }
+            "#,
+            None,
+        )
+        .unwrap();
+        let plan = |scene: Option<&str>, frames: Option<Range<u64>>| {
+            let selection = Selection {
+                scene: scene.map(String::from),
+                frames,
+            };
+            plan_frames(&script, &selection).map(|plan| {
+                plan.into_iter()
+                    .map(|(scene, frames)| (scene.name.clone(), frames))
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        assert_eq!(
+            plan(None, None).unwrap(),
+            [("A".to_string(), 0..10), ("B".to_string(), 0..20)]
+        );
+        // A range across both scenes, counted from the start of each.
+        assert_eq!(
+            plan(None, Some(5..15)).unwrap(),
+            [("A".to_string(), 5..10), ("B".to_string(), 0..5)]
+        );
+        assert_eq!(plan(None, Some(12..13)).unwrap(), [("B".to_string(), 2..3)]);
+        // With a scene, frames count from its start and stop at its end.
+        assert_eq!(
+            plan(Some("B"), Some(15..u64::MAX)).unwrap(),
+            [("B".to_string(), 15..20)]
+        );
+        assert!(plan(Some("C"), None).is_err());
+        assert!(plan(None, Some(30..31)).is_err());
+    }
+
+    #[test]
+    fn test_group_membership() {
+        assert!(is_member("diagram", "diagram"));
+        assert!(is_member("diagram.a", "diagram"));
+        assert!(!is_member("diagrams", "diagram"));
+        assert!(is_member("t[2][3]", "t"));
+        assert!(is_member("t[2][3]", "t[2]"));
+        assert!(!is_member("t[12][3]", "t[1]"));
+        assert!(is_member("t[2][3]", "t[*][3]"));
+        assert!(!is_member("t[2][4]", "t[*][3]"));
+        assert!(is_member("t[2][3]", "t[*]"));
+    }
+}
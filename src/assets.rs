@@ -0,0 +1,229 @@
+//! Loading and caching of external files referenced by scripts.
+
+use crate::ast::{BeamScript, Object, Value};
+use image::imageops::{self, FilterType};
+use image::RgbaImage;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+static BASE_DIR: OnceLock<PathBuf> = OnceLock::new();
+static IMAGE_CACHE: OnceLock<Mutex<HashMap<PathBuf, Option<Arc<RgbaImage>>>>> = OnceLock::new();
+static SCALED_CACHE: OnceLock<Mutex<HashMap<ScaledKey, Arc<RgbaImage>>>> = OnceLock::new();
+
+/// How many scaled images are kept. An image whose size is animated is drawn
+/// at a new size on every frame, so older sizes are let go.
+const MAX_SCALED_IMAGES: usize = 64;
+
+/// A source image, the region of it drawn, the size it is drawn at and the
+/// name of the filter it is resized with.
+type ScaledKey = (PathBuf, (u32, u32, u32, u32), (u32, u32), String);
+
+/// Sets the directory that relative asset paths are resolved against,
+/// normally the directory containing the script.
+pub fn set_base_dir(dir: &Path) {
+    let _ = BASE_DIR.set(dir.to_path_buf());
+}
+
+pub fn resolve(src: &str) -> PathBuf {
+    let path = Path::new(src);
+    match BASE_DIR.get() {
+        Some(base) if path.is_relative() => base.join(path),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// The files that `items` name in `src` properties, such as images.
+pub fn sources(items: &[Object]) -> BTreeSet<PathBuf> {
+    items
+        .iter()
+        .flat_map(|object| &object.properties)
+        .filter_map(|property| match &property.value {
+            Value::String(src) if property.name == "src" => Some(resolve(src)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The files `script` was made from, to load it again when they change.
+pub fn dependencies(script: &BeamScript) -> BTreeSet<PathBuf> {
+    script.sources.clone()
+}
+
+/// Forgets loaded images, so that files changed since are read again.
+pub fn clear_cache() {
+    if let Some(cache) = IMAGE_CACHE.get() {
+        cache.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+    if let Some(cache) = SCALED_CACHE.get() {
+        cache.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+}
+
+/// Loads a PNG or JPEG file, decoding each file at most once per run.
+///
+/// Files that fail to load are reported once and then skipped.
+pub fn load_image(src: &str) -> Option<Arc<RgbaImage>> {
+    let path = resolve(src);
+    let mut cache = IMAGE_CACHE
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap();
+    cache
+        .entry(path)
+        .or_insert_with_key(|path| match image::open(path) {
+            Ok(image) => Some(Arc::new(image.to_rgba8())),
+            Err(e) => {
+                eprintln!("Warning: Failed to load image '{}': {}", path.display(), e);
+                None
+            }
+        })
+        .clone()
+}
+
+/// The `crop` of an image cropped and resized to `size` with the `nearest`,
+/// `lanczos` or default bilinear filter, made once for each size it is
+/// drawn at rather than on every frame.
+pub fn scaled_image(
+    src: &str,
+    crop: (u32, u32, u32, u32),
+    size: (u32, u32),
+    filter: &str,
+) -> Option<Arc<RgbaImage>> {
+    let source = load_image(src)?;
+    let key = (resolve(src), crop, size, filter.to_string());
+    let cache = SCALED_CACHE.get_or_init(Default::default);
+    if let Some(scaled) = cache.lock().unwrap_or_else(|e| e.into_inner()).get(&key) {
+        return Some(scaled.clone());
+    }
+
+    // Resize without holding the lock, so that other frames can be drawn meanwhile.
+    let filter = match filter {
+        "nearest" => FilterType::Nearest,
+        "lanczos" => FilterType::Lanczos3,
+        _ => FilterType::Triangle,
+    };
+    let (x, y, width, height) = crop;
+    let cropped = imageops::crop_imm(source.as_ref(), x, y, width, height).to_image();
+    let scaled = Arc::new(imageops::resize(&cropped, size.0, size.1, filter));
+    let mut cache = cache.lock().unwrap_or_else(|e| e.into_inner());
+    if cache.len() >= MAX_SCALED_IMAGES {
+        cache.clear();
+    }
+    cache.insert(key, scaled.clone());
+    Some(scaled)
+}
+
+/// The region of a source image to draw and the size to draw it at.
+#[derive(Debug, PartialEq)]
+pub struct Placement {
+    /// `(x, y, width, height)` in source pixels.
+    pub crop: (u32, u32, u32, u32),
+    pub size: (f64, f64),
+}
+
+/// The box an image is fitted into, from its optional `width` and `height`.
+///
+/// A missing dimension is derived from the other one to keep the source
+/// aspect ratio; with neither set the source size is used.
+pub fn frame_size(source: (u32, u32), width: Option<f64>, height: Option<f64>) -> (f64, f64) {
+    let (sw, sh) = (source.0 as f64, source.1 as f64);
+    match (width, height) {
+        (Some(w), Some(h)) => (w, h),
+        (Some(w), None) => (w, w * sh / sw),
+        (None, Some(h)) => (h * sw / sh, h),
+        (None, None) => (sw, sh),
+    }
+}
+
+/// Fits a source image into a frame using `contain`, `cover` or `fill`.
+pub fn fit_image(source: (u32, u32), frame: (f64, f64), fit: &str) -> Placement {
+    let (sw, sh) = (source.0 as f64, source.1 as f64);
+    let full = (0, 0, source.0, source.1);
+    match fit {
+        "fill" => Placement {
+            crop: full,
+            size: frame,
+        },
+        "cover" => {
+            let scale = (frame.0 / sw).max(frame.1 / sh);
+            let crop_w = (frame.0 / scale).round().clamp(1.0, sw) as u32;
+            let crop_h = (frame.1 / scale).round().clamp(1.0, sh) as u32;
+            Placement {
+                crop: ((source.0 - crop_w) / 2, (source.1 - crop_h) / 2, crop_w, crop_h),
+                size: frame,
+            }
+        }
+        _ => {
+            let scale = (frame.0 / sw).min(frame.1 / sh);
+            Placement {
+                crop: full,
+                size: (sw * scale, sh * scale),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_size_keeps_aspect_ratio() {
+        assert_eq!(frame_size((200, 100), Some(100.0), None), (100.0, 50.0));
+        assert_eq!(frame_size((200, 100), None, Some(25.0)), (50.0, 25.0));
+        assert_eq!(frame_size((200, 100), None, None), (200.0, 100.0));
+        assert_eq!(frame_size((200, 100), Some(10.0), Some(10.0)), (10.0, 10.0));
+    }
+
+    #[test]
+    fn test_fit_contain() {
+        let placement = fit_image((200, 100), (100.0, 100.0), "contain");
+        assert_eq!(placement.crop, (0, 0, 200, 100));
+        assert_eq!(placement.size, (100.0, 50.0));
+    }
+
+    #[test]
+    fn test_fit_cover_crops_center() {
+        let placement = fit_image((200, 100), (100.0, 100.0), "cover");
+        assert_eq!(placement.crop, (50, 0, 100, 100));
+        assert_eq!(placement.size, (100.0, 100.0));
+    }
+
+    #[test]
+    fn test_fit_fill_stretches() {
+        let placement = fit_image((200, 100), (100.0, 100.0), "fill");
+        assert_eq!(placement.crop, (0, 0, 200, 100));
+        assert_eq!(placement.size, (100.0, 100.0));
+    }
+
+    #[test]
+    fn test_load_missing_image() {
+        assert!(load_image("does/not/exist.png").is_none());
+    }
+
+    #[test]
+    fn test_scaled_image_is_cropped_and_resized() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("halves.png");
+        // Red on the left half and blue on the right.
+        RgbaImage::from_fn(20, 10, |x, _| {
+            if x < 10 {
+                image::Rgba([255, 0, 0, 255])
+            } else {
+                image::Rgba([0, 0, 255, 255])
+            }
+        })
+        .save(&path)
+        .unwrap();
+        let src = path.to_str().unwrap();
+
+        let right = scaled_image(src, (10, 0, 10, 10), (4, 4), "nearest").unwrap();
+        assert_eq!(right.dimensions(), (4, 4));
+        assert!(right.pixels().all(|p| p.0 == [0, 0, 255, 255]));
+        let whole = scaled_image(src, (0, 0, 20, 10), (8, 4), "nearest").unwrap();
+        assert_eq!(whole.dimensions(), (8, 4));
+        assert_eq!(whole.get_pixel(0, 0).0, [255, 0, 0, 255]);
+        assert!(scaled_image("does/not/exist.png", (0, 0, 1, 1), (1, 1), "nearest").is_none());
+    }
+}
@@ -1,9 +1,14 @@
+use std::collections::BTreeSet;
+use std::path::PathBuf;
 use std::time::Duration;
 
 #[derive(Debug, PartialEq, Default, Clone)]
 pub struct BeamScript {
     pub camera: Option<Camera>,
     pub scenes: Vec<Scene>,
+    /// The files the script names in `src`. Some, such as SVG files and chart
+    /// data, are read while parsing and leave no trace in the scenes.
+    pub sources: BTreeSet<PathBuf>,
 }
 
 #[derive(Debug, PartialEq, Clone, Default)]
@@ -11,12 +16,38 @@ pub struct Camera {
     pub properties: Vec<Property>,
 }
 
+/// Frame rate used when neither the camera nor the command line sets one.
+pub const DEFAULT_FPS: f64 = 60.0;
+
+impl BeamScript {
+    /// Frames per second to render at, from the camera's `fps`.
+    pub fn fps(&self) -> f64 {
+        self.camera.as_ref().map_or(DEFAULT_FPS, Camera::fps)
+    }
+}
+
+impl Camera {
+    /// The camera's `fps`, which may be fractional like `29.97`, or
+    /// [`DEFAULT_FPS`] if it is unset or not a positive number.
+    pub fn fps(&self) -> f64 {
+        self.properties
+            .iter()
+            .find(|p| p.name == "fps")
+            .and_then(|p| match p.value {
+                Value::Number(n) if n > 0.0 && n.is_finite() => Some(n),
+                _ => None,
+            })
+            .unwrap_or(DEFAULT_FPS)
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct Scene {
     pub name: String,
     pub items: Vec<Object>,
     pub timeline: Option<Timeline>,
     pub duration: Option<Duration>,
+    pub layers: Vec<String>,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -38,11 +69,126 @@ pub enum Value {
     Number(f64),
     Color(String),
     Tuple(f64, f64),
+    List(Vec<Value>),
+    Reference(Reference),
+}
+
+/// A point given in terms of another object, resolved once the scene is known.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Reference {
+    /// `axes "name".c2p(x, y)`: the point at data coordinates `(x, y)` on a set of axes.
+    AxesPoint { axes: String, x: f64, y: f64 },
+    /// `below("name", gap)` and its kin: a place next to another object, or
+    /// an edge lined up with it, resolved anew for every frame.
+    Layout {
+        relation: String,
+        target: String,
+        gap: Option<f64>,
+    },
+    /// `"name".anchor`: a point of another object, such as `"box".top_right`,
+    /// resolved anew for every frame.
+    Anchor { object: String, anchor: String },
+}
+
+/// Layer order used when a scene does not declare its own `layers`.
+pub const DEFAULT_LAYERS: [&str; 3] = ["background", "default", "foreground"];
+
+impl Scene {
+    /// Returns the scene's objects in the order they should be drawn.
+    ///
+    /// Objects are sorted by layer first and `z_index` second. The sort is
+    /// stable, so objects that tie keep their declaration order.
+    pub fn draw_order(&self) -> Vec<&Object> {
+        let mut ordered: Vec<&Object> = self.items.iter().collect();
+        ordered.sort_by(|a, b| {
+            let a_key = (self.layer_rank(a), a.z_index());
+            let b_key = (self.layer_rank(b), b.z_index());
+            a_key.partial_cmp(&b_key).unwrap_or(std::cmp::Ordering::Equal)
+        });
+        ordered
+    }
+
+    fn layer_rank(&self, object: &Object) -> usize {
+        let layer = object.layer();
+        if self.layers.is_empty() {
+            DEFAULT_LAYERS
+                .iter()
+                .position(|l| *l == layer)
+                .unwrap_or(1)
+        } else {
+            self.layers
+                .iter()
+                .position(|l| l == layer)
+                .or_else(|| self.layers.iter().position(|l| l == "default"))
+                .unwrap_or(self.layers.len())
+        }
+    }
+}
+
+const SHARED_PROPERTIES: &[&str] = &["position", "rotation", "opacity", "layer", "z_index"];
+
+impl Object {
+    /// The name of the layer this object belongs to, `"default"` if unset.
+    pub fn layer(&self) -> &str {
+        self.properties
+            .iter()
+            .find(|p| p.name == "layer")
+            .and_then(|p| match &p.value {
+                Value::String(s) => Some(s.as_str()),
+                _ => None,
+            })
+            .unwrap_or("default")
+    }
+
+    /// The object's position within its layer, `0` if unset.
+    pub fn z_index(&self) -> f64 {
+        self.properties
+            .iter()
+            .find(|p| p.name == "z_index")
+            .and_then(|p| match p.value {
+                Value::Number(n) => Some(n),
+                _ => None,
+            })
+            .unwrap_or(0.0)
+    }
+
+    /// Properties that an object expanded into several objects, such as an
+    /// imported SVG, passes on to each of them. Layout references are passed
+    /// on too, so that the parts are laid out as a whole.
+    pub fn shared_properties(&self) -> Vec<Property> {
+        self.properties
+            .iter()
+            .filter(|p| {
+                SHARED_PROPERTIES.contains(&p.name.as_str())
+                    || matches!(p.value, Value::Reference(Reference::Layout { .. }))
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+impl Object {
+    /// An object with the given properties, for tests.
+    pub fn new(r#type: &str, name: &str, properties: Vec<(&str, Value)>) -> Self {
+        Object {
+            r#type: r#type.to_string(),
+            name: name.to_string(),
+            properties: properties
+                .into_iter()
+                .map(|(name, value)| Property {
+                    name: name.to_string(),
+                    value,
+                })
+                .collect(),
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Timeline {
     pub animations: Vec<Animation>,
+    pub markers: Vec<Marker>,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -55,6 +201,24 @@ pub struct Animation {
     pub easing: Option<String>,
 }
 
+/// A `marker "name" at 2s` statement, which names a moment of the scene.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Marker {
+    pub name: String,
+    pub time: Duration,
+}
+
+/// A `transform "a" -> "b"` statement, which morphs the objects of one group
+/// into the matching objects of another.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Transform {
+    pub start: Duration,
+    pub end: Duration,
+    pub from: String,
+    pub to: String,
+    pub easing: Option<String>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -90,6 +254,7 @@ mod tests {
             items: vec![],
             timeline: None,
             duration: Some(Duration::from_secs(5)),
+            layers: vec![],
         };
         
         assert_eq!(scene.name, "TestScene");
@@ -148,6 +313,7 @@ mod tests {
                     easing: None,
                 }
             ],
+            markers: vec![],
         };
         
         assert_eq!(timeline.animations.len(), 1);
@@ -205,14 +371,73 @@ mod tests {
                                 easing: Some("linear".to_string()),
                             }
                         ],
+                        markers: vec![],
                     }),
                     duration: Some(Duration::from_secs(2)),
+                    layers: vec![],
                 }
             ],
+            ..Default::default()
         };
         
         assert!(script.camera.is_some());
         assert_eq!(script.scenes.len(), 1);
         assert!(script.scenes[0].timeline.is_some());
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_draw_order_sorts_by_layer_then_z_index() {
+        let object = |name: &str, properties: Vec<Property>| Object {
+            r#type: "circle".to_string(),
+            name: name.to_string(),
+            properties,
+        };
+        let scene = Scene {
+            name: "Layered".to_string(),
+            items: vec![
+                object("top", vec![Property {
+                    name: "layer".to_string(),
+                    value: Value::String("foreground".to_string()),
+                }]),
+                object("raised", vec![Property {
+                    name: "z_index".to_string(),
+                    value: Value::Number(1.0),
+                }]),
+                object("first", vec![]),
+                object("second", vec![]),
+                object("bottom", vec![Property {
+                    name: "layer".to_string(),
+                    value: Value::String("background".to_string()),
+                }]),
+            ],
+            timeline: None,
+            duration: None,
+            layers: vec![],
+        };
+
+        let names: Vec<_> = scene.draw_order().iter().map(|o| o.name.as_str()).collect();
+        assert_eq!(names, vec!["bottom", "first", "second", "raised", "top"]);
+    }
+
+    #[test]
+    fn test_draw_order_uses_declared_layers() {
+        let object = |name: &str, layer: &str| Object {
+            r#type: "circle".to_string(),
+            name: name.to_string(),
+            properties: vec![Property {
+                name: "layer".to_string(),
+                value: Value::String(layer.to_string()),
+            }],
+        };
+        let scene = Scene {
+            name: "Layered".to_string(),
+            items: vec![object("a", "hud"), object("b", "world")],
+            timeline: None,
+            duration: None,
+            layers: vec!["world".to_string(), "hud".to_string()],
+        };
+
+        let names: Vec<_> = scene.draw_order().iter().map(|o| o.name.as_str()).collect();
+        assert_eq!(names, vec!["b", "a"]);
+    }
+}
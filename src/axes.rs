@@ -0,0 +1,711 @@
+//! `axes` objects and the `function_graph` and `parametric_curve` objects
+//! plotted on them.
+//!
+//! Like imported SVGs and equations, these are expanded into `path` objects
+//! when the script is parsed. The parts of a set of axes are named after it,
+//! such as `"ax.x_axis"` or `"ax.x_numbers.3"`, so animating `"ax"` animates
+//! all of them. Every part shares the axes' position, with its path data
+//! relative to it, so the axes can be moved as a whole.
+
+use crate::ast::{Object, Property, Reference, Value};
+use crate::formula::Formula;
+use crate::math;
+use crate::path::{self, PathData, Segment};
+use std::collections::HashMap;
+
+const DEFAULT_RANGE: (f64, f64) = (-5.0, 5.0);
+const DEFAULT_WIDTH: f64 = 800.0;
+const DEFAULT_HEIGHT: f64 = 600.0;
+const DEFAULT_COLOR: &str = "#FFFFFF";
+const DEFAULT_CURVE_COLOR: &str = "#58C4DD";
+const DEFAULT_SAMPLES: f64 = 200.0;
+const TICK_SIZE: f64 = 6.0;
+/// The most ticks an axis is labeled with. Longer axes get a rounder step
+/// than the default of 1, and a finer step that is given is not labeled.
+const MAX_LABELED_TICKS: f64 = 50.0;
+/// The most tick marks drawn along an axis, however fine its step.
+const MAX_TICKS: f64 = 1000.0;
+const LABEL_GAP: f64 = 8.0;
+
+/// The mapping from data coordinates to scene coordinates of an `axes` object.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Axes {
+    pub center: (f64, f64),
+    pub width: f64,
+    pub height: f64,
+    pub x_range: (f64, f64),
+    pub y_range: (f64, f64),
+}
+
+impl Axes {
+    pub fn new(object: &Object) -> Axes {
+        Axes {
+            center: tuple(object, "position").unwrap_or((0.0, 0.0)),
+            width: number(object, "width").unwrap_or(DEFAULT_WIDTH),
+            height: number(object, "height").unwrap_or(DEFAULT_HEIGHT),
+            x_range: range(object, "x_range").unwrap_or(DEFAULT_RANGE),
+            y_range: range(object, "y_range").unwrap_or(DEFAULT_RANGE),
+        }
+    }
+
+    /// Converts data coordinates to a point in the scene ("coordinates to point").
+    pub fn c2p(&self, x: f64, y: f64) -> (f64, f64) {
+        let (x_min, x_max) = self.x_range;
+        let (y_min, y_max) = self.y_range;
+        (
+            self.center.0 - self.width / 2.0 + (x - x_min) / (x_max - x_min) * self.width,
+            self.center.1 + self.height / 2.0 - (y - y_min) / (y_max - y_min) * self.height,
+        )
+    }
+
+    fn contains(&self, x: f64, y: f64) -> bool {
+        let inside = |v: f64, (min, max): (f64, f64)| {
+            let slack = (max - min).abs() * 1e-9;
+            v >= min.min(max) - slack && v <= max.max(min) + slack
+        };
+        inside(x, self.x_range) && inside(y, self.y_range)
+    }
+
+    // Where each axis crosses the other: at zero if it is in range.
+    fn origin(&self) -> (f64, f64) {
+        let clamp = |(min, max): (f64, f64)| 0.0f64.clamp(min.min(max), max.max(min));
+        (clamp(self.x_range), clamp(self.y_range))
+    }
+}
+
+fn axes_by_name(items: &[Object]) -> HashMap<String, Axes> {
+    items
+        .iter()
+        .filter(|o| o.r#type == "axes")
+        .map(|o| (o.name.clone(), Axes::new(o)))
+        .collect()
+}
+
+/// Replaces points given in data coordinates in the properties of `items`.
+pub fn resolve_scene_references(items: &mut [Object]) {
+    let axes = axes_by_name(items);
+    for property in items.iter_mut().flat_map(|o| o.properties.iter_mut()) {
+        resolve(&axes, &mut property.value);
+    }
+}
+
+/// Replaces points given in data coordinates in `value`, such as the target
+/// of an animation, with the point on the axes among `items`.
+pub fn resolve_references(items: &[Object], value: &mut Value) {
+    let axes = axes_by_name(items);
+    resolve(&axes, value);
+}
+
+fn resolve(axes: &HashMap<String, Axes>, value: &mut Value) {
+    match value {
+        Value::Reference(Reference::AxesPoint { axes: name, x, y }) => {
+            let point = match axes.get(name.as_str()) {
+                Some(axes) => axes.c2p(*x, *y),
+                None => {
+                    eprintln!("Warning: No axes named '{}' for c2p({}, {})", name, x, y);
+                    (0.0, 0.0)
+                }
+            };
+            *value = Value::Tuple(point.0, point.1);
+        }
+        Value::List(values) => values.iter_mut().for_each(|v| resolve(axes, v)),
+        _ => (),
+    }
+}
+
+/// Replaces every `axes` object with its parts and every plotted curve with a
+/// `path` object.
+pub fn expand_axes_objects(items: &mut Vec<Object>) {
+    let plotted = ["axes", "function_graph", "parametric_curve"];
+    if !items.iter().any(|o| plotted.contains(&o.r#type.as_str())) {
+        return;
+    }
+    let axes = axes_by_name(items);
+
+    let mut expanded = Vec::with_capacity(items.len());
+    for object in items.drain(..) {
+        match object.r#type.as_str() {
+            "axes" => {
+                for name in ["x_range", "y_range"] {
+                    if tuple(&object, name).is_some() && range(&object, name).is_none() {
+                        eprintln!(
+                            "Warning: The {} of axes '{}' must go from a lower to a higher value, using {:?}",
+                            name, object.name, DEFAULT_RANGE
+                        );
+                    }
+                }
+                expanded.extend(axes_parts(&object, &axes[&object.name]));
+            }
+            "function_graph" | "parametric_curve" => match plot(&object, &axes) {
+                Ok(curve) => expanded.push(curve),
+                Err(e) => eprintln!("Warning: Failed to plot '{}': {}", object.name, e),
+            },
+            _ => expanded.push(object),
+        }
+    }
+    *items = expanded;
+}
+
+type Line = ((f64, f64), (f64, f64));
+
+fn axes_parts(parent: &Object, axes: &Axes) -> Vec<Object> {
+    let axes_color = color(parent, "color").unwrap_or_else(|| DEFAULT_COLOR.to_string());
+    let stroke_width = number(parent, "stroke_width").unwrap_or(2.0);
+    let tip_length = number(parent, "tip_length").unwrap_or(12.0);
+    let font_size = number(parent, "font_size").unwrap_or(20.0);
+    let (x_min, x_max) = axes.x_range;
+    let (y_min, y_max) = axes.y_range;
+    let (x0, y0) = axes.origin();
+    let (x_ticks, x_labeled) = axis_ticks(parent, "x_step", axes.x_range);
+    let (y_ticks, y_labeled) = axis_ticks(parent, "y_step", axes.y_range);
+
+    // Path data is relative to the axes' center, which all parts share.
+    let local = |x: f64, y: f64| {
+        let p = axes.c2p(x, y);
+        (p.0 - axes.center.0, p.1 - axes.center.1)
+    };
+    let stroke = |name: &str, lines: &[Line], color: &str, width: f64| {
+        let mut data = Vec::new();
+        for &(from, to) in lines {
+            data.push(Segment::MoveTo(from));
+            data.push(path::line_to(from, to));
+        }
+        part(
+            parent,
+            name,
+            data,
+            vec![
+                property("border_color", Value::Color(color.to_string())),
+                property("stroke_width", Value::Number(width)),
+            ],
+        )
+    };
+
+    let mut parts = Vec::new();
+    if let Some(grid_color) = color(parent, "grid_color") {
+        let mut lines: Vec<_> = x_ticks
+            .iter()
+            .map(|&x| (local(x, y_min), local(x, y_max)))
+            .collect();
+        lines.extend(y_ticks.iter().map(|&y| (local(x_min, y), local(x_max, y))));
+        parts.push(stroke("grid", &lines, &grid_color, 1.0));
+    }
+
+    let x_end = local(x_max, y0);
+    let y_end = local(x0, y_max);
+    parts.push(stroke(
+        "x_axis",
+        &[(local(x_min, y0), x_end)],
+        &axes_color,
+        stroke_width,
+    ));
+    parts.push(stroke(
+        "y_axis",
+        &[(local(x0, y_min), y_end)],
+        &axes_color,
+        stroke_width,
+    ));
+    if tip_length > 0.0 {
+        let half = tip_length / 2.0;
+        let tips = [
+            (
+                "x_tip",
+                [
+                    (x_end.0 + tip_length, x_end.1),
+                    (x_end.0, x_end.1 - half),
+                    (x_end.0, x_end.1 + half),
+                ],
+            ),
+            (
+                "y_tip",
+                [
+                    (y_end.0, y_end.1 - tip_length),
+                    (y_end.0 + half, y_end.1),
+                    (y_end.0 - half, y_end.1),
+                ],
+            ),
+        ];
+        for (name, corners) in tips {
+            let data = vec![
+                Segment::MoveTo(corners[0]),
+                path::line_to(corners[0], corners[1]),
+                path::line_to(corners[1], corners[2]),
+                path::line_to(corners[2], corners[0]),
+                Segment::Close,
+            ];
+            let fill = vec![property("fill", Value::Color(axes_color.clone()))];
+            parts.push(part(parent, name, data, fill));
+        }
+    }
+
+    let x_tick_lines: Vec<_> = x_ticks
+        .iter()
+        .map(|&x| {
+            let p = local(x, y0);
+            ((p.0, p.1 - TICK_SIZE), (p.0, p.1 + TICK_SIZE))
+        })
+        .collect();
+    let y_tick_lines: Vec<_> = y_ticks
+        .iter()
+        .map(|&y| {
+            let p = local(x0, y);
+            ((p.0 - TICK_SIZE, p.1), (p.0 + TICK_SIZE, p.1))
+        })
+        .collect();
+    parts.push(stroke("x_ticks", &x_tick_lines, &axes_color, stroke_width));
+    parts.push(stroke("y_ticks", &y_tick_lines, &axes_color, stroke_width));
+
+    if font_size > 0.0 {
+        let label = |name: String, tex: &str, anchor: (f64, f64), align: (f64, f64)| {
+            typeset_label(parent, &name, tex, anchor, align, font_size, &axes_color)
+        };
+        // The crossing of the axes is left unlabeled, as the other axis runs through it.
+        let x_numbers = if x_labeled { x_ticks.as_slice() } else { &[] };
+        let y_numbers = if y_labeled { y_ticks.as_slice() } else { &[] };
+        for (i, &x) in x_numbers.iter().enumerate().filter(|(_, &x)| x != x0) {
+            let p = local(x, y0);
+            let anchor = (p.0, p.1 + TICK_SIZE + LABEL_GAP);
+            let name = format!("x_numbers.{}", i + 1);
+            parts.extend(label(name, &format_number(x), anchor, (0.5, 0.0)));
+        }
+        for (i, &y) in y_numbers.iter().enumerate().filter(|(_, &y)| y != y0) {
+            let p = local(x0, y);
+            let anchor = (p.0 - TICK_SIZE - LABEL_GAP, p.1);
+            let name = format!("y_numbers.{}", i + 1);
+            parts.extend(label(name, &format_number(y), anchor, (1.0, 0.5)));
+        }
+        let tip = tip_length.max(0.0);
+        if let Some(Value::String(tex)) = value(parent, "x_label") {
+            let anchor = (x_end.0 + tip + LABEL_GAP, x_end.1);
+            parts.extend(label("x_label".to_string(), tex, anchor, (0.0, 0.5)));
+        }
+        if let Some(Value::String(tex)) = value(parent, "y_label") {
+            let anchor = (y_end.0, y_end.1 - tip - LABEL_GAP);
+            parts.extend(label("y_label".to_string(), tex, anchor, (0.5, 1.0)));
+        }
+    }
+    parts
+}
+
+// The ticks along one axis of `parent` and whether to label them, at its
+// `step` property or else at 1 or a rounder step if that gives too many.
+fn axis_ticks(parent: &Object, step: &str, range: (f64, f64)) -> (Vec<f64>, bool) {
+    let span = (range.1 - range.0).abs();
+    let Some(given) = number(parent, step) else {
+        let step = if span + 1.0 > MAX_LABELED_TICKS {
+            nice_step(span / (MAX_LABELED_TICKS - 1.0))
+        } else {
+            1.0
+        };
+        return (ticks(range, step), true);
+    };
+    let count = span / given + 1.0;
+    if given > 0.0 && count > MAX_LABELED_TICKS {
+        eprintln!(
+            "Warning: {} {} of axes '{}' gives {:.0} ticks, too many to label",
+            step, given, parent.name, count
+        );
+        if count > MAX_TICKS {
+            return (Vec::new(), false);
+        }
+        return (ticks(range, given), false);
+    }
+    (ticks(range, given), true)
+}
+
+/// The smallest of 1, 2 or 5 times a power of ten that is at least `raw`.
+pub fn nice_step(raw: f64) -> f64 {
+    if raw <= 0.0 || !raw.is_finite() {
+        return 1.0;
+    }
+    let magnitude = 10f64.powf(raw.log10().floor());
+    let fraction = raw / magnitude;
+    let nice = if fraction <= 1.0 {
+        1.0
+    } else if fraction <= 2.0 {
+        2.0
+    } else if fraction <= 5.0 {
+        5.0
+    } else {
+        10.0
+    };
+    nice * magnitude
+}
+
+/// Multiples of `step` within `range`.
+pub fn ticks((min, max): (f64, f64), step: f64) -> Vec<f64> {
+    if step <= 0.0 {
+        return Vec::new();
+    }
+    let (min, max) = (min.min(max), max.max(min));
+    let first = (min / step - 1e-9).ceil() as i64;
+    let last = (max / step + 1e-9).floor() as i64;
+    (first..=last).map(|k| k as f64 * step).collect()
+}
+
+/// Formats a tick value without the rounding noise of repeated steps.
+pub fn format_number(n: f64) -> String {
+    let rounded = (n * 1e9).round() / 1e9;
+    if rounded == 0.0 {
+        "0".to_string()
+    } else {
+        rounded.to_string()
+    }
+}
+
+// Typesets a label and moves it so that the point `align`, given as fractions
+// of its bounding box, lies on `anchor`.
+fn typeset_label(
+    parent: &Object,
+    name: &str,
+    tex: &str,
+    anchor: (f64, f64),
+    align: (f64, f64),
+    font_size: f64,
+    color: &str,
+) -> Vec<Object> {
+    let mut properties = parent.shared_properties();
+    properties.extend([
+        property("tex", Value::String(tex.to_string())),
+        property("font_size", Value::Number(font_size)),
+        property("fill", Value::Color(color.to_string())),
+    ]);
+    let label = Object {
+        r#type: "math".to_string(),
+        name: format!("{}.{}", parent.name, name),
+        properties,
+    };
+    let mut glyphs = match math::typeset(&label) {
+        Ok(glyphs) => glyphs,
+        Err(e) => {
+            eprintln!("Warning: Failed to typeset label '{}': {}", tex, e);
+            return Vec::new();
+        }
+    };
+
+    let paths: Vec<PathData> = glyphs
+        .iter()
+        .filter_map(|g| match value(g, "d") {
+            Some(Value::String(d)) => path::parse_path_data(d).ok(),
+            _ => None,
+        })
+        .collect();
+    let points = paths.iter().flat_map(|data| {
+        data.iter().filter_map(|segment| match segment {
+            Segment::MoveTo(p) | Segment::CubicTo(_, _, p) => Some(*p),
+            Segment::Close => None,
+        })
+    });
+    let (mut min, mut max) = ((f64::MAX, f64::MAX), (f64::MIN, f64::MIN));
+    for (x, y) in points {
+        min = (min.0.min(x), min.1.min(y));
+        max = (max.0.max(x), max.1.max(y));
+    }
+    let offset = (
+        anchor.0 - (min.0 + (max.0 - min.0) * align.0),
+        anchor.1 - (min.1 + (max.1 - min.1) * align.1),
+    );
+    for (glyph, data) in glyphs.iter_mut().zip(&paths) {
+        let moved = path::to_path_data(&path::translate(data, offset));
+        for p in glyph.properties.iter_mut().filter(|p| p.name == "d") {
+            p.value = Value::String(moved.clone());
+        }
+    }
+    glyphs
+}
+
+fn part(parent: &Object, name: &str, data: PathData, mut properties: Vec<Property>) -> Object {
+    properties.insert(0, property("d", Value::String(path::to_path_data(&data))));
+    properties.extend(parent.shared_properties());
+    Object {
+        r#type: "path".to_string(),
+        name: format!("{}.{}", parent.name, name),
+        properties,
+    }
+}
+
+// Samples a `function_graph` or `parametric_curve` into a `path` object on its axes.
+fn plot(object: &Object, axes: &HashMap<String, Axes>) -> Result<Object, String> {
+    let axes = match value(object, "axes") {
+        Some(Value::String(name)) => axes
+            .get(name)
+            .ok_or_else(|| format!("no axes named '{}'", name))?,
+        _ => return Err("missing 'axes'".to_string()),
+    };
+    let formula = |name: &str| match value(object, name) {
+        Some(Value::String(text)) => {
+            Formula::parse(text).map_err(|e| format!("invalid '{}': {}", name, e))
+        }
+        _ => Err(format!("missing '{}'", name)),
+    };
+    let samples = number(object, "samples")
+        .unwrap_or(DEFAULT_SAMPLES)
+        .max(1.0) as usize;
+    let parameters = |(start, end): (f64, f64)| {
+        (0..=samples).map(move |i| start + (end - start) * i as f64 / samples as f64)
+    };
+    let points: Vec<(f64, f64)> = if object.r#type == "function_graph" {
+        let f = formula("expression")?;
+        let range = tuple(object, "x_range").unwrap_or(axes.x_range);
+        parameters(range).map(|x| (x, f.eval(x))).collect()
+    } else {
+        let (fx, fy) = (formula("x")?, formula("y")?);
+        let range = tuple(object, "t_range").unwrap_or((0.0, 1.0));
+        parameters(range)
+            .map(|t| (fx.eval(t), fy.eval(t)))
+            .collect()
+    };
+
+    // Lift the pen wherever the curve leaves the axes or is undefined.
+    let mut data = Vec::new();
+    let mut previous: Option<(f64, f64)> = None;
+    for (x, y) in points {
+        if !x.is_finite() || !y.is_finite() || !axes.contains(x, y) {
+            previous = None;
+            continue;
+        }
+        let p = axes.c2p(x, y);
+        let p = (p.0 - axes.center.0, p.1 - axes.center.1);
+        match previous {
+            Some(from) => data.push(path::line_to(from, p)),
+            None => data.push(Segment::MoveTo(p)),
+        }
+        previous = Some(p);
+    }
+
+    let consumed = [
+        "axes",
+        "expression",
+        "x",
+        "y",
+        "x_range",
+        "t_range",
+        "samples",
+        "position",
+    ];
+    let mut properties: Vec<Property> = object
+        .properties
+        .iter()
+        .filter(|p| !consumed.contains(&p.name.as_str()))
+        .cloned()
+        .collect();
+    if value(object, "border_color").is_none() {
+        properties.push(property(
+            "border_color",
+            Value::Color(DEFAULT_CURVE_COLOR.to_string()),
+        ));
+    }
+    if value(object, "stroke_width").is_none() {
+        properties.push(property("stroke_width", Value::Number(3.0)));
+    }
+    properties.push(property("d", Value::String(path::to_path_data(&data))));
+    properties.push(property(
+        "position",
+        Value::Tuple(axes.center.0, axes.center.1),
+    ));
+    Ok(Object {
+        r#type: "path".to_string(),
+        name: object.name.clone(),
+        properties,
+    })
+}
+
+fn property(name: &str, value: Value) -> Property {
+    Property {
+        name: name.to_string(),
+        value,
+    }
+}
+
+fn value<'a>(object: &'a Object, name: &str) -> Option<&'a Value> {
+    object
+        .properties
+        .iter()
+        .find(|p| p.name == name)
+        .map(|p| &p.value)
+}
+
+fn number(object: &Object, name: &str) -> Option<f64> {
+    match value(object, name) {
+        Some(Value::Number(n)) => Some(*n),
+        _ => None,
+    }
+}
+
+// A range such as `x_range`, if it is given and not empty.
+fn range(object: &Object, name: &str) -> Option<(f64, f64)> {
+    tuple(object, name).filter(|(min, max)| min < max && (max - min).is_finite())
+}
+
+fn tuple(object: &Object, name: &str) -> Option<(f64, f64)> {
+    match value(object, name) {
+        Some(Value::Tuple(x, y)) => Some((*x, *y)),
+        _ => None,
+    }
+}
+
+fn color(object: &Object, name: &str) -> Option<String> {
+    match value(object, name) {
+        Some(Value::Color(color)) => Some(color.clone()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn axes() -> Object {
+        Object::new(
+            "axes",
+            "ax",
+            vec![
+                ("x_range", Value::Tuple(0.0, 10.0)),
+                ("y_range", Value::Tuple(-5.0, 5.0)),
+                ("width", Value::Number(1000.0)),
+                ("height", Value::Number(500.0)),
+                ("position", Value::Tuple(500.0, 300.0)),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_c2p() {
+        let axes = Axes::new(&axes());
+        assert_eq!(axes.c2p(0.0, -5.0), (0.0, 550.0));
+        assert_eq!(axes.c2p(10.0, 5.0), (1000.0, 50.0));
+        assert_eq!(axes.c2p(5.0, 0.0), (500.0, 300.0));
+    }
+
+    #[test]
+    fn test_empty_ranges_fall_back_to_the_default() {
+        let flat = Object::new(
+            "axes",
+            "ax",
+            vec![
+                ("x_range", Value::Tuple(2.0, 2.0)),
+                ("y_range", Value::Tuple(5.0, -5.0)),
+            ],
+        );
+        let axes = Axes::new(&flat);
+        assert_eq!((axes.x_range, axes.y_range), (DEFAULT_RANGE, DEFAULT_RANGE));
+        let mut items = vec![flat];
+        expand_axes_objects(&mut items);
+        assert!(items.iter().all(|o| match value(o, "d") {
+            Some(Value::String(d)) => !d.contains("NaN"),
+            _ => true,
+        }));
+    }
+
+    #[test]
+    fn test_axes_expand_into_named_parts() {
+        let mut items = vec![axes()];
+        expand_axes_objects(&mut items);
+        let names: Vec<&str> = items.iter().map(|o| o.name.as_str()).collect();
+        for part in [
+            "ax.x_axis",
+            "ax.y_axis",
+            "ax.x_tip",
+            "ax.x_ticks",
+            "ax.y_ticks",
+        ] {
+            assert!(names.contains(&part), "{}", part);
+        }
+        assert!(!names.contains(&"ax.grid"));
+        // Ticks from 0 to 10, but the crossing of the axes at 0 has no number.
+        assert!(names.contains(&"ax.x_numbers.11.1"));
+        assert!(!names.iter().any(|n| n.starts_with("ax.x_numbers.1.")));
+        assert!(items.iter().all(|o| o.r#type == "path"));
+        assert!(items
+            .iter()
+            .all(|o| value(o, "position") == Some(&Value::Tuple(500.0, 300.0))));
+    }
+
+    #[test]
+    fn test_long_axes_get_fewer_ticks() {
+        let long = Object::new(
+            "axes",
+            "ax",
+            vec![
+                ("x_range", Value::Tuple(0.0, 200000.0)),
+                ("y_range", Value::Tuple(0.0, 60.0)),
+            ],
+        );
+        let (x_ticks, labeled) = axis_ticks(&long, "x_step", (0.0, 200000.0));
+        assert!(labeled);
+        assert_eq!(x_ticks.len(), 41);
+        assert_eq!(x_ticks[1], 5000.0);
+        let (y_ticks, _) = axis_ticks(&long, "y_step", (0.0, 60.0));
+        assert_eq!(y_ticks[1], 2.0);
+
+        // A step that is given is kept, but too many ticks go unlabeled.
+        let fine = Object::new("axes", "ax", vec![("x_step", Value::Number(0.5))]);
+        assert_eq!(axis_ticks(&fine, "x_step", (0.0, 10.0)), (ticks((0.0, 10.0), 0.5), true));
+        assert!(!axis_ticks(&fine, "x_step", (0.0, 100.0)).1);
+        assert!(axis_ticks(&fine, "x_step", (0.0, 1e6)).0.is_empty());
+    }
+
+    #[test]
+    fn test_nice_step() {
+        assert_eq!(nice_step(0.3), 0.5);
+        assert_eq!(nice_step(200.0), 200.0);
+        assert_eq!(nice_step(4081.6), 5000.0);
+    }
+
+    #[test]
+    fn test_function_graph_lifts_pen_outside_axes() {
+        let graph = Object::new(
+            "function_graph",
+            "f",
+            vec![
+                ("axes", Value::String("ax".to_string())),
+                ("expression", Value::String("1 / (x - 5)".to_string())),
+                ("samples", Value::Number(100.0)),
+            ],
+        );
+        let mut items = vec![axes(), graph];
+        expand_axes_objects(&mut items);
+        let curve = items.iter().find(|o| o.name == "f").unwrap();
+        assert_eq!(curve.r#type, "path");
+        let Some(Value::String(d)) = value(curve, "d") else {
+            panic!("expected path data");
+        };
+        let data = path::parse_path_data(d).unwrap();
+        let moves = data
+            .iter()
+            .filter(|s| matches!(s, Segment::MoveTo(_)))
+            .count();
+        assert_eq!(moves, 2);
+        assert_eq!(
+            value(curve, "border_color"),
+            Some(&Value::Color(DEFAULT_CURVE_COLOR.to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parametric_curve_needs_both_coordinates() {
+        let curve = Object::new(
+            "parametric_curve",
+            "c",
+            vec![
+                ("axes", Value::String("ax".to_string())),
+                ("x", Value::String("cos(t)".to_string())),
+            ],
+        );
+        let mut items = vec![axes(), curve];
+        expand_axes_objects(&mut items);
+        assert!(!items.iter().any(|o| o.name == "c"));
+    }
+
+    #[test]
+    fn test_resolve_references() {
+        let items = vec![axes()];
+        let mut value = Value::List(vec![Value::Reference(Reference::AxesPoint {
+            axes: "ax".to_string(),
+            x: 10.0,
+            y: 0.0,
+        })]);
+        resolve_references(&items, &mut value);
+        assert_eq!(value, Value::List(vec![Value::Tuple(1000.0, 300.0)]));
+    }
+}
@@ -0,0 +1,387 @@
+//! Axis-aligned bounding boxes of objects, worked out from the same
+//! properties and defaults the renderers draw them from.
+//!
+//! Bounds follow an object's geometry and ignore its `rotation`, stroke width
+//! and arrow tips, so that lining objects up does not depend on how they are
+//! styled.
+
+use crate::ast::{Object, Property, Value};
+use crate::path;
+use crate::shapes::{self, Shape};
+use crate::{assets, chart, code, number_line, table};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    pub min: (f64, f64),
+    pub max: (f64, f64),
+}
+
+impl Bounds {
+    /// The smallest box containing all of `points`, if there are any.
+    pub fn from_points(points: impl IntoIterator<Item = (f64, f64)>) -> Option<Bounds> {
+        points.into_iter().fold(None, |bounds, p| {
+            let point = Bounds { min: p, max: p };
+            Some(match bounds {
+                Some(bounds) => bounds.union(&point),
+                None => point,
+            })
+        })
+    }
+
+    pub fn union(&self, other: &Bounds) -> Bounds {
+        Bounds {
+            min: (self.min.0.min(other.min.0), self.min.1.min(other.min.1)),
+            max: (self.max.0.max(other.max.0), self.max.1.max(other.max.1)),
+        }
+    }
+
+    pub fn center(&self) -> (f64, f64) {
+        (
+            (self.min.0 + self.max.0) / 2.0,
+            (self.min.1 + self.max.1) / 2.0,
+        )
+    }
+
+    /// A named point on the box: `center`, an edge's midpoint such as `top`,
+    /// or a corner such as `top_right`.
+    pub fn anchor(&self, name: &str) -> Option<(f64, f64)> {
+        let (cx, cy) = self.center();
+        let (left, top, right, bottom) = (self.min.0, self.min.1, self.max.0, self.max.1);
+        match name {
+            "center" => Some((cx, cy)),
+            "top" => Some((cx, top)),
+            "bottom" => Some((cx, bottom)),
+            "left" => Some((left, cy)),
+            "right" => Some((right, cy)),
+            "top_left" => Some((left, top)),
+            "top_right" => Some((right, top)),
+            "bottom_left" => Some((left, bottom)),
+            "bottom_right" => Some((right, bottom)),
+            _ => None,
+        }
+    }
+}
+
+/// The bounds of a single object, or `None` for objects that draw nothing,
+/// such as containers and images that fail to load.
+pub fn object_bounds(object: &Object) -> Option<Bounds> {
+    let properties = &object.properties;
+    let number = |name: &str, default: f64| match property(properties, name) {
+        Some(Value::Number(n)) => *n,
+        _ => default,
+    };
+    let point = |name: &str, default: (f64, f64)| match property(properties, name) {
+        Some(Value::Tuple(x, y)) => (*x, *y),
+        _ => default,
+    };
+    let (x, y) = point("position", (0.0, 0.0));
+    let around = |rx: f64, ry: f64| Bounds {
+        min: (x - rx, y - ry),
+        max: (x + rx, y + ry),
+    };
+
+    match object.r#type.as_str() {
+        "circle" => Some(around(number("radius", 50.0), number("radius", 50.0))),
+        "square" => Some(around(
+            number("size", 100.0) / 2.0,
+            number("size", 100.0) / 2.0,
+        )),
+        "rectangle" => Some(around(
+            number("width", 100.0) / 2.0,
+            number("height", 50.0) / 2.0,
+        )),
+        "ellipse" => Some(around(number("rx", 50.0), number("ry", 25.0))),
+        "triangle" | "line" | "arrow" | "vector" | "double_arrow" | "polyline" => {
+            Bounds::from_points(points(object))
+        }
+        "polygon" => Bounds::from_points(shapes::regular_polygon(
+            (x, y),
+            number("radius", 50.0),
+            number("sides", 6.0) as usize,
+        )),
+        "star" => {
+            let outer_radius = number("outer_radius", 50.0);
+            Bounds::from_points(shapes::star(
+                (x, y),
+                outer_radius,
+                number("inner_radius", outer_radius / 2.0),
+                number("point_count", 5.0) as usize,
+            ))
+        }
+        "arc" => Bounds::from_points(shapes::arc(
+            (x, y),
+            number("radius", 50.0),
+            number("start_angle", 0.0),
+            number("end_angle", 90.0),
+        )),
+        "sector" => Bounds::from_points(shapes::sector(
+            (x, y),
+            number("radius", 50.0),
+            number("inner_radius", 0.0),
+            number("start_angle", 0.0),
+            number("end_angle", 90.0),
+        )),
+        "path" => {
+            let Some(Value::String(d)) = property(properties, "d") else {
+                return None;
+            };
+            let data = path::parse_path_data(d).ok()?;
+            Bounds::from_points(
+                path::flatten(&path::translate(&data, (x, y)))
+                    .into_iter()
+                    .flat_map(|s| s.points),
+            )
+        }
+        "image" => {
+            let Some(Value::String(src)) = property(properties, "src") else {
+                return None;
+            };
+            let source = assets::load_image(src)?;
+            let frame = assets::frame_size(
+                source.dimensions(),
+                match property(properties, "width") {
+                    Some(Value::Number(n)) => Some(*n),
+                    _ => None,
+                },
+                match property(properties, "height") {
+                    Some(Value::Number(n)) => Some(*n),
+                    _ => None,
+                },
+            );
+            let scale = number("scale", 1.0);
+            let fit = match property(properties, "fit") {
+                Some(Value::String(fit)) => fit.as_str(),
+                _ => "contain",
+            };
+            let size =
+                assets::fit_image(source.dimensions(), (frame.0 * scale, frame.1 * scale), fit)
+                    .size;
+            Some(around(size.0 / 2.0, size.1 / 2.0))
+        }
+        "number_line" => {
+            let line = number_line::layout(properties);
+            let mut points = vec![line.start, line.end];
+            points.extend(line.ticks.iter().flat_map(|&(a, b)| [a, b]));
+            points.extend(line.pointer.iter().flat_map(|&(a, b)| [a, b]));
+            points.extend(
+                line.labels
+                    .iter()
+                    .flat_map(path::flatten)
+                    .flat_map(|s| s.points),
+            );
+            Bounds::from_points(points)
+        }
+        "code" => shapes_bounds(&code::shapes(properties)),
+        "bar_chart" | "line_chart" | "pie_chart" => {
+            shapes_bounds(&chart::shapes(&object.r#type, properties))
+        }
+        "table_cell" => shapes_bounds(&table::cell_shapes(properties)),
+        _ => None,
+    }
+}
+
+/// `start`, `end` or `midpoint` of an object drawn along a line: where it
+/// starts, where it ends and halfway along it. The end of a `path` that is
+/// being drawn on is the tip of the part drawn so far.
+pub fn path_anchor(object: &Object, name: &str) -> Option<(f64, f64)> {
+    let points = match object.r#type.as_str() {
+        "line" | "arrow" | "vector" | "double_arrow" | "polyline" => points(object),
+        "arc" => {
+            let number = |name: &str, default: f64| match property(&object.properties, name) {
+                Some(Value::Number(n)) => *n,
+                _ => default,
+            };
+            let position = match property(&object.properties, "position") {
+                Some(Value::Tuple(x, y)) => (*x, *y),
+                _ => (0.0, 0.0),
+            };
+            shapes::arc(
+                position,
+                number("radius", 50.0),
+                number("start_angle", 0.0),
+                number("end_angle", 90.0),
+            )
+        }
+        "path" => {
+            let Some(Value::String(d)) = property(&object.properties, "d") else {
+                return None;
+            };
+            let data = path::parse_path_data(d).ok()?;
+            let draw = match property(&object.properties, "draw") {
+                Some(Value::Number(n)) => *n,
+                _ => 1.0,
+            };
+            let position = match property(&object.properties, "position") {
+                Some(Value::Tuple(x, y)) => (*x, *y),
+                _ => (0.0, 0.0),
+            };
+            path::flatten(&path::translate(&path::trim(&data, draw), position))
+                .into_iter()
+                .flat_map(|s| s.points)
+                .collect()
+        }
+        _ => return None,
+    };
+    match name {
+        "start" => points.first().copied(),
+        "end" => points.last().copied(),
+        "midpoint" => halfway(&points),
+        _ => None,
+    }
+}
+
+// The point halfway along a polyline.
+fn halfway(points: &[(f64, f64)]) -> Option<(f64, f64)> {
+    let distance = |a: (f64, f64), b: (f64, f64)| (b.0 - a.0).hypot(b.1 - a.1);
+    let length: f64 = points.windows(2).map(|w| distance(w[0], w[1])).sum();
+    let mut remaining = length / 2.0;
+    for w in points.windows(2) {
+        let segment = distance(w[0], w[1]);
+        if segment >= remaining && segment > 0.0 {
+            let t = remaining / segment;
+            return Some((
+                w[0].0 + (w[1].0 - w[0].0) * t,
+                w[0].1 + (w[1].1 - w[0].1) * t,
+            ));
+        }
+        remaining -= segment;
+    }
+    points.first().copied()
+}
+
+/// The properties that place objects drawn between points rather than around
+/// their `position`, with the renderers' defaults.
+pub fn point_properties(object_type: &str) -> &'static [(&'static str, (f64, f64))] {
+    match object_type {
+        "triangle" => &[
+            ("p1", (0.0, 0.0)),
+            ("p2", (50.0, 50.0)),
+            ("p3", (0.0, 50.0)),
+        ],
+        "line" | "arrow" | "vector" | "double_arrow" => &[("p1", (0.0, 0.0)), ("p2", (50.0, 50.0))],
+        _ => &[],
+    }
+}
+
+// The points an object is drawn between, for the types that have them.
+fn points(object: &Object) -> Vec<(f64, f64)> {
+    if object.r#type == "polyline" {
+        return match property(&object.properties, "points") {
+            Some(Value::List(points)) => points
+                .iter()
+                .filter_map(|p| match p {
+                    Value::Tuple(x, y) => Some((*x, *y)),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+    }
+    point_properties(&object.r#type)
+        .iter()
+        .map(|(name, default)| match property(&object.properties, name) {
+            Some(Value::Tuple(x, y)) => (*x, *y),
+            _ => *default,
+        })
+        .collect()
+}
+
+fn shapes_bounds(shapes: &[Shape]) -> Option<Bounds> {
+    Bounds::from_points(
+        shapes
+            .iter()
+            .flat_map(|s| path::flatten(&s.data))
+            .flat_map(|s| s.points),
+    )
+}
+
+fn property<'a>(properties: &'a [Property], name: &str) -> Option<&'a Value> {
+    properties.iter().find(|p| p.name == name).map(|p| &p.value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shape_bounds() {
+        let rectangle = Object::new(
+            "rectangle",
+            "r",
+            vec![
+                ("position", Value::Tuple(100.0, 100.0)),
+                ("width", Value::Number(80.0)),
+            ],
+        );
+        assert_eq!(
+            object_bounds(&rectangle),
+            Some(Bounds {
+                min: (60.0, 75.0),
+                max: (140.0, 125.0)
+            })
+        );
+
+        let line = Object::new("line", "l", vec![("p2", Value::Tuple(-20.0, 40.0))]);
+        assert_eq!(
+            object_bounds(&line),
+            Some(Bounds {
+                min: (-20.0, 0.0),
+                max: (0.0, 40.0)
+            })
+        );
+
+        let path = Object::new(
+            "path",
+            "p",
+            vec![
+                ("position", Value::Tuple(10.0, 10.0)),
+                ("d", Value::String("M 0 0 L 30 0 L 30 20 Z".to_string())),
+            ],
+        );
+        let bounds = object_bounds(&path).unwrap();
+        let round = |(x, y): (f64, f64)| (x.round(), y.round());
+        assert_eq!(round(bounds.min), (10.0, 10.0));
+        assert_eq!(round(bounds.max), (40.0, 30.0));
+
+        // A square star reaches its outer radius on every side.
+        let star = Object::new("star", "s", vec![("point_count", Value::Number(4.0))]);
+        let bounds = object_bounds(&star).unwrap();
+        assert_eq!(round(bounds.min), (-50.0, -50.0));
+        assert_eq!(round(bounds.max), (50.0, 50.0));
+
+        assert_eq!(object_bounds(&Object::new("group", "g", vec![])), None);
+    }
+
+    #[test]
+    fn test_anchors() {
+        let square = Object::new(
+            "square",
+            "s",
+            vec![
+                ("position", Value::Tuple(100.0, 100.0)),
+                ("size", Value::Number(40.0)),
+            ],
+        );
+        let bounds = object_bounds(&square).unwrap();
+        assert_eq!(bounds.anchor("top_right"), Some((120.0, 80.0)));
+        assert_eq!(bounds.anchor("bottom"), Some((100.0, 120.0)));
+        assert_eq!(bounds.anchor("middle"), None);
+
+        let line = Object::new(
+            "polyline",
+            "l",
+            vec![(
+                "points",
+                Value::List(vec![
+                    Value::Tuple(0.0, 0.0),
+                    Value::Tuple(30.0, 0.0),
+                    Value::Tuple(30.0, 10.0),
+                ]),
+            )],
+        );
+        assert_eq!(path_anchor(&line, "start"), Some((0.0, 0.0)));
+        assert_eq!(path_anchor(&line, "end"), Some((30.0, 10.0)));
+        assert_eq!(path_anchor(&line, "midpoint"), Some((20.0, 0.0)));
+        assert_eq!(path_anchor(&square, "start"), None);
+    }
+}
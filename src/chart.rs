@@ -0,0 +1,890 @@
+//! `bar_chart`, `line_chart` and `pie_chart` objects drawn from a list of
+//! values, given inline or loaded from a CSV or JSON file.
+//!
+//! Data files are read once at parse time into the chart's `values` and
+//! `labels` properties, and an animation of `src` to another file becomes an
+//! animation of `values`, so switching datasets interpolates bar heights, line
+//! points and pie angles like any other list. Both renderers fill the shapes
+//! returned by [`shapes`].
+
+use crate::assets;
+use crate::ast::{Animation, Object, Property, Value};
+use crate::axes;
+use crate::color;
+use crate::font;
+use crate::path::{self, PathData};
+use crate::shapes::{self as outlines, Shape};
+use std::collections::HashMap;
+use std::fs;
+
+pub const CHART_TYPES: [&str; 3] = ["bar_chart", "line_chart", "pie_chart"];
+
+/// Colors given to bars and slices in turn when a chart sets no `colors`.
+const PALETTE: [&str; 8] = [
+    "#58C4DD", "#83C167", "#FC6255", "#FFFF00", "#9A72AC", "#FF862F", "#5CD0B3", "#F7A1A3",
+];
+const DEFAULT_WIDTH: f64 = 800.0;
+const DEFAULT_HEIGHT: f64 = 500.0;
+const DEFAULT_RADIUS: f64 = 200.0;
+const DEFAULT_FONT_SIZE: f64 = 24.0;
+const AXIS_WIDTH: f64 = 2.0;
+const TICK_SIZE: f64 = 8.0;
+
+/// A dataset read from a file: one value per row, with optional row labels.
+#[derive(Debug, PartialEq)]
+struct Data {
+    values: Vec<f64>,
+    labels: Vec<String>,
+}
+
+/// Reads the `src` file of every chart into its `values` and `labels`
+/// properties. Labels given in the script take precedence over the file's.
+pub fn load_chart_data(items: &mut [Object]) {
+    for object in items
+        .iter_mut()
+        .filter(|o| CHART_TYPES.contains(&o.r#type.as_str()))
+    {
+        let src = match string_property(&object.properties, "src") {
+            Some(src) => src.to_string(),
+            None => continue,
+        };
+        let Some(data) = load(&src, string_property(&object.properties, "column")) else {
+            continue;
+        };
+        let has_labels = object.properties.iter().any(|p| p.name == "labels");
+        object.properties.retain(|p| p.name != "values");
+        object.properties.push(Property {
+            name: "values".to_string(),
+            value: numbers(&data.values),
+        });
+        if !has_labels && !data.labels.is_empty() {
+            object.properties.push(Property {
+                name: "labels".to_string(),
+                value: strings(&data.labels),
+            });
+        }
+    }
+}
+
+/// Replaces animations of a chart's `src` with animations of the `values`
+/// and `labels` read from the new file. When the new file has a different
+/// number of rows, the shorter dataset is padded with zeros, so that new bars
+/// grow and new slices open while those left over shrink away.
+pub fn load_animation_data(items: &[Object], animations: &mut Vec<Animation>) {
+    // The values and labels each chart shows after the animations so far.
+    let mut shown: HashMap<&str, (Vec<f64>, Vec<String>)> = HashMap::new();
+    let mut loaded = Vec::with_capacity(animations.len());
+    for animation in animations.drain(..) {
+        let chart = items.iter().find(|o| {
+            o.name == animation.target_object && CHART_TYPES.contains(&o.r#type.as_str())
+        });
+        let (chart, src) = match (chart, &animation.to) {
+            (Some(chart), Value::String(src)) if animation.property == "src" => (chart, src),
+            _ => {
+                loaded.push(animation);
+                continue;
+            }
+        };
+        let Some(data) = load(src, string_property(&chart.properties, "column")) else {
+            continue;
+        };
+        let (values, labels) = shown.entry(&chart.name).or_insert_with(|| {
+            (
+                list_numbers(property(&chart.properties, "values")),
+                list_strings(property(&chart.properties, "labels")),
+            )
+        });
+        let rows = values.len().max(data.values.len());
+        if values.len() < rows {
+            values.resize(rows, 0.0);
+            loaded.push(Animation {
+                property: "values".to_string(),
+                to: numbers(values),
+                end: None,
+                easing: None,
+                ..animation.clone()
+            });
+        }
+        let mut new_values = data.values;
+        new_values.resize(rows, 0.0);
+        if !data.labels.is_empty() {
+            labels.resize(rows, String::new());
+            for (label, new) in labels.iter_mut().zip(data.labels) {
+                *label = new;
+            }
+            loaded.push(Animation {
+                property: "labels".to_string(),
+                to: strings(labels),
+                ..animation.clone()
+            });
+        }
+        *values = new_values;
+        loaded.push(Animation {
+            property: "values".to_string(),
+            to: numbers(values),
+            ..animation
+        });
+    }
+    *animations = loaded;
+}
+
+/// Gives bar and line charts whose `values` are animated a fixed `y_range`
+/// that fits every dataset they show, so the axis holds still while the data
+/// changes. Charts with their own `y_range` are left alone.
+pub fn fit_value_ranges(items: &mut [Object], animations: &[Animation]) {
+    for object in items
+        .iter_mut()
+        .filter(|o| o.r#type == "bar_chart" || o.r#type == "line_chart")
+    {
+        if object.properties.iter().any(|p| p.name == "y_range") {
+            continue;
+        }
+        let animated: Vec<f64> = animations
+            .iter()
+            .filter(|a| a.target_object == object.name && a.property == "values")
+            .flat_map(|a| list_numbers(Some(&a.to)))
+            .collect();
+        if animated.is_empty() {
+            continue;
+        }
+        let mut all = list_numbers(property(&object.properties, "values"));
+        all.extend(animated);
+        let (min, max) = fit_range(&all);
+        object.properties.push(Property {
+            name: "y_range".to_string(),
+            value: Value::Tuple(min, max),
+        });
+    }
+}
+
+fn load(src: &str, column: Option<&str>) -> Option<Data> {
+    let path = assets::resolve(src);
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) => {
+            eprintln!(
+                "Warning: Failed to read chart data '{}': {}",
+                path.display(),
+                e
+            );
+            return None;
+        }
+    };
+    let is_json = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    let data = if is_json {
+        parse_json(&text, column)
+    } else {
+        parse_csv(&text, column)
+    };
+    match data {
+        Ok(data) => Some(data),
+        Err(e) => {
+            eprintln!(
+                "Warning: Failed to load chart data '{}': {}",
+                path.display(),
+                e
+            );
+            None
+        }
+    }
+}
+
+// Reads one column of values from CSV text. A first row without any numbers
+// is a header naming the columns. Values come from `column`, or else from the
+// second column, and labels from the first.
+fn parse_csv(text: &str, column: Option<&str>) -> Result<Data, String> {
+    let mut rows: Vec<Vec<String>> = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(split_csv_line)
+        .collect();
+    if rows.is_empty() {
+        return Err("the file is empty".to_string());
+    }
+    let header = if rows[0].iter().all(|field| field.parse::<f64>().is_err()) {
+        Some(rows.remove(0))
+    } else {
+        None
+    };
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let value_column = match (column, &header) {
+        (Some(name), Some(header)) => header
+            .iter()
+            .position(|h| h == name)
+            .ok_or_else(|| format!("no column named '{}'", name))?,
+        (Some(name), None) => return Err(format!("no header row to find column '{}'", name)),
+        (None, _) if width > 1 => 1,
+        (None, _) => 0,
+    };
+    let label_column = (value_column != 0).then_some(0);
+
+    let mut data = Data {
+        values: Vec::new(),
+        labels: Vec::new(),
+    };
+    for (i, row) in rows.iter().enumerate() {
+        let field = row.get(value_column).map_or("", String::as_str);
+        let value = field.parse().map_err(|_| {
+            format!(
+                "row {}: '{}' is not a number",
+                i + 1 + header.is_some() as usize,
+                field
+            )
+        })?;
+        data.values.push(value);
+        if let Some(label_column) = label_column {
+            data.labels
+                .push(row.get(label_column).cloned().unwrap_or_default());
+        }
+    }
+    Ok(data)
+}
+
+// Splits a CSV line into trimmed fields, honouring double-quoted fields with
+// `""` for a literal quote.
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field).trim().to_string()),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field.trim().to_string());
+    fields
+}
+
+// Reads values from JSON given as an array of numbers, an array of records or
+// an object of columns. Records and columns take their values from `column`,
+// or else from a `value`/`values` field, and their labels from a
+// `label`/`labels` field or the first text field.
+fn parse_json(text: &str, column: Option<&str>) -> Result<Data, String> {
+    use serde_json::Value as Json;
+
+    let json: Json = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    let number = |value: &Json| {
+        value
+            .as_f64()
+            .ok_or_else(|| format!("'{}' is not a number", value))
+    };
+    let text = |value: &Json| match value {
+        Json::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    let mut data = Data {
+        values: Vec::new(),
+        labels: Vec::new(),
+    };
+    match json {
+        Json::Array(items) if items.iter().all(Json::is_number) => {
+            data.values = items.iter().map(number).collect::<Result<_, _>>()?;
+        }
+        Json::Array(records) => {
+            for record in &records {
+                let Json::Object(fields) = record else {
+                    return Err(format!("expected a record, found '{}'", record));
+                };
+                let key = column.unwrap_or("value");
+                let value = fields
+                    .get(key)
+                    .ok_or_else(|| format!("record without a '{}' field", key))?;
+                data.values.push(number(value)?);
+                let label = fields
+                    .get("label")
+                    .or_else(|| fields.values().find(|v| v.is_string()));
+                if let Some(label) = label {
+                    data.labels.push(text(label));
+                }
+            }
+            if data.labels.len() != data.values.len() {
+                data.labels.clear();
+            }
+        }
+        Json::Object(columns) => {
+            let key = column.unwrap_or("values");
+            let Some(Json::Array(values)) = columns.get(key) else {
+                return Err(format!("no '{}' array", key));
+            };
+            data.values = values.iter().map(number).collect::<Result<_, _>>()?;
+            let labels = columns.get("labels").or_else(|| columns.get("label"));
+            if let Some(Json::Array(labels)) = labels {
+                data.labels = labels.iter().map(text).collect();
+            }
+        }
+        other => return Err(format!("expected an array or an object, found '{}'", other)),
+    }
+    Ok(data)
+}
+
+/// The filled shapes that draw a chart of the given type.
+pub fn shapes(chart_type: &str, properties: &[Property]) -> Vec<Shape> {
+    let chart = Chart::new(properties);
+    match chart_type {
+        "pie_chart" => chart.pie(),
+        "line_chart" => chart.line(),
+        _ => chart.bars(),
+    }
+}
+
+struct Chart<'a> {
+    properties: &'a [Property],
+    values: Vec<f64>,
+    labels: Vec<String>,
+    center: (f64, f64),
+    font_size: f64,
+}
+
+impl<'a> Chart<'a> {
+    fn new(properties: &'a [Property]) -> Self {
+        let labels = match property(properties, "labels") {
+            Some(Value::List(labels)) => labels
+                .iter()
+                .map(|label| match label {
+                    Value::String(s) => s.clone(),
+                    Value::Number(n) => axes::format_number(*n),
+                    _ => String::new(),
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        let center = match property(properties, "position") {
+            Some(Value::Tuple(x, y)) => (*x, *y),
+            _ => (0.0, 0.0),
+        };
+        let mut chart = Chart {
+            properties,
+            values: list_numbers(property(properties, "values")),
+            labels,
+            center,
+            font_size: 0.0,
+        };
+        chart.font_size = chart.number("font_size").unwrap_or(DEFAULT_FONT_SIZE);
+        chart
+    }
+
+    fn number(&self, name: &str) -> Option<f64> {
+        match property(self.properties, name) {
+            Some(Value::Number(n)) => Some(*n),
+            _ => None,
+        }
+    }
+
+    fn color(&self, name: &str, default: &str) -> [u8; 4] {
+        match property(self.properties, name) {
+            Some(Value::Color(hex)) => color::parse(hex),
+            _ => None,
+        }
+        .or_else(|| color::parse(default))
+        .unwrap_or([255, 255, 255, 255])
+    }
+
+    // The color of the i-th bar or slice: from `colors` in turn, else the
+    // chart's `color`, else the palette.
+    fn item_color(&self, i: usize) -> [u8; 4] {
+        if let Some(Value::List(colors)) = property(self.properties, "colors") {
+            if let Some(Value::Color(hex)) = colors.get(i % colors.len().max(1)) {
+                if let Some(color) = color::parse(hex) {
+                    return color;
+                }
+            }
+        }
+        self.color("color", PALETTE[i % PALETTE.len()])
+    }
+
+    fn bars(&self) -> Vec<Shape> {
+        let plot = self.plot_area();
+        let mut shapes = Vec::new();
+        self.push_grid(&mut shapes, &plot);
+        let slot = plot.width / self.values.len().max(1) as f64;
+        let bar_width = slot * self.number("bar_width").unwrap_or(0.7).clamp(0.0, 1.0);
+        for (i, &value) in self.values.iter().enumerate() {
+            let x = plot.left + slot * (i as f64 + 0.5);
+            let (y0, y1) = (plot.to_y(0.0), plot.to_y(value));
+            let (top, bottom) = (y0.min(y1), y0.max(y1));
+            if bottom - top > 0.0 {
+                shapes.push(Shape {
+                    data: rect(x - bar_width / 2.0, top, bar_width, bottom - top),
+                    color: self.item_color(i),
+                });
+            }
+        }
+        self.push_axes(&mut shapes, &plot, slot);
+        shapes
+    }
+
+    fn line(&self) -> Vec<Shape> {
+        let plot = self.plot_area();
+        let mut shapes = Vec::new();
+        self.push_axes(
+            &mut shapes,
+            &plot,
+            plot.width / self.values.len().max(1) as f64,
+        );
+
+        let color = self.color("color", PALETTE[0]);
+        let stroke_width = self.number("stroke_width").unwrap_or(4.0);
+        let dot_radius = self.number("dot_radius").unwrap_or(stroke_width * 1.5);
+        let slot = plot.width / self.values.len().max(1) as f64;
+        let points: Vec<(f64, f64)> = self
+            .values
+            .iter()
+            .enumerate()
+            .map(|(i, &v)| (plot.left + slot * (i as f64 + 0.5), plot.to_y(v)))
+            .collect();
+        for pair in points.windows(2) {
+            shapes.push(line(pair[0], pair[1], stroke_width, color));
+        }
+        // Dots on every point, which also round off the joins between segments.
+        let join_radius = dot_radius.max(stroke_width / 2.0);
+        for &point in &points {
+            if join_radius > 0.0 {
+                shapes.push(Shape {
+                    data: path::polygon(&outlines::regular_polygon(point, join_radius, 32)),
+                    color,
+                });
+            }
+        }
+        shapes
+    }
+
+    fn pie(&self) -> Vec<Shape> {
+        let mut shapes = Vec::new();
+        let radius = self.number("radius").unwrap_or(DEFAULT_RADIUS);
+        let inner_radius = self.number("inner_radius").unwrap_or(0.0);
+        let total: f64 = self.values.iter().map(|v| v.max(0.0)).sum();
+        if total <= 0.0 {
+            return shapes;
+        }
+        let label_color = self.color("axis_color", "#FFFFFF");
+        // Slices run clockwise from the top.
+        let mut angle = -90.0;
+        for (i, value) in self.values.iter().enumerate() {
+            let sweep = value.max(0.0) / total * 360.0;
+            if sweep <= 0.0 {
+                continue;
+            }
+            shapes.push(Shape {
+                data: path::polygon(&outlines::sector(
+                    self.center,
+                    radius,
+                    inner_radius,
+                    angle,
+                    angle + sweep,
+                )),
+                color: self.item_color(i),
+            });
+            if let Some(label) = self.labels.get(i).filter(|_| self.font_size > 0.0) {
+                let middle = (angle + sweep / 2.0).to_radians();
+                let distance = radius + self.font_size * 0.6;
+                let anchor = (
+                    self.center.0 + distance * middle.cos(),
+                    self.center.1 + distance * middle.sin(),
+                );
+                // Labels on the right start at the anchor and those on the left end there.
+                let align = 0.5 - middle.cos().clamp(-0.5, 0.5);
+                let top = anchor.1 - self.font_size * (0.35 - 0.35 * middle.sin());
+                push_text(
+                    &mut shapes,
+                    label,
+                    (anchor.0, top),
+                    align,
+                    self.font_size,
+                    label_color,
+                );
+            }
+            angle += sweep;
+        }
+        shapes
+    }
+
+    fn ticks(&self, plot: &Plot) -> Vec<f64> {
+        let (min, max) = plot.y_range;
+        let step = self
+            .number("y_step")
+            .filter(|step| *step > 0.0)
+            .unwrap_or_else(|| axes::nice_step((max - min) / 5.0));
+        axes::ticks(plot.y_range, step)
+    }
+
+    fn plot_area(&self) -> Plot {
+        let width = self.number("width").unwrap_or(DEFAULT_WIDTH);
+        let height = self.number("height").unwrap_or(DEFAULT_HEIGHT);
+        let y_range = match property(self.properties, "y_range") {
+            Some(Value::Tuple(min, max)) if max > min => (*min, *max),
+            _ => fit_range(&self.values),
+        };
+        Plot {
+            left: self.center.0 - width / 2.0,
+            bottom: self.center.1 + height / 2.0,
+            width,
+            height,
+            y_range,
+        }
+    }
+
+    // Horizontal lines at the value ticks, if the chart sets a `grid_color`.
+    fn push_grid(&self, shapes: &mut Vec<Shape>, plot: &Plot) {
+        let grid = match property(self.properties, "grid_color") {
+            Some(Value::Color(hex)) => color::parse(hex),
+            _ => None,
+        };
+        if let Some(grid) = grid {
+            for tick in self.ticks(plot) {
+                let y = plot.to_y(tick);
+                shapes.push(line((plot.left, y), (plot.left + plot.width, y), 1.0, grid));
+            }
+        }
+    }
+
+    // The value axis with its ticks and numbers, the baseline, and a label
+    // under each slot.
+    fn push_axes(&self, shapes: &mut Vec<Shape>, plot: &Plot, slot: f64) {
+        let color = self.color("axis_color", "#FFFFFF");
+        let (min, max) = plot.y_range;
+        let right = plot.left + plot.width;
+        let top = plot.bottom - plot.height;
+        let ticks = self.ticks(plot);
+
+        shapes.push(line(
+            (plot.left, top),
+            (plot.left, plot.bottom),
+            AXIS_WIDTH,
+            color,
+        ));
+        let base = plot.to_y(0.0_f64.clamp(min, max));
+        shapes.push(line((plot.left, base), (right, base), AXIS_WIDTH, color));
+        for &tick in &ticks {
+            let y = plot.to_y(tick);
+            shapes.push(line(
+                (plot.left - TICK_SIZE, y),
+                (plot.left, y),
+                AXIS_WIDTH,
+                color,
+            ));
+        }
+        if self.font_size <= 0.0 {
+            return;
+        }
+        for &tick in &ticks {
+            let top = plot.to_y(tick) - self.font_size * 0.35;
+            let anchor = (plot.left - TICK_SIZE * 2.0, top);
+            push_text(
+                shapes,
+                &axes::format_number(tick),
+                anchor,
+                1.0,
+                self.font_size,
+                color,
+            );
+        }
+        for (i, label) in self.labels.iter().enumerate() {
+            let anchor = (plot.left + slot * (i as f64 + 0.5), plot.bottom + TICK_SIZE);
+            push_text(shapes, label, anchor, 0.5, self.font_size, color);
+        }
+    }
+}
+
+// The rectangle that bar and line charts plot their values in.
+struct Plot {
+    left: f64,
+    bottom: f64,
+    width: f64,
+    height: f64,
+    y_range: (f64, f64),
+}
+
+impl Plot {
+    fn to_y(&self, value: f64) -> f64 {
+        let (min, max) = self.y_range;
+        self.bottom - (value - min) / (max - min) * self.height
+    }
+}
+
+// A range from a round number at or below both zero and the smallest value to
+// one at or above both zero and the largest.
+fn fit_range(values: &[f64]) -> (f64, f64) {
+    let min = values.iter().copied().fold(0.0, f64::min);
+    let max = values.iter().copied().fold(0.0, f64::max);
+    if max - min <= 0.0 {
+        return (min, min + 1.0);
+    }
+    let step = axes::nice_step((max - min) / 5.0);
+    ((min / step).floor() * step, (max / step).ceil() * step)
+}
+
+fn push_text(
+    shapes: &mut Vec<Shape>,
+    text: &str,
+    top: (f64, f64),
+    align: f64,
+    font_size: f64,
+    color: [u8; 4],
+) {
+    shapes.extend(
+        font::label(text, top, align, font_size)
+            .into_iter()
+            .map(|data| Shape { data, color }),
+    );
+}
+
+fn rect(left: f64, top: f64, width: f64, height: f64) -> PathData {
+    path::polygon(&[
+        (left, top),
+        (left + width, top),
+        (left + width, top + height),
+        (left, top + height),
+    ])
+}
+
+// A straight line of the given width as a filled quad.
+fn line(from: (f64, f64), to: (f64, f64), width: f64, color: [u8; 4]) -> Shape {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let length = dx.hypot(dy).max(1e-9);
+    let (nx, ny) = (-dy / length * width / 2.0, dx / length * width / 2.0);
+    Shape {
+        data: path::polygon(&[
+            (from.0 + nx, from.1 + ny),
+            (to.0 + nx, to.1 + ny),
+            (to.0 - nx, to.1 - ny),
+            (from.0 - nx, from.1 - ny),
+        ]),
+        color,
+    }
+}
+
+fn property<'a>(properties: &'a [Property], name: &str) -> Option<&'a Value> {
+    properties.iter().find(|p| p.name == name).map(|p| &p.value)
+}
+
+fn string_property<'a>(properties: &'a [Property], name: &str) -> Option<&'a str> {
+    match property(properties, name) {
+        Some(Value::String(s)) => Some(s),
+        _ => None,
+    }
+}
+
+fn list_numbers(value: Option<&Value>) -> Vec<f64> {
+    match value {
+        Some(Value::List(items)) => items
+            .iter()
+            .map(|item| match item {
+                Value::Number(n) => *n,
+                _ => 0.0,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn list_strings(value: Option<&Value>) -> Vec<String> {
+    match value {
+        Some(Value::List(items)) => items
+            .iter()
+            .map(|item| match item {
+                Value::String(s) => s.clone(),
+                _ => String::new(),
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn numbers(values: &[f64]) -> Value {
+    Value::List(values.iter().map(|&n| Value::Number(n)).collect())
+}
+
+fn strings(labels: &[String]) -> Value {
+    Value::List(labels.iter().map(|s| Value::String(s.clone())).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn properties(values: &[f64], extra: Vec<(&str, Value)>) -> Vec<Property> {
+        let mut properties = vec![
+            ("position", Value::Tuple(500.0, 300.0)),
+            ("width", Value::Number(400.0)),
+            ("height", Value::Number(200.0)),
+            ("values", numbers(values)),
+            ("font_size", Value::Number(0.0)),
+        ];
+        properties.extend(extra);
+        properties
+            .into_iter()
+            .map(|(name, value)| Property {
+                name: name.to_string(),
+                value,
+            })
+            .collect()
+    }
+
+    fn bounds(data: &PathData) -> (f64, f64, f64, f64) {
+        let points: Vec<(f64, f64)> = path::flatten(data)
+            .into_iter()
+            .flat_map(|s| s.points)
+            .collect();
+        points.iter().fold(
+            (f64::MAX, f64::MAX, f64::MIN, f64::MIN),
+            |(x0, y0, x1, y1), &(x, y)| (x0.min(x), y0.min(y), x1.max(x), y1.max(y)),
+        )
+    }
+
+    #[test]
+    fn test_csv_with_header_and_quotes() {
+        let csv = "quarter,revenue,costs\n\"Q1, early\",120,80\nQ2,150.5,90\n";
+        let data = parse_csv(csv, None).unwrap();
+        assert_eq!(data.values, vec![120.0, 150.5]);
+        assert_eq!(data.labels, vec!["Q1, early", "Q2"]);
+        assert_eq!(
+            parse_csv(csv, Some("costs")).unwrap().values,
+            vec![80.0, 90.0]
+        );
+        assert!(parse_csv(csv, Some("profit")).is_err());
+        assert_eq!(parse_csv("3\n4\n", None).unwrap().values, vec![3.0, 4.0]);
+        assert!(parse_csv("a,b\nQ1,x\n", None).is_err());
+    }
+
+    #[test]
+    fn test_json_shapes() {
+        assert_eq!(parse_json("[1, 2.5]", None).unwrap().values, vec![1.0, 2.5]);
+        let records = r#"[{"label": "Q1", "value": 3}, {"label": "Q2", "value": 4}]"#;
+        let data = parse_json(records, None).unwrap();
+        assert_eq!(data.values, vec![3.0, 4.0]);
+        assert_eq!(data.labels, vec!["Q1", "Q2"]);
+        let columns = r#"{"labels": ["a", "b"], "profit": [5, 6]}"#;
+        let data = parse_json(columns, Some("profit")).unwrap();
+        assert_eq!(data.values, vec![5.0, 6.0]);
+        assert_eq!(data.labels, vec!["a", "b"]);
+        assert!(parse_json(columns, None).is_err());
+    }
+
+    #[test]
+    fn test_fit_range() {
+        assert_eq!(fit_range(&[3.0, 7.0, 9.5]), (0.0, 10.0));
+        assert_eq!(fit_range(&[-12.0, 30.0]), (-20.0, 30.0));
+        assert_eq!(fit_range(&[]), (0.0, 1.0));
+    }
+
+    #[test]
+    fn test_bars_grow_from_baseline() {
+        let shapes = shapes(
+            "bar_chart",
+            &properties(&[5.0, 10.0], vec![("y_range", Value::Tuple(0.0, 10.0))]),
+        );
+        // The plot spans x 300..700 and y 200..400, with a slot of 200 per bar.
+        let (x0, y0, x1, y1) = bounds(&shapes[0].data);
+        assert!((x0 - 330.0).abs() < 1e-9 && (x1 - 470.0).abs() < 1e-9);
+        assert!((y0 - 300.0).abs() < 1e-9 && (y1 - 400.0).abs() < 1e-9);
+        assert!((bounds(&shapes[1].data).1 - 200.0).abs() < 1e-9);
+        assert_eq!(shapes[0].color, color::parse(PALETTE[0]).unwrap());
+        assert_eq!(shapes[1].color, color::parse(PALETTE[1]).unwrap());
+    }
+
+    #[test]
+    fn test_pie_slices_follow_values() {
+        let mut props = properties(&[1.0, 3.0], vec![("radius", Value::Number(100.0))]);
+        props.push(Property {
+            name: "colors".to_string(),
+            value: Value::List(vec![Value::Color("#FF0000".to_string())]),
+        });
+        let shapes = shapes("pie_chart", &props);
+        assert_eq!(shapes.len(), 2);
+        // The first quarter runs clockwise from the top to the right.
+        let (x0, y0, x1, y1) = bounds(&shapes[0].data);
+        assert!((x0 - 500.0).abs() < 1e-9 && (x1 - 600.0).abs() < 1e-9);
+        assert!((y0 - 200.0).abs() < 1e-9 && (y1 - 300.0).abs() < 1e-9);
+        assert_eq!(shapes[1].color, [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_src_animation_becomes_values() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("q2.csv");
+        fs::write(&file, "quarter,revenue\nQ1,40\nQ2,60\n").unwrap();
+
+        let mut items = vec![Object {
+            r#type: "bar_chart".to_string(),
+            name: "sales".to_string(),
+            properties: properties(&[10.0, 20.0], vec![]),
+        }];
+        let mut animations = vec![Animation {
+            start: std::time::Duration::from_secs(1),
+            end: Some(std::time::Duration::from_secs(2)),
+            target_object: "sales".to_string(),
+            property: "src".to_string(),
+            to: Value::String(file.to_string_lossy().into_owned()),
+            easing: None,
+        }];
+        load_animation_data(&items, &mut animations);
+        assert_eq!(animations.len(), 2);
+        assert_eq!(animations[0].property, "labels");
+        assert_eq!(animations[1].property, "values");
+        assert_eq!(animations[1].to, numbers(&[40.0, 60.0]));
+
+        fit_value_ranges(&mut items, &animations);
+        assert_eq!(
+            property(&items[0].properties, "y_range"),
+            Some(&Value::Tuple(0.0, 60.0))
+        );
+    }
+
+    #[test]
+    fn test_src_animation_pads_datasets_of_other_lengths() {
+        let dir = tempfile::tempdir().unwrap();
+        let longer = dir.path().join("d2.csv");
+        fs::write(&longer, "name,n\na,4\nb,5\nc,6\nd,7\n").unwrap();
+        let shorter = dir.path().join("d3.csv");
+        fs::write(&shorter, "n\n8\n").unwrap();
+
+        let items = vec![Object {
+            r#type: "bar_chart".to_string(),
+            name: "c".to_string(),
+            properties: properties(&[1.0, 2.0, 3.0], vec![]),
+        }];
+        let switch = |file: &std::path::Path, start: u64| Animation {
+            start: std::time::Duration::from_secs(start),
+            end: Some(std::time::Duration::from_secs(start + 2)),
+            target_object: "c".to_string(),
+            property: "src".to_string(),
+            to: Value::String(file.to_string_lossy().into_owned()),
+            easing: None,
+        };
+        let mut animations = vec![switch(&longer, 0), switch(&shorter, 4)];
+        load_animation_data(&items, &mut animations);
+        let changes: Vec<_> = animations
+            .iter()
+            .map(|a| {
+                (
+                    a.start.as_secs(),
+                    a.end.is_some(),
+                    a.property.as_str(),
+                    a.to.clone(),
+                )
+            })
+            .collect();
+        let labels =
+            |labels: &[&str]| strings(&labels.iter().map(|l| l.to_string()).collect::<Vec<_>>());
+        assert_eq!(
+            changes,
+            [
+                // The new fourth bar grows from nothing.
+                (0, false, "values", numbers(&[1.0, 2.0, 3.0, 0.0])),
+                (0, true, "labels", labels(&["a", "b", "c", "d"])),
+                (0, true, "values", numbers(&[4.0, 5.0, 6.0, 7.0])),
+                // Bars without a row in the shorter file shrink away.
+                (4, true, "values", numbers(&[8.0, 0.0, 0.0, 0.0])),
+            ]
+        );
+    }
+}
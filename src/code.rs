@@ -0,0 +1,986 @@
+//! `code` objects: syntax highlighted source code drawn with the bundled
+//! monospace font.
+//!
+//! Both renderers draw a code block from the list of filled shapes returned by
+//! [`shapes`], so highlighting, typing and diff animations look the same on
+//! the CPU and the GPU.
+
+use crate::assets;
+use crate::ast::{Object, Property, Value};
+use crate::font::{self, Font};
+use crate::path;
+use crate::shapes::{self as outlines, Shape};
+use std::fs;
+
+const DEFAULT_FONT_SIZE: f64 = 24.0;
+const TAB: &str = "    ";
+
+/// Reads the `src` file of every `code` object into its `source` property.
+pub fn load_code_sources(items: &mut [Object]) {
+    for object in items.iter_mut().filter(|o| o.r#type == "code") {
+        let src = match object.properties.iter().find(|p| p.name == "src") {
+            Some(Property {
+                value: Value::String(src),
+                ..
+            }) => src.clone(),
+            _ => continue,
+        };
+        let path = assets::resolve(&src);
+        let source = match fs::read_to_string(&path) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!(
+                    "Warning: Failed to read code file '{}': {}",
+                    path.display(),
+                    e
+                );
+                continue;
+            }
+        };
+        object.properties.retain(|p| p.name != "source");
+        object.properties.push(Property {
+            name: "source".to_string(),
+            value: Value::String(source),
+        });
+    }
+    for object in items.iter().filter(|o| o.r#type == "code") {
+        if let Some(Value::String(name)) = object
+            .properties
+            .iter()
+            .find(|p| p.name == "language")
+            .map(|p| &p.value)
+        {
+            if language(name).is_none() {
+                eprintln!(
+                    "Warning: Unknown language '{}' for code object '{}', it will not be highlighted",
+                    name, object.name
+                );
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Plain,
+    Keyword,
+    Type,
+    Function,
+    String,
+    Number,
+    Comment,
+    Punctuation,
+}
+
+struct Language {
+    keywords: &'static [&'static str],
+    types: &'static [&'static str],
+    line_comment: Option<&'static str>,
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static str,
+    /// Capitalized identifiers name types, as in most C-like languages.
+    capitalized_types: bool,
+    /// `'` starts a lifetime unless it encloses a single character, as in Rust.
+    lifetimes: bool,
+}
+
+const PLAIN: Language = Language {
+    keywords: &[],
+    types: &[],
+    line_comment: None,
+    block_comment: None,
+    quotes: "",
+    capitalized_types: false,
+    lifetimes: false,
+};
+
+const C_KEYWORDS: &[&str] = &[
+    "auto",
+    "break",
+    "case",
+    "const",
+    "continue",
+    "default",
+    "do",
+    "else",
+    "enum",
+    "extern",
+    "for",
+    "goto",
+    "if",
+    "inline",
+    "register",
+    "return",
+    "sizeof",
+    "static",
+    "struct",
+    "switch",
+    "typedef",
+    "union",
+    "volatile",
+    "while",
+    "#include",
+    "#define",
+    "#if",
+    "#ifdef",
+    "#ifndef",
+    "#endif",
+    // C++
+    "class",
+    "delete",
+    "false",
+    "namespace",
+    "new",
+    "nullptr",
+    "private",
+    "protected",
+    "public",
+    "template",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "catch",
+    "typename",
+    "using",
+    "virtual",
+    "override",
+];
+
+const C_TYPES: &[&str] = &[
+    "void", "char", "short", "int", "long", "float", "double", "signed", "unsigned", "bool",
+    "size_t", "int8_t", "int16_t", "int32_t", "int64_t", "uint8_t", "uint16_t", "uint32_t",
+    "uint64_t", "std", "string", "vector",
+];
+
+fn language(name: &str) -> Option<Language> {
+    Some(match name.to_ascii_lowercase().as_str() {
+        "rust" | "rs" => Language {
+            keywords: &[
+                "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else",
+                "enum", "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match",
+                "mod", "move", "mut", "pub", "ref", "return", "self", "static", "struct", "super",
+                "trait", "true", "type", "unsafe", "use", "where", "while",
+            ],
+            types: &[
+                "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64", "u128",
+                "usize", "f32", "f64", "bool", "char", "str", "Self",
+            ],
+            line_comment: Some("//"),
+            block_comment: Some(("/*", "*/")),
+            quotes: "\"'",
+            capitalized_types: true,
+            lifetimes: true,
+        },
+        "python" | "py" => Language {
+            keywords: &[
+                "and", "as", "assert", "async", "await", "break", "class", "continue", "def",
+                "del", "elif", "else", "except", "False", "finally", "for", "from", "global", "if",
+                "import", "in", "is", "lambda", "None", "nonlocal", "not", "or", "pass", "raise",
+                "return", "True", "try", "while", "with", "yield", "self",
+            ],
+            types: &[
+                "int", "float", "str", "list", "dict", "set", "tuple", "bool", "bytes", "object",
+            ],
+            line_comment: Some("#"),
+            block_comment: None,
+            quotes: "\"'",
+            capitalized_types: true,
+            lifetimes: false,
+        },
+        "javascript" | "js" | "typescript" | "ts" => Language {
+            keywords: &[
+                "async",
+                "await",
+                "break",
+                "case",
+                "catch",
+                "class",
+                "const",
+                "continue",
+                "debugger",
+                "default",
+                "delete",
+                "do",
+                "else",
+                "enum",
+                "export",
+                "extends",
+                "false",
+                "finally",
+                "for",
+                "function",
+                "if",
+                "implements",
+                "import",
+                "in",
+                "instanceof",
+                "interface",
+                "let",
+                "new",
+                "null",
+                "of",
+                "return",
+                "super",
+                "switch",
+                "this",
+                "throw",
+                "true",
+                "try",
+                "type",
+                "typeof",
+                "undefined",
+                "var",
+                "void",
+                "while",
+                "yield",
+            ],
+            types: &[
+                "number", "string", "boolean", "any", "unknown", "never", "object",
+            ],
+            line_comment: Some("//"),
+            block_comment: Some(("/*", "*/")),
+            quotes: "\"'`",
+            capitalized_types: true,
+            lifetimes: false,
+        },
+        "c" | "cpp" | "c++" | "h" | "hpp" => Language {
+            keywords: C_KEYWORDS,
+            types: C_TYPES,
+            line_comment: Some("//"),
+            block_comment: Some(("/*", "*/")),
+            quotes: "\"'",
+            capitalized_types: true,
+            lifetimes: false,
+        },
+        "go" => Language {
+            keywords: &[
+                "break",
+                "case",
+                "chan",
+                "const",
+                "continue",
+                "default",
+                "defer",
+                "else",
+                "fallthrough",
+                "for",
+                "func",
+                "go",
+                "goto",
+                "if",
+                "import",
+                "interface",
+                "map",
+                "package",
+                "range",
+                "return",
+                "select",
+                "struct",
+                "switch",
+                "type",
+                "var",
+                "true",
+                "false",
+                "nil",
+            ],
+            types: &[
+                "bool", "byte", "error", "float32", "float64", "int", "int8", "int16", "int32",
+                "int64", "rune", "string", "uint", "uint8", "uint16", "uint32", "uint64",
+            ],
+            line_comment: Some("//"),
+            block_comment: Some(("/*", "*/")),
+            quotes: "\"'`",
+            capitalized_types: false,
+            lifetimes: false,
+        },
+        "java" => Language {
+            keywords: &[
+                "abstract",
+                "break",
+                "case",
+                "catch",
+                "class",
+                "continue",
+                "default",
+                "do",
+                "else",
+                "enum",
+                "extends",
+                "false",
+                "final",
+                "finally",
+                "for",
+                "if",
+                "implements",
+                "import",
+                "instanceof",
+                "interface",
+                "new",
+                "null",
+                "package",
+                "private",
+                "protected",
+                "public",
+                "return",
+                "static",
+                "super",
+                "switch",
+                "this",
+                "throw",
+                "throws",
+                "true",
+                "try",
+                "var",
+                "while",
+            ],
+            types: &[
+                "boolean", "byte", "char", "double", "float", "int", "long", "short", "void",
+            ],
+            line_comment: Some("//"),
+            block_comment: Some(("/*", "*/")),
+            quotes: "\"'",
+            capitalized_types: true,
+            lifetimes: false,
+        },
+        "json" => Language {
+            keywords: &["true", "false", "null"],
+            quotes: "\"",
+            ..PLAIN
+        },
+        "bash" | "sh" | "shell" => Language {
+            keywords: &[
+                "if", "then", "else", "elif", "fi", "for", "while", "until", "do", "done", "case",
+                "esac", "function", "in", "return", "export", "local", "echo", "cd", "exit",
+            ],
+            line_comment: Some("#"),
+            quotes: "\"'",
+            ..PLAIN
+        },
+        "text" | "plain" => PLAIN,
+        _ => return None,
+    })
+}
+
+/// Splits source code into highlighted tokens, one list per line.
+fn tokenize(source: &str, language: &Language) -> Vec<Vec<(Kind, String)>> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut lines = vec![Vec::new()];
+    let mut i = 0;
+    while i < chars.len() {
+        let rest = &chars[i..];
+        let (kind, len) = next_token(rest, language);
+        let text: String = rest[..len].iter().collect();
+        // Block comments and some strings span several lines.
+        for (n, piece) in text.split('\n').enumerate() {
+            if n > 0 {
+                lines.push(Vec::new());
+            }
+            if !piece.is_empty() {
+                lines.last_mut().unwrap().push((kind, piece.to_string()));
+            }
+        }
+        i += len;
+    }
+    lines
+}
+
+// The kind and length in characters of the token at the start of `rest`.
+fn next_token(rest: &[char], language: &Language) -> (Kind, usize) {
+    let starts_with = |pattern: &str| {
+        let pattern: Vec<char> = pattern.chars().collect();
+        rest.starts_with(&pattern)
+    };
+    let run = |from: usize, pred: &dyn Fn(char) -> bool| {
+        rest[from..]
+            .iter()
+            .position(|&c| !pred(c))
+            .map_or(rest.len(), |p| p + from)
+    };
+    let first = rest[0];
+
+    if language.line_comment.is_some_and(starts_with) {
+        return (Kind::Comment, run(0, &|c| c != '\n'));
+    }
+    if let Some((open, close)) = language.block_comment.filter(|(open, _)| starts_with(open)) {
+        let close: Vec<char> = close.chars().collect();
+        let start = open.chars().count();
+        let end = (start..rest.len())
+            .find(|&j| rest[j..].starts_with(&close))
+            .map_or(rest.len(), |j| j + close.len());
+        return (Kind::Comment, end);
+    }
+    if language.quotes.contains(first) {
+        let is_lifetime = language.lifetimes
+            && first == '\''
+            && rest.get(1) != Some(&'\\')
+            && rest.get(2) != Some(&'\'');
+        if !is_lifetime {
+            return (Kind::String, string_length(rest));
+        }
+        return (Kind::Type, run(1, &|c| c.is_alphanumeric() || c == '_'));
+    }
+    if first.is_ascii_digit() {
+        return (
+            Kind::Number,
+            run(0, &|c| c.is_ascii_alphanumeric() || c == '.' || c == '_'),
+        );
+    }
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    if is_word(first) || (first == '#' && rest.get(1).is_some_and(|c| c.is_alphabetic())) {
+        let len = run(1, &is_word);
+        let word: String = rest[..len].iter().collect();
+        let kind = if language.keywords.contains(&word.as_str()) {
+            Kind::Keyword
+        } else if language.types.contains(&word.as_str())
+            || (language.capitalized_types && first.is_uppercase())
+        {
+            Kind::Type
+        } else if matches!(rest.get(len), Some('('))
+            || (language.lifetimes && rest.get(len) == Some(&'!'))
+        {
+            Kind::Function
+        } else {
+            Kind::Plain
+        };
+        return (kind, len);
+    }
+    if first.is_whitespace() {
+        return (Kind::Plain, run(0, &|c| c.is_whitespace()));
+    }
+    (Kind::Punctuation, 1)
+}
+
+// Length of the string literal at the start of `rest`, including its quotes.
+fn string_length(rest: &[char]) -> usize {
+    let quote = rest[0];
+    let mut j = 1;
+    while j < rest.len() {
+        match rest[j] {
+            '\\' => j += 2,
+            c if c == quote => return j + 1,
+            // Only template literals continue past the end of a line.
+            '\n' if quote != '`' => return j,
+            _ => j += 1,
+        }
+    }
+    rest.len()
+}
+
+struct Theme {
+    background: u32,
+    foreground: u32,
+    line_number: u32,
+    highlight: u32,
+    cursor: u32,
+    inserted: u32,
+    removed: u32,
+    keyword: u32,
+    type_name: u32,
+    function: u32,
+    string: u32,
+    number: u32,
+    comment: u32,
+}
+
+const DARK: Theme = Theme {
+    background: 0x282C34,
+    foreground: 0xABB2BF,
+    line_number: 0x5C6370,
+    highlight: 0x3E4451,
+    cursor: 0x528BFF,
+    inserted: 0x98C379,
+    removed: 0xE06C75,
+    keyword: 0xC678DD,
+    type_name: 0xE5C07B,
+    function: 0x61AFEF,
+    string: 0x98C379,
+    number: 0xD19A66,
+    comment: 0x7F848E,
+};
+
+const LIGHT: Theme = Theme {
+    background: 0xFAFBFC,
+    foreground: 0x24292E,
+    line_number: 0x959DA5,
+    highlight: 0xFFF5B1,
+    cursor: 0x044289,
+    inserted: 0x28A745,
+    removed: 0xD73A49,
+    keyword: 0xD73A49,
+    type_name: 0x6F42C1,
+    function: 0x6F42C1,
+    string: 0x032F62,
+    number: 0x005CC5,
+    comment: 0x6A737D,
+};
+
+impl Theme {
+    fn color(&self, kind: Kind) -> u32 {
+        match kind {
+            Kind::Plain | Kind::Punctuation => self.foreground,
+            Kind::Keyword => self.keyword,
+            Kind::Type => self.type_name,
+            Kind::Function => self.function,
+            Kind::String => self.string,
+            Kind::Number => self.number,
+            Kind::Comment => self.comment,
+        }
+    }
+}
+
+fn rgba(color: u32, alpha: f64) -> [u8; 4] {
+    let [_, r, g, b] = color.to_be_bytes();
+    [r, g, b, (alpha.clamp(0.0, 1.0) * 255.0).round() as u8]
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Diff {
+    Keep(usize, usize),
+    Remove(usize),
+    Insert(usize),
+}
+
+/// Line diff from the longest common subsequence of the two sides.
+fn diff_lines(old: &[String], new: &[String]) -> Vec<Diff> {
+    let (n, m) = (old.len(), new.len());
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut ops = Vec::new();
+    while i < n || j < m {
+        if i < n && j < m && old[i] == new[j] {
+            ops.push(Diff::Keep(i, j));
+            i += 1;
+            j += 1;
+        } else if j < m && (i == n || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            ops.push(Diff::Insert(j));
+            j += 1;
+        } else {
+            ops.push(Diff::Remove(i));
+            i += 1;
+        }
+    }
+    ops
+}
+
+/// A line of code placed in the block, at a possibly fractional row.
+struct Row<'a> {
+    tokens: &'a [(Kind, String)],
+    number: usize,
+    row: f64,
+    /// Fraction of a line the row takes up while it grows or shrinks.
+    height: f64,
+    alpha: f64,
+    /// Background color and opacity of inserted and removed lines.
+    tint: Option<(u32, f64)>,
+}
+
+fn line_text(tokens: &[(Kind, String)]) -> String {
+    tokens.iter().map(|(_, text)| text.as_str()).collect()
+}
+
+/// The shapes that make up a `code` object with the given properties.
+pub fn shapes(properties: &[Property]) -> Vec<Shape> {
+    let property = |name: &str| properties.iter().find(|p| p.name == name).map(|p| &p.value);
+    let number = |name: &str| match property(name) {
+        Some(Value::Number(n)) => Some(*n),
+        _ => None,
+    };
+    let string = |name: &str| match property(name) {
+        Some(Value::String(s)) => Some(s.as_str()),
+        _ => None,
+    };
+
+    // While a `source` animation runs, the version being left and how far
+    // along it is are passed beside the new one.
+    let Some(new) = string("source") else {
+        return Vec::new();
+    };
+    let (old, progress) = match (string("previous_source"), number("source_progress")) {
+        (Some(old), Some(progress)) => (old, progress),
+        _ => (new, 1.0),
+    };
+    let language = string("language").and_then(language).unwrap_or(PLAIN);
+    let theme = match string("theme") {
+        Some("light") => LIGHT,
+        _ => DARK,
+    };
+    let old_lines = tokenize(&old.replace('\t', TAB), &language);
+    let new_lines = tokenize(&new.replace('\t', TAB), &language);
+
+    let rows: Vec<Row> = if old == new {
+        new_lines
+            .iter()
+            .enumerate()
+            .map(|(i, tokens)| Row {
+                tokens,
+                number: i,
+                row: i as f64,
+                height: 1.0,
+                alpha: 1.0,
+                tint: None,
+            })
+            .collect()
+    } else {
+        let old_text: Vec<String> = old_lines.iter().map(|l| line_text(l)).collect();
+        let new_text: Vec<String> = new_lines.iter().map(|l| line_text(l)).collect();
+        // Removed lines shrink away and inserted lines grow in, pushing the
+        // lines around them into place.
+        let mut row = 0.0;
+        let mut rows = Vec::new();
+        for op in diff_lines(&old_text, &new_text) {
+            let (tokens, number, height, tint) = match op {
+                Diff::Keep(a, b) => {
+                    let number = if progress < 0.5 { a } else { b };
+                    (&new_lines[b], number, 1.0, None)
+                }
+                Diff::Remove(a) => (&old_lines[a], a, 1.0 - progress, Some(theme.removed)),
+                Diff::Insert(b) => (&new_lines[b], b, progress, Some(theme.inserted)),
+            };
+            rows.push(Row {
+                tokens,
+                number,
+                row,
+                height,
+                alpha: if tint.is_some() { height } else { 1.0 },
+                tint: tint.map(|color| (color, progress * (1.0 - progress))),
+            });
+            row += height;
+        }
+        rows
+    };
+
+    let font = font::mono();
+    let font_size = number("font_size").unwrap_or(DEFAULT_FONT_SIZE);
+    let scale = font_size / font.units_per_em();
+    let advance = font
+        .glyph_id('0')
+        .map_or(0.6 * font_size, |id| font.advance(id) * scale);
+    let line_height = 1.5 * font_size;
+    let padding = font_size;
+    let first_number = number("line_numbers").unwrap_or(1.0).max(0.0) as usize;
+
+    let longest = old_lines
+        .iter()
+        .chain(&new_lines)
+        .map(|l| line_text(l).chars().count());
+    let columns = longest.max().unwrap_or(0);
+    let line_count =
+        old_lines.len() as f64 + (new_lines.len() as f64 - old_lines.len() as f64) * progress;
+    let digits = (first_number + old_lines.len().max(new_lines.len()))
+        .to_string()
+        .len();
+    let gutter = if first_number > 0 {
+        (digits + 2) as f64 * advance
+    } else {
+        0.0
+    };
+
+    let width = 2.0 * padding + gutter + columns as f64 * advance;
+    let height = 2.0 * padding + line_count * line_height;
+    let (cx, cy) = match property("position") {
+        Some(Value::Tuple(x, y)) => (*x, *y),
+        _ => (0.0, 0.0),
+    };
+    let (left, top) = (cx - width / 2.0, cy - height / 2.0);
+    let text_left = left + padding + gutter;
+    let row_top = |row: f64| top + padding + row * line_height;
+    // Center the font's ascent and descent within each line.
+    let baseline_offset = line_height / 2.0 + (font.ascender() + font.descender()) / 2.0 * scale;
+
+    let mut result = vec![Shape {
+        data: path::polygon(&outlines::rounded_rect(
+            (left, top),
+            width,
+            height,
+            0.4 * font_size,
+        )),
+        color: rgba(theme.background, 1.0),
+    }];
+
+    if let Some(Value::Tuple(first, last)) = property("highlight") {
+        if *last >= *first && *last > 0.0 {
+            let (y0, y1) = (row_top(first - 1.0), row_top(*last));
+            result.push(Shape {
+                data: path::polygon(&[
+                    (left, y0),
+                    (left + width, y0),
+                    (left + width, y1),
+                    (left, y1),
+                ]),
+                color: rgba(theme.highlight, 1.0),
+            });
+        }
+    }
+
+    // Typing reveals a fraction of the characters, in reading order.
+    let typed = if old == new {
+        number("typed").unwrap_or(1.0).clamp(0.0, 1.0)
+    } else {
+        1.0
+    };
+    let total: usize = rows
+        .iter()
+        .map(|r| line_text(r.tokens).chars().count())
+        .sum();
+    let mut remaining = (typed * total as f64).floor() as usize;
+    let mut cursor = None;
+
+    for row in &rows {
+        let y = row_top(row.row);
+        if typed < 1.0 && remaining == 0 && cursor.is_some() {
+            break;
+        }
+        if let Some((color, alpha)) = row.tint.filter(|(_, alpha)| *alpha > 0.0) {
+            result.push(Shape {
+                data: path::polygon(&[
+                    (left, y),
+                    (left + width, y),
+                    (left + width, y + row.height * line_height),
+                    (left, y + row.height * line_height),
+                ]),
+                color: rgba(color, alpha),
+            });
+        }
+        // Rows that are growing or shrinking keep their text centered in the gap.
+        let baseline = y + (row.height - 1.0) * line_height / 2.0 + baseline_offset;
+        if first_number > 0 {
+            let label = (first_number + row.number).to_string();
+            let x = left + padding + (digits - label.len()) as f64 * advance;
+            push_text(
+                &mut result,
+                font,
+                &label,
+                x,
+                baseline,
+                scale,
+                advance,
+                rgba(theme.line_number, row.alpha),
+            );
+        }
+
+        let mut x = text_left;
+        for (kind, text) in row.tokens {
+            let shown: String = if typed < 1.0 {
+                let shown: String = text.chars().take(remaining).collect();
+                remaining -= shown.chars().count();
+                shown
+            } else {
+                text.clone()
+            };
+            push_text(
+                &mut result,
+                font,
+                &shown,
+                x,
+                baseline,
+                scale,
+                advance,
+                rgba(theme.color(*kind), row.alpha),
+            );
+            x += shown.chars().count() as f64 * advance;
+        }
+        cursor = Some((x, y));
+    }
+
+    if typed < 1.0 {
+        if let Some((x, y)) = cursor {
+            let (y0, y1) = (y + 0.1 * line_height, y + 0.9 * line_height);
+            let x1 = x + 0.2 * advance;
+            result.push(Shape {
+                data: path::polygon(&[(x, y0), (x1, y0), (x1, y1), (x, y1)]),
+                color: rgba(theme.cursor, 1.0),
+            });
+        }
+    }
+    result
+}
+
+#[allow(clippy::too_many_arguments)]
+fn push_text(
+    shapes: &mut Vec<Shape>,
+    font: &Font,
+    text: &str,
+    x: f64,
+    baseline: f64,
+    scale: f64,
+    advance: f64,
+    color: [u8; 4],
+) {
+    if color[3] == 0 {
+        return;
+    }
+    for (i, ch) in text.chars().enumerate() {
+        if ch.is_whitespace() {
+            continue;
+        }
+        let Some(id) = font.glyph_id(ch).or_else(|| font.glyph_id('?')) else {
+            continue;
+        };
+        let origin = x + i as f64 * advance;
+        let data = path::transform(
+            &font.outline(id),
+            [scale, 0.0, 0.0, -scale, origin, baseline],
+        );
+        if !data.is_empty() {
+            shapes.push(Shape { data, color });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(source: &str, name: &str) -> Vec<(Kind, String)> {
+        tokenize(source, &language(name).unwrap())
+            .concat()
+            .into_iter()
+            .filter(|(_, text)| !text.trim().is_empty())
+            .collect()
+    }
+
+    fn code(source: Value, extra: Vec<Property>) -> Vec<Property> {
+        let mut properties = vec![
+            Property {
+                name: "source".to_string(),
+                value: source,
+            },
+            Property {
+                name: "language".to_string(),
+                value: Value::String("rust".to_string()),
+            },
+        ];
+        properties.extend(extra);
+        properties
+    }
+
+    fn number(name: &str, n: f64) -> Property {
+        Property {
+            name: name.to_string(),
+            value: Value::Number(n),
+        }
+    }
+
+    #[test]
+    fn test_tokenize_rust() {
+        let tokens = kinds("fn main() { let s = \"hi\"; // done\n}", "rust");
+        assert_eq!(tokens[0], (Kind::Keyword, "fn".to_string()));
+        assert_eq!(tokens[1], (Kind::Function, "main".to_string()));
+        assert!(tokens.contains(&(Kind::String, "\"hi\"".to_string())));
+        assert!(tokens.contains(&(Kind::Comment, "// done".to_string())));
+        assert_eq!(tokens.last(), Some(&(Kind::Punctuation, "}".to_string())));
+    }
+
+    #[test]
+    fn test_rust_lifetimes_are_not_strings() {
+        let tokens = kinds("fn f<'a>(c: char) -> &'a str { 'x' }", "rust");
+        assert!(tokens.contains(&(Kind::String, "'x'".to_string())));
+        assert!(tokens.contains(&(Kind::Type, "'a".to_string())));
+        assert!(tokens.contains(&(Kind::Type, "str".to_string())));
+    }
+
+    #[test]
+    fn test_block_comment_spans_lines() {
+        let lines = tokenize("/* one\ntwo */ x", &language("c").unwrap());
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], vec![(Kind::Comment, "/* one".to_string())]);
+        assert_eq!(lines[1][0], (Kind::Comment, "two */".to_string()));
+    }
+
+    #[test]
+    fn test_python_comments_and_keywords() {
+        let tokens = kinds("def f(x):\n    return None  # nothing", "python");
+        assert_eq!(tokens[0], (Kind::Keyword, "def".to_string()));
+        assert!(tokens.contains(&(Kind::Keyword, "None".to_string())));
+        assert!(tokens.contains(&(Kind::Comment, "# nothing".to_string())));
+    }
+
+    #[test]
+    fn test_diff_lines() {
+        let lines = |s: &str| s.split('\n').map(str::to_string).collect::<Vec<_>>();
+        let ops = diff_lines(&lines("a\nb\nc"), &lines("a\nx\nc\nd"));
+        assert_eq!(
+            ops,
+            vec![
+                Diff::Keep(0, 0),
+                Diff::Insert(1),
+                Diff::Remove(1),
+                Diff::Keep(2, 2),
+                Diff::Insert(3),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_typing_reveals_characters() {
+        let source = Value::String("let x = 1;".to_string());
+        let full = shapes(&code(source.clone(), vec![]));
+        let half = shapes(&code(source.clone(), vec![number("typed", 0.5)]));
+        let none = shapes(&code(source, vec![number("typed", 0.0)]));
+        // Background, line number and one shape per visible character.
+        assert_eq!(full.len(), 2 + 7);
+        // "let x" plus the cursor.
+        assert_eq!(half.len(), 2 + 4 + 1);
+        assert_eq!(none.len(), 2 + 1);
+    }
+
+    #[test]
+    fn test_highlight_adds_bar() {
+        let source = Value::String("a\nb\nc".to_string());
+        let plain = shapes(&code(source.clone(), vec![]));
+        let highlighted = shapes(&code(
+            source,
+            vec![Property {
+                name: "highlight".to_string(),
+                value: Value::Tuple(2.0, 3.0),
+            }],
+        ));
+        assert_eq!(highlighted.len(), plain.len() + 1);
+    }
+
+    #[test]
+    fn test_diff_fades_lines() {
+        let diff = |progress| {
+            let previous = Property {
+                name: "previous_source".to_string(),
+                value: Value::String("a\nb".to_string()),
+            };
+            shapes(&code(
+                Value::String("a\nc".to_string()),
+                vec![
+                    previous,
+                    number("source_progress", progress),
+                    number("line_numbers", 0.0),
+                ],
+            ))
+        };
+        let start = diff(0.0);
+        let end = diff(1.0);
+        // At the start the inserted line is invisible, at the end the removed one.
+        assert_eq!(
+            start,
+            shapes(&code(
+                Value::String("a\nb".to_string()),
+                vec![number("line_numbers", 0.0)]
+            ))
+        );
+        assert_eq!(end.len(), 3);
+        let middle = diff(0.5);
+        assert!(middle
+            .iter()
+            .any(|s| s.color[..3] == rgba(DARK.removed, 1.0)[..3]));
+        assert!(middle
+            .iter()
+            .any(|s| s.color[..3] == rgba(DARK.inserted, 1.0)[..3]));
+    }
+}
@@ -0,0 +1,49 @@
+//! Colors as written in scripts: `#RRGGBB`, or `transparent`. Colors made
+//! while animating may also be translucent, written `#RRGGBBAA`.
+
+/// The red, green, blue and alpha channels of a color, or `None` if `text`
+/// is not one.
+pub fn parse(text: &str) -> Option<[u8; 4]> {
+    if text == "transparent" {
+        return Some([0, 0, 0, 0]);
+    }
+    let hex = text.strip_prefix('#')?;
+    if hex.len() != 6 && hex.len() != 8 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    let alpha = if hex.len() == 8 { channel(6)? } else { 255 };
+    Some([channel(0)?, channel(2)?, channel(4)?, alpha])
+}
+
+/// `rgba` written as `#rrggbb`, or `#rrggbbaa` if it is not opaque.
+pub fn format([r, g, b, a]: [u8; 4]) -> String {
+    if a == 255 {
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    } else {
+        format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("#FF0000"), Some([255, 0, 0, 255]));
+        assert_eq!(parse("#00ff80"), Some([0, 255, 128, 255]));
+        assert_eq!(parse("#00ff8040"), Some([0, 255, 128, 64]));
+        assert_eq!(parse("transparent"), Some([0, 0, 0, 0]));
+        assert_eq!(parse("#FFF"), None);
+        assert_eq!(parse("#GG0000"), None);
+        assert_eq!(parse("invalid"), None);
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(format([255, 0, 128, 255]), "#ff0080");
+        assert_eq!(format([255, 0, 128, 64]), "#ff008040");
+        assert_eq!(parse(&format([1, 2, 3, 4])), Some([1, 2, 3, 4]));
+    }
+}
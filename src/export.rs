@@ -0,0 +1,522 @@
+//! Animated GIF, APNG and WebP files, written from rendered frames without
+//! ffmpeg.
+//!
+//! Runs of identical frames, such as those of a static scene, become a single
+//! frame shown for longer. GIF delays are counted in hundredths of a second
+//! and most viewers slow down delays under two of them, so frames that would
+//! be shown for less than that are dropped instead.
+
+use color_quant::NeuQuant;
+use image::{ImageFormat, RgbaImage};
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Cursor, Write};
+use std::path::{Path, PathBuf};
+
+/// Sampling factor for building GIF palettes, from 1 (best) to 30 (fastest).
+const GIF_SAMPLE_FACTOR: i32 = 10;
+
+/// The file an animation is written to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    /// H.264 video, encoded by ffmpeg.
+    Mp4,
+    /// VP9 video with alpha, encoded by ffmpeg.
+    Webm,
+    /// ProRes 4444 video with alpha in a QuickTime file, encoded by ffmpeg.
+    Prores,
+    Gif,
+    Apng,
+    Webp,
+    /// A directory of numbered PNG frames.
+    Png,
+    /// A directory of numbered QOI frames, which are quicker to write.
+    Qoi,
+    /// Uncompressed YUV4MPEG2 video, to standard output unless written to a
+    /// file.
+    Y4m,
+    /// A single PNG image of one frame.
+    #[value(skip)]
+    Still,
+}
+
+impl Format {
+    /// The format a file is written in, judging by its extension.
+    pub fn from_path(path: &Path) -> Option<Format> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "mp4" => Some(Format::Mp4),
+            "webm" => Some(Format::Webm),
+            "mov" => Some(Format::Prores),
+            "gif" => Some(Format::Gif),
+            "png" | "apng" => Some(Format::Apng),
+            "webp" => Some(Format::Webp),
+            "y4m" => Some(Format::Y4m),
+            _ => None,
+        }
+    }
+
+    /// Whether see-through pixels stay see-through in this format. GIF only
+    /// keeps pixels that are fully transparent or fully opaque.
+    pub fn has_alpha(&self) -> bool {
+        !matches!(self, Format::Mp4 | Format::Y4m)
+    }
+
+    /// Where the animation of `script` is written in this format by default:
+    /// next to it, under the same name. Y4M goes to standard output, `-`.
+    pub fn default_output(&self, script: &Path) -> PathBuf {
+        match self {
+            Format::Mp4 => script.with_extension("mp4"),
+            Format::Webm => script.with_extension("webm"),
+            Format::Prores => script.with_extension("mov"),
+            Format::Gif => script.with_extension("gif"),
+            // Animated PNGs keep the extension, so that viewers without
+            // APNG support still show the first frame.
+            Format::Apng => script.with_extension("png"),
+            Format::Webp => script.with_extension("webp"),
+            Format::Y4m => PathBuf::from("-"),
+            Format::Still => script.with_extension("png"),
+            Format::Png | Format::Qoi => {
+                let stem = script.file_stem().unwrap_or_default().to_string_lossy();
+                script.with_file_name(format!("{}_frames", stem))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportOptions {
+    pub format: Format,
+    /// How many times the animation plays, `0` for forever.
+    pub loops: u32,
+    /// Whether GIF frames are dithered to hide the banding of their palettes.
+    pub dither: bool,
+    /// File names of image sequences, such as `frame_%05d.png`.
+    pub pattern: Option<String>,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        ExportOptions {
+            format: Format::Mp4,
+            loops: 0,
+            dither: false,
+            pattern: None,
+        }
+    }
+}
+
+/// Writes `frames`, rendered at `fps` frames per second, as an animated
+/// image in the format of `options`, which must be GIF, APNG or WebP.
+pub fn write_animation(
+    frames: impl Iterator<Item = RgbaImage>,
+    fps: f64,
+    options: &ExportOptions,
+    path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    match options.format {
+        Format::Gif => encode(frames, fps, GifWriter::new(path, options)),
+        Format::Apng => encode(frames, fps, ApngWriter::new(path, options)),
+        Format::Webp => encode(frames, fps, WebpWriter::new(path, options)),
+        _ => Err("Not an animated image format".into()),
+    }
+}
+
+trait AnimationWriter {
+    /// Number of units per second that frame delays are counted in.
+    const UNITS_PER_SECOND: f64;
+    /// The shortest delay a frame can be shown for.
+    const MIN_DELAY: u64;
+
+    fn add_frame(&mut self, image: &RgbaImage, delay: u64) -> Result<(), Box<dyn Error>>;
+
+    fn finish(self) -> Result<(), Box<dyn Error>>;
+}
+
+// Hands frames to `writer`, each with the delay until the next different one.
+fn encode<W: AnimationWriter>(
+    frames: impl Iterator<Item = RgbaImage>,
+    fps: f64,
+    mut writer: W,
+) -> Result<(), Box<dyn Error>> {
+    let start = |i: usize| (i as f64 * W::UNITS_PER_SECOND / fps).round() as u64;
+    let mut shown: Option<(RgbaImage, u64)> = None;
+    let mut count = 0;
+    for (i, frame) in frames.enumerate() {
+        count = i + 1;
+        let time = start(i);
+        if let Some((image, since)) = &shown {
+            if *image == frame || time - since < W::MIN_DELAY {
+                continue;
+            }
+            writer.add_frame(image, time - since)?;
+        }
+        shown = Some((frame, time));
+    }
+    let Some((image, since)) = shown else {
+        return Err("No frames to write".into());
+    };
+    writer.add_frame(&image, (start(count) - since).max(W::MIN_DELAY))?;
+    writer.finish()
+}
+
+struct GifWriter {
+    path: PathBuf,
+    loops: u32,
+    dither: bool,
+    // Created with the size of the first frame.
+    encoder: Option<gif::Encoder<BufWriter<File>>>,
+}
+
+impl GifWriter {
+    fn new(path: &Path, options: &ExportOptions) -> GifWriter {
+        GifWriter {
+            path: path.to_path_buf(),
+            loops: options.loops,
+            dither: options.dither,
+            encoder: None,
+        }
+    }
+}
+
+impl AnimationWriter for GifWriter {
+    const UNITS_PER_SECOND: f64 = 100.0;
+    const MIN_DELAY: u64 = 2;
+
+    fn add_frame(&mut self, image: &RgbaImage, delay: u64) -> Result<(), Box<dyn Error>> {
+        let (width, height) = (
+            u16::try_from(image.width())?,
+            u16::try_from(image.height())?,
+        );
+        let encoder = match &mut self.encoder {
+            Some(encoder) => encoder,
+            None => {
+                let file = BufWriter::new(File::create(&self.path)?);
+                let mut encoder = gif::Encoder::new(file, width, height, &[])?;
+                // The repeat count is the number of plays after the first.
+                match self.loops {
+                    0 => encoder.set_repeat(gif::Repeat::Infinite)?,
+                    1 => (),
+                    n => encoder
+                        .set_repeat(gif::Repeat::Finite((n - 1).min(u16::MAX as u32) as u16))?,
+                }
+                self.encoder.insert(encoder)
+            }
+        };
+
+        let (palette, indices, transparent) = quantize(image, self.dither);
+        let frame = gif::Frame {
+            width,
+            height,
+            delay: delay.min(u16::MAX as u64) as u16,
+            palette: Some(palette),
+            transparent,
+            buffer: indices.into(),
+            ..gif::Frame::default()
+        };
+        encoder.write_frame(&frame)?;
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(), Box<dyn Error>> {
+        if let Some(encoder) = self.encoder {
+            encoder.into_inner()?.flush()?;
+        }
+        Ok(())
+    }
+}
+
+// Reduces an image to a palette of up to 256 RGB colors and one index per
+// pixel. Pixels that are more than half transparent get an index of their
+// own, which is returned as the transparent one.
+fn quantize(image: &RgbaImage, dither: bool) -> (Vec<u8>, Vec<u8>, Option<u8>) {
+    let opaque: Vec<u8> = image
+        .pixels()
+        .filter(|p| p[3] >= 128)
+        .flat_map(|p| [p[0], p[1], p[2], 255])
+        .collect();
+    let has_transparency = opaque.len() < image.as_raw().len();
+    if opaque.is_empty() {
+        return (vec![0, 0, 0], vec![0; image.as_raw().len() / 4], Some(0));
+    }
+    let colors = if has_transparency { 255 } else { 256 };
+    let quantizer = NeuQuant::new(GIF_SAMPLE_FACTOR, colors, &opaque);
+    let mut palette = quantizer.color_map_rgb();
+    let transparent = has_transparency.then(|| {
+        let index = palette.len() / 3;
+        palette.extend([0, 0, 0]);
+        index as u8
+    });
+
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let mut indices = Vec::with_capacity(width * height);
+    // Floyd-Steinberg errors carried to the current and the next row.
+    let mut errors = vec![[0.0f32; 3]; width + 2];
+    let mut next_errors = vec![[0.0f32; 3]; width + 2];
+    for y in 0..height {
+        for x in 0..width {
+            let pixel = image.get_pixel(x as u32, y as u32);
+            if pixel[3] < 128 {
+                indices.push(transparent.unwrap_or(0));
+                continue;
+            }
+            let mut color = [pixel[0], pixel[1], pixel[2], 255];
+            if dither {
+                for c in 0..3 {
+                    color[c] = (color[c] as f32 + errors[x + 1][c])
+                        .round()
+                        .clamp(0.0, 255.0) as u8;
+                }
+            }
+            let index = quantizer.index_of(&color);
+            indices.push(index as u8);
+            if dither {
+                for c in 0..3 {
+                    let error = color[c] as f32 - palette[index * 3 + c] as f32;
+                    errors[x + 2][c] += error * 7.0 / 16.0;
+                    next_errors[x][c] += error * 3.0 / 16.0;
+                    next_errors[x + 1][c] += error * 5.0 / 16.0;
+                    next_errors[x + 2][c] += error / 16.0;
+                }
+            }
+        }
+        std::mem::swap(&mut errors, &mut next_errors);
+        next_errors.iter_mut().for_each(|e| *e = [0.0; 3]);
+    }
+    (palette, indices, transparent)
+}
+
+// APNG needs the number of frames up front, so frames are kept as PNGs until
+// all of them are known.
+struct ApngWriter {
+    path: PathBuf,
+    loops: u32,
+    frames: Vec<(Vec<u8>, u64)>,
+}
+
+impl ApngWriter {
+    fn new(path: &Path, options: &ExportOptions) -> ApngWriter {
+        ApngWriter {
+            path: path.to_path_buf(),
+            loops: options.loops,
+            frames: Vec::new(),
+        }
+    }
+}
+
+impl AnimationWriter for ApngWriter {
+    const UNITS_PER_SECOND: f64 = 1000.0;
+    const MIN_DELAY: u64 = 1;
+
+    fn add_frame(&mut self, image: &RgbaImage, delay: u64) -> Result<(), Box<dyn Error>> {
+        let mut png = Vec::new();
+        image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+        self.frames.push((png, delay));
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(), Box<dyn Error>> {
+        let Some((first, _)) = self.frames.first() else {
+            return Ok(());
+        };
+        let first = image::load_from_memory(first)?;
+        let file = BufWriter::new(File::create(&self.path)?);
+        let mut encoder = png::Encoder::new(file, first.width(), first.height());
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_animated(self.frames.len() as u32, self.loops)?;
+        let mut writer = encoder.write_header()?;
+        for (png, delay) in &self.frames {
+            // Delays longer than a u16 of milliseconds are given in hundredths.
+            if let Ok(delay) = u16::try_from(*delay) {
+                writer.set_frame_delay(delay, 1000)?;
+            } else {
+                writer.set_frame_delay((*delay / 10).min(u16::MAX as u64) as u16, 100)?;
+            }
+            writer.write_image_data(image::load_from_memory(png)?.to_rgba8().as_raw())?;
+        }
+        writer.finish()?;
+        Ok(())
+    }
+}
+
+// Animated WebP wraps the bitstream of each frame, encoded as a still
+// lossless WebP image, in an animation frame chunk.
+struct WebpWriter {
+    path: PathBuf,
+    loops: u32,
+    size: (u32, u32),
+    has_alpha: bool,
+    frames: Vec<(Vec<u8>, u64)>,
+}
+
+impl WebpWriter {
+    fn new(path: &Path, options: &ExportOptions) -> WebpWriter {
+        WebpWriter {
+            path: path.to_path_buf(),
+            loops: options.loops,
+            size: (0, 0),
+            has_alpha: false,
+            frames: Vec::new(),
+        }
+    }
+}
+
+impl AnimationWriter for WebpWriter {
+    const UNITS_PER_SECOND: f64 = 1000.0;
+    const MIN_DELAY: u64 = 1;
+
+    fn add_frame(&mut self, image: &RgbaImage, delay: u64) -> Result<(), Box<dyn Error>> {
+        let mut still = Vec::new();
+        image_webp::WebPEncoder::new(&mut still).encode(
+            image.as_raw(),
+            image.width(),
+            image.height(),
+            image_webp::ColorType::Rgba8,
+        )?;
+        // A simple WebP file is a RIFF header followed by a single chunk.
+        let chunk = still.get(12..).ok_or("Invalid WebP frame")?.to_vec();
+        self.size = image.dimensions();
+        self.has_alpha |= image.pixels().any(|p| p[3] < 255);
+        self.frames.push((chunk, delay));
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(), Box<dyn Error>> {
+        let (width, height) = self.size;
+        let mut body = Vec::new();
+        let mut header = vec![if self.has_alpha { 0x12 } else { 0x02 }, 0, 0, 0];
+        header.extend(&u24(width - 1));
+        header.extend(&u24(height - 1));
+        write_chunk(&mut body, b"VP8X", &header);
+        let mut animation = vec![0, 0, 0, 0];
+        animation.extend((self.loops.min(u16::MAX as u32) as u16).to_le_bytes());
+        write_chunk(&mut body, b"ANIM", &animation);
+        for (chunk, delay) in &self.frames {
+            let mut frame = Vec::with_capacity(16 + chunk.len());
+            frame.extend(&u24(0));
+            frame.extend(&u24(0));
+            frame.extend(&u24(width - 1));
+            frame.extend(&u24(height - 1));
+            frame.extend(&u24((*delay).min(0xFF_FFFF) as u32));
+            // Each frame covers the canvas and replaces it without blending.
+            frame.push(0b10);
+            frame.extend(chunk);
+            write_chunk(&mut body, b"ANMF", &frame);
+        }
+
+        let mut file = BufWriter::new(File::create(&self.path)?);
+        file.write_all(b"RIFF")?;
+        file.write_all(&(body.len() as u32 + 4).to_le_bytes())?;
+        file.write_all(b"WEBP")?;
+        file.write_all(&body)?;
+        file.flush()?;
+        Ok(())
+    }
+}
+
+fn u24(n: u32) -> [u8; 3] {
+    let [a, b, c, _] = n.to_le_bytes();
+    [a, b, c]
+}
+
+fn write_chunk(out: &mut Vec<u8>, id: &[u8; 4], data: &[u8]) {
+    out.extend(id);
+    out.extend((data.len() as u32).to_le_bytes());
+    out.extend(data);
+    if data.len() % 2 == 1 {
+        out.push(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+    use std::fs;
+
+    #[derive(Default)]
+    struct Recorder {
+        frames: Vec<(u8, u64)>,
+    }
+
+    impl AnimationWriter for &mut Recorder {
+        const UNITS_PER_SECOND: f64 = 100.0;
+        const MIN_DELAY: u64 = 2;
+
+        fn add_frame(&mut self, image: &RgbaImage, delay: u64) -> Result<(), Box<dyn Error>> {
+            self.frames.push((image.get_pixel(0, 0)[0], delay));
+            Ok(())
+        }
+
+        fn finish(self) -> Result<(), Box<dyn Error>> {
+            Ok(())
+        }
+    }
+
+    fn frames(shades: &[u8]) -> impl Iterator<Item = RgbaImage> + '_ {
+        shades
+            .iter()
+            .map(|&s| RgbaImage::from_pixel(2, 2, Rgba([s, 0, 0, 255])))
+    }
+
+    #[test]
+    fn test_identical_frames_are_merged() {
+        let mut recorder = Recorder::default();
+        encode(frames(&[1, 1, 1, 2, 2, 3]), 10.0, &mut recorder).unwrap();
+        assert_eq!(recorder.frames, vec![(1, 30), (2, 20), (3, 10)]);
+    }
+
+    #[test]
+    fn test_frames_faster_than_the_minimum_delay_are_dropped() {
+        let mut recorder = Recorder::default();
+        // At 100 fps every frame lasts one hundredth, half the GIF minimum.
+        encode(frames(&[1, 2, 3, 4, 5]), 100.0, &mut recorder).unwrap();
+        assert_eq!(recorder.frames, vec![(1, 2), (3, 2), (5, 2)]);
+    }
+
+    #[test]
+    fn test_quantize_with_transparency() {
+        let mut image = RgbaImage::from_pixel(8, 8, Rgba([200, 40, 40, 255]));
+        image.put_pixel(0, 0, Rgba([0, 0, 0, 0]));
+        image.put_pixel(1, 0, Rgba([20, 20, 220, 255]));
+        for dither in [false, true] {
+            let (palette, indices, transparent) = quantize(&image, dither);
+            let transparent = transparent.unwrap() as usize;
+            assert_eq!(palette.len(), (transparent + 1) * 3);
+            assert_eq!(indices[0] as usize, transparent);
+            let color = |i: usize| &palette[indices[i] as usize * 3..indices[i] as usize * 3 + 3];
+            assert_eq!(color(1), &[20, 20, 220]);
+            assert_eq!(color(2), &[200, 40, 40]);
+        }
+    }
+
+    #[test]
+    fn test_webp_container() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("animation.webp");
+        let options = ExportOptions {
+            format: Format::Webp,
+            loops: 3,
+            ..ExportOptions::default()
+        };
+        write_animation(frames(&[1, 1, 2]), 10.0, &options, &path).unwrap();
+        let data = fs::read(&path).unwrap();
+        assert_eq!(&data[0..4], b"RIFF");
+        assert_eq!(
+            u32::from_le_bytes(data[4..8].try_into().unwrap()) as usize,
+            data.len() - 8
+        );
+        assert_eq!(&data[8..16], b"WEBPVP8X");
+        // Two frames, shown for 200 and 100 milliseconds.
+        let frames: Vec<_> = data
+            .windows(4)
+            .enumerate()
+            .filter(|(_, w)| w == b"ANMF")
+            .collect();
+        assert_eq!(frames.len(), 2);
+        let delay =
+            |at: usize| u32::from_le_bytes([data[at + 20], data[at + 21], data[at + 22], 0]);
+        assert_eq!(delay(frames[0].0), 200);
+        assert_eq!(delay(frames[1].0), 100);
+    }
+}
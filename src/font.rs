@@ -0,0 +1,195 @@
+//! Fonts bundled with beam and conversion of their glyphs to path data.
+//!
+//! Glyph metrics and outlines are in font design units with y pointing up,
+//! as stored in the font file.
+
+use crate::path::{self, PathData, Segment};
+use std::sync::OnceLock;
+use ttf_parser::{Face, GlyphId, OutlineBuilder};
+
+static MATH_FONT_DATA: &[u8] = include_bytes!("../assets/fonts/DejaVuMathTeXGyre.ttf");
+static MATH_FONT: OnceLock<Font> = OnceLock::new();
+static MONO_FONT_DATA: &[u8] = include_bytes!("../assets/fonts/DejaVuSansMono.ttf");
+static MONO_FONT: OnceLock<Font> = OnceLock::new();
+
+pub struct Font {
+    face: Face<'static>,
+}
+
+/// The OpenType math font used to typeset `math` objects.
+pub fn math() -> &'static Font {
+    MATH_FONT.get_or_init(|| Font {
+        face: Face::parse(MATH_FONT_DATA, 0).expect("bundled math font is valid"),
+    })
+}
+
+/// The monospace font used for `code` objects.
+pub fn mono() -> &'static Font {
+    MONO_FONT.get_or_init(|| Font {
+        face: Face::parse(MONO_FONT_DATA, 0).expect("bundled monospace font is valid"),
+    })
+}
+
+/// Outlines of a one-line label in the math font, hanging from `top.1` and
+/// placed on `top.0` by `align`: 0 for its left edge, 0.5 for its center and
+/// 1 for its right edge. Numbers get a true minus sign rather than a hyphen.
+pub fn label(text: &str, top: (f64, f64), align: f64, font_size: f64) -> Vec<PathData> {
+    let font = math();
+    let scale = font_size / font.units_per_em();
+    let glyphs = label_glyphs(text);
+    let width: f64 = glyphs.iter().map(|&id| font.advance(id) * scale).sum();
+    let ascent = font
+        .glyph_id('0')
+        .map_or(0.7 * font_size, |id| font.bounds(id).3 * scale);
+
+    let mut x = top.0 - width * align;
+    let baseline = top.1 + ascent;
+    let mut outlines = Vec::new();
+    for id in glyphs {
+        let outline = path::transform(&font.outline(id), [scale, 0.0, 0.0, -scale, x, baseline]);
+        if !outline.is_empty() {
+            outlines.push(outline);
+        }
+        x += font.advance(id) * scale;
+    }
+    outlines
+}
+
+/// The advance width of a label drawn by [`label`].
+pub fn label_width(text: &str, font_size: f64) -> f64 {
+    let font = math();
+    let advance: f64 = label_glyphs(text).iter().map(|&id| font.advance(id)).sum();
+    advance * font_size / font.units_per_em()
+}
+
+fn label_glyphs(text: &str) -> Vec<GlyphId> {
+    let numeric = text.parse::<f64>().is_ok();
+    text.chars()
+        .map(|c| if numeric && c == '-' { '\u{2212}' } else { c })
+        .filter_map(|c| math().glyph_id(c))
+        .collect()
+}
+
+impl Font {
+    pub fn face(&self) -> &Face<'static> {
+        &self.face
+    }
+
+    pub fn units_per_em(&self) -> f64 {
+        self.face.units_per_em() as f64
+    }
+
+    pub fn ascender(&self) -> f64 {
+        self.face.ascender() as f64
+    }
+
+    pub fn descender(&self) -> f64 {
+        self.face.descender() as f64
+    }
+
+    pub fn glyph_id(&self, ch: char) -> Option<GlyphId> {
+        self.face.glyph_index(ch)
+    }
+
+    pub fn advance(&self, id: GlyphId) -> f64 {
+        self.face.glyph_hor_advance(id).unwrap_or(0) as f64
+    }
+
+    /// Ink bounds as `(x_min, y_min, x_max, y_max)`, all zero for blank glyphs.
+    pub fn bounds(&self, id: GlyphId) -> (f64, f64, f64, f64) {
+        self.face
+            .glyph_bounding_box(id)
+            .map(|r| {
+                (
+                    r.x_min as f64,
+                    r.y_min as f64,
+                    r.x_max as f64,
+                    r.y_max as f64,
+                )
+            })
+            .unwrap_or((0.0, 0.0, 0.0, 0.0))
+    }
+
+    pub fn outline(&self, id: GlyphId) -> PathData {
+        let mut builder = Builder {
+            path: Vec::new(),
+            current: (0.0, 0.0),
+        };
+        self.face.outline_glyph(id, &mut builder);
+        builder.path
+    }
+}
+
+struct Builder {
+    path: PathData,
+    current: (f64, f64),
+}
+
+impl OutlineBuilder for Builder {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.current = (x as f64, y as f64);
+        self.path.push(Segment::MoveTo(self.current));
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let to = (x as f64, y as f64);
+        self.path.push(path::line_to(self.current, to));
+        self.current = to;
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let to = (x as f64, y as f64);
+        self.path
+            .push(path::quad_to(self.current, (x1 as f64, y1 as f64), to));
+        self.current = to;
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let to = (x as f64, y as f64);
+        self.path.push(Segment::CubicTo(
+            (x1 as f64, y1 as f64),
+            (x2 as f64, y2 as f64),
+            to,
+        ));
+        self.current = to;
+    }
+
+    fn close(&mut self) {
+        self.path.push(Segment::Close);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_math_font_has_math_table() {
+        assert!(math().face().tables().math.is_some());
+    }
+
+    #[test]
+    fn test_glyph_outline_is_closed() {
+        let font = math();
+        let id = font.glyph_id('x').unwrap();
+        let outline = font.outline(id);
+        assert!(matches!(outline.first(), Some(Segment::MoveTo(_))));
+        assert_eq!(outline.last(), Some(&Segment::Close));
+        assert!(font.advance(id) > 0.0);
+    }
+
+    #[test]
+    fn test_mono_font_has_fixed_advance() {
+        let font = mono();
+        let advance = |ch| font.advance(font.glyph_id(ch).unwrap());
+        assert_eq!(advance('i'), advance('W'));
+    }
+
+    #[test]
+    fn test_space_has_no_ink() {
+        let font = math();
+        let id = font.glyph_id(' ').unwrap();
+        assert!(font.outline(id).is_empty());
+        assert_eq!(font.bounds(id), (0.0, 0.0, 0.0, 0.0));
+    }
+}
@@ -0,0 +1,265 @@
+//! Arithmetic formulas of one variable, such as the `expression` of a
+//! `function_graph` object.
+//!
+//! Formulas support numbers, `+ - * / ^`, parentheses, the constants `pi`
+//! and `e`, and common functions like `sin(x)` or `sqrt(x)`. Any other name
+//! stands for the variable, so `x^2` and `t^2` describe the same function.
+
+#[derive(Debug, Clone)]
+enum Node {
+    Number(f64),
+    Variable,
+    Negate(Box<Node>),
+    Binary(char, Box<Node>, Box<Node>),
+    Call(fn(f64) -> f64, Box<Node>),
+}
+
+#[derive(Debug, Clone)]
+pub struct Formula {
+    root: Node,
+}
+
+impl Formula {
+    pub fn parse(text: &str) -> Result<Formula, String> {
+        let mut parser = Parser {
+            chars: text.chars().collect(),
+            pos: 0,
+            variable: None,
+        };
+        let root = parser.sum()?;
+        parser.skip_whitespace();
+        if parser.pos < parser.chars.len() {
+            return Err(format!(
+                "unexpected '{}' at offset {}",
+                parser.chars[parser.pos], parser.pos
+            ));
+        }
+        Ok(Formula { root })
+    }
+
+    /// The formula's value for the given value of its variable.
+    pub fn eval(&self, variable: f64) -> f64 {
+        eval(&self.root, variable)
+    }
+}
+
+fn eval(node: &Node, variable: f64) -> f64 {
+    match node {
+        Node::Number(n) => *n,
+        Node::Variable => variable,
+        Node::Negate(a) => -eval(a, variable),
+        Node::Binary(op, a, b) => {
+            let (a, b) = (eval(a, variable), eval(b, variable));
+            match op {
+                '+' => a + b,
+                '-' => a - b,
+                '*' => a * b,
+                '/' => a / b,
+                _ => a.powf(b),
+            }
+        }
+        Node::Call(f, a) => f(eval(a, variable)),
+    }
+}
+
+fn function(name: &str) -> Option<fn(f64) -> f64> {
+    Some(match name {
+        "sin" => f64::sin,
+        "cos" => f64::cos,
+        "tan" => f64::tan,
+        "asin" => f64::asin,
+        "acos" => f64::acos,
+        "atan" => f64::atan,
+        "sinh" => f64::sinh,
+        "cosh" => f64::cosh,
+        "tanh" => f64::tanh,
+        "exp" => f64::exp,
+        "ln" => f64::ln,
+        "log" => f64::log10,
+        "sqrt" => f64::sqrt,
+        "abs" => f64::abs,
+        "floor" => f64::floor,
+        "ceil" => f64::ceil,
+        "round" => f64::round,
+        "sign" => f64::signum,
+        _ => return None,
+    })
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    variable: Option<String>,
+}
+
+impl Parser {
+    fn skip_whitespace(&mut self) {
+        while self.chars.get(self.pos).is_some_and(|c| c.is_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.chars.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, ch: char) -> Result<(), String> {
+        if self.peek() == Some(ch) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(format!("expected '{}' at offset {}", ch, self.pos))
+        }
+    }
+
+    // sum = product (("+" | "-") product)*
+    fn sum(&mut self) -> Result<Node, String> {
+        let mut node = self.product()?;
+        while let Some(op @ ('+' | '-')) = self.peek() {
+            self.pos += 1;
+            node = Node::Binary(op, Box::new(node), Box::new(self.product()?));
+        }
+        Ok(node)
+    }
+
+    // product = unary (("*" | "/")? unary)*, where a missing operator multiplies
+    fn product(&mut self) -> Result<Node, String> {
+        let mut node = self.unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(op @ ('*' | '/')) => {
+                    self.pos += 1;
+                    op
+                }
+                // Implicit multiplication, as in `2x` or `3(x + 1)`.
+                Some(c) if c.is_alphanumeric() || c == '(' || c == '.' => '*',
+                _ => return Ok(node),
+            };
+            node = Node::Binary(op, Box::new(node), Box::new(self.unary()?));
+        }
+    }
+
+    // unary = "-" unary | power
+    fn unary(&mut self) -> Result<Node, String> {
+        match self.peek() {
+            Some('-') => {
+                self.pos += 1;
+                Ok(Node::Negate(Box::new(self.unary()?)))
+            }
+            Some('+') => {
+                self.pos += 1;
+                self.unary()
+            }
+            _ => self.power(),
+        }
+    }
+
+    // power = atom ("^" unary)?, which makes `^` right associative
+    fn power(&mut self) -> Result<Node, String> {
+        let base = self.atom()?;
+        if self.peek() == Some('^') {
+            self.pos += 1;
+            return Ok(Node::Binary('^', Box::new(base), Box::new(self.unary()?)));
+        }
+        Ok(base)
+    }
+
+    fn atom(&mut self) -> Result<Node, String> {
+        match self.peek() {
+            Some('(') => {
+                self.pos += 1;
+                let node = self.sum()?;
+                self.expect(')')?;
+                Ok(node)
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                let start = self.pos;
+                while self
+                    .chars
+                    .get(self.pos)
+                    .is_some_and(|c| c.is_ascii_digit() || *c == '.')
+                {
+                    self.pos += 1;
+                }
+                let text: String = self.chars[start..self.pos].iter().collect();
+                text.parse()
+                    .map(Node::Number)
+                    .map_err(|_| format!("invalid number '{}'", text))
+            }
+            Some(c) if c.is_alphabetic() => {
+                let start = self.pos;
+                while self
+                    .chars
+                    .get(self.pos)
+                    .is_some_and(|c| c.is_alphanumeric() || *c == '_')
+                {
+                    self.pos += 1;
+                }
+                let name: String = self.chars[start..self.pos].iter().collect();
+                if let Some(f) = function(&name) {
+                    self.expect('(')?;
+                    let argument = self.sum()?;
+                    self.expect(')')?;
+                    return Ok(Node::Call(f, Box::new(argument)));
+                }
+                match name.as_str() {
+                    "pi" => Ok(Node::Number(std::f64::consts::PI)),
+                    "e" => Ok(Node::Number(std::f64::consts::E)),
+                    _ => match &self.variable {
+                        Some(variable) if *variable != name => Err(format!(
+                            "unknown name '{}', the formula already uses '{}'",
+                            name, variable
+                        )),
+                        _ => {
+                            self.variable = Some(name);
+                            Ok(Node::Variable)
+                        }
+                    },
+                }
+            }
+            Some(c) => Err(format!("unexpected '{}' at offset {}", c, self.pos)),
+            None => Err("unexpected end of formula".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(text: &str, x: f64) -> f64 {
+        Formula::parse(text).unwrap().eval(x)
+    }
+
+    #[test]
+    fn test_precedence() {
+        assert_eq!(eval("1 + 2 * 3", 0.0), 7.0);
+        assert_eq!(eval("(1 + 2) * 3", 0.0), 9.0);
+        assert_eq!(eval("2 ^ 3 ^ 2", 0.0), 512.0);
+        assert_eq!(eval("-x^2", 3.0), -9.0);
+        assert_eq!(eval("10 / 4 - 1", 0.0), 1.5);
+    }
+
+    #[test]
+    fn test_variable_and_functions() {
+        assert_eq!(eval("x^2 / 4", 2.0), 1.0);
+        assert_eq!(eval("t + 1", 2.0), 3.0);
+        assert!((eval("sin(pi / 2)", 0.0) - 1.0).abs() < 1e-12);
+        assert_eq!(eval("sqrt(abs(x))", -16.0), 4.0);
+    }
+
+    #[test]
+    fn test_implicit_multiplication() {
+        assert_eq!(eval("2x + 1", 3.0), 7.0);
+        assert_eq!(eval("3(x + 1)", 1.0), 6.0);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(Formula::parse("x +").is_err());
+        assert!(Formula::parse("sin x").is_err());
+        assert!(Formula::parse("x + y").is_err());
+        assert!(Formula::parse("(x").is_err());
+    }
+}
@@ -1,11 +1,15 @@
 use crate::ast::{Camera, Object, Property, Value};
+use crate::shapes::{self, Shape};
+use crate::{assets, chart, code, color, number_line, path, renderer, table};
 use vello::{kurbo, peniko, Renderer, RendererOptions, Scene};
 use image::{ImageBuffer, Rgba};
+use std::collections::HashMap;
 
 pub struct GpuRendererState {
     device: vello::wgpu::Device,
     queue: vello::wgpu::Queue,
     renderer: Renderer,
+    images: HashMap<String, Option<peniko::Image>>,
 }
 
 impl GpuRendererState {
@@ -33,8 +37,26 @@ impl GpuRendererState {
             device,
             queue,
             renderer,
+            images: HashMap::new(),
         }
     }
+
+    // Uploads each image file once and reuses it for every later frame.
+    fn image(&mut self, src: &str) -> Option<peniko::Image> {
+        self.images
+            .entry(src.to_string())
+            .or_insert_with(|| {
+                assets::load_image(src).map(|source| {
+                    peniko::Image::new(
+                        source.as_raw().clone().into(),
+                        peniko::ImageFormat::Rgba8,
+                        source.width(),
+                        source.height(),
+                    )
+                })
+            })
+            .clone()
+    }
 }
 
 fn get_property<'a>(properties: &'a [Property], name: &str) -> Option<&'a Value> {
@@ -61,29 +83,23 @@ fn get_radius(properties: &[Property]) -> f64 {
 }
 
 fn get_fill_color(properties: &[Property]) -> Option<peniko::Color> {
-    if let Some(Value::Color(c)) = get_property(properties, "fill").or_else(|| get_property(properties, "color")) {
-        let c = c.trim_start_matches('#');
-        let r = u8::from_str_radix(&c[0..2], 16).unwrap();
-        let g = u8::from_str_radix(&c[2..4], 16).unwrap();
-        let b = u8::from_str_radix(&c[4..6], 16).unwrap();
-        Some(peniko::Color::from_rgb8(r, g, b))
-    } else {
-        None
+    match get_property(properties, "fill").or_else(|| get_property(properties, "color")) {
+        Some(Value::Color(c)) => parse_color(c),
+        _ => None,
     }
 }
 
 fn get_stroke_color(properties: &[Property]) -> Option<peniko::Color> {
-    if let Some(Value::Color(c)) = get_property(properties, "border_color") {
-        let c = c.trim_start_matches('#');
-        let r = u8::from_str_radix(&c[0..2], 16).unwrap();
-        let g = u8::from_str_radix(&c[2..4], 16).unwrap();
-        let b = u8::from_str_radix(&c[4..6], 16).unwrap();
-        Some(peniko::Color::from_rgb8(r, g, b))
-    } else {
-        None
+    match get_property(properties, "border_color") {
+        Some(Value::Color(c)) => parse_color(c),
+        _ => None,
     }
 }
 
+fn parse_color(c: &str) -> Option<peniko::Color> {
+    color::parse(c).map(|[r, g, b, a]| peniko::Color::from_rgba8(r, g, b, a))
+}
+
 fn get_width(properties: &[Property]) -> f64 {
     if let Some(Value::Number(w)) = get_property(properties, "width") {
         *w
@@ -149,6 +165,59 @@ fn get_p3(properties: &[Property]) -> (f64, f64) {
     }
 }
 
+fn get_number(properties: &[Property], name: &str, default: f64) -> f64 {
+    if let Some(Value::Number(n)) = get_property(properties, name) {
+        *n
+    } else {
+        default
+    }
+}
+
+fn get_points(properties: &[Property]) -> Vec<(f64, f64)> {
+    if let Some(Value::List(items)) = get_property(properties, "points") {
+        items
+            .iter()
+            .filter_map(|v| match v {
+                Value::Tuple(x, y) => Some((*x, *y)),
+                _ => None,
+            })
+            .collect()
+    } else {
+        Vec::new()
+    }
+}
+
+fn points_to_path(points: &[(f64, f64)], closed: bool) -> kurbo::BezPath {
+    let mut path = kurbo::BezPath::new();
+    if let Some((first, rest)) = points.split_first() {
+        path.move_to(*first);
+        for point in rest {
+            path.line_to(*point);
+        }
+        if closed {
+            path.close_path();
+        }
+    }
+    path
+}
+
+fn path_data_to_bez_path(data: &path::PathData) -> kurbo::BezPath {
+    let mut bez_path = kurbo::BezPath::new();
+    for segment in data {
+        match *segment {
+            path::Segment::MoveTo(p) => bez_path.move_to(p),
+            path::Segment::CubicTo(c1, c2, p) => bez_path.curve_to(c1, c2, p),
+            path::Segment::Close => bez_path.close_path(),
+        }
+    }
+    bez_path
+}
+
+/// Size in pixels of the frames rendered through `camera`.
+pub fn frame_size(camera: &Option<Camera>) -> (u32, u32) {
+    (get_camera_width(camera), get_camera_height(camera))
+}
+
 fn get_camera_width(camera: &Option<Camera>) -> u32 {
     if let Some(camera) = camera {
         if let Some(Value::Number(w)) = get_property(&camera.properties, "width") {
@@ -175,138 +244,23 @@ fn get_camera_height(camera: &Option<Camera>) -> u32 {
 
 pub async fn render_scene_gpu(
     state: &mut GpuRendererState,
-    items: &[Object],
+    beam_scene: &crate::ast::Scene,
     camera: &Option<Camera>,
 ) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
     let width = get_camera_width(camera);
     let height = get_camera_height(camera);
+    let [r, g, b, a] = renderer::background_color(camera).0;
 
     let mut scene = Scene::new();
-    for item in items {
-        if item.r#type == "circle" {
-            let position = get_position(&item.properties);
-            let radius = get_radius(&item.properties);
-            let fill_color = get_fill_color(&item.properties);
-            let stroke_color = get_stroke_color(&item.properties);
-
-            let circle = kurbo::Circle::new((position.0, position.1), radius);
-            if let Some(color) = fill_color {
-                scene.fill(peniko::Fill::NonZero, kurbo::Affine::IDENTITY, &color, None, &circle);
-            }
-            if let Some(color) = stroke_color {
-                scene.stroke(&kurbo::Stroke::new(1.0), kurbo::Affine::IDENTITY, &color, None, &circle);
-            }
-        } else if item.r#type == "square" {
-            let position = get_position(&item.properties);
-            let size = get_size(&item.properties);
-            let fill_color = get_fill_color(&item.properties);
-            let stroke_color = get_stroke_color(&item.properties);
-
-            let rect = kurbo::Rect::new(
-                position.0,
-                position.1,
-                position.0 + size,
-                position.1 + size,
-            );
-            if let Some(color) = fill_color {
-                scene.fill(peniko::Fill::NonZero, kurbo::Affine::IDENTITY, &color, None, &rect);
-            }
-            if let Some(color) = stroke_color {
-                scene.stroke(&kurbo::Stroke::new(1.0), kurbo::Affine::IDENTITY, &color, None, &rect);
-            }
-        } else if item.r#type == "rectangle" {
-            let position = get_position(&item.properties);
-            let width = get_width(&item.properties);
-            let height = get_height(&item.properties);
-            let fill_color = get_fill_color(&item.properties);
-            let stroke_color = get_stroke_color(&item.properties);
-
-            let rect = kurbo::Rect::new(
-                position.0,
-                position.1,
-                position.0 + width,
-                position.1 + height,
-            );
-            if let Some(color) = fill_color {
-                scene.fill(peniko::Fill::NonZero, kurbo::Affine::IDENTITY, &color, None, &rect);
-            }
-            if let Some(color) = stroke_color {
-                scene.stroke(&kurbo::Stroke::new(1.0), kurbo::Affine::IDENTITY, &color, None, &rect);
-            }
-        } else if item.r#type == "ellipse" {
-            let position = get_position(&item.properties);
-            let rx = get_rx(&item.properties);
-            let ry = get_ry(&item.properties);
-            let fill_color = get_fill_color(&item.properties);
-            let stroke_color = get_stroke_color(&item.properties);
-
-            let ellipse = kurbo::Ellipse::new(
-                (position.0, position.1),
-                (rx, ry),
-                0.0,
-            );
-            if let Some(color) = fill_color {
-                scene.fill(peniko::Fill::NonZero, kurbo::Affine::IDENTITY, &color, None, &ellipse);
-            }
-            if let Some(color) = stroke_color {
-                scene.stroke(&kurbo::Stroke::new(1.0), kurbo::Affine::IDENTITY, &color, None, &ellipse);
-            }
-        } else if item.r#type == "line" {
-            let start = get_p1(&item.properties);
-            let end = get_p2(&item.properties);
-            let stroke_color = get_stroke_color(&item.properties);
-
-            if let Some(color) = stroke_color {
-                scene.stroke(
-                    &kurbo::Stroke::new(1.0),
-                    kurbo::Affine::IDENTITY,
-                    &color,
-                    None,
-                    &kurbo::Line::new(start, end),
-                );
-            }
-        } else if item.r#type == "triangle" {
-            let p1 = get_p1(&item.properties);
-            let p2 = get_p2(&item.properties);
-            let p3 = get_p3(&item.properties);
-            let fill_color = get_fill_color(&item.properties);
-            let stroke_color = get_stroke_color(&item.properties);
-
-            let mut path = kurbo::BezPath::new();
-            path.move_to(p1);
-            path.line_to(p2);
-            path.line_to(p3);
-            path.close_path();
-
-            if let Some(color) = fill_color {
-                scene.fill(peniko::Fill::NonZero, kurbo::Affine::IDENTITY, &color, None, &path);
-            }
-            if let Some(color) = stroke_color {
-                scene.stroke(&kurbo::Stroke::new(1.0), kurbo::Affine::IDENTITY, &color, None, &path);
-            }
-        } else if item.r#type == "arrow" || item.r#type == "double_arrow" {
-            let p1 = get_p1(&item.properties);
-            let p2 = get_p2(&item.properties);
-            let stroke_color = get_stroke_color(&item.properties);
-
-            if let Some(color) = stroke_color {
-                // Draw the main line
-                scene.stroke(
-                    &kurbo::Stroke::new(1.0),
-                    kurbo::Affine::IDENTITY,
-                    &color,
-                    None,
-                    &kurbo::Line::new(p1, p2),
-                );
-
-                // Draw arrowhead at p2
-                draw_arrowhead(&mut scene, p1, p2, &color);
-
-                if item.r#type == "double_arrow" {
-                    // Draw arrowhead at p1
-                    draw_arrowhead(&mut scene, p2, p1, &color);
-                }
-            }
+    for item in beam_scene.draw_order() {
+        let opacity = get_number(&item.properties, "opacity", 1.0).clamp(0.0, 1.0);
+        if opacity < 1.0 {
+            let canvas = kurbo::Rect::new(0.0, 0.0, width as f64, height as f64);
+            scene.push_layer(peniko::Mix::Normal, opacity as f32, kurbo::Affine::IDENTITY, &canvas);
+            draw_item(&mut scene, state, item);
+            scene.pop_layer();
+        } else {
+            draw_item(&mut scene, state, item);
         }
     }
 
@@ -335,7 +289,7 @@ pub async fn render_scene_gpu(
             &scene,
             &view,
             &vello::RenderParams {
-                base_color: peniko::Color::TRANSPARENT,
+                base_color: peniko::Color::from_rgba8(r, g, b, a),
                 width,
                 height,
                 antialiasing_method: vello::AaConfig::Msaa16,
@@ -386,6 +340,312 @@ pub async fn render_scene_gpu(
     ImageBuffer::from_raw(width, height, data.to_vec()).unwrap()
 }
 
+fn draw_item(scene: &mut Scene, state: &mut GpuRendererState, item: &Object) {
+    if item.r#type == "circle" {
+        let position = get_position(&item.properties);
+        let radius = get_radius(&item.properties);
+        let fill_color = get_fill_color(&item.properties);
+        let stroke_color = get_stroke_color(&item.properties);
+
+        let circle = kurbo::Circle::new((position.0, position.1), radius);
+        if let Some(color) = fill_color {
+            scene.fill(peniko::Fill::NonZero, kurbo::Affine::IDENTITY, &color, None, &circle);
+        }
+        if let Some(color) = stroke_color {
+            scene.stroke(&kurbo::Stroke::new(1.0), kurbo::Affine::IDENTITY, &color, None, &circle);
+        }
+    } else if item.r#type == "square" {
+        let position = get_position(&item.properties);
+        let size = get_size(&item.properties);
+        let fill_color = get_fill_color(&item.properties);
+        let stroke_color = get_stroke_color(&item.properties);
+
+        let rect = kurbo::Rect::new(
+            position.0,
+            position.1,
+            position.0 + size,
+            position.1 + size,
+        );
+        let rect = kurbo::RoundedRect::from_rect(rect, get_number(&item.properties, "corner_radius", 0.0));
+        if let Some(color) = fill_color {
+            scene.fill(peniko::Fill::NonZero, kurbo::Affine::IDENTITY, &color, None, &rect);
+        }
+        if let Some(color) = stroke_color {
+            scene.stroke(&kurbo::Stroke::new(1.0), kurbo::Affine::IDENTITY, &color, None, &rect);
+        }
+    } else if item.r#type == "rectangle" {
+        let position = get_position(&item.properties);
+        let width = get_width(&item.properties);
+        let height = get_height(&item.properties);
+        let fill_color = get_fill_color(&item.properties);
+        let stroke_color = get_stroke_color(&item.properties);
+
+        let rect = kurbo::Rect::new(
+            position.0,
+            position.1,
+            position.0 + width,
+            position.1 + height,
+        );
+        let rect = kurbo::RoundedRect::from_rect(rect, get_number(&item.properties, "corner_radius", 0.0));
+        if let Some(color) = fill_color {
+            scene.fill(peniko::Fill::NonZero, kurbo::Affine::IDENTITY, &color, None, &rect);
+        }
+        if let Some(color) = stroke_color {
+            scene.stroke(&kurbo::Stroke::new(1.0), kurbo::Affine::IDENTITY, &color, None, &rect);
+        }
+    } else if item.r#type == "ellipse" {
+        let position = get_position(&item.properties);
+        let rx = get_rx(&item.properties);
+        let ry = get_ry(&item.properties);
+        let fill_color = get_fill_color(&item.properties);
+        let stroke_color = get_stroke_color(&item.properties);
+
+        let ellipse = kurbo::Ellipse::new(
+            (position.0, position.1),
+            (rx, ry),
+            0.0,
+        );
+        if let Some(color) = fill_color {
+            scene.fill(peniko::Fill::NonZero, kurbo::Affine::IDENTITY, &color, None, &ellipse);
+        }
+        if let Some(color) = stroke_color {
+            scene.stroke(&kurbo::Stroke::new(1.0), kurbo::Affine::IDENTITY, &color, None, &ellipse);
+        }
+    } else if item.r#type == "line" {
+        let start = get_p1(&item.properties);
+        let end = get_p2(&item.properties);
+        let stroke_color = get_stroke_color(&item.properties);
+
+        if let Some(color) = stroke_color {
+            scene.stroke(
+                &kurbo::Stroke::new(1.0),
+                kurbo::Affine::IDENTITY,
+                &color,
+                None,
+                &kurbo::Line::new(start, end),
+            );
+        }
+    } else if item.r#type == "triangle" {
+        let p1 = get_p1(&item.properties);
+        let p2 = get_p2(&item.properties);
+        let p3 = get_p3(&item.properties);
+        let fill_color = get_fill_color(&item.properties);
+        let stroke_color = get_stroke_color(&item.properties);
+
+        let mut path = kurbo::BezPath::new();
+        path.move_to(p1);
+        path.line_to(p2);
+        path.line_to(p3);
+        path.close_path();
+
+        if let Some(color) = fill_color {
+            scene.fill(peniko::Fill::NonZero, kurbo::Affine::IDENTITY, &color, None, &path);
+        }
+        if let Some(color) = stroke_color {
+            scene.stroke(&kurbo::Stroke::new(1.0), kurbo::Affine::IDENTITY, &color, None, &path);
+        }
+    } else if item.r#type == "arrow" || item.r#type == "double_arrow" {
+        let p1 = get_p1(&item.properties);
+        let p2 = get_p2(&item.properties);
+        let stroke_color = get_stroke_color(&item.properties);
+
+        if let Some(color) = stroke_color {
+            // Draw the main line
+            scene.stroke(
+                &kurbo::Stroke::new(1.0),
+                kurbo::Affine::IDENTITY,
+                &color,
+                None,
+                &kurbo::Line::new(p1, p2),
+            );
+
+            // Draw arrowhead at p2
+            draw_arrowhead(scene, p1, p2, &color);
+
+            if item.r#type == "double_arrow" {
+                // Draw arrowhead at p1
+                draw_arrowhead(scene, p2, p1, &color);
+            }
+        }
+    } else if matches!(item.r#type.as_str(), "polygon" | "star" | "sector") {
+        let position = get_position(&item.properties);
+        let fill_color = get_fill_color(&item.properties);
+        let stroke_color = get_stroke_color(&item.properties);
+
+        let outline = match item.r#type.as_str() {
+            "polygon" => shapes::regular_polygon(
+                position,
+                get_number(&item.properties, "radius", 50.0),
+                get_number(&item.properties, "sides", 6.0) as usize,
+            ),
+            "star" => {
+                let outer_radius = get_number(&item.properties, "outer_radius", 50.0);
+                shapes::star(
+                    position,
+                    outer_radius,
+                    get_number(&item.properties, "inner_radius", outer_radius / 2.0),
+                    get_number(&item.properties, "point_count", 5.0) as usize,
+                )
+            }
+            _ => shapes::sector(
+                position,
+                get_number(&item.properties, "radius", 50.0),
+                get_number(&item.properties, "inner_radius", 0.0),
+                get_number(&item.properties, "start_angle", 0.0),
+                get_number(&item.properties, "end_angle", 90.0),
+            ),
+        };
+        let path = points_to_path(&outline, true);
+
+        if let Some(color) = fill_color {
+            scene.fill(peniko::Fill::NonZero, kurbo::Affine::IDENTITY, color, None, &path);
+        }
+        if let Some(color) = stroke_color {
+            scene.stroke(&kurbo::Stroke::new(1.0), kurbo::Affine::IDENTITY, color, None, &path);
+        }
+    } else if item.r#type == "path" {
+        let Some(Value::String(d)) = get_property(&item.properties, "d") else {
+            return;
+        };
+        let data = match path::parse_path_data(d) {
+            Ok(data) => data,
+            Err(e) => {
+                eprintln!("Warning: Invalid path data '{}': {}", d, e);
+                return;
+            }
+        };
+        let data = path::trim(&data, get_number(&item.properties, "draw", 1.0));
+        let bez_path = path_data_to_bez_path(&data);
+        let transform = kurbo::Affine::translate(get_position(&item.properties));
+        let fill_rule = match get_property(&item.properties, "fill_rule") {
+            Some(Value::String(rule)) if rule == "evenodd" => peniko::Fill::EvenOdd,
+            _ => peniko::Fill::NonZero,
+        };
+
+        if let Some(color) = get_fill_color(&item.properties) {
+            scene.fill(fill_rule, transform, color, None, &bez_path);
+        }
+        if let Some(color) = get_stroke_color(&item.properties) {
+            let stroke = kurbo::Stroke::new(get_number(&item.properties, "stroke_width", 1.0));
+            scene.stroke(&stroke, transform, color, None, &bez_path);
+        }
+    } else if item.r#type == "code" {
+        fill_shapes(scene, &code::shapes(&item.properties));
+    } else if matches!(item.r#type.as_str(), "bar_chart" | "line_chart" | "pie_chart") {
+        fill_shapes(scene, &chart::shapes(&item.r#type, &item.properties));
+    } else if item.r#type == "table_cell" {
+        fill_shapes(scene, &table::cell_shapes(&item.properties));
+    } else if item.r#type == "number_line" {
+        let line = number_line::layout(&item.properties);
+        let [r, g, b, a] = line.color;
+        let color = peniko::Color::from_rgba8(r, g, b, a);
+        let stroke = kurbo::Stroke::new(line.stroke_width);
+        let mut lines = kurbo::BezPath::new();
+        for (from, to) in std::iter::once((line.start, line.end)).chain(line.ticks) {
+            lines.move_to(from);
+            lines.line_to(to);
+        }
+        scene.stroke(&stroke, kurbo::Affine::IDENTITY, color, None, &lines);
+        for label in &line.labels {
+            let bez_path = path_data_to_bez_path(label);
+            scene.fill(peniko::Fill::NonZero, kurbo::Affine::IDENTITY, color, None, &bez_path);
+        }
+
+        if line.tips.0 {
+            draw_arrowhead(scene, line.end, line.start, &color);
+        }
+        if line.tips.1 {
+            draw_arrowhead(scene, line.start, line.end, &color);
+        }
+        if let Some((tail, tip)) = line.pointer {
+            let [r, g, b, a] = line.pointer_color;
+            let color = peniko::Color::from_rgba8(r, g, b, a);
+            scene.stroke(&stroke, kurbo::Affine::IDENTITY, color, None, &kurbo::Line::new(tail, tip));
+            draw_arrowhead(scene, tail, tip, &color);
+        }
+    } else if item.r#type == "image" {
+        let Some(Value::String(src)) = get_property(&item.properties, "src") else {
+            return;
+        };
+        let Some(source) = state.image(src) else {
+            return;
+        };
+        let fit = match get_property(&item.properties, "fit") {
+            Some(Value::String(fit)) => fit.as_str(),
+            _ => "contain",
+        };
+        let quality = match get_property(&item.properties, "filter") {
+            Some(Value::String(f)) if f == "nearest" => peniko::ImageQuality::Low,
+            Some(Value::String(f)) if f == "lanczos" => peniko::ImageQuality::High,
+            _ => peniko::ImageQuality::Medium,
+        };
+        let dimension = |name| match get_property(&item.properties, name) {
+            Some(Value::Number(n)) => Some(*n),
+            _ => None,
+        };
+        let scale = get_number(&item.properties, "scale", 1.0);
+
+        let frame = assets::frame_size(
+            (source.width, source.height),
+            dimension("width"),
+            dimension("height"),
+        );
+        let placement = assets::fit_image(
+            (source.width, source.height),
+            (frame.0 * scale, frame.1 * scale),
+            fit,
+        );
+        let (crop_x, crop_y, crop_w, crop_h) = placement.crop;
+        let (width, height) = placement.size;
+
+        // 'position' is the center of the image, which is also the rotation origin.
+        let transform = kurbo::Affine::translate(get_position(&item.properties))
+            * kurbo::Affine::rotate(get_number(&item.properties, "rotation", 0.0).to_radians())
+            * kurbo::Affine::translate((-width / 2.0, -height / 2.0));
+        let image_transform = transform
+            * kurbo::Affine::scale_non_uniform(width / crop_w as f64, height / crop_h as f64)
+            * kurbo::Affine::translate((-(crop_x as f64), -(crop_y as f64)));
+
+        scene.push_layer(
+            peniko::Mix::Clip,
+            1.0,
+            transform,
+            &kurbo::Rect::new(0.0, 0.0, width, height),
+        );
+        scene.draw_image(&source.with_quality(quality), image_transform);
+        scene.pop_layer();
+    } else if item.r#type == "arc" || item.r#type == "polyline" {
+        let points = if item.r#type == "arc" {
+            shapes::arc(
+                get_position(&item.properties),
+                get_number(&item.properties, "radius", 50.0),
+                get_number(&item.properties, "start_angle", 0.0),
+                get_number(&item.properties, "end_angle", 90.0),
+            )
+        } else {
+            get_points(&item.properties)
+        };
+        let color = get_stroke_color(&item.properties).unwrap_or(peniko::Color::WHITE);
+
+        scene.stroke(
+            &kurbo::Stroke::new(1.0),
+            kurbo::Affine::IDENTITY,
+            color,
+            None,
+            &points_to_path(&points, false),
+        );
+    }
+}
+
+fn fill_shapes(scene: &mut Scene, shapes: &[Shape]) {
+    for shape in shapes {
+        let [r, g, b, a] = shape.color;
+        let color = peniko::Color::from_rgba8(r, g, b, a);
+        let bez_path = path_data_to_bez_path(&shape.data);
+        scene.fill(peniko::Fill::NonZero, kurbo::Affine::IDENTITY, color, None, &bez_path);
+    }
+}
+
 fn draw_arrowhead(scene: &mut Scene, from: (f64, f64), to: (f64, f64), color: &peniko::Color) {
     let length = 10.0;
     let angle = std::f64::consts::PI / 6.0; // 30 degrees
@@ -0,0 +1,814 @@
+//! `graph` objects: nodes joined by edges, placed by an automatic layout.
+//!
+//! Like axes, a graph is expanded into `path` objects when the script is
+//! parsed: `"net.edges.a-b"` for each edge, `"net.nodes.a"` for each node and
+//! `"net.labels.a"` for its label. All parts share the graph's position, with
+//! their path data relative to it.
+//!
+//! Animating the graph's `nodes` or `edges` to new lists lays the graph out
+//! again and becomes animations of its parts: nodes and edges move to their
+//! new places, new ones fade in and removed ones fade out.
+
+use crate::ast::{Animation, Object, Property, Value};
+use crate::axes;
+use crate::font;
+use crate::path::{self, PathData, Segment};
+use crate::shapes;
+use std::collections::HashMap;
+use std::time::Duration;
+
+const DEFAULT_WIDTH: f64 = 800.0;
+const DEFAULT_HEIGHT: f64 = 600.0;
+const DEFAULT_NODE_RADIUS: f64 = 24.0;
+const DEFAULT_NODE_COLOR: &str = "#58C4DD";
+const DEFAULT_LABEL_COLOR: &str = "#000000";
+const DEFAULT_EDGE_COLOR: &str = "#FFFFFF";
+const FORCE_ITERATIONS: usize = 300;
+
+/// The nodes and edges of a graph at one point of its timeline.
+#[derive(Debug, Clone, PartialEq)]
+struct Graph {
+    nodes: Vec<String>,
+    edges: Vec<(String, String)>,
+}
+
+type Layout = HashMap<String, (f64, f64)>;
+
+/// Replaces every `graph` object with its edges, nodes and labels, and the
+/// animations of its `nodes` and `edges` with animations of those parts.
+pub fn expand_graph_objects(items: &mut Vec<Object>, animations: &mut Vec<Animation>) {
+    if !items.iter().any(|o| o.r#type == "graph") {
+        return;
+    }
+    let mut expanded = Vec::with_capacity(items.len());
+    for object in items.drain(..) {
+        if object.r#type != "graph" {
+            expanded.push(object);
+            continue;
+        }
+        // Changes to the graph itself, in timeline order.
+        let mut changes: Vec<Animation> = Vec::new();
+        animations.retain(|a| {
+            let change =
+                a.target_object == object.name && (a.property == "nodes" || a.property == "edges");
+            if change {
+                changes.push(a.clone());
+            }
+            !change
+        });
+        changes.sort_by_key(|a| a.start);
+        let (parts, part_animations) = expand(&object, &changes);
+        expanded.extend(parts);
+        animations.extend(part_animations);
+    }
+    *items = expanded;
+}
+
+// A change of the graph over a span of the timeline, made of the animations
+// of its `nodes` and `edges` that start at the same time.
+struct Step {
+    start: Duration,
+    end: Option<Duration>,
+    easing: Option<String>,
+    graph: Graph,
+}
+
+fn expand(object: &Object, changes: &[Animation]) -> (Vec<Object>, Vec<Animation>) {
+    let style = Style::new(object);
+    let first = Graph {
+        nodes: node_list(value(object, "nodes")),
+        edges: edge_list(value(object, "edges")),
+    };
+    let mut graph = first.clone();
+
+    let mut steps: Vec<Step> = Vec::new();
+    for change in changes {
+        match change.property.as_str() {
+            "nodes" => graph.nodes = node_list(Some(&change.to)),
+            _ => graph.edges = edge_list(Some(&change.to)),
+        }
+        match steps.last_mut() {
+            Some(step) if step.start == change.start => step.graph = graph.clone(),
+            _ => steps.push(Step {
+                start: change.start,
+                end: change.end,
+                easing: change.easing.clone(),
+                graph: graph.clone(),
+            }),
+        }
+    }
+
+    // Each layout starts from the one before, so the graph settles into its
+    // new shape rather than being rearranged from scratch.
+    let mut layouts = vec![layout(&style, &first, None)];
+    for step in &steps {
+        let previous = layouts.last().unwrap();
+        layouts.push(layout(&style, &step.graph, Some(previous)));
+    }
+    let graphs: Vec<&Graph> = std::iter::once(&first)
+        .chain(steps.iter().map(|s| &s.graph))
+        .collect();
+
+    // Every node and edge the graph ever has, in order of appearance.
+    let mut all_nodes: Vec<&String> = Vec::new();
+    let mut all_edges: Vec<&(String, String)> = Vec::new();
+    for graph in &graphs {
+        for node in &graph.nodes {
+            if !all_nodes.contains(&node) {
+                all_nodes.push(node);
+            }
+        }
+        for edge in &graph.edges {
+            if !all_edges.contains(&edge) {
+                all_edges.push(edge);
+            }
+        }
+    }
+    for (a, b) in &first.edges {
+        if !first.nodes.contains(a) || !first.nodes.contains(b) {
+            eprintln!(
+                "Warning: Edge '{}-{}' of graph '{}' joins a node that is not in its nodes",
+                a, b, object.name
+            );
+        }
+    }
+
+    // The parts of the graph with their properties, edges first so that they
+    // run under the nodes.
+    let mut parts: Vec<(Part, Vec<Property>)> = Vec::new();
+    for (a, b) in &all_edges {
+        parts.push((Part::Edge(a, b), style.edge_properties()));
+    }
+    for node in &all_nodes {
+        let fill = vec![property("fill", Value::Color(style.node_color.clone()))];
+        parts.push((Part::Node(node), style.node_properties(fill)));
+    }
+    if style.font_size > 0.0 {
+        for node in &all_nodes {
+            let fill = vec![property("fill", Value::Color(style.label_color.clone()))];
+            parts.push((Part::Label(node), fill));
+        }
+    }
+
+    let mut objects = Vec::with_capacity(parts.len());
+    let mut animations = Vec::new();
+    for (part, mut properties) in parts {
+        // The part's shape in each layout, or None where it is not in the graph.
+        let shapes: Vec<Option<PathData>> = layouts
+            .iter()
+            .zip(&graphs)
+            .map(|(layout, graph)| style.shape(&part, layout, graph))
+            .collect();
+        // A part that is not there at first waits, invisible, in the place
+        // where it first appears.
+        let Some(first_shape) = shapes.iter().flatten().find(|data| !data.is_empty()) else {
+            continue;
+        };
+        let name = format!("{}.{}", object.name, part.name());
+        properties.insert(0, property("d", data_value(first_shape)));
+        properties.extend(object.shared_properties());
+        if shapes[0].is_none() {
+            properties.retain(|p| p.name != "opacity");
+            properties.push(property("opacity", Value::Number(0.0)));
+        }
+
+        for (i, step) in steps.iter().enumerate() {
+            let animation = |property: &str, to: Value, end: Option<Duration>| Animation {
+                start: step.start,
+                end,
+                target_object: name.clone(),
+                property: property.to_string(),
+                to,
+                easing: step.easing.clone(),
+            };
+            match (&shapes[i], &shapes[i + 1]) {
+                (Some(before), Some(after)) => {
+                    if before != after {
+                        animations.push(animation("d", data_value(after), step.end));
+                    }
+                }
+                (None, Some(after)) => {
+                    // An edge to a node that was already there leaves from
+                    // where that node starts, and follows it as it moves.
+                    let mut entering = layouts[i + 1].clone();
+                    entering.extend(layouts[i].iter().map(|(k, v)| (k.clone(), *v)));
+                    let start = style.shape(&part, &entering, graphs[i + 1]);
+                    let start = start.unwrap_or_else(|| after.clone());
+                    animations.push(animation("d", data_value(&start), None));
+                    if start != *after {
+                        animations.push(animation("d", data_value(after), step.end));
+                    }
+                    animations.push(animation("opacity", Value::Number(1.0), step.end));
+                }
+                (Some(_), None) => {
+                    animations.push(animation("opacity", Value::Number(0.0), step.end));
+                }
+                (None, None) => (),
+            }
+        }
+        objects.push(Object {
+            r#type: "path".to_string(),
+            name,
+            properties,
+        });
+    }
+    (objects, animations)
+}
+
+// An edge, node or node label of a graph.
+enum Part<'a> {
+    Edge(&'a str, &'a str),
+    Node(&'a str),
+    Label(&'a str),
+}
+
+impl Part<'_> {
+    fn name(&self) -> String {
+        match self {
+            Part::Edge(a, b) => format!("edges.{}-{}", a, b),
+            Part::Node(node) => format!("nodes.{}", node),
+            Part::Label(node) => format!("labels.{}", node),
+        }
+    }
+}
+
+// How a graph draws its parts, read from the graph object.
+struct Style {
+    kind: String,
+    root: Option<String>,
+    width: f64,
+    height: f64,
+    node_radius: f64,
+    node_shape: String,
+    node_color: String,
+    node_border_color: Option<String>,
+    label_color: String,
+    font_size: f64,
+    edge_color: String,
+    stroke_width: f64,
+    arrows: bool,
+    tip_length: f64,
+}
+
+impl Style {
+    fn new(object: &Object) -> Style {
+        let string = |name: &str| match value(object, name) {
+            Some(Value::String(s)) => Some(s.clone()),
+            _ => None,
+        };
+        let number = |name: &str| match value(object, name) {
+            Some(Value::Number(n)) => Some(*n),
+            _ => None,
+        };
+        let color = |name: &str| match value(object, name) {
+            Some(Value::Color(c)) => Some(c.clone()),
+            _ => None,
+        };
+        let node_radius = number("node_radius").unwrap_or(DEFAULT_NODE_RADIUS);
+        Style {
+            kind: string("layout").unwrap_or_else(|| "force".to_string()),
+            root: string("root"),
+            width: number("width").unwrap_or(DEFAULT_WIDTH),
+            height: number("height").unwrap_or(DEFAULT_HEIGHT),
+            node_radius,
+            node_shape: string("node_shape").unwrap_or_else(|| "circle".to_string()),
+            node_color: color("node_color").unwrap_or_else(|| DEFAULT_NODE_COLOR.to_string()),
+            node_border_color: color("node_border_color"),
+            label_color: color("label_color").unwrap_or_else(|| DEFAULT_LABEL_COLOR.to_string()),
+            font_size: number("font_size").unwrap_or(node_radius * 0.9),
+            edge_color: color("edge_color").unwrap_or_else(|| DEFAULT_EDGE_COLOR.to_string()),
+            stroke_width: number("stroke_width").unwrap_or(2.0),
+            arrows: string("edge_type").is_some_and(|t| t == "arrow"),
+            tip_length: number("tip_length").unwrap_or(12.0),
+        }
+    }
+
+    // The path data of a part with the nodes placed by `layout`, or None if
+    // the part is not in `graph`.
+    fn shape(&self, part: &Part, layout: &Layout, graph: &Graph) -> Option<PathData> {
+        match *part {
+            Part::Edge(a, b) => {
+                graph.edges.iter().find(|e| e.0 == a && e.1 == b)?;
+                Some(self.edge(*layout.get(a)?, *layout.get(b)?))
+            }
+            Part::Node(node) => layout.get(node).map(|&p| self.node(p)),
+            Part::Label(node) => layout.get(node).map(|&p| self.label(node, p)),
+        }
+    }
+
+    fn node(&self, center: (f64, f64)) -> PathData {
+        let r = self.node_radius;
+        if self.node_shape == "square" {
+            let (x, y) = center;
+            path::polygon(&[
+                (x - r, y - r),
+                (x + r, y - r),
+                (x + r, y + r),
+                (x - r, y + r),
+            ])
+        } else {
+            circle(center, r)
+        }
+    }
+
+    fn node_properties(&self, mut properties: Vec<Property>) -> Vec<Property> {
+        if let Some(border) = &self.node_border_color {
+            properties.push(property("border_color", Value::Color(border.clone())));
+            properties.push(property("stroke_width", Value::Number(self.stroke_width)));
+        }
+        properties
+    }
+
+    fn label(&self, text: &str, center: (f64, f64)) -> PathData {
+        let top = (center.0, center.1 - self.font_size * 0.35);
+        font::label(text, top, 0.5, self.font_size)
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+
+    // A line between the edges of two nodes, with an arrowhead at the second
+    // for directed graphs.
+    fn edge(&self, from: (f64, f64), to: (f64, f64)) -> PathData {
+        let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+        let length = dx.hypot(dy);
+        let (ux, uy) = if length > 0.0 {
+            (dx / length, dy / length)
+        } else {
+            (1.0, 0.0)
+        };
+        let r = self.node_radius;
+        let start = (from.0 + ux * r, from.1 + uy * r);
+        let tip = (to.0 - ux * r, to.1 - uy * r);
+        if !self.arrows {
+            return vec![Segment::MoveTo(start), path::line_to(start, tip)];
+        }
+        let base = (tip.0 - ux * self.tip_length, tip.1 - uy * self.tip_length);
+        let half = self.tip_length / 2.0;
+        let (left, right) = (
+            (base.0 - uy * half, base.1 + ux * half),
+            (base.0 + uy * half, base.1 - ux * half),
+        );
+        vec![
+            Segment::MoveTo(start),
+            path::line_to(start, base),
+            Segment::MoveTo(tip),
+            path::line_to(tip, left),
+            path::line_to(left, right),
+            path::line_to(right, tip),
+            Segment::Close,
+        ]
+    }
+
+    fn edge_properties(&self) -> Vec<Property> {
+        let color = Value::Color(self.edge_color.clone());
+        let mut properties = vec![
+            property("border_color", color.clone()),
+            property("stroke_width", Value::Number(self.stroke_width)),
+        ];
+        if self.arrows {
+            properties.push(property("fill", color));
+        }
+        properties
+    }
+}
+
+// Node positions relative to the graph's center, kept a node's radius inside
+// its `width` by `height` area.
+fn layout(style: &Style, graph: &Graph, previous: Option<&Layout>) -> Layout {
+    let nodes = &graph.nodes;
+    let edges: Vec<(usize, usize)> = graph
+        .edges
+        .iter()
+        .filter_map(|(a, b)| {
+            let a = nodes.iter().position(|n| n == a)?;
+            let b = nodes.iter().position(|n| n == b)?;
+            Some((a, b))
+        })
+        .collect();
+    let half = (
+        (style.width / 2.0 - style.node_radius).max(0.0),
+        (style.height / 2.0 - style.node_radius).max(0.0),
+    );
+    let points = match style.kind.as_str() {
+        "circular" => circular(nodes.len(), half),
+        "tree" => {
+            let root = style
+                .root
+                .as_ref()
+                .and_then(|root| nodes.iter().position(|n| n == root));
+            tree(nodes.len(), &edges, root, half)
+        }
+        "layered" => layered(nodes.len(), &edges, half),
+        other => {
+            if other != "force" {
+                eprintln!("Warning: Unknown graph layout '{}', using 'force'", other);
+            }
+            let start: Vec<(f64, f64)> = circular(nodes.len(), half)
+                .into_iter()
+                .zip(nodes)
+                .map(|(p, node)| previous.and_then(|l| l.get(node).copied()).unwrap_or(p))
+                .collect();
+            force(start, &edges, half)
+        }
+    };
+    nodes.iter().cloned().zip(points).collect()
+}
+
+fn circular(count: usize, half: (f64, f64)) -> Vec<(f64, f64)> {
+    let radius = half.0.min(half.1);
+    (0..count)
+        .map(|i| {
+            if count == 1 {
+                return (0.0, 0.0);
+            }
+            let angle = (-90.0 + 360.0 * i as f64 / count as f64).to_radians();
+            (radius * angle.cos(), radius * angle.sin())
+        })
+        .collect()
+}
+
+// A spring embedding after Fruchterman and Reingold: edges pull their nodes
+// together and all nodes push each other apart, with moves that shrink over
+// a fixed number of rounds. The result is scaled to fit the area.
+fn force(
+    mut points: Vec<(f64, f64)>,
+    edges: &[(usize, usize)],
+    half: (f64, f64),
+) -> Vec<(f64, f64)> {
+    let n = points.len();
+    if n < 2 {
+        return vec![(0.0, 0.0); n];
+    }
+    let k = (4.0 * half.0.max(1.0) * half.1.max(1.0) / n as f64).sqrt();
+    let mut temperature = half.0.max(half.1) / 4.0;
+    let cooling = temperature / FORCE_ITERATIONS as f64;
+    for _ in 0..FORCE_ITERATIONS {
+        let mut moves = vec![(0.0, 0.0); n];
+        for i in 0..n {
+            for j in i + 1..n {
+                let (mut dx, mut dy) = (points[i].0 - points[j].0, points[i].1 - points[j].1);
+                if dx == 0.0 && dy == 0.0 {
+                    // Nodes on top of each other are pulled apart the same way every time.
+                    (dx, dy) = ((j - i) as f64 * 0.01, 0.01);
+                }
+                let distance = dx.hypot(dy);
+                let push = k * k / distance / distance;
+                moves[i] = (moves[i].0 + dx * push, moves[i].1 + dy * push);
+                moves[j] = (moves[j].0 - dx * push, moves[j].1 - dy * push);
+            }
+        }
+        for &(a, b) in edges.iter().filter(|(a, b)| a != b) {
+            let (dx, dy) = (points[a].0 - points[b].0, points[a].1 - points[b].1);
+            let pull = dx.hypot(dy) / k;
+            moves[a] = (moves[a].0 - dx * pull, moves[a].1 - dy * pull);
+            moves[b] = (moves[b].0 + dx * pull, moves[b].1 + dy * pull);
+        }
+        for (point, (mx, my)) in points.iter_mut().zip(moves) {
+            let length = mx.hypot(my);
+            if length > 0.0 {
+                let step = length.min(temperature);
+                *point = (point.0 + mx / length * step, point.1 + my / length * step);
+            }
+        }
+        temperature -= cooling;
+    }
+    fit(points, half)
+}
+
+// Scales and centers points to fill the area without distorting them.
+fn fit(points: Vec<(f64, f64)>, half: (f64, f64)) -> Vec<(f64, f64)> {
+    let (mut min, mut max) = ((f64::MAX, f64::MAX), (f64::MIN, f64::MIN));
+    for &(x, y) in &points {
+        min = (min.0.min(x), min.1.min(y));
+        max = (max.0.max(x), max.1.max(y));
+    }
+    let center = ((min.0 + max.0) / 2.0, (min.1 + max.1) / 2.0);
+    let scale = |span: f64, half: f64| {
+        if span > 0.0 {
+            2.0 * half / span
+        } else {
+            f64::MAX
+        }
+    };
+    let scale = scale(max.0 - min.0, half.0).min(scale(max.1 - min.1, half.1));
+    let scale = if scale == f64::MAX { 0.0 } else { scale };
+    points
+        .into_iter()
+        .map(|(x, y)| ((x - center.0) * scale, (y - center.1) * scale))
+        .collect()
+}
+
+// A tree growing down from `root`, or from the first node, with each parent
+// centered over its children. Nodes the root cannot reach start trees of
+// their own to its right.
+fn tree(
+    count: usize,
+    edges: &[(usize, usize)],
+    root: Option<usize>,
+    half: (f64, f64),
+) -> Vec<(f64, f64)> {
+    let mut neighbors = vec![Vec::new(); count];
+    for &(a, b) in edges {
+        neighbors[a].push(b);
+        neighbors[b].push(a);
+    }
+    let mut depth = vec![usize::MAX; count];
+    let mut slot = vec![0.0; count];
+    let mut leaves = 0;
+    let roots = root.into_iter().chain(0..count).collect::<Vec<_>>();
+    for root in roots {
+        if depth[root] == usize::MAX {
+            depth[root] = 0;
+            place_subtree(root, &neighbors, &mut depth, &mut slot, &mut leaves);
+        }
+    }
+    let max_depth = depth.iter().copied().max().unwrap_or(0);
+    let spread = |value: f64, last: f64, half: f64| {
+        if last > 0.0 {
+            -half + 2.0 * half * value / last
+        } else {
+            0.0
+        }
+    };
+    (0..count)
+        .map(|i| {
+            (
+                spread(slot[i], (leaves - 1) as f64, half.0),
+                spread(depth[i] as f64, max_depth as f64, half.1),
+            )
+        })
+        .collect()
+}
+
+// Gives the subtree under `node` consecutive leaf slots, depth first.
+fn place_subtree(
+    node: usize,
+    neighbors: &[Vec<usize>],
+    depth: &mut [usize],
+    slot: &mut [f64],
+    leaves: &mut usize,
+) {
+    let mut children = Vec::new();
+    for &child in &neighbors[node] {
+        if depth[child] == usize::MAX {
+            depth[child] = depth[node] + 1;
+            children.push(child);
+        }
+    }
+    for &child in &children {
+        place_subtree(child, neighbors, depth, slot, leaves);
+    }
+    slot[node] = match (children.first(), children.last()) {
+        (Some(&first), Some(&last)) => (slot[first] + slot[last]) / 2.0,
+        _ => {
+            *leaves += 1;
+            (*leaves - 1) as f64
+        }
+    };
+}
+
+// Rows from top to bottom where every edge points down: each node sits one
+// row below the lowest node with an edge to it. Within a row, nodes are
+// ordered by the average place of the nodes pointing to them.
+fn layered(count: usize, edges: &[(usize, usize)], half: (f64, f64)) -> Vec<(f64, f64)> {
+    let mut layer = vec![0; count];
+    // At most `count` rounds, so that cycles still end.
+    for _ in 0..count {
+        let mut changed = false;
+        for &(a, b) in edges {
+            if a != b && layer[b] < layer[a] + 1 && layer[a] + 1 < count {
+                layer[b] = layer[a] + 1;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+    let layers = layer.iter().copied().max().map_or(0, |max| max + 1);
+    let mut rows: Vec<Vec<usize>> = vec![Vec::new(); layers];
+    for (node, &l) in layer.iter().enumerate() {
+        rows[l].push(node);
+    }
+    let mut place = vec![0.0; count];
+    for row in rows.iter_mut() {
+        let barycenter = |node: usize| {
+            let parents: Vec<f64> = edges
+                .iter()
+                .filter(|(a, b)| *b == node && layer[*a] < layer[node])
+                .map(|(a, _)| place[*a])
+                .collect();
+            if parents.is_empty() {
+                f64::MAX
+            } else {
+                parents.iter().sum::<f64>() / parents.len() as f64
+            }
+        };
+        let keys: HashMap<usize, f64> = row.iter().map(|&n| (n, barycenter(n))).collect();
+        row.sort_by(|a, b| keys[a].total_cmp(&keys[b]));
+        for (i, &node) in row.iter().enumerate() {
+            place[node] = (i as f64 + 0.5) / row.len() as f64;
+        }
+    }
+    (0..count)
+        .map(|i| {
+            let y = if layers > 1 {
+                -half.1 + 2.0 * half.1 * layer[i] as f64 / (layers - 1) as f64
+            } else {
+                0.0
+            };
+            (-half.0 + 2.0 * half.0 * place[i], y)
+        })
+        .collect()
+}
+
+fn circle(center: (f64, f64), radius: f64) -> PathData {
+    let points = shapes::arc(center, radius, 0.0, 360.0);
+    let mut data = vec![Segment::MoveTo(points[0])];
+    data.extend(
+        points
+            .windows(2)
+            .map(|pair| path::line_to(pair[0], pair[1])),
+    );
+    data.push(Segment::Close);
+    data
+}
+
+fn node_list(value: Option<&Value>) -> Vec<String> {
+    let Some(Value::List(items)) = value else {
+        return Vec::new();
+    };
+    let mut nodes: Vec<String> = Vec::new();
+    for item in items {
+        let node = match item {
+            Value::String(s) => s.clone(),
+            Value::Number(n) => axes::format_number(*n),
+            other => {
+                eprintln!("Warning: Graph node {:?} is not a name", other);
+                continue;
+            }
+        };
+        if !nodes.contains(&node) {
+            nodes.push(node);
+        }
+    }
+    nodes
+}
+
+fn edge_list(value: Option<&Value>) -> Vec<(String, String)> {
+    let Some(Value::List(items)) = value else {
+        return Vec::new();
+    };
+    items
+        .iter()
+        .filter_map(|item| match node_list(Some(item)).as_slice() {
+            [a, b] => Some((a.clone(), b.clone())),
+            _ => {
+                eprintln!("Warning: Graph edge {:?} is not a pair of nodes", item);
+                None
+            }
+        })
+        .collect()
+}
+
+fn data_value(data: &PathData) -> Value {
+    Value::String(path::to_path_data(data))
+}
+
+fn property(name: &str, value: Value) -> Property {
+    Property {
+        name: name.to_string(),
+        value,
+    }
+}
+
+fn value<'a>(object: &'a Object, name: &str) -> Option<&'a Value> {
+    object
+        .properties
+        .iter()
+        .find(|p| p.name == name)
+        .map(|p| &p.value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(values: &[&str]) -> Value {
+        Value::List(
+            values
+                .iter()
+                .map(|s| Value::String(s.to_string()))
+                .collect(),
+        )
+    }
+
+    fn graph(layout: &str, nodes: &[&str], edges: &[(&str, &str)]) -> Object {
+        let edges = edges.iter().map(|(a, b)| names(&[a, b])).collect();
+        Object {
+            r#type: "graph".to_string(),
+            name: "net".to_string(),
+            properties: vec![
+                property("position", Value::Tuple(400.0, 300.0)),
+                property("nodes", names(nodes)),
+                property("edges", Value::List(edges)),
+                property("layout", Value::String(layout.to_string())),
+                property("width", Value::Number(648.0)),
+                property("height", Value::Number(448.0)),
+            ],
+        }
+    }
+
+    fn positions(object: &Object) -> Layout {
+        let graph = Graph {
+            nodes: node_list(value(object, "nodes")),
+            edges: edge_list(value(object, "edges")),
+        };
+        layout(&Style::new(object), &graph, None)
+    }
+
+    #[test]
+    fn test_tree_layout() {
+        let object = graph(
+            "tree",
+            &["r", "a", "b", "c"],
+            &[("r", "a"), ("r", "b"), ("b", "c")],
+        );
+        let layout = positions(&object);
+        // The area is 600 by 400 once the node radius is kept clear of its edges.
+        assert_eq!(layout["a"], (-300.0, 0.0));
+        assert_eq!(layout["c"], (300.0, 200.0));
+        assert_eq!(layout["b"], (300.0, 0.0));
+        assert_eq!(layout["r"], (0.0, -200.0));
+    }
+
+    #[test]
+    fn test_layered_layout_follows_edges() {
+        let edges = [("a", "b"), ("a", "c"), ("b", "d"), ("c", "d")];
+        let layout = positions(&graph("layered", &["d", "c", "b", "a"], &edges));
+        assert_eq!(layout["a"].1, -200.0);
+        assert_eq!(layout["b"].1, layout["c"].1);
+        assert_eq!(layout["d"], (0.0, 200.0));
+    }
+
+    #[test]
+    fn test_force_layout_is_deterministic_and_fits() {
+        let edges = [("a", "b"), ("b", "c"), ("c", "a"), ("c", "d")];
+        let object = graph("force", &["a", "b", "c", "d"], &edges);
+        let layout = positions(&object);
+        assert_eq!(layout, positions(&object));
+        for &(x, y) in layout.values() {
+            assert!(x.abs() <= 300.0 + 1e-9 && y.abs() <= 200.0 + 1e-9);
+        }
+        // The leaf hangs off the triangle, further from the others than they are from each other.
+        let distance = |a: &str, b: &str| {
+            let (p, q) = (layout[a], layout[b]);
+            (p.0 - q.0).hypot(p.1 - q.1)
+        };
+        assert!(distance("a", "d") > distance("a", "b"));
+    }
+
+    #[test]
+    fn test_expand_names_parts() {
+        let mut items = vec![graph("circular", &["a", "b"], &[("a", "b")])];
+        expand_graph_objects(&mut items, &mut Vec::new());
+        let names: Vec<&str> = items.iter().map(|o| o.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "net.edges.a-b",
+                "net.nodes.a",
+                "net.nodes.b",
+                "net.labels.a",
+                "net.labels.b"
+            ]
+        );
+        assert!(items.iter().all(|o| o.r#type == "path"));
+    }
+
+    #[test]
+    fn test_adding_a_node_fades_it_in() {
+        let mut items = vec![graph("circular", &["a", "b"], &[("a", "b")])];
+        let mut animations = vec![Animation {
+            start: Duration::from_secs(1),
+            end: Some(Duration::from_secs(2)),
+            target_object: "net".to_string(),
+            property: "nodes".to_string(),
+            to: names(&["a", "b", "c"]),
+            easing: None,
+        }];
+        expand_graph_objects(&mut items, &mut animations);
+
+        let node = items.iter().find(|o| o.name == "net.nodes.c").unwrap();
+        assert_eq!(value(node, "opacity"), Some(&Value::Number(0.0)));
+        let targets: Vec<(&str, &str)> = animations
+            .iter()
+            .map(|a| (a.target_object.as_str(), a.property.as_str()))
+            .collect();
+        assert!(!targets.contains(&("net", "nodes")));
+        assert!(targets.contains(&("net.nodes.b", "d")));
+        assert!(targets.contains(&("net.nodes.c", "opacity")));
+        assert!(targets.contains(&("net.edges.a-b", "d")));
+    }
+}
@@ -0,0 +1,753 @@
+//! Layout references and stacks, which place objects relative to the bounds
+//! of other objects instead of at fixed coordinates.
+//!
+//! - `position: below("box", 20)` puts an object 20 pixels below `box`,
+//!   centered on it. `above`, `left_of` and `right_of` work alike, and the gap
+//!   defaults to 20.
+//! - `align: left_of("title")` moves an object sideways until its left edge
+//!   lines up with that of `title`. `right_of`, `top_of` and `bottom_of` line
+//!   up the other edges, and the number, 0 by default, shifts the object
+//!   further right or down.
+//! - `hstack` and `vstack` objects line up the objects named in their `items`
+//!   from left to right or from top to bottom, `spacing` apart and aligned by
+//!   their `align`: `"top"`, `"center"` or `"bottom"` for rows, `"left"`,
+//!   `"center"` or `"right"` for columns. A stack with a `position` is
+//!   centered on it, otherwise its first item stays where it is.
+//! - Anchors such as `"box".top_right` stand for a point of another object
+//!   wherever a point is expected: a corner or edge of its bounds, its
+//!   `center`, the `start`, `end` or `midpoint` of a line or path, or any of
+//!   its points by name, such as `"tri".p2`.
+//!
+//! The animator resolves all of them for every frame, after that frame's
+//! animations are applied, so an object placed below another follows it as
+//! it moves. An object expanded into parts moves as a whole.
+
+use crate::animator::is_member;
+use crate::ast::{Object, Property, Reference, Value};
+use crate::bounds::{self, Bounds};
+
+pub const STACK_TYPES: &[&str] = &["hstack", "vstack"];
+
+const DEFAULT_GAP: f64 = 20.0;
+
+/// Replaces every layout reference and anchor in `items` with a point and
+/// arranges the items of every stack. Stacks themselves draw nothing.
+pub fn resolve_layout(items: &mut [Object]) {
+    let mut pending = tasks(items);
+    while !pending.is_empty() {
+        // Objects are placed only once everything they depend on is in place.
+        let ready = (0..pending.len()).find(|&i| {
+            pending[i].dependencies().iter().all(|name| {
+                pending.iter().enumerate().all(|(j, other)| {
+                    j == i || other.encloses(items, &pending[i]) || !other.moves(items, name)
+                })
+            })
+        });
+        let Some(ready) = ready else {
+            for task in &pending {
+                eprintln!(
+                    "Warning: Could not lay out '{}', its position depends on itself",
+                    task.name
+                );
+                task.resolve_anchors(items);
+                let members = members(items, &task.name);
+                detach(items, &members);
+            }
+            break;
+        };
+        pending.remove(ready).resolve(items);
+    }
+}
+
+/// Replaces anchors such as `"box".top_right` in `value`, such as the target
+/// of an animation, with the points of `items` they stand for.
+pub fn resolve_anchors(items: &[Object], value: &mut Value) {
+    match value {
+        Value::Reference(Reference::Anchor { object, anchor }) => {
+            let point = anchor_point(items, object, anchor).unwrap_or_else(|| {
+                eprintln!("Warning: No anchor '{}' on '{}'", anchor, object);
+                (0.0, 0.0)
+            });
+            *value = Value::Tuple(point.0, point.1);
+        }
+        Value::List(values) => values.iter_mut().for_each(|v| resolve_anchors(items, v)),
+        _ => (),
+    }
+}
+
+/// Whether `value` contains an anchor, which is resolved anew for every frame.
+pub fn has_anchors(value: &Value) -> bool {
+    match value {
+        Value::Reference(Reference::Anchor { .. }) => true,
+        Value::List(values) => values.iter().any(has_anchors),
+        _ => false,
+    }
+}
+
+fn anchor_point(items: &[Object], name: &str, anchor: &str) -> Option<(f64, f64)> {
+    if let Some(object) = items.iter().find(|o| o.name == name) {
+        if let Some(point) = bounds::path_anchor(object, anchor) {
+            return Some(point);
+        }
+        if let Some(Value::Tuple(x, y)) = value(object, anchor) {
+            return Some((x, y));
+        }
+    }
+    union_bounds(items, &members(items, name))?.anchor(anchor)
+}
+
+struct Task {
+    /// The object, group or stack that this task moves.
+    name: String,
+    stack: Option<Stack>,
+    position: Option<Reference>,
+    align: Option<Reference>,
+    /// The objects whose anchors the task's properties refer to.
+    anchors: Vec<String>,
+}
+
+struct Stack {
+    vertical: bool,
+    items: Vec<String>,
+    spacing: f64,
+    align: String,
+    position: Option<(f64, f64)>,
+}
+
+fn tasks(items: &[Object]) -> Vec<Task> {
+    let mut tasks: Vec<Task> = Vec::new();
+    for object in items {
+        let stack = STACK_TYPES
+            .contains(&object.r#type.as_str())
+            .then(|| Stack::new(object));
+        let position = layout_reference(object, "position");
+        let align = layout_reference(object, "align");
+        let mut anchors = Vec::new();
+        for property in &object.properties {
+            anchor_targets(&property.value, &mut anchors);
+        }
+        if stack.is_none() && position.is_none() && align.is_none() && anchors.is_empty() {
+            continue;
+        }
+        // The parts of an expanded object share its references and move with it.
+        let name = if stack.is_some() {
+            object.name.clone()
+        } else {
+            root_name(&object.name).to_string()
+        };
+        if tasks.iter().any(|t| t.name == name) {
+            continue;
+        }
+        tasks.push(Task {
+            name,
+            stack,
+            position,
+            align,
+            anchors,
+        });
+    }
+    tasks
+}
+
+fn anchor_targets(value: &Value, targets: &mut Vec<String>) {
+    match value {
+        Value::Reference(Reference::Anchor { object, .. }) => targets.push(object.clone()),
+        Value::List(values) => values.iter().for_each(|v| anchor_targets(v, targets)),
+        _ => (),
+    }
+}
+
+impl Task {
+    // The objects that must be in place before this task can run.
+    fn dependencies(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = [&self.position, &self.align]
+            .into_iter()
+            .flatten()
+            .filter_map(|reference| match reference {
+                Reference::Layout { target, .. } => Some(target.as_str()),
+                _ => None,
+            })
+            .collect();
+        if let Some(stack) = &self.stack {
+            names.extend(stack.items.iter().map(String::as_str));
+        }
+        names.extend(self.anchors.iter().map(String::as_str));
+        names
+    }
+
+    // Whether this task may move any of the objects that make up `name`.
+    fn moves(&self, items: &[Object], name: &str) -> bool {
+        let moved = members(items, &self.name);
+        members(items, name).iter().any(|i| moved.contains(i))
+    }
+
+    // Whether this is a stack that `other` is laid out within, which then
+    // has to wait for `other` rather than the other way around.
+    fn encloses(&self, items: &[Object], other: &Task) -> bool {
+        let moved = members(items, &self.name);
+        self.stack.is_some()
+            && members(items, &other.name)
+                .iter()
+                .all(|i| moved.contains(i))
+    }
+
+    fn resolve_anchors(&self, items: &mut [Object]) {
+        for i in 0..items.len() {
+            if !is_member(&items[i].name, &self.name) {
+                continue;
+            }
+            for j in 0..items[i].properties.len() {
+                if has_anchors(&items[i].properties[j].value) {
+                    let mut value = items[i].properties[j].value.clone();
+                    resolve_anchors(items, &mut value);
+                    items[i].properties[j].value = value;
+                }
+            }
+        }
+    }
+
+    fn resolve(self, items: &mut [Object]) {
+        self.resolve_anchors(items);
+        // A stack's own properties may have been given by anchors.
+        let stack = self.stack.as_ref().and_then(|_| {
+            items
+                .iter()
+                .find(|o| o.name == self.name && STACK_TYPES.contains(&o.r#type.as_str()))
+                .map(Stack::new)
+        });
+        let members = match &stack {
+            Some(stack) => stack.arrange(items, &self.name),
+            None => {
+                let members = members(items, &self.name);
+                detach(items, &members);
+                members
+            }
+        };
+        if let Some(reference) = &self.position {
+            place(items, &members, &self.name, reference, false);
+        }
+        if let Some(reference) = &self.align {
+            place(items, &members, &self.name, reference, true);
+        }
+    }
+}
+
+impl Stack {
+    fn new(object: &Object) -> Stack {
+        let property = |name: &str| {
+            object
+                .properties
+                .iter()
+                .find(|p| p.name == name)
+                .map(|p| &p.value)
+        };
+        let items = match property("items") {
+            Some(Value::List(items)) => items
+                .iter()
+                .filter_map(|item| match item {
+                    Value::String(name) => Some(name.clone()),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        Stack {
+            vertical: object.r#type == "vstack",
+            items,
+            spacing: match property("spacing") {
+                Some(Value::Number(n)) => *n,
+                _ => DEFAULT_GAP,
+            },
+            align: match property("align") {
+                Some(Value::String(align)) => align.clone(),
+                _ => "center".to_string(),
+            },
+            position: match property("position") {
+                Some(Value::Tuple(x, y)) => Some((*x, *y)),
+                _ => None,
+            },
+        }
+    }
+
+    // Lines up the stack's items and returns the objects they consist of.
+    fn arrange(&self, items: &mut [Object], name: &str) -> Vec<usize> {
+        let mut all = Vec::new();
+        let mut previous: Option<Bounds> = None;
+        let mut first: Option<Bounds> = None;
+        for item in &self.items {
+            let item_members = members(items, item);
+            detach(items, &item_members);
+            let Some(bounds) = union_bounds(items, &item_members) else {
+                eprintln!("Warning: No object named '{}' in stack '{}'", item, name);
+                continue;
+            };
+            let delta = match (previous, first) {
+                (Some(previous), Some(first)) => {
+                    let cross = |min: f64, max: f64, first_min: f64, first_max: f64| match self
+                        .align
+                        .as_str()
+                    {
+                        "top" | "left" => first_min - min,
+                        "bottom" | "right" => first_max - max,
+                        _ => (first_min + first_max - min - max) / 2.0,
+                    };
+                    if self.vertical {
+                        (
+                            cross(bounds.min.0, bounds.max.0, first.min.0, first.max.0),
+                            previous.max.1 + self.spacing - bounds.min.1,
+                        )
+                    } else {
+                        (
+                            previous.max.0 + self.spacing - bounds.min.0,
+                            cross(bounds.min.1, bounds.max.1, first.min.1, first.max.1),
+                        )
+                    }
+                }
+                _ => {
+                    first = Some(bounds);
+                    (0.0, 0.0)
+                }
+            };
+            translate_all(items, &item_members, delta);
+            previous = Some(moved(bounds, delta));
+            all.extend(item_members);
+        }
+        if let (Some(position), Some(bounds)) = (self.position, union_bounds(items, &all)) {
+            let center = bounds.center();
+            translate_all(items, &all, (position.0 - center.0, position.1 - center.1));
+        }
+        all
+    }
+}
+
+// Moves the objects `moved` as a whole next to, or in line with, the target of
+// `reference`.
+fn place(items: &mut [Object], moved: &[usize], name: &str, reference: &Reference, align: bool) {
+    let Reference::Layout {
+        relation,
+        target,
+        gap,
+    } = reference
+    else {
+        return;
+    };
+    let Some(own) = union_bounds(items, moved) else {
+        return;
+    };
+    let Some(other) = union_bounds(items, &members(items, target)) else {
+        eprintln!(
+            "Warning: No object named '{}' to lay out '{}' against",
+            target, name
+        );
+        return;
+    };
+    let (own_center, other_center) = (own.center(), other.center());
+    let delta = if align {
+        let gap = gap.unwrap_or(0.0);
+        match relation.as_str() {
+            "left_of" => (other.min.0 + gap - own.min.0, 0.0),
+            "right_of" => (other.max.0 + gap - own.max.0, 0.0),
+            "top_of" => (0.0, other.min.1 + gap - own.min.1),
+            "bottom_of" => (0.0, other.max.1 + gap - own.max.1),
+            _ => {
+                eprintln!(
+                    "Warning: Unknown alignment '{}' for '{}', expected left_of, right_of, top_of or bottom_of",
+                    relation, name
+                );
+                return;
+            }
+        }
+    } else {
+        let gap = gap.unwrap_or(DEFAULT_GAP);
+        match relation.as_str() {
+            "below" => (other_center.0 - own_center.0, other.max.1 + gap - own.min.1),
+            "above" => (other_center.0 - own_center.0, other.min.1 - gap - own.max.1),
+            "right_of" => (other.max.0 + gap - own.min.0, other_center.1 - own_center.1),
+            "left_of" => (other.min.0 - gap - own.max.0, other_center.1 - own_center.1),
+            _ => {
+                eprintln!(
+                    "Warning: Unknown position '{}' for '{}', expected below, above, left_of or right_of",
+                    relation, name
+                );
+                return;
+            }
+        }
+    };
+    translate_all(items, moved, delta);
+}
+
+// The objects that make up `name`: the object or group itself, or for a
+// stack, the objects of its items.
+fn members(items: &[Object], name: &str) -> Vec<usize> {
+    let mut members = Vec::new();
+    collect_members(items, name, &mut Vec::new(), &mut members);
+    members
+}
+
+fn collect_members(
+    items: &[Object],
+    name: &str,
+    stacks: &mut Vec<String>,
+    members: &mut Vec<usize>,
+) {
+    for (i, object) in items.iter().enumerate() {
+        if !is_member(&object.name, name) {
+            continue;
+        }
+        if !STACK_TYPES.contains(&object.r#type.as_str()) {
+            if !members.contains(&i) {
+                members.push(i);
+            }
+        } else if !stacks.contains(&object.name) {
+            stacks.push(object.name.clone());
+            for item in Stack::new(object).items {
+                collect_members(items, &item, stacks, members);
+            }
+        }
+    }
+}
+
+fn union_bounds(items: &[Object], members: &[usize]) -> Option<Bounds> {
+    members
+        .iter()
+        .filter_map(|&i| bounds::object_bounds(&items[i]))
+        .reduce(|a, b| a.union(&b))
+}
+
+// Drops the layout references of `members`, leaving objects placed by their
+// position at the origin, ready to be measured and moved.
+fn detach(items: &mut [Object], members: &[usize]) {
+    for &i in members {
+        let object = &mut items[i];
+        let placed_by_points =
+            object.r#type == "polyline" || !bounds::point_properties(&object.r#type).is_empty();
+        object.properties.retain(|p| {
+            !(is_layout_reference(&p.value) && (p.name == "align" || placed_by_points))
+        });
+        for property in &mut object.properties {
+            if is_layout_reference(&property.value) {
+                property.value = Value::Tuple(0.0, 0.0);
+            }
+        }
+    }
+}
+
+fn translate_all(items: &mut [Object], members: &[usize], delta: (f64, f64)) {
+    for &i in members {
+        translate(&mut items[i], delta);
+    }
+}
+
+fn translate(object: &mut Object, (dx, dy): (f64, f64)) {
+    let shift = |value: &Value| match value {
+        Value::Tuple(x, y) => Value::Tuple(x + dx, y + dy),
+        other => other.clone(),
+    };
+    let point_properties = bounds::point_properties(&object.r#type);
+    if object.r#type == "polyline" {
+        for property in object.properties.iter_mut().filter(|p| p.name == "points") {
+            if let Value::List(points) = &property.value {
+                property.value = Value::List(points.iter().map(shift).collect());
+            }
+        }
+    } else if !point_properties.is_empty() {
+        for (name, default) in point_properties {
+            let value = value(object, name).unwrap_or(Value::Tuple(default.0, default.1));
+            set(object, name, shift(&value));
+        }
+        // A triangle's position, if set, is the center it rotates about.
+        if let Some(position) = value(object, "position") {
+            set(object, "position", shift(&position));
+        }
+    } else {
+        let position = value(object, "position").unwrap_or(Value::Tuple(0.0, 0.0));
+        set(object, "position", shift(&position));
+    }
+}
+
+fn value(object: &Object, name: &str) -> Option<Value> {
+    object
+        .properties
+        .iter()
+        .find(|p| p.name == name)
+        .map(|p| p.value.clone())
+}
+
+fn set(object: &mut Object, name: &str, value: Value) {
+    match object.properties.iter_mut().find(|p| p.name == name) {
+        Some(property) => property.value = value,
+        None => object.properties.push(Property {
+            name: name.to_string(),
+            value,
+        }),
+    }
+}
+
+fn layout_reference(object: &Object, name: &str) -> Option<Reference> {
+    object
+        .properties
+        .iter()
+        .find(|p| p.name == name && is_layout_reference(&p.value))
+        .and_then(|p| match &p.value {
+            Value::Reference(reference) => Some(reference.clone()),
+            _ => None,
+        })
+}
+
+fn is_layout_reference(value: &Value) -> bool {
+    matches!(value, Value::Reference(Reference::Layout { .. }))
+}
+
+fn moved(bounds: Bounds, (dx, dy): (f64, f64)) -> Bounds {
+    Bounds {
+        min: (bounds.min.0 + dx, bounds.min.1 + dy),
+        max: (bounds.max.0 + dx, bounds.max.1 + dy),
+    }
+}
+
+// The name of the object that `name` is a part of, such as `"eq"` for `"eq.3"`.
+fn root_name(name: &str) -> &str {
+    name.find(['.', '['])
+        .map(|end| &name[..end])
+        .filter(|root| !root.is_empty())
+        .unwrap_or(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout(relation: &str, target: &str, gap: Option<f64>) -> Value {
+        Value::Reference(Reference::Layout {
+            relation: relation.to_string(),
+            target: target.to_string(),
+            gap,
+        })
+    }
+
+    fn position(items: &[Object], name: &str) -> Option<Value> {
+        items
+            .iter()
+            .find(|o| o.name == name)
+            .and_then(|o| match value(o, "position") {
+                Some(Value::Tuple(x, y)) => Some(Value::Tuple(x.round(), y.round())),
+                other => other,
+            })
+    }
+
+    #[test]
+    fn test_position_next_to_another_object() {
+        let mut items = vec![
+            Object::new(
+                "circle",
+                "label",
+                vec![
+                    ("radius", Value::Number(10.0)),
+                    ("position", layout("below", "box", None)),
+                ],
+            ),
+            Object::new(
+                "square",
+                "box",
+                vec![("position", Value::Tuple(400.0, 300.0))],
+            ),
+            Object::new(
+                "square",
+                "side",
+                vec![
+                    ("size", Value::Number(20.0)),
+                    ("position", layout("right_of", "label", Some(5.0))),
+                ],
+            ),
+        ];
+        resolve_layout(&mut items);
+        // 20 below the box's bottom edge at 350, centered on it.
+        assert_eq!(position(&items, "label"), Some(Value::Tuple(400.0, 380.0)));
+        // Placed after "label", which it depends on.
+        assert_eq!(position(&items, "side"), Some(Value::Tuple(425.0, 380.0)));
+    }
+
+    #[test]
+    fn test_align_edges() {
+        let mut items = vec![
+            Object::new(
+                "rectangle",
+                "title",
+                vec![
+                    ("position", Value::Tuple(300.0, 100.0)),
+                    ("width", Value::Number(200.0)),
+                ],
+            ),
+            Object::new(
+                "line",
+                "rule",
+                vec![
+                    ("p1", Value::Tuple(0.0, 200.0)),
+                    ("p2", Value::Tuple(100.0, 200.0)),
+                    ("align", layout("left_of", "title", None)),
+                ],
+            ),
+        ];
+        resolve_layout(&mut items);
+        let rule = &items[1];
+        assert_eq!(value(rule, "p1"), Some(Value::Tuple(200.0, 200.0)));
+        assert_eq!(value(rule, "p2"), Some(Value::Tuple(300.0, 200.0)));
+        assert_eq!(value(rule, "align"), None);
+    }
+
+    #[test]
+    fn test_parts_move_together() {
+        let reference = layout("above", "base", Some(0.0));
+        let mut items = vec![
+            Object::new("square", "base", vec![("position", Value::Tuple(0.0, 0.0))]),
+            Object::new(
+                "path",
+                "eq.0",
+                vec![
+                    ("d", Value::String("M 0 0 L 10 0 L 10 10 Z".to_string())),
+                    ("position", reference.clone()),
+                ],
+            ),
+            Object::new(
+                "path",
+                "eq.1",
+                vec![
+                    ("d", Value::String("M 20 0 L 30 0 L 30 10 Z".to_string())),
+                    ("position", reference),
+                ],
+            ),
+        ];
+        resolve_layout(&mut items);
+        // The formula spans 0..30 by 0..10 and ends up centered above the square.
+        assert_eq!(position(&items, "eq.0"), Some(Value::Tuple(-15.0, -60.0)));
+        assert_eq!(position(&items, "eq.1"), Some(Value::Tuple(-15.0, -60.0)));
+    }
+
+    #[test]
+    fn test_stacks() {
+        let mut items = vec![
+            Object::new("square", "a", vec![("size", Value::Number(40.0))]),
+            Object::new("circle", "b", vec![("radius", Value::Number(10.0))]),
+            Object::new(
+                "hstack",
+                "row",
+                vec![
+                    (
+                        "items",
+                        Value::List(vec![
+                            Value::String("a".to_string()),
+                            Value::String("b".to_string()),
+                        ]),
+                    ),
+                    ("spacing", Value::Number(10.0)),
+                    ("align", Value::String("top".to_string())),
+                    ("position", Value::Tuple(100.0, 100.0)),
+                ],
+            ),
+            Object::new(
+                "vstack",
+                "column",
+                vec![
+                    (
+                        "items",
+                        Value::List(vec![
+                            Value::String("row".to_string()),
+                            Value::String("c".to_string()),
+                        ]),
+                    ),
+                    ("spacing", Value::Number(0.0)),
+                    ("align", Value::String("left".to_string())),
+                ],
+            ),
+            Object::new("circle", "c", vec![("radius", Value::Number(5.0))]),
+        ];
+        resolve_layout(&mut items);
+        // The row spans 70 wide and 40 high, centered on (100, 100).
+        assert_eq!(position(&items, "a"), Some(Value::Tuple(85.0, 100.0)));
+        assert_eq!(position(&items, "b"), Some(Value::Tuple(125.0, 90.0)));
+        // The column keeps the row in place and puts "c" under it, flush left.
+        assert_eq!(position(&items, "c"), Some(Value::Tuple(70.0, 125.0)));
+    }
+
+    #[test]
+    fn test_cycles_and_missing_targets_leave_objects_placed() {
+        let mut items = vec![
+            Object::new(
+                "circle",
+                "a",
+                vec![("position", layout("below", "b", None))],
+            ),
+            Object::new(
+                "circle",
+                "b",
+                vec![("position", layout("below", "a", None))],
+            ),
+            Object::new(
+                "circle",
+                "c",
+                vec![("position", layout("below", "nothing", None))],
+            ),
+        ];
+        resolve_layout(&mut items);
+        for object in &items {
+            assert_eq!(value(object, "position"), Some(Value::Tuple(0.0, 0.0)));
+        }
+    }
+
+    fn anchor(object: &str, anchor: &str) -> Value {
+        Value::Reference(Reference::Anchor {
+            object: object.to_string(),
+            anchor: anchor.to_string(),
+        })
+    }
+
+    #[test]
+    fn test_anchors_follow_layout() {
+        let mut items = vec![
+            Object::new(
+                "arrow",
+                "pointer",
+                vec![
+                    ("p1", anchor("label", "bottom_left")),
+                    ("p2", anchor("line", "midpoint")),
+                ],
+            ),
+            Object::new(
+                "line",
+                "line",
+                vec![
+                    ("p1", Value::Tuple(0.0, 100.0)),
+                    ("p2", Value::Tuple(200.0, 300.0)),
+                ],
+            ),
+            Object::new(
+                "square",
+                "label",
+                vec![
+                    ("size", Value::Number(20.0)),
+                    ("position", layout("right_of", "line", Some(0.0))),
+                ],
+            ),
+            Object::new(
+                "circle",
+                "dot",
+                vec![
+                    ("position", anchor("line", "p2")),
+                    ("radius", Value::Number(5.0)),
+                ],
+            ),
+            Object::new(
+                "circle",
+                "lost",
+                vec![("position", anchor("line", "corner"))],
+            ),
+        ];
+        resolve_layout(&mut items);
+        let pointer = &items[0];
+        // "label" is placed before the arrow that points from it.
+        assert_eq!(value(pointer, "p1"), Some(Value::Tuple(200.0, 210.0)));
+        assert_eq!(value(pointer, "p2"), Some(Value::Tuple(100.0, 200.0)));
+        assert_eq!(position(&items, "dot"), Some(Value::Tuple(200.0, 300.0)));
+        assert_eq!(position(&items, "lost"), Some(Value::Tuple(0.0, 0.0)));
+    }
+}
@@ -1,36 +1,369 @@
-use clap::Parser;
+use clap::{Args, Parser, Subcommand};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 mod animator;
+mod assets;
 mod ast;
+mod axes;
+mod bounds;
+mod chart;
+mod code;
+mod color;
+mod export;
+mod font;
+mod formula;
 mod gpu_renderer;
+mod graph;
+mod layout;
+mod math;
+mod number_line;
+mod output;
 mod parser;
+mod path;
+mod reload;
 mod renderer;
+mod serve;
+mod shapes;
+mod sink;
+mod storyboard;
+mod svg;
+mod table;
+mod transform;
+mod watch;
 
 #[derive(Parser)]
-#[clap(version = "1.0", author = "Your Name")]
+#[clap(version = "1.0", author = "Your Name", args_conflicts_with_subcommands = true)]
 struct Cli {
+    #[clap(subcommand)]
+    command: Option<Command>,
+
+    #[clap(flatten)]
+    render: Option<RenderArgs>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Render a script, which is also what happens without a command
+    Render(RenderArgs),
+    /// Write one image of thumbnails from across a script, for reviews
+    Storyboard(StoryboardArgs),
+    /// Render previews of each scene and render them again when the script
+    /// or the files it uses change
+    Watch(WatchArgs),
+    /// Preview a script in the browser, with a timeline to scrub through
+    Serve(ServeArgs),
+}
+
+#[derive(Args)]
+struct RenderArgs {
+    /// The path to the .beam file
+    path: PathBuf,
+
+    /// Render with GPU acceleration
+    #[clap(long)]
+    gpu: bool,
+
+    /// Frames per second, such as 24 or 29.97, instead of the camera's `fps`
+    #[clap(long)]
+    fps: Option<f64>,
+
+    /// Where to write the animation, next to the script by default, or `-`
+    /// for standard output
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+
+    /// Replace the output if it already exists
+    #[clap(long)]
+    force: bool,
+
+    /// The kind of file to write, by default judged from the output's
+    /// extension, or mp4
+    #[clap(long, value_enum)]
+    format: Option<export::Format>,
+
+    /// How many times a GIF, APNG or WebP animation plays, 0 for forever
+    #[clap(long, default_value_t = 0)]
+    loops: u32,
+
+    /// Dither GIF frames to smooth gradients
+    #[clap(long)]
+    dither: bool,
+
+    /// File names of png and qoi frames, such as `frame_%05d.png`
+    #[clap(long)]
+    pattern: Option<String>,
+
+    /// Render only the scene with this name
+    #[clap(long)]
+    scene: Option<String>,
+
+    /// Write only this frame, counted from 0, as a PNG image
+    #[clap(long, conflicts_with_all = ["at", "from", "to", "format", "loops", "dither", "pattern"])]
+    frame: Option<u64>,
+
+    /// Write only the frame at this time, such as 2.5s, as a PNG image
+    #[clap(long, conflicts_with_all = ["from", "to", "format", "loops", "dither", "pattern"])]
+    at: Option<String>,
+
+    /// Start rendering at this time, such as 10s
+    #[clap(long)]
+    from: Option<String>,
+
+    /// Stop rendering at this time, such as 15s
+    #[clap(long)]
+    to: Option<String>,
+}
+
+#[derive(Args)]
+struct StoryboardArgs {
+    /// The path to the .beam file
+    path: PathBuf,
+
+    /// Frames per second, such as 24 or 29.97, instead of the camera's `fps`
+    #[clap(long)]
+    fps: Option<f64>,
+
+    /// Where to write the storyboard, as `{script}_storyboard.png` next to
+    /// the script by default
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+
+    /// Replace the output if it already exists
+    #[clap(long)]
+    force: bool,
+
+    /// Time between thumbnails, such as 2s or 48f
+    #[clap(long, default_value = "1s")]
+    every: String,
+
+    /// Show a thumbnail at each marker instead of at regular times
+    #[clap(long, conflicts_with = "every")]
+    markers: bool,
+
+    /// Thumbnails in each row
+    #[clap(long, default_value_t = 4)]
+    columns: u32,
+
+    /// Width of each thumbnail in pixels
+    #[clap(long, default_value_t = 320)]
+    width: u32,
+}
+
+#[derive(Args)]
+struct WatchArgs {
     /// The path to the .beam file
     path: PathBuf,
 
     /// Render with GPU acceleration
     #[clap(long)]
     gpu: bool,
+
+    /// Frames per second, such as 24 or 29.97, instead of the camera's `fps`
+    #[clap(long)]
+    fps: Option<f64>,
+
+    /// Frames per second to render previews at, when lower than the script's
+    #[clap(long, default_value_t = 15.0)]
+    preview_fps: f64,
+
+    /// The directory to write previews to, `{script}_preview` next to the
+    /// script by default
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+
+    /// The kind of file to write for each scene, mp4 by default
+    #[clap(long, value_enum, default_value = "mp4")]
+    format: export::Format,
+}
+
+#[derive(Args)]
+struct ServeArgs {
+    /// The path to the .beam file
+    path: PathBuf,
+
+    /// Frames per second, such as 24 or 29.97, instead of the camera's `fps`
+    #[clap(long)]
+    fps: Option<f64>,
+
+    /// The port to serve the player on, at http://127.0.0.1:<PORT>/
+    #[clap(long, default_value_t = 8000)]
+    port: u16,
 }
 
 fn main() {
-    let args = Cli::parse();
-    let unparsed_file = fs::read_to_string(&args.path).expect("cannot read file");
+    let cli = Cli::parse();
+    match cli.command {
+        Some(Command::Render(args)) => render(&args),
+        Some(Command::Storyboard(args)) => storyboard(&args),
+        Some(Command::Watch(args)) => watch(&args),
+        Some(Command::Serve(args)) => serve(&args),
+        None => render(&cli.render.expect("a script to render")),
+    }
+}
+
+fn render(args: &RenderArgs) {
+    let Some(script) = load_script(&args.path, args.fps) else {
+        return;
+    };
+
+    let selection = match selection(args, script.fps()) {
+        Ok(selection) => selection,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return;
+        }
+    };
+    let still = args.frame.is_some() || args.at.is_some();
+    let format = if still {
+        export::Format::Still
+    } else {
+        args.format
+            .or_else(|| args.output.as_deref().and_then(export::Format::from_path))
+            .unwrap_or(export::Format::Mp4)
+    };
+    let output = args.output.clone().unwrap_or_else(|| match &selection.frames {
+        Some(frames) if still => {
+            let stem = args.path.file_stem().unwrap_or_default().to_string_lossy();
+            args.path
+                .with_file_name(format!("{}_{:05}.png", stem, frames.start))
+        }
+        _ => format.default_output(&args.path),
+    });
+    let options = export::ExportOptions {
+        format,
+        loops: args.loops,
+        dither: args.dither,
+        pattern: args.pattern.clone(),
+    };
+    let frames = sink::frame_pattern(&options);
+    if let Err(e) = output::check_output(&output, args.force, frames.as_deref()) {
+        eprintln!("Error: {}", e);
+        return;
+    }
+    output::install_interrupt_handler();
+    animator::animate_script(&script, &output, args.gpu, &options, &selection);
+}
 
-    match parser::parse_str(&unparsed_file) {
+fn storyboard(args: &StoryboardArgs) {
+    let Some(script) = load_script(&args.path, args.fps) else {
+        return;
+    };
+    let every = if args.markers {
+        None
+    } else {
+        match parser::parse_time(&args.every, script.fps()) {
+            Ok(every) => Some(every),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                return;
+            }
+        }
+    };
+    let output = args.output.clone().unwrap_or_else(|| {
+        let stem = args.path.file_stem().unwrap_or_default().to_string_lossy();
+        args.path.with_file_name(format!("{}_storyboard.png", stem))
+    });
+    if let Err(e) = output::check_output(&output, args.force, None) {
+        eprintln!("Error: {}", e);
+        return;
+    }
+
+    let options = storyboard::StoryboardOptions {
+        every,
+        columns: args.columns,
+        thumbnail_width: args.width,
+    };
+    output::install_interrupt_handler();
+    storyboard::export_storyboard(&script, &output, &options);
+}
+
+fn watch(args: &WatchArgs) {
+    if args.format == export::Format::Y4m {
+        eprintln!("Error: y4m previews would all go to standard output, choose another format");
+        return;
+    }
+    if let Some(dir) = args.path.parent() {
+        assets::set_base_dir(dir);
+    }
+    let output = args.output.clone().unwrap_or_else(|| {
+        let stem = args.path.file_stem().unwrap_or_default().to_string_lossy();
+        args.path.with_file_name(format!("{}_preview", stem))
+    });
+
+    let options = watch::WatchOptions {
+        fps: args.fps,
+        preview_fps: args.preview_fps,
+        gpu: args.gpu,
+        export: export::ExportOptions {
+            format: args.format,
+            ..export::ExportOptions::default()
+        },
+    };
+    output::install_interrupt_handler();
+    if let Err(e) = watch::watch(&args.path, &output, &options) {
+        eprintln!("Error watching {}: {}", args.path.display(), e);
+    }
+}
+
+fn serve(args: &ServeArgs) {
+    if let Some(dir) = args.path.parent() {
+        assets::set_base_dir(dir);
+    }
+    if let Err(e) = serve::serve(&args.path, args.fps, args.port) {
+        eprintln!("Error serving {}: {}", args.path.display(), e);
+    }
+}
+
+// Reads and parses the script at `path`, reporting any error.
+fn load_script(path: &Path, fps: Option<f64>) -> Option<ast::BeamScript> {
+    let unparsed_file = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) => {
+            eprintln!("Error reading {}: {}", path.display(), e);
+            return None;
+        }
+    };
+    if let Some(dir) = path.parent() {
+        assets::set_base_dir(dir);
+    }
+
+    match parser::parse_str(&unparsed_file, fps) {
         Ok(script) => {
-            println!("✅ Parsed successfully!");
-            let output_base = args.path.file_stem().unwrap().to_string_lossy();
-            animator::animate_script(&script, &output_base, args.gpu);
+            eprintln!("✅ Parsed successfully!");
+            Some(script)
         }
         Err(e) => {
             eprintln!("Error parsing script: {}", e);
+            None
         }
     }
 }
+
+// The scene and frames chosen on the command line, with times converted to
+// frames at `fps` frames per second.
+fn selection(args: &RenderArgs, fps: f64) -> Result<animator::Selection, String> {
+    let time = |time: &Option<String>| -> Result<Option<Duration>, String> {
+        time.as_deref().map(|t| parser::parse_time(t, fps)).transpose()
+    };
+    let frames = if let Some(frame) = args.frame {
+        Some(frame..frame + 1)
+    } else if let Some(at) = time(&args.at)? {
+        let frame = animator::frame_at(at, fps);
+        Some(frame..frame + 1)
+    } else {
+        match (time(&args.from)?, time(&args.to)?) {
+            (None, None) => None,
+            (from, to) => {
+                let start = from.map_or(0, |from| animator::frame_count(from, fps));
+                let end = to.map_or(u64::MAX, |to| animator::frame_count(to, fps));
+                Some(start..end)
+            }
+        }
+    };
+    Ok(animator::Selection {
+        scene: args.scene.clone(),
+        frames,
+    })
+}
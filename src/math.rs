@@ -0,0 +1,1634 @@
+//! Native typesetting of TeX math for `math` objects.
+//!
+//! The `tex` source is parsed into a small tree of math atoms, laid out with
+//! the metrics from the bundled font's OpenType MATH table and turned into one
+//! `path` object per glyph or rule, named `"<math name>.<n>"` in reading order.
+//! Each of them carries the symbol it was made from in its `glyph` property.
+//!
+//! Layout works in ems with y pointing up from the baseline, like TeX does.
+
+use crate::ast::{Object, Property, Value};
+use crate::font::{self, Font};
+use crate::path::{self, PathData, Segment};
+use ttf_parser::math::{Constants, MathValue, Variants};
+use ttf_parser::GlyphId;
+
+const DEFAULT_FONT_SIZE: f64 = 48.0;
+const DEFAULT_FILL: &str = "#FFFFFF";
+
+const THIN_SPACE: f64 = 3.0 / 18.0;
+const MEDIUM_SPACE: f64 = 4.0 / 18.0;
+const THICK_SPACE: f64 = 5.0 / 18.0;
+
+/// Replaces every `math` object with the glyphs of its typeset `tex`.
+pub fn expand_math_objects(items: &mut Vec<Object>) {
+    if !items.iter().any(|o| o.r#type == "math") {
+        return;
+    }
+    let mut expanded = Vec::with_capacity(items.len());
+    for object in items.drain(..) {
+        if object.r#type != "math" {
+            expanded.push(object);
+            continue;
+        }
+        match typeset(&object) {
+            Ok(children) => expanded.extend(children),
+            Err(e) => eprintln!(
+                "Warning: Failed to typeset math object '{}': {}",
+                object.name, e
+            ),
+        }
+    }
+    *items = expanded;
+}
+
+/// Lays out the `tex` of a `math` object as `path` objects centered on its position.
+pub fn typeset(parent: &Object) -> Result<Vec<Object>, String> {
+    let property = |name: &str| {
+        parent
+            .properties
+            .iter()
+            .find(|p| p.name == name)
+            .map(|p| &p.value)
+    };
+    let tex = match property("tex") {
+        Some(Value::String(tex)) => tex,
+        _ => return Err("missing 'tex'".to_string()),
+    };
+    let font_size = match property("font_size") {
+        Some(Value::Number(size)) => *size,
+        _ => DEFAULT_FONT_SIZE,
+    };
+    let fill = match property("fill") {
+        Some(Value::Color(color)) => color.clone(),
+        _ => DEFAULT_FILL.to_string(),
+    };
+
+    let nodes = Parser::new(tex).parse()?;
+    let layout = Layout::new(font::math())?;
+    let root = layout.list(&nodes, Style::Display);
+
+    // Center the formula's box on the object's position.
+    let origin = (root.width / 2.0, (root.height - root.depth) / 2.0);
+    let to_local = |p: (f64, f64)| ((p.0 - origin.0) * font_size, (origin.1 - p.1) * font_size);
+    let upem = layout.font.units_per_em();
+    let shared = parent.shared_properties();
+
+    let mut objects = Vec::new();
+    for item in root.items {
+        let (source, data) = match item {
+            Item::Glyph {
+                id,
+                source,
+                x,
+                y,
+                size,
+                stretch,
+            } => {
+                let k = size / upem;
+                let outline =
+                    path::transform(&layout.font.outline(id), [k, 0.0, 0.0, k * stretch, x, y]);
+                (source, outline)
+            }
+            Item::Rule {
+                x,
+                y,
+                width,
+                height,
+                source,
+            } => (source.to_string(), rectangle(x, y, width, height)),
+        };
+        if data.is_empty() {
+            continue;
+        }
+        let data: PathData = data
+            .iter()
+            .map(|segment| match *segment {
+                Segment::MoveTo(p) => Segment::MoveTo(to_local(p)),
+                Segment::CubicTo(c1, c2, p) => {
+                    Segment::CubicTo(to_local(c1), to_local(c2), to_local(p))
+                }
+                Segment::Close => Segment::Close,
+            })
+            .collect();
+
+        let property = |name: &str, value: Value| Property {
+            name: name.to_string(),
+            value,
+        };
+        let mut properties = vec![
+            property("d", Value::String(path::to_path_data(&data))),
+            property("fill", Value::Color(fill.clone())),
+            property("glyph", Value::String(source)),
+        ];
+        properties.extend(shared.iter().cloned());
+        objects.push(Object {
+            r#type: "path".to_string(),
+            name: format!("{}.{}", parent.name, objects.len() + 1),
+            properties,
+        });
+    }
+    Ok(objects)
+}
+
+fn rectangle(x: f64, y: f64, width: f64, height: f64) -> PathData {
+    let corners = [
+        (x, y),
+        (x + width, y),
+        (x + width, y + height),
+        (x, y + height),
+    ];
+    let mut data = vec![Segment::MoveTo(corners[0])];
+    for i in 0..4 {
+        data.push(path::line_to(corners[i], corners[(i + 1) % 4]));
+    }
+    data.push(Segment::Close);
+    data
+}
+
+/// TeX atom classes, which decide the spacing between neighbouring atoms.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Class {
+    Ord,
+    Op,
+    Bin,
+    Rel,
+    Open,
+    Close,
+    Punct,
+    Inner,
+}
+
+/// Alphabets that letters and digits can be drawn from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Variant {
+    Normal,
+    Upright,
+    Bold,
+    DoubleStruck,
+    Calligraphic,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Symbol {
+        ch: char,
+        class: Class,
+        variant: Variant,
+    },
+    LargeOp {
+        ch: char,
+        limits: bool,
+    },
+    Function {
+        name: String,
+        limits: bool,
+    },
+    Text(String),
+    Space(f64),
+    Group(Vec<Node>),
+    Scripts {
+        base: Box<Node>,
+        sub: Option<Box<Node>>,
+        sup: Option<Box<Node>>,
+    },
+    Fraction {
+        numerator: Box<Node>,
+        denominator: Box<Node>,
+    },
+    Radical {
+        degree: Option<Box<Node>>,
+        body: Box<Node>,
+    },
+    Delimited {
+        open: Option<char>,
+        body: Vec<Node>,
+        close: Option<char>,
+    },
+    Matrix {
+        rows: Vec<Vec<Vec<Node>>>,
+        open: Option<char>,
+        close: Option<char>,
+        align_left: bool,
+    },
+}
+
+impl Node {
+    fn class(&self) -> Class {
+        match self {
+            Node::Symbol { class, .. } => *class,
+            Node::LargeOp { .. } | Node::Function { .. } => Class::Op,
+            Node::Scripts { base, .. } => base.class(),
+            Node::Fraction { .. } | Node::Delimited { .. } | Node::Matrix { .. } => Class::Inner,
+            _ => Class::Ord,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Char(char),
+    Command(String),
+    BeginGroup,
+    EndGroup,
+    Superscript,
+    Subscript,
+    Align,
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    variant: Variant,
+}
+
+impl Parser {
+    fn new(tex: &str) -> Self {
+        Parser {
+            chars: tex.chars().collect(),
+            pos: 0,
+            variant: Variant::Normal,
+        }
+    }
+
+    fn parse(mut self) -> Result<Vec<Node>, String> {
+        let nodes = self.list(None)?;
+        match self.next_token() {
+            None => Ok(nodes),
+            Some(token) => Err(format!("unexpected {:?}", token)),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.get(self.pos).is_some_and(|c| c.is_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn next_token(&mut self) -> Option<Token> {
+        self.skip_whitespace();
+        let c = *self.chars.get(self.pos)?;
+        self.pos += 1;
+        Some(match c {
+            '{' => Token::BeginGroup,
+            '}' => Token::EndGroup,
+            '^' => Token::Superscript,
+            '_' => Token::Subscript,
+            '&' => Token::Align,
+            '\\' => {
+                let start = self.pos;
+                while self
+                    .chars
+                    .get(self.pos)
+                    .is_some_and(|c| c.is_ascii_alphabetic())
+                {
+                    self.pos += 1;
+                }
+                if self.pos == start && self.pos < self.chars.len() {
+                    // Control symbols such as `\,` and `\{` are a single character.
+                    self.pos += 1;
+                }
+                Token::Command(self.chars[start..self.pos].iter().collect())
+            }
+            c => Token::Char(c),
+        })
+    }
+
+    fn peek_token(&mut self) -> Option<Token> {
+        let pos = self.pos;
+        let token = self.next_token();
+        self.pos = pos;
+        token
+    }
+
+    /// Parses atoms up to, but not including, the token that ends the list.
+    fn list(&mut self, closing: Option<char>) -> Result<Vec<Node>, String> {
+        let mut nodes = Vec::new();
+        loop {
+            match self.peek_token() {
+                None | Some(Token::EndGroup) | Some(Token::Align) => break,
+                Some(Token::Char(c)) if Some(c) == closing => break,
+                Some(Token::Command(name)) if matches!(name.as_str(), "\\" | "right" | "end") => {
+                    break
+                }
+                _ => {}
+            }
+            let atom = self.atom()?;
+            nodes.push(self.scripts(atom)?);
+        }
+        Ok(nodes)
+    }
+
+    fn scripts(&mut self, base: Node) -> Result<Node, String> {
+        let (mut sub, mut sup) = (None, None);
+        loop {
+            match self.peek_token() {
+                Some(Token::Superscript) => {
+                    self.next_token();
+                    if sup.is_some() {
+                        return Err("double superscript".to_string());
+                    }
+                    sup = Some(Box::new(self.argument()?));
+                }
+                Some(Token::Subscript) => {
+                    self.next_token();
+                    if sub.is_some() {
+                        return Err("double subscript".to_string());
+                    }
+                    sub = Some(Box::new(self.argument()?));
+                }
+                Some(Token::Char('\'')) => {
+                    self.next_token();
+                    let prime = Node::Symbol {
+                        ch: '′',
+                        class: Class::Ord,
+                        variant: Variant::Upright,
+                    };
+                    sup = Some(Box::new(match sup.map(|s| *s) {
+                        Some(Node::Group(mut nodes)) => {
+                            nodes.push(prime);
+                            Node::Group(nodes)
+                        }
+                        Some(other) => Node::Group(vec![other, prime]),
+                        None => prime,
+                    }));
+                }
+                _ => break,
+            }
+        }
+        if sub.is_none() && sup.is_none() {
+            return Ok(base);
+        }
+        Ok(Node::Scripts {
+            base: Box::new(base),
+            sub,
+            sup,
+        })
+    }
+
+    /// A braced group or a single atom, as taken by `^`, `\frac` and friends.
+    fn argument(&mut self) -> Result<Node, String> {
+        if self.peek_token() == Some(Token::BeginGroup) {
+            self.next_token();
+            let nodes = self.list(None)?;
+            self.expect(Token::EndGroup)?;
+            return Ok(Node::Group(nodes));
+        }
+        self.atom()
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), String> {
+        match self.next_token() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => Err(format!("expected {:?}, found {:?}", expected, token)),
+            None => Err(format!("expected {:?}, found end of input", expected)),
+        }
+    }
+
+    /// The raw text of a braced argument, for `\text` and environment names.
+    fn raw_argument(&mut self) -> Result<String, String> {
+        self.expect(Token::BeginGroup)?;
+        let mut depth = 0;
+        let mut text = String::new();
+        while let Some(&c) = self.chars.get(self.pos) {
+            self.pos += 1;
+            match c {
+                '{' => depth += 1,
+                '}' if depth == 0 => return Ok(text),
+                '}' => depth -= 1,
+                _ => {}
+            }
+            text.push(c);
+        }
+        Err("unclosed '{'".to_string())
+    }
+
+    fn with_variant(&mut self, variant: Variant) -> Result<Node, String> {
+        let previous = std::mem::replace(&mut self.variant, variant);
+        let node = self.argument();
+        self.variant = previous;
+        node
+    }
+
+    fn atom(&mut self) -> Result<Node, String> {
+        let token = self.next_token().ok_or("unexpected end of input")?;
+        match token {
+            Token::BeginGroup => {
+                let nodes = self.list(None)?;
+                self.expect(Token::EndGroup)?;
+                Ok(Node::Group(nodes))
+            }
+            Token::Char(c) => Ok(self.char_atom(c)),
+            Token::Command(name) => self.command(&name),
+            token => Err(format!("unexpected {:?}", token)),
+        }
+    }
+
+    fn char_atom(&self, c: char) -> Node {
+        let (ch, class) = match c {
+            '+' => ('+', Class::Bin),
+            '-' => ('−', Class::Bin),
+            '*' => ('∗', Class::Bin),
+            '=' | '<' | '>' | ':' => (c, Class::Rel),
+            ',' | ';' => (c, Class::Punct),
+            '(' | '[' => (c, Class::Open),
+            ')' | ']' | '!' | '?' => (c, Class::Close),
+            _ => (c, Class::Ord),
+        };
+        Node::Symbol {
+            ch,
+            class,
+            variant: self.variant,
+        }
+    }
+
+    fn command(&mut self, name: &str) -> Result<Node, String> {
+        if let Some((ch, class)) = symbol(name) {
+            return Ok(Node::Symbol {
+                ch,
+                class,
+                variant: self.variant,
+            });
+        }
+        if let Some((ch, limits)) = large_operator(name) {
+            return Ok(Node::LargeOp { ch, limits });
+        }
+        if FUNCTIONS.contains(&name) {
+            return Ok(Node::Function {
+                name: name.to_string(),
+                limits: LIMIT_FUNCTIONS.contains(&name),
+            });
+        }
+        if let Some(width) = space(name) {
+            return Ok(Node::Space(width));
+        }
+        match name {
+            "frac" | "dfrac" | "tfrac" => Ok(Node::Fraction {
+                numerator: Box::new(self.argument()?),
+                denominator: Box::new(self.argument()?),
+            }),
+            "sqrt" => {
+                let degree = if self.peek_token() == Some(Token::Char('[')) {
+                    self.next_token();
+                    let nodes = self.list(Some(']'))?;
+                    self.expect(Token::Char(']'))?;
+                    Some(Box::new(Node::Group(nodes)))
+                } else {
+                    None
+                };
+                Ok(Node::Radical {
+                    degree,
+                    body: Box::new(self.argument()?),
+                })
+            }
+            "left" => {
+                let open = self.delimiter()?;
+                let body = self.list(None)?;
+                self.expect(Token::Command("right".to_string()))?;
+                let close = self.delimiter()?;
+                Ok(Node::Delimited { open, body, close })
+            }
+            "begin" => self.environment(),
+            "text" => Ok(Node::Text(self.raw_argument()?)),
+            "operatorname" => Ok(Node::Function {
+                name: self.raw_argument()?,
+                limits: false,
+            }),
+            "mathrm" => self.with_variant(Variant::Upright),
+            "mathbf" => self.with_variant(Variant::Bold),
+            "mathbb" => self.with_variant(Variant::DoubleStruck),
+            "mathcal" => self.with_variant(Variant::Calligraphic),
+            "mathit" => self.with_variant(Variant::Normal),
+            _ => Err(format!("unknown command '\\{}'", name)),
+        }
+    }
+
+    /// The delimiter after `\left` or `\right`, `None` for the invisible `.`.
+    fn delimiter(&mut self) -> Result<Option<char>, String> {
+        match self.next_token() {
+            Some(Token::Char('.')) => Ok(None),
+            Some(Token::Char(c)) => Ok(Some(c)),
+            Some(Token::Command(name)) => match symbol(&name) {
+                Some((ch, _)) => Ok(Some(ch)),
+                None => Err(format!("'\\{}' is not a delimiter", name)),
+            },
+            _ => Err("missing delimiter".to_string()),
+        }
+    }
+
+    fn environment(&mut self) -> Result<Node, String> {
+        let name = self.raw_argument()?;
+        let (open, close) = match name.as_str() {
+            "matrix" => (None, None),
+            "pmatrix" => (Some('('), Some(')')),
+            "bmatrix" => (Some('['), Some(']')),
+            "Bmatrix" | "cases" => (Some('{'), None),
+            "vmatrix" => (Some('|'), Some('|')),
+            "Vmatrix" => (Some('‖'), Some('‖')),
+            _ => return Err(format!("unknown environment '{}'", name)),
+        };
+        let close = if name == "Bmatrix" { Some('}') } else { close };
+
+        let mut rows = vec![Vec::new()];
+        loop {
+            let cell = self.list(None)?;
+            rows.last_mut().unwrap().push(cell);
+            match self.next_token() {
+                Some(Token::Align) => {}
+                Some(Token::Command(command)) if command == "\\" => rows.push(Vec::new()),
+                Some(Token::Command(command)) if command == "end" => {
+                    let end = self.raw_argument()?;
+                    if end != name {
+                        return Err(format!("'\\begin{{{}}}' ended by '\\end{{{}}}'", name, end));
+                    }
+                    break;
+                }
+                _ => return Err(format!("unterminated environment '{}'", name)),
+            }
+        }
+        // A trailing `\\` does not start another row.
+        if rows.len() > 1
+            && rows
+                .last()
+                .is_some_and(|row| row.len() == 1 && row[0].is_empty())
+        {
+            rows.pop();
+        }
+        Ok(Node::Matrix {
+            rows,
+            open,
+            close,
+            align_left: name == "cases",
+        })
+    }
+}
+
+const FUNCTIONS: &[&str] = &[
+    "sin", "cos", "tan", "cot", "sec", "csc", "arcsin", "arccos", "arctan", "sinh", "cosh", "tanh",
+    "log", "ln", "lg", "exp", "det", "dim", "ker", "deg", "arg", "gcd", "lim", "max", "min", "sup",
+    "inf", "Pr",
+];
+
+/// Functions whose scripts go above and below in display style, like `\lim_{x \to 0}`.
+const LIMIT_FUNCTIONS: &[&str] = &["lim", "max", "min", "sup", "inf", "det", "gcd", "Pr"];
+
+fn large_operator(name: &str) -> Option<(char, bool)> {
+    Some(match name {
+        "sum" => ('∑', true),
+        "prod" => ('∏', true),
+        "coprod" => ('∐', true),
+        "bigcup" => ('⋃', true),
+        "bigcap" => ('⋂', true),
+        "int" => ('∫', false),
+        "iint" => ('∬', false),
+        "iiint" => ('∭', false),
+        "oint" => ('∮', false),
+        _ => return None,
+    })
+}
+
+fn space(name: &str) -> Option<f64> {
+    Some(match name {
+        "," => THIN_SPACE,
+        ":" | ">" => MEDIUM_SPACE,
+        ";" => THICK_SPACE,
+        "!" => -THIN_SPACE,
+        " " => 0.25,
+        "quad" => 1.0,
+        "qquad" => 2.0,
+        _ => return None,
+    })
+}
+
+fn symbol(name: &str) -> Option<(char, Class)> {
+    use Class::*;
+    Some(match name {
+        // Greek letters
+        "alpha" => ('α', Ord),
+        "beta" => ('β', Ord),
+        "gamma" => ('γ', Ord),
+        "delta" => ('δ', Ord),
+        "epsilon" => ('ϵ', Ord),
+        "varepsilon" => ('ε', Ord),
+        "zeta" => ('ζ', Ord),
+        "eta" => ('η', Ord),
+        "theta" => ('θ', Ord),
+        "vartheta" => ('ϑ', Ord),
+        "iota" => ('ι', Ord),
+        "kappa" => ('κ', Ord),
+        "lambda" => ('λ', Ord),
+        "mu" => ('μ', Ord),
+        "nu" => ('ν', Ord),
+        "xi" => ('ξ', Ord),
+        "omicron" => ('ο', Ord),
+        "pi" => ('π', Ord),
+        "varpi" => ('ϖ', Ord),
+        "rho" => ('ρ', Ord),
+        "varrho" => ('ϱ', Ord),
+        "sigma" => ('σ', Ord),
+        "varsigma" => ('ς', Ord),
+        "tau" => ('τ', Ord),
+        "upsilon" => ('υ', Ord),
+        "phi" => ('ϕ', Ord),
+        "varphi" => ('φ', Ord),
+        "chi" => ('χ', Ord),
+        "psi" => ('ψ', Ord),
+        "omega" => ('ω', Ord),
+        "Gamma" => ('Γ', Ord),
+        "Delta" => ('Δ', Ord),
+        "Theta" => ('Θ', Ord),
+        "Lambda" => ('Λ', Ord),
+        "Xi" => ('Ξ', Ord),
+        "Pi" => ('Π', Ord),
+        "Sigma" => ('Σ', Ord),
+        "Upsilon" => ('Υ', Ord),
+        "Phi" => ('Φ', Ord),
+        "Psi" => ('Ψ', Ord),
+        "Omega" => ('Ω', Ord),
+        // Binary operators
+        "pm" => ('±', Bin),
+        "mp" => ('∓', Bin),
+        "times" => ('×', Bin),
+        "div" => ('÷', Bin),
+        "cdot" => ('⋅', Bin),
+        "ast" => ('∗', Bin),
+        "star" => ('⋆', Bin),
+        "circ" => ('∘', Bin),
+        "bullet" => ('∙', Bin),
+        "cap" => ('∩', Bin),
+        "cup" => ('∪', Bin),
+        "wedge" | "land" => ('∧', Bin),
+        "vee" | "lor" => ('∨', Bin),
+        "oplus" => ('⊕', Bin),
+        "otimes" => ('⊗', Bin),
+        "setminus" => ('∖', Bin),
+        // Relations
+        "leq" | "le" => ('≤', Rel),
+        "geq" | "ge" => ('≥', Rel),
+        "neq" | "ne" => ('≠', Rel),
+        "equiv" => ('≡', Rel),
+        "approx" => ('≈', Rel),
+        "sim" => ('∼', Rel),
+        "simeq" => ('≃', Rel),
+        "cong" => ('≅', Rel),
+        "propto" => ('∝', Rel),
+        "ll" => ('≪', Rel),
+        "gg" => ('≫', Rel),
+        "in" => ('∈', Rel),
+        "notin" => ('∉', Rel),
+        "ni" => ('∋', Rel),
+        "subset" => ('⊂', Rel),
+        "supset" => ('⊃', Rel),
+        "subseteq" => ('⊆', Rel),
+        "supseteq" => ('⊇', Rel),
+        "to" | "rightarrow" => ('→', Rel),
+        "leftarrow" | "gets" => ('←', Rel),
+        "leftrightarrow" => ('↔', Rel),
+        "Rightarrow" => ('⇒', Rel),
+        "Leftarrow" => ('⇐', Rel),
+        "Leftrightarrow" => ('⇔', Rel),
+        "implies" => ('⟹', Rel),
+        "iff" => ('⟺', Rel),
+        "mapsto" => ('↦', Rel),
+        "perp" => ('⊥', Rel),
+        "parallel" => ('∥', Rel),
+        "mid" => ('∣', Rel),
+        // Delimiters
+        "{" | "lbrace" => ('{', Open),
+        "}" | "rbrace" => ('}', Close),
+        "langle" => ('⟨', Open),
+        "rangle" => ('⟩', Close),
+        "lceil" => ('⌈', Open),
+        "rceil" => ('⌉', Close),
+        "lfloor" => ('⌊', Open),
+        "rfloor" => ('⌋', Close),
+        "|" | "Vert" => ('‖', Ord),
+        "vert" => ('|', Ord),
+        // Other symbols
+        "infty" => ('∞', Ord),
+        "partial" => ('∂', Ord),
+        "nabla" => ('∇', Ord),
+        "forall" => ('∀', Ord),
+        "exists" => ('∃', Ord),
+        "emptyset" | "varnothing" => ('∅', Ord),
+        "hbar" => ('ℏ', Ord),
+        "ell" => ('ℓ', Ord),
+        "prime" => ('′', Ord),
+        "angle" => ('∠', Ord),
+        "triangle" => ('△', Ord),
+        "neg" | "lnot" => ('¬', Ord),
+        "Re" => ('ℜ', Ord),
+        "Im" => ('ℑ', Ord),
+        "aleph" => ('ℵ', Ord),
+        "ldots" | "dots" => ('…', Inner),
+        "cdots" => ('⋯', Inner),
+        "vdots" => ('⋮', Ord),
+        "ddots" => ('⋱', Inner),
+        "%" | "#" | "$" | "&" | "_" => (name.chars().next().unwrap(), Ord),
+        _ => return None,
+    })
+}
+
+/// The Unicode math alphanumeric for `ch` in the given alphabet, if there is one.
+fn styled_char(ch: char, variant: Variant) -> char {
+    let offset = |base: char, first: char| char::from_u32(first as u32 + (ch as u32 - base as u32));
+    let styled = match (variant, ch) {
+        (Variant::Normal, 'h') => Some('ℎ'),
+        (Variant::Normal, 'a'..='z') => offset('a', '\u{1D44E}'),
+        (Variant::Normal, 'A'..='Z') => offset('A', '\u{1D434}'),
+        (Variant::Normal, 'α'..='ω') => offset('α', '\u{1D6FC}'),
+        (Variant::Normal, 'ϵ') => Some('\u{1D716}'),
+        (Variant::Normal, 'ϑ') => Some('\u{1D717}'),
+        (Variant::Normal, 'ϕ') => Some('\u{1D719}'),
+        (Variant::Normal, 'ϱ') => Some('\u{1D71A}'),
+        (Variant::Normal, 'ϖ') => Some('\u{1D71B}'),
+        (Variant::Bold, 'a'..='z') => offset('a', '\u{1D41A}'),
+        (Variant::Bold, 'A'..='Z') => offset('A', '\u{1D400}'),
+        (Variant::Bold, '0'..='9') => offset('0', '\u{1D7CE}'),
+        (Variant::DoubleStruck, 'C') => Some('ℂ'),
+        (Variant::DoubleStruck, 'H') => Some('ℍ'),
+        (Variant::DoubleStruck, 'N') => Some('ℕ'),
+        (Variant::DoubleStruck, 'P') => Some('ℙ'),
+        (Variant::DoubleStruck, 'Q') => Some('ℚ'),
+        (Variant::DoubleStruck, 'R') => Some('ℝ'),
+        (Variant::DoubleStruck, 'Z') => Some('ℤ'),
+        (Variant::DoubleStruck, 'A'..='Z') => offset('A', '\u{1D538}'),
+        (Variant::DoubleStruck, '0'..='9') => offset('0', '\u{1D7D8}'),
+        (Variant::Calligraphic, 'B') => Some('ℬ'),
+        (Variant::Calligraphic, 'E') => Some('ℰ'),
+        (Variant::Calligraphic, 'F') => Some('ℱ'),
+        (Variant::Calligraphic, 'H') => Some('ℋ'),
+        (Variant::Calligraphic, 'I') => Some('ℐ'),
+        (Variant::Calligraphic, 'L') => Some('ℒ'),
+        (Variant::Calligraphic, 'M') => Some('ℳ'),
+        (Variant::Calligraphic, 'R') => Some('ℛ'),
+        (Variant::Calligraphic, 'A'..='Z') => offset('A', '\u{1D49C}'),
+        _ => None,
+    };
+    styled.unwrap_or(ch)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Style {
+    Display,
+    Text,
+    Script,
+    ScriptScript,
+}
+
+impl Style {
+    fn superscript(self) -> Style {
+        match self {
+            Style::Display | Style::Text => Style::Script,
+            _ => Style::ScriptScript,
+        }
+    }
+
+    fn fraction(self) -> Style {
+        match self {
+            Style::Display => Style::Text,
+            other => other.superscript(),
+        }
+    }
+
+    fn is_script(self) -> bool {
+        matches!(self, Style::Script | Style::ScriptScript)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Item {
+    Glyph {
+        id: GlyphId,
+        source: String,
+        /// Origin of the glyph, in ems.
+        x: f64,
+        y: f64,
+        /// Em size of the glyph relative to the font size.
+        size: f64,
+        /// Vertical stretch for delimiters taller than the font's largest variant.
+        stretch: f64,
+    },
+    Rule {
+        /// Bottom left corner, in ems.
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+        source: &'static str,
+    },
+}
+
+/// A laid out piece of a formula with its origin on the baseline at the left.
+#[derive(Debug, Default)]
+struct LayoutBox {
+    width: f64,
+    height: f64,
+    depth: f64,
+    items: Vec<Item>,
+}
+
+impl LayoutBox {
+    /// Adds `other` to the right of the current contents.
+    fn append(&mut self, other: LayoutBox) {
+        let x = self.width;
+        self.width += other.width;
+        self.height = self.height.max(other.height);
+        self.depth = self.depth.max(other.depth);
+        self.place(other, x, 0.0);
+    }
+
+    /// Moves the items of `other` into this box at the given offset.
+    fn place(&mut self, other: LayoutBox, dx: f64, dy: f64) {
+        self.items
+            .extend(other.items.into_iter().map(|item| match item {
+                Item::Glyph {
+                    id,
+                    source,
+                    x,
+                    y,
+                    size,
+                    stretch,
+                } => Item::Glyph {
+                    id,
+                    source,
+                    x: x + dx,
+                    y: y + dy,
+                    size,
+                    stretch,
+                },
+                Item::Rule {
+                    x,
+                    y,
+                    width,
+                    height,
+                    source,
+                } => Item::Rule {
+                    x: x + dx,
+                    y: y + dy,
+                    width,
+                    height,
+                    source,
+                },
+            }));
+    }
+}
+
+struct Layout {
+    font: &'static Font,
+    constants: Constants<'static>,
+    variants: Option<Variants<'static>>,
+}
+
+impl Layout {
+    fn new(font: &'static Font) -> Result<Self, String> {
+        let table = font
+            .face()
+            .tables()
+            .math
+            .ok_or("the math font has no MATH table")?;
+        Ok(Layout {
+            font,
+            constants: table
+                .constants
+                .ok_or("the math font has no math constants")?,
+            variants: table.variants,
+        })
+    }
+
+    /// A MATH table value in ems.
+    fn em(&self, value: MathValue) -> f64 {
+        value.value as f64 / self.font.units_per_em()
+    }
+
+    fn size(&self, style: Style) -> f64 {
+        match style {
+            Style::Display | Style::Text => 1.0,
+            Style::Script => self.constants.script_percent_scale_down() as f64 / 100.0,
+            Style::ScriptScript => self.constants.script_script_percent_scale_down() as f64 / 100.0,
+        }
+    }
+
+    fn axis_height(&self, style: Style) -> f64 {
+        self.em(self.constants.axis_height()) * self.size(style)
+    }
+
+    fn list(&self, nodes: &[Node], style: Style) -> LayoutBox {
+        let classes = atom_classes(nodes);
+        let mut result = LayoutBox::default();
+        let mut previous: Option<Class> = None;
+        for (node, class) in nodes.iter().zip(classes) {
+            if let (Some(left), Some(right)) = (previous, class) {
+                result.width += spacing(left, right, style) * self.size(style);
+            }
+            if class.is_some() {
+                previous = class;
+            }
+            result.append(self.node(node, style));
+        }
+        result
+    }
+
+    fn node(&self, node: &Node, style: Style) -> LayoutBox {
+        let size = self.size(style);
+        match node {
+            Node::Symbol { ch, variant, .. } => self.char_box(*ch, *variant, size),
+            Node::Text(text) => self.text(text, size),
+            Node::Function { name, .. } => self.text(name, size),
+            Node::LargeOp { ch, .. } => self.large_operator(*ch, style),
+            Node::Space(width) => LayoutBox {
+                width: width * size,
+                ..Default::default()
+            },
+            Node::Group(nodes) => self.list(nodes, style),
+            Node::Scripts { base, sub, sup } => {
+                self.scripts(base, sub.as_deref(), sup.as_deref(), style)
+            }
+            Node::Fraction {
+                numerator,
+                denominator,
+            } => self.fraction(numerator, denominator, style),
+            Node::Radical { degree, body } => self.radical(degree.as_deref(), body, style),
+            Node::Delimited { open, body, close } => {
+                let body = self.list(body, style);
+                self.delimited(*open, body, *close, style)
+            }
+            Node::Matrix {
+                rows,
+                open,
+                close,
+                align_left,
+            } => {
+                let body = self.matrix(rows, *align_left, style);
+                self.delimited(*open, body, *close, style)
+            }
+        }
+    }
+
+    fn glyph_box(&self, id: GlyphId, source: String, size: f64) -> LayoutBox {
+        let scale = size / self.font.units_per_em();
+        let (_, y_min, _, y_max) = self.font.bounds(id);
+        LayoutBox {
+            width: self.font.advance(id) * scale,
+            height: (y_max * scale).max(0.0),
+            depth: (-y_min * scale).max(0.0),
+            items: vec![Item::Glyph {
+                id,
+                source,
+                x: 0.0,
+                y: 0.0,
+                size,
+                stretch: 1.0,
+            }],
+        }
+    }
+
+    fn char_box(&self, ch: char, variant: Variant, size: f64) -> LayoutBox {
+        let styled = styled_char(ch, variant);
+        match self
+            .font
+            .glyph_id(styled)
+            .or_else(|| self.font.glyph_id(ch))
+        {
+            Some(id) => self.glyph_box(id, ch.to_string(), size),
+            None => {
+                eprintln!("Warning: The math font has no glyph for '{}'", ch);
+                LayoutBox::default()
+            }
+        }
+    }
+
+    /// Upright text, as used by `\text` and function names such as `\sin`.
+    fn text(&self, text: &str, size: f64) -> LayoutBox {
+        let mut result = LayoutBox::default();
+        for ch in text.chars() {
+            result.append(self.char_box(ch, Variant::Upright, size));
+        }
+        result
+    }
+
+    /// The font's first size variant of `id` that is at least `min_height` ems tall,
+    /// or its largest variant.
+    fn vertical_variant(&self, id: GlyphId, min_height: f64) -> GlyphId {
+        let min_units = min_height * self.font.units_per_em();
+        let Some(construction) = self.variants.and_then(|v| v.vertical_constructions.get(id))
+        else {
+            return id;
+        };
+        let mut chosen = id;
+        for variant in construction.variants {
+            chosen = variant.variant_glyph;
+            if variant.advance_measurement as f64 >= min_units {
+                break;
+            }
+        }
+        chosen
+    }
+
+    /// A glyph centered on the math axis, like large operators and delimiters.
+    fn centered_glyph(&self, id: GlyphId, source: String, style: Style, stretch: f64) -> LayoutBox {
+        let size = self.size(style);
+        let scale = size / self.font.units_per_em();
+        let (_, y_min, _, y_max) = self.font.bounds(id);
+        let shift = self.axis_height(style) - (y_min + y_max) / 2.0 * scale * stretch;
+        let mut result = LayoutBox {
+            width: self.font.advance(id) * scale,
+            height: y_max * scale * stretch + shift,
+            depth: -(y_min * scale * stretch + shift),
+            items: Vec::new(),
+        };
+        result.items.push(Item::Glyph {
+            id,
+            source,
+            x: 0.0,
+            y: shift,
+            size,
+            stretch,
+        });
+        result
+    }
+
+    fn large_operator(&self, ch: char, style: Style) -> LayoutBox {
+        let Some(mut id) = self.font.glyph_id(ch) else {
+            return self.char_box(ch, Variant::Upright, self.size(style));
+        };
+        if style == Style::Display {
+            let min_height =
+                self.constants.display_operator_min_height() as f64 / self.font.units_per_em();
+            id = self.vertical_variant(id, min_height);
+        }
+        self.centered_glyph(id, ch.to_string(), style, 1.0)
+    }
+
+    fn delimiter(&self, ch: char, height: f64, style: Style) -> LayoutBox {
+        let Some(id) = self.font.glyph_id(ch) else {
+            return self.char_box(ch, Variant::Upright, self.size(style));
+        };
+        let id = self.vertical_variant(id, height / self.size(style));
+        let (_, y_min, _, y_max) = self.font.bounds(id);
+        let glyph_height = (y_max - y_min) / self.font.units_per_em() * self.size(style);
+        let stretch = if glyph_height > 0.0 {
+            (height / glyph_height).max(1.0)
+        } else {
+            1.0
+        };
+        self.centered_glyph(id, ch.to_string(), style, stretch)
+    }
+
+    fn delimited(
+        &self,
+        open: Option<char>,
+        body: LayoutBox,
+        close: Option<char>,
+        style: Style,
+    ) -> LayoutBox {
+        if open.is_none() && close.is_none() {
+            return body;
+        }
+        // Delimiters cover the body symmetrically around the math axis.
+        let axis = self.axis_height(style);
+        let extent = (body.height - axis).max(body.depth + axis);
+        let height = (2.0 * extent * 0.901).max(2.0 * extent - 0.5 * self.size(style));
+        let null_space = 0.12 * self.size(style);
+
+        let mut result = LayoutBox::default();
+        match open {
+            Some(ch) => result.append(self.delimiter(ch, height, style)),
+            None => result.width += null_space,
+        }
+        result.append(body);
+        match close {
+            Some(ch) => result.append(self.delimiter(ch, height, style)),
+            None => result.width += null_space,
+        }
+        result
+    }
+
+    fn scripts(
+        &self,
+        base: &Node,
+        sub: Option<&Node>,
+        sup: Option<&Node>,
+        style: Style,
+    ) -> LayoutBox {
+        let limits = matches!(
+            base,
+            Node::LargeOp { limits: true, .. } | Node::Function { limits: true, .. }
+        );
+        if limits && style == Style::Display {
+            return self.limits(base, sub, sup, style);
+        }
+
+        let c = &self.constants;
+        let size = self.size(style);
+        let is_symbol = matches!(base, Node::Symbol { .. } | Node::Function { .. });
+        let mut result = self.node(base, style);
+        let x = result.width;
+        let sup = sup.map(|node| self.node(node, style.superscript()));
+        let sub = sub.map(|node| self.node(node, style.superscript()));
+
+        let mut shift_up = 0.0;
+        if let Some(sup) = &sup {
+            shift_up = (self.em(c.superscript_shift_up()) * size)
+                .max(sup.depth + self.em(c.superscript_bottom_min()) * size);
+            if !is_symbol {
+                shift_up =
+                    shift_up.max(result.height - self.em(c.superscript_baseline_drop_max()) * size);
+            }
+        }
+        let mut shift_down = 0.0;
+        if let Some(sub) = &sub {
+            shift_down = (self.em(c.subscript_shift_down()) * size)
+                .max(sub.height - self.em(c.subscript_top_max()) * size);
+            if !is_symbol {
+                shift_down =
+                    shift_down.max(result.depth + self.em(c.subscript_baseline_drop_min()) * size);
+            }
+        }
+        if let (Some(sup), Some(sub)) = (&sup, &sub) {
+            let gap = (shift_up - sup.depth) - (sub.height - shift_down);
+            let min_gap = self.em(c.sub_superscript_gap_min()) * size;
+            if gap < min_gap {
+                shift_down += min_gap - gap;
+            }
+        }
+
+        let mut scripts_width: f64 = 0.0;
+        if let Some(sup) = sup {
+            scripts_width = scripts_width.max(sup.width);
+            result.height = result.height.max(sup.height + shift_up);
+            result.place(sup, x, shift_up);
+        }
+        if let Some(sub) = sub {
+            scripts_width = scripts_width.max(sub.width);
+            result.depth = result.depth.max(sub.depth + shift_down);
+            result.place(sub, x, -shift_down);
+        }
+        result.width += scripts_width + self.em(c.space_after_script()) * size;
+        result
+    }
+
+    /// Scripts set above and below the base, as for `\sum` in display style.
+    fn limits(
+        &self,
+        base: &Node,
+        sub: Option<&Node>,
+        sup: Option<&Node>,
+        style: Style,
+    ) -> LayoutBox {
+        let c = &self.constants;
+        let size = self.size(style);
+        let base = self.node(base, style);
+        let sup = sup.map(|node| self.node(node, style.superscript()));
+        let sub = sub.map(|node| self.node(node, style.superscript()));
+        let width = [Some(&base), sup.as_ref(), sub.as_ref()]
+            .into_iter()
+            .flatten()
+            .map(|b| b.width)
+            .fold(0.0, f64::max);
+
+        let mut result = LayoutBox {
+            width,
+            height: base.height,
+            depth: base.depth,
+            items: Vec::new(),
+        };
+        if let Some(sup) = sup {
+            let shift = (base.height + self.em(c.upper_limit_gap_min()) * size + sup.depth)
+                .max(self.em(c.upper_limit_baseline_rise_min()) * size + base.height);
+            result.height = shift + sup.height;
+            let x = (width - sup.width) / 2.0;
+            result.place(sup, x, shift);
+        }
+        if let Some(sub) = sub {
+            let shift = (base.depth + self.em(c.lower_limit_gap_min()) * size + sub.height)
+                .max(self.em(c.lower_limit_baseline_drop_min()) * size + base.depth);
+            result.depth = shift + sub.depth;
+            let x = (width - sub.width) / 2.0;
+            result.place(sub, x, -shift);
+        }
+        let x = (width - base.width) / 2.0;
+        result.place(base, x, 0.0);
+        result
+    }
+
+    fn fraction(&self, numerator: &Node, denominator: &Node, style: Style) -> LayoutBox {
+        let c = &self.constants;
+        let size = self.size(style);
+        let display = style == Style::Display;
+        let numerator = self.node(numerator, style.fraction());
+        let denominator = self.node(denominator, style.fraction());
+
+        let axis = self.axis_height(style);
+        let thickness = self.em(c.fraction_rule_thickness()) * size;
+        let values = if display {
+            [
+                c.fraction_numerator_display_style_shift_up(),
+                c.fraction_denominator_display_style_shift_down(),
+                c.fraction_num_display_style_gap_min(),
+                c.fraction_denom_display_style_gap_min(),
+            ]
+        } else {
+            [
+                c.fraction_numerator_shift_up(),
+                c.fraction_denominator_shift_down(),
+                c.fraction_numerator_gap_min(),
+                c.fraction_denominator_gap_min(),
+            ]
+        };
+        let [mut shift_up, mut shift_down, numerator_gap, denominator_gap] =
+            values.map(|v| self.em(v) * size);
+        shift_up = shift_up.max(axis + thickness / 2.0 + numerator_gap + numerator.depth);
+        shift_down = shift_down.max(denominator.height + denominator_gap + thickness / 2.0 - axis);
+
+        let padding = 0.12 * size;
+        let width = numerator.width.max(denominator.width) + 2.0 * padding;
+        let mut result = LayoutBox {
+            width,
+            height: shift_up + numerator.height,
+            depth: shift_down + denominator.depth,
+            items: vec![Item::Rule {
+                x: 0.0,
+                y: axis - thickness / 2.0,
+                width,
+                height: thickness,
+                source: "frac",
+            }],
+        };
+        let x = (width - numerator.width) / 2.0;
+        result.place(numerator, x, shift_up);
+        let x = (width - denominator.width) / 2.0;
+        result.place(denominator, x, -shift_down);
+        result
+    }
+
+    fn radical(&self, degree: Option<&Node>, body: &Node, style: Style) -> LayoutBox {
+        let c = &self.constants;
+        let size = self.size(style);
+        let body = self.node(body, style);
+        let gap = if style == Style::Display {
+            self.em(c.radical_display_style_vertical_gap())
+        } else {
+            self.em(c.radical_vertical_gap())
+        } * size;
+        let thickness = self.em(c.radical_rule_thickness()) * size;
+        let top = body.height + gap + thickness;
+
+        // The top of the radical sign lines up with the top of its overbar.
+        let sign = match self.font.glyph_id('√') {
+            Some(id) => {
+                let id = self.vertical_variant(id, (top + body.depth) / size);
+                let scale = size / self.font.units_per_em();
+                let (_, y_min, _, y_max) = self.font.bounds(id);
+                let stretch = ((top + body.depth) / ((y_max - y_min) * scale)).max(1.0);
+                let shift = top - y_max * scale * stretch;
+                LayoutBox {
+                    width: self.font.advance(id) * scale,
+                    height: top,
+                    depth: -(y_min * scale * stretch + shift),
+                    items: vec![Item::Glyph {
+                        id,
+                        source: "√".to_string(),
+                        x: 0.0,
+                        y: shift,
+                        size,
+                        stretch,
+                    }],
+                }
+            }
+            None => LayoutBox::default(),
+        };
+
+        let mut result = LayoutBox {
+            height: top + self.em(c.radical_extra_ascender()) * size,
+            depth: body.depth.max(sign.depth),
+            ..Default::default()
+        };
+        if let Some(degree) = degree {
+            let degree = self.node(degree, Style::ScriptScript);
+            let raise = c.radical_degree_bottom_raise_percent() as f64 / 100.0
+                * (sign.height + sign.depth)
+                - sign.depth;
+            let before = self.em(c.radical_kern_before_degree()) * size;
+            let after = self.em(c.radical_kern_after_degree()) * size;
+            result.height = result.height.max(raise + degree.height);
+            result.width = (before + degree.width + after).max(0.0);
+            result.place(degree, before, raise);
+        }
+
+        let sign_x = result.width;
+        let x = sign_x + sign.width;
+        result.place(sign, sign_x, 0.0);
+        result.items.push(Item::Rule {
+            x,
+            y: top - thickness,
+            width: body.width,
+            height: thickness,
+            source: "sqrt",
+        });
+        result.width = x + body.width;
+        result.place(body, x, 0.0);
+        result
+    }
+
+    /// Cells laid out in rows and centered columns, centered on the math axis.
+    fn matrix(&self, rows: &[Vec<Vec<Node>>], align_left: bool, style: Style) -> LayoutBox {
+        let size = self.size(style);
+        let cell_style = if style == Style::Display {
+            Style::Text
+        } else {
+            style
+        };
+        let cells: Vec<Vec<LayoutBox>> = rows
+            .iter()
+            .map(|row| row.iter().map(|cell| self.list(cell, cell_style)).collect())
+            .collect();
+        let columns = cells.iter().map(|row| row.len()).max().unwrap_or(0);
+        let column_widths: Vec<f64> = (0..columns)
+            .map(|i| {
+                cells
+                    .iter()
+                    .filter_map(|row| row.get(i))
+                    .map(|b| b.width)
+                    .fold(0.0, f64::max)
+            })
+            .collect();
+        let column_gap = 0.8 * size;
+        let row_gap = 0.3 * size;
+        let min_baseline_skip = 1.2 * size;
+
+        // Baselines from the top of the first row downwards.
+        let mut baselines = Vec::new();
+        let mut y = 0.0;
+        let mut previous_depth: Option<f64> = None;
+        for row in &cells {
+            let height = row.iter().map(|b| b.height).fold(0.0, f64::max);
+            y += match previous_depth {
+                Some(depth) => (depth + row_gap + height).max(min_baseline_skip),
+                None => height,
+            };
+            baselines.push(y);
+            previous_depth = Some(row.iter().map(|b| b.depth).fold(0.0, f64::max));
+        }
+        let total = y + previous_depth.unwrap_or(0.0);
+        let top = total / 2.0 + self.axis_height(style);
+
+        let mut result = LayoutBox {
+            width: column_widths.iter().sum::<f64>()
+                + column_gap * columns.saturating_sub(1) as f64,
+            height: top,
+            depth: total - top,
+            items: Vec::new(),
+        };
+        for (row, baseline) in cells.into_iter().zip(baselines) {
+            let mut x = 0.0;
+            for (cell, column_width) in row.into_iter().zip(&column_widths) {
+                let offset = if align_left {
+                    0.0
+                } else {
+                    (column_width - cell.width) / 2.0
+                };
+                result.place(cell, x + offset, top - baseline);
+                x += column_width + column_gap;
+            }
+        }
+        result
+    }
+}
+
+/// The class of every node for spacing purposes, `None` for explicit spaces.
+///
+/// As in TeX, a binary operator without operands on both sides, such as a
+/// leading minus sign, is treated as an ordinary symbol.
+fn atom_classes(nodes: &[Node]) -> Vec<Option<Class>> {
+    let mut classes: Vec<Option<Class>> = nodes
+        .iter()
+        .map(|node| match node {
+            Node::Space(_) => None,
+            node => Some(node.class()),
+        })
+        .collect();
+    let atoms: Vec<usize> = (0..nodes.len()).filter(|&i| classes[i].is_some()).collect();
+    for (n, &i) in atoms.iter().enumerate() {
+        if classes[i] != Some(Class::Bin) {
+            continue;
+        }
+        let previous = n.checked_sub(1).and_then(|p| classes[atoms[p]]);
+        let next = atoms.get(n + 1).and_then(|&j| classes[j]);
+        let no_left = matches!(
+            previous,
+            None | Some(Class::Bin | Class::Op | Class::Rel | Class::Open | Class::Punct)
+        );
+        let no_right = matches!(next, None | Some(Class::Rel | Class::Close | Class::Punct));
+        if no_left || no_right {
+            classes[i] = Some(Class::Ord);
+        }
+    }
+    classes
+}
+
+/// Space in ems between two adjacent atoms, following TeX's spacing table.
+fn spacing(left: Class, right: Class, style: Style) -> f64 {
+    use Class::*;
+    match (left, right) {
+        (Ord | Op | Close | Inner, Op) | (Op, Ord) => THIN_SPACE,
+        _ if style.is_script() => 0.0,
+        (Ord | Close | Inner, Bin) | (Bin, Ord | Op | Open | Inner) => MEDIUM_SPACE,
+        (Ord | Op | Close | Inner, Rel) | (Rel, Ord | Op | Open | Inner) => THICK_SPACE,
+        (Ord | Op | Close, Inner) | (Inner, Ord | Open | Punct | Inner) | (Punct, _) => THIN_SPACE,
+        _ => 0.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn math_object(tex: &str) -> Object {
+        Object {
+            r#type: "math".to_string(),
+            name: "eq".to_string(),
+            properties: vec![
+                Property {
+                    name: "tex".to_string(),
+                    value: Value::String(tex.to_string()),
+                },
+                Property {
+                    name: "position".to_string(),
+                    value: Value::Tuple(100.0, 50.0),
+                },
+            ],
+        }
+    }
+
+    fn glyphs(objects: &[Object]) -> Vec<String> {
+        objects
+            .iter()
+            .filter_map(
+                |o| match &o.properties.iter().find(|p| p.name == "glyph")?.value {
+                    Value::String(s) => Some(s.clone()),
+                    _ => None,
+                },
+            )
+            .collect()
+    }
+
+    fn bounds(object: &Object) -> (f64, f64, f64, f64) {
+        let Some(Value::String(d)) = object
+            .properties
+            .iter()
+            .find(|p| p.name == "d")
+            .map(|p| &p.value)
+        else {
+            panic!("object has no path data");
+        };
+        let points: Vec<(f64, f64)> = path::flatten(&path::parse_path_data(d).unwrap())
+            .into_iter()
+            .flat_map(|s| s.points)
+            .collect();
+        points.iter().fold(
+            (f64::MAX, f64::MAX, f64::MIN, f64::MIN),
+            |(x0, y0, x1, y1), &(x, y)| (x0.min(x), y0.min(y), x1.max(x), y1.max(y)),
+        )
+    }
+
+    #[test]
+    fn test_parse_scripts_and_commands() {
+        let nodes = Parser::new(r"e^{i\pi} + 1").parse().unwrap();
+        assert_eq!(nodes.len(), 3);
+        match &nodes[0] {
+            Node::Scripts {
+                sup: Some(sup),
+                sub: None,
+                ..
+            } => {
+                assert!(matches!(&**sup, Node::Group(nodes) if nodes.len() == 2));
+            }
+            other => panic!("unexpected node {:?}", other),
+        }
+        assert_eq!(nodes[1].class(), Class::Bin);
+    }
+
+    #[test]
+    fn test_parse_matrix_rows() {
+        let nodes = Parser::new(r"\begin{pmatrix} a & b \\ c & d \\ \end{pmatrix}")
+            .parse()
+            .unwrap();
+        match &nodes[0] {
+            Node::Matrix {
+                rows, open, close, ..
+            } => {
+                assert_eq!(rows.len(), 2);
+                assert!(rows.iter().all(|row| row.len() == 2));
+                assert_eq!((*open, *close), (Some('('), Some(')')));
+            }
+            other => panic!("unexpected node {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Parser::new(r"\frac{1}{").parse().is_err());
+        assert!(Parser::new(r"x^2^3").parse().is_err());
+        assert!(Parser::new(r"\notacommand").parse().is_err());
+        assert!(Parser::new(r"\begin{matrix} a \end{pmatrix}")
+            .parse()
+            .is_err());
+    }
+
+    #[test]
+    fn test_leading_minus_is_not_binary() {
+        let nodes = Parser::new("-a - b").parse().unwrap();
+        let classes = atom_classes(&nodes);
+        assert_eq!(classes[0], Some(Class::Ord));
+        assert_eq!(classes[2], Some(Class::Bin));
+    }
+
+    #[test]
+    fn test_styled_chars() {
+        assert_eq!(styled_char('x', Variant::Normal), '\u{1D465}');
+        assert_eq!(styled_char('h', Variant::Normal), 'ℎ');
+        assert_eq!(styled_char('R', Variant::DoubleStruck), 'ℝ');
+        assert_eq!(styled_char('2', Variant::Normal), '2');
+    }
+
+    #[test]
+    fn test_typeset_names_glyphs_in_reading_order() {
+        let objects = typeset(&math_object(r"e^{i\pi} + 1 = 0")).unwrap();
+        assert_eq!(glyphs(&objects), vec!["e", "i", "π", "+", "1", "=", "0"]);
+        assert_eq!(objects[0].name, "eq.1");
+        assert_eq!(objects[6].name, "eq.7");
+        assert!(objects.iter().all(|o| o.r#type == "path"));
+        assert!(objects
+            .iter()
+            .all(|o| o.properties.iter().any(|p| p.name == "position")));
+    }
+
+    #[test]
+    fn test_typeset_superscript_is_raised_and_smaller() {
+        let objects = typeset(&math_object("x^x")).unwrap();
+        let base = bounds(&objects[0]);
+        let script = bounds(&objects[1]);
+        assert!(script.1 < base.1);
+        assert!(script.3 - script.1 < base.3 - base.1);
+        assert!(script.0 > base.0);
+    }
+
+    #[test]
+    fn test_typeset_fraction_bar_separates_parts() {
+        let objects = typeset(&math_object(r"\frac{a}{b}")).unwrap();
+        assert_eq!(glyphs(&objects), vec!["frac", "a", "b"]);
+        let bar = bounds(&objects[0]);
+        assert!(bounds(&objects[1]).3 < bar.1);
+        assert!(bounds(&objects[2]).1 > bar.3);
+    }
+
+    #[test]
+    fn test_typeset_radical_covers_body() {
+        let objects = typeset(&math_object(r"\sqrt{x}")).unwrap();
+        assert_eq!(glyphs(&objects), vec!["√", "sqrt", "x"]);
+        let rule = bounds(&objects[1]);
+        let body = bounds(&objects[2]);
+        assert!(rule.0 <= body.0 && rule.2 >= body.2);
+        assert!(rule.3 < body.1);
+    }
+
+    #[test]
+    fn test_typeset_is_centered_on_position() {
+        let objects = typeset(&math_object("x = y")).unwrap();
+        let left = bounds(&objects[0]).0;
+        let right = bounds(&objects[2]).2;
+        assert!((left + right).abs() < 5.0, "{} {}", left, right);
+    }
+
+    #[test]
+    fn test_typeset_tall_delimiters_grow() {
+        let plain = typeset(&math_object("(x)")).unwrap();
+        let tall = typeset(&math_object(r"\left( \frac{\frac{a}{b}}{c} \right)")).unwrap();
+        let height = |o: &Object| {
+            let b = bounds(o);
+            b.3 - b.1
+        };
+        assert!(height(&tall[0]) > 1.5 * height(&plain[0]));
+    }
+
+    #[test]
+    fn test_typeset_missing_tex() {
+        let object = Object {
+            r#type: "math".to_string(),
+            name: "eq".to_string(),
+            properties: vec![],
+        };
+        assert!(typeset(&object).is_err());
+    }
+
+    #[test]
+    fn test_expand_math_objects() {
+        let mut items = vec![math_object(
+            r"\sum_{n=1}^{\infty} \frac{1}{n^2} = \frac{\pi^2}{6}",
+        )];
+        expand_math_objects(&mut items);
+        assert!(items.len() > 10);
+        assert!(items.iter().all(|o| o.name.starts_with("eq.")));
+    }
+}
@@ -0,0 +1,195 @@
+//! `number_line` objects: a line with numbered ticks and an optional pointer
+//! at an animatable value.
+//!
+//! [`layout`] works out the geometry in scene coordinates, which each
+//! renderer then draws with its own lines and arrowheads.
+
+use crate::ast::{Object, Property, Value};
+use crate::axes;
+use crate::color;
+use crate::font;
+use crate::path::PathData;
+
+const DEFAULT_LENGTH: f64 = 800.0;
+const DEFAULT_RANGE: (f64, f64) = (0.0, 10.0);
+const DEFAULT_FONT_SIZE: f64 = 24.0;
+const TICK_SIZE: f64 = 10.0;
+const POINTER_LENGTH: f64 = 40.0;
+
+pub struct NumberLine {
+    pub start: (f64, f64),
+    pub end: (f64, f64),
+    pub ticks: Vec<((f64, f64), (f64, f64))>,
+    /// Outlines of the tick numbers, to be filled with `color`.
+    pub labels: Vec<PathData>,
+    /// The pointer as a line from its tail to its tip on the number line.
+    pub pointer: Option<((f64, f64), (f64, f64))>,
+    /// Whether the line ends in an arrow tip at its start and at its end.
+    pub tips: (bool, bool),
+    pub stroke_width: f64,
+    pub color: [u8; 4],
+    pub pointer_color: [u8; 4],
+}
+
+/// Warns about number lines among `items` whose `range` is empty, which are
+/// drawn one unit long instead.
+pub fn check_ranges(items: &[Object]) {
+    for object in items.iter().filter(|o| o.r#type == "number_line") {
+        let range = object.properties.iter().find(|p| p.name == "range");
+        if let Some(&Value::Tuple(min, max)) = range.map(|p| &p.value) {
+            if min == max {
+                eprintln!(
+                    "Warning: The range of number line '{}' is empty, showing {} to {}",
+                    object.name,
+                    min,
+                    min + 1.0
+                );
+            }
+        }
+    }
+}
+
+pub fn layout(properties: &[Property]) -> NumberLine {
+    let property = |name: &str| properties.iter().find(|p| p.name == name).map(|p| &p.value);
+    let number = |name: &str| match property(name) {
+        Some(Value::Number(n)) => Some(*n),
+        _ => None,
+    };
+    let color = |name: &str, default: [u8; 4]| match property(name) {
+        Some(Value::Color(hex)) => color::parse(hex).unwrap_or(default),
+        _ => default,
+    };
+
+    let (cx, cy) = match property("position") {
+        Some(Value::Tuple(x, y)) => (*x, *y),
+        _ => (0.0, 0.0),
+    };
+    let (min, max) = match property("range") {
+        Some(&Value::Tuple(min, max)) if min == max => (min, min + 1.0),
+        Some(&Value::Tuple(min, max)) => (min, max),
+        _ => DEFAULT_RANGE,
+    };
+    let length = number("length").unwrap_or(DEFAULT_LENGTH);
+    let step = number("step").unwrap_or(1.0);
+    let font_size = number("font_size").unwrap_or(DEFAULT_FONT_SIZE);
+    let start = (cx - length / 2.0, cy);
+    let end = (cx + length / 2.0, cy);
+    let to_x = |value: f64| start.0 + (value - min) / (max - min) * length;
+
+    let values = axes::ticks((min, max), step);
+    let ticks = values
+        .iter()
+        .map(|&v| {
+            (
+                (to_x(v), cy - TICK_SIZE / 2.0),
+                (to_x(v), cy + TICK_SIZE / 2.0),
+            )
+        })
+        .collect();
+    let labels = if font_size > 0.0 {
+        values
+            .iter()
+            .flat_map(|&v| {
+                let top = cy + TICK_SIZE / 2.0 + font_size * 0.4;
+                font::label(&axes::format_number(v), (to_x(v), top), 0.5, font_size)
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let pointer = number("pointer").map(|value| {
+        let tip = (to_x(value), cy - TICK_SIZE);
+        (
+            (
+                tip.0,
+                tip.1 - number("pointer_length").unwrap_or(POINTER_LENGTH),
+            ),
+            tip,
+        )
+    });
+    let tips = match property("tips") {
+        Some(Value::String(tips)) => match tips.as_str() {
+            "both" => (true, true),
+            "end" => (false, true),
+            "start" => (true, false),
+            _ => (false, false),
+        },
+        _ => (false, false),
+    };
+
+    NumberLine {
+        start,
+        end,
+        ticks,
+        labels,
+        pointer,
+        tips,
+        stroke_width: number("stroke_width").unwrap_or(2.0),
+        color: color("color", [255, 255, 255, 255]),
+        pointer_color: color("pointer_color", [255, 255, 0, 255]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn properties(extra: Vec<(&str, Value)>) -> Vec<Property> {
+        let mut properties = vec![
+            ("position", Value::Tuple(500.0, 300.0)),
+            ("range", Value::Tuple(0.0, 10.0)),
+            ("length", Value::Number(1000.0)),
+        ];
+        properties.extend(extra);
+        properties
+            .into_iter()
+            .map(|(name, value)| Property {
+                name: name.to_string(),
+                value,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_ticks_and_labels() {
+        let line = layout(&properties(vec![("step", Value::Number(2.0))]));
+        assert_eq!(line.start, (0.0, 300.0));
+        assert_eq!(line.end, (1000.0, 300.0));
+        assert_eq!(line.ticks.len(), 6);
+        assert_eq!(line.ticks[1].0 .0, 200.0);
+        // "10" has two glyphs, the others one each.
+        assert_eq!(line.labels.len(), 7);
+        assert!(line.pointer.is_none());
+        assert_eq!(line.tips, (false, false));
+    }
+
+    #[test]
+    fn test_pointer_follows_value() {
+        let line = layout(&properties(vec![("pointer", Value::Number(7.5))]));
+        let (tail, tip) = line.pointer.unwrap();
+        assert_eq!(tip.0, 750.0);
+        assert_eq!(tail.0, 750.0);
+        assert!(tail.1 < tip.1 && tip.1 < 300.0);
+    }
+
+    #[test]
+    fn test_empty_range_is_widened() {
+        let mut properties = properties(vec![]);
+        properties[1].value = Value::Tuple(3.0, 3.0);
+        let line = layout(&properties);
+        let xs: Vec<f64> = line.ticks.iter().map(|tick| tick.0 .0).collect();
+        assert_eq!(xs, [0.0, 1000.0]);
+    }
+
+    #[test]
+    fn test_hidden_labels_and_tips() {
+        let line = layout(&properties(vec![
+            ("font_size", Value::Number(0.0)),
+            ("tips", Value::String("both".to_string())),
+            ("color", Value::Color("#FF8000".to_string())),
+        ]));
+        assert!(line.labels.is_empty());
+        assert_eq!(line.tips, (true, true));
+        assert_eq!(line.color, [255, 128, 0, 255]);
+    }
+}
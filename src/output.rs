@@ -0,0 +1,268 @@
+//! Where renders are written. Output is first staged in a private directory
+//! under the system's temp location and only moved to its destination once
+//! it is complete, so a failed or interrupted render leaves nothing behind
+//! and an earlier result is only ever replaced by a finished one.
+
+use crate::sink;
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tempfile::TempDir;
+
+// Staging directories still in use, for the Ctrl-C handler to remove.
+static STAGING_DIRS: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// Removes staging directories when the process is interrupted. Dropping a
+/// [`StagedOutput`] removes its directory in every other case, including a
+/// panic.
+pub fn install_interrupt_handler() {
+    let result = ctrlc::set_handler(|| {
+        for dir in staging_dirs().drain(..) {
+            let _ = fs::remove_dir_all(dir);
+        }
+        eprintln!("Interrupted");
+        std::process::exit(130);
+    });
+    if let Err(e) = result {
+        eprintln!("Warning: Cannot handle Ctrl-C: {}", e);
+    }
+}
+
+fn staging_dirs() -> std::sync::MutexGuard<'static, Vec<PathBuf>> {
+    STAGING_DIRS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Fails when `path` already exists, unless it may be replaced. A directory
+/// may only be replaced by frames named after `frames`, a pattern such as
+/// `frame_%05d.png`, and only if it holds nothing but frames like them, which
+/// is checked before anything is rendered. A path of `-` stands for standard
+/// output and is always fine.
+pub fn check_output(path: &Path, force: bool, frames: Option<&str>) -> Result<(), String> {
+    if path == Path::new("-") || !path.exists() {
+        return Ok(());
+    }
+    if !force {
+        return Err(format!(
+            "{} already exists, use --force to overwrite it",
+            path.display()
+        ));
+    }
+    if path.is_dir() {
+        let Some(pattern) = frames else {
+            return Err(format!("{} is a directory", path.display()));
+        };
+        // A pattern without a frame number is reported when the frames are
+        // written.
+        let Some(name) = sink::frame_name(pattern, 0) else {
+            return Ok(());
+        };
+        let names = HashSet::from([name_pattern(name.as_ref())]);
+        match holds_only_frames(path, &names) {
+            Ok(true) => {}
+            Ok(false) => return Err(other_files_error(path)),
+            Err(e) => return Err(format!("Error reading {}: {}", path.display(), e)),
+        }
+    }
+    Ok(())
+}
+
+/// A file or directory being written, which only appears at its destination
+/// once [`StagedOutput::commit`] is called.
+pub struct StagedOutput {
+    dir: TempDir,
+    staged: PathBuf,
+    target: PathBuf,
+}
+
+impl StagedOutput {
+    pub fn new(target: &Path) -> io::Result<StagedOutput> {
+        let dir = tempfile::Builder::new().prefix("beam-").tempdir()?;
+        let name = target.file_name().unwrap_or("output".as_ref());
+        let staged = dir.path().join(name);
+        staging_dirs().push(dir.path().to_path_buf());
+        Ok(StagedOutput {
+            dir,
+            staged,
+            target: target.to_path_buf(),
+        })
+    }
+
+    /// Where the output is written until it is committed.
+    pub fn path(&self) -> &Path {
+        &self.staged
+    }
+
+    /// Moves the finished output to its destination, replacing what is
+    /// there. A directory is only replaced if it holds nothing but frames,
+    /// so that a folder of other files is never lost.
+    pub fn commit(self) -> io::Result<()> {
+        if self.target.is_dir() {
+            if !self.staged.is_dir() {
+                return Err(io::Error::other(format!(
+                    "{} is a directory",
+                    self.target.display()
+                )));
+            }
+            let mut names = HashSet::new();
+            for entry in fs::read_dir(&self.staged)? {
+                names.insert(name_pattern(&entry?.file_name()));
+            }
+            if !holds_only_frames(&self.target, &names)? {
+                return Err(io::Error::other(other_files_error(&self.target)));
+            }
+            fs::remove_dir_all(&self.target)?;
+        } else if self.target.exists() && self.staged.is_dir() {
+            fs::remove_file(&self.target)?;
+        }
+        if let Some(parent) = self.target.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+        // Renaming fails when the temp location is on another file system.
+        if fs::rename(&self.staged, &self.target).is_err() {
+            copy_all(&self.staged, &self.target)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for StagedOutput {
+    fn drop(&mut self) {
+        staging_dirs().retain(|dir| dir != self.dir.path());
+    }
+}
+
+// Whether every entry of the directory `dir` is a file named like one of the
+// frames with the name patterns `names`, as when it was written before.
+fn holds_only_frames(dir: &Path, names: &HashSet<String>) -> io::Result<bool> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_file() || !names.contains(&name_pattern(&entry.file_name())) {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+// A frame's file name but for its numbers, the same for every frame.
+fn name_pattern(name: &OsStr) -> String {
+    name.to_string_lossy()
+        .chars()
+        .filter(|c| !c.is_ascii_digit())
+        .collect()
+}
+
+fn other_files_error(dir: &Path) -> String {
+    format!(
+        "{} holds files other than frames, remove it or choose another output",
+        dir.display()
+    )
+}
+
+fn copy_all(from: &Path, to: &Path) -> io::Result<()> {
+    if from.is_dir() {
+        fs::create_dir_all(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_all(&entry.path(), &to.join(entry.file_name()))?;
+        }
+    } else {
+        fs::copy(from, to)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_existing_output_needs_force() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("video.mp4");
+        assert!(check_output(&path, false, None).is_ok());
+        fs::write(&path, "old").unwrap();
+        assert!(check_output(&path, false, None).is_err());
+        assert!(check_output(&path, true, None).is_ok());
+    }
+
+    #[test]
+    fn test_only_frame_directories_are_replaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let frames = dir.path().join("frames");
+        fs::create_dir(&frames).unwrap();
+        fs::write(frames.join("frame_00000.png"), "old").unwrap();
+        fs::write(frames.join("frame_00001.png"), "old").unwrap();
+        assert!(check_output(&frames, false, Some("frame_%05d.png")).is_err());
+        assert!(check_output(&frames, true, Some("frame_%05d.png")).is_ok());
+
+        // Not for a single file, nor for frames named otherwise.
+        assert!(check_output(&frames, true, None).is_err());
+        assert!(check_output(&frames, true, Some("shot_%03d.png")).is_err());
+        assert!(check_output(&frames, true, Some("frame_%05d.qoi")).is_err());
+
+        fs::write(frames.join("notes.txt"), "keep").unwrap();
+        assert!(check_output(&frames, true, Some("frame_%05d.png")).is_err());
+    }
+
+    #[test]
+    fn test_staged_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("out").join("video.gif");
+
+        // Output that is never committed is removed with its staging directory.
+        let staged = StagedOutput::new(&target).unwrap();
+        fs::write(staged.path(), "partial").unwrap();
+        let staging_dir = staged.dir.path().to_path_buf();
+        drop(staged);
+        assert!(!staging_dir.exists());
+        assert!(!target.exists());
+
+        let staged = StagedOutput::new(&target).unwrap();
+        fs::write(staged.path(), "done").unwrap();
+        staged.commit().unwrap();
+        assert_eq!(fs::read_to_string(&target).unwrap(), "done");
+    }
+
+    #[test]
+    fn test_staged_frames_only_replace_frames() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("frames");
+        let stage = |count: usize| {
+            let staged = StagedOutput::new(&target).unwrap();
+            fs::create_dir(staged.path()).unwrap();
+            for i in 0..count {
+                fs::write(staged.path().join(format!("frame_{:05}.png", i)), "new").unwrap();
+            }
+            staged
+        };
+
+        // Frames written before are replaced, even when there were more.
+        fs::create_dir(&target).unwrap();
+        for i in 0..3 {
+            fs::write(target.join(format!("frame_{:05}.png", i)), "old").unwrap();
+        }
+        stage(2).commit().unwrap();
+        assert_eq!(fs::read_dir(&target).unwrap().count(), 2);
+        assert_eq!(
+            fs::read_to_string(target.join("frame_00001.png")).unwrap(),
+            "new"
+        );
+
+        // A directory with anything else in it is left alone.
+        fs::write(target.join("notes.txt"), "keep").unwrap();
+        assert!(stage(2).commit().is_err());
+        assert_eq!(
+            fs::read_to_string(target.join("notes.txt")).unwrap(),
+            "keep"
+        );
+        fs::remove_file(target.join("notes.txt")).unwrap();
+        fs::create_dir(target.join("photos")).unwrap();
+        assert!(stage(2).commit().is_err());
+        assert!(target.join("photos").is_dir());
+    }
+}
@@ -1,8 +1,13 @@
 use pest::iterators::Pair;
 use pest::Parser;
+use std::collections::BTreeSet;
 use std::time::Duration;
 
-use crate::ast::{Animation, BeamScript, Camera, Object, Property, Scene, Timeline, Value};
+use crate::ast::{
+    Animation, BeamScript, Camera, Marker, Object, Property, Reference, Scene, Timeline, Transform,
+    Value, DEFAULT_FPS,
+};
+use crate::{animator, assets, axes, chart, code, graph, math, number_line, svg, table, transform};
 
 #[derive(pest_derive::Parser)]
 #[grammar = "beam.pest"]
@@ -11,21 +16,56 @@ pub struct BeamParser;
 struct ParsedTimeline {
     scene_name: String,
     animations: Vec<Animation>,
+    transforms: Vec<Transform>,
+    markers: Vec<Marker>,
 }
 
-pub fn parse_str(input: &str) -> Result<BeamScript, Box<dyn std::error::Error>> {
+/// Parses a script, to be rendered at `fps` frames per second if given, such
+/// as from the command line, instead of at the camera's `fps`.
+pub fn parse_str(
+    input: &str,
+    fps: Option<f64>,
+) -> Result<BeamScript, Box<dyn std::error::Error>> {
     let mut pairs = BeamParser::parse(Rule::file, input)?;
     let file = pairs.next().unwrap();
 
-    let mut camera: Option<Camera> = None;
+    // Times given in frames depend on the frame rate, so find it first
+    let mut camera = file
+        .clone()
+        .into_inner()
+        .filter(|pair| pair.as_rule() == Rule::camera)
+        .last()
+        .map(parse_camera);
+    if let Some(fps) = fps {
+        let properties = &mut camera.get_or_insert_with(Camera::default).properties;
+        properties.retain(|p| p.name != "fps");
+        properties.push(Property {
+            name: "fps".to_string(),
+            value: Value::Number(fps),
+        });
+    }
+    if let Some(Property { value, .. }) = camera
+        .iter()
+        .flat_map(|c| &c.properties)
+        .find(|p| p.name == "fps")
+    {
+        if !matches!(value, Value::Number(n) if *n > 0.0 && n.is_finite()) {
+            eprintln!(
+                "Warning: Invalid fps {:?}, rendering at {} fps",
+                value, DEFAULT_FPS
+            );
+        }
+    }
+    let fps = camera.as_ref().map_or(DEFAULT_FPS, Camera::fps);
+
     let mut scenes: Vec<Scene> = Vec::new();
     let mut temp_timelines: Vec<ParsedTimeline> = Vec::new();
 
     for pair in file.into_inner() {
         match pair.as_rule() {
-            Rule::camera => camera = Some(parse_camera(pair)),
-            Rule::scene => scenes.push(parse_scene(pair)),
-            Rule::timeline => temp_timelines.push(parse_temp_timeline(pair)),
+            Rule::camera => (),
+            Rule::scene => scenes.push(parse_scene(pair, fps)?),
+            Rule::timeline => temp_timelines.push(parse_temp_timeline(pair, fps)?),
             Rule::EOI | Rule::COMMENT => (),
             _ => {
                 println!("Unexpected rule: {:?}", pair.as_rule());
@@ -34,15 +74,71 @@ pub fn parse_str(input: &str) -> Result<BeamScript, Box<dyn std::error::Error>>
         }
     }
 
+    // Note the files the script names before the objects that read them are
+    // replaced by what they contain
+    let mut sources = BTreeSet::new();
+    for scene in &scenes {
+        sources.extend(assets::sources(&scene.items));
+    }
+    for animation in temp_timelines.iter().flat_map(|t| &t.animations) {
+        if let Value::String(src) = &animation.to {
+            if animation.property == "src" {
+                sources.insert(assets::resolve(src));
+            }
+        }
+    }
+
+    // Points given in data coordinates need the axes they refer to, so
+    // resolve them before the axes are expanded. Charts load their data here
+    // too, as animations to another data file start from it
+    for scene in &mut scenes {
+        axes::resolve_scene_references(&mut scene.items);
+        chart::load_chart_data(&mut scene.items);
+    }
+    for temp_timeline in &mut temp_timelines {
+        if let Some(scene) = scenes.iter().find(|s| s.name == temp_timeline.scene_name) {
+            for animation in &mut temp_timeline.animations {
+                axes::resolve_references(&scene.items, &mut animation.to);
+            }
+            chart::load_animation_data(&scene.items, &mut temp_timeline.animations);
+        }
+    }
+
+    // Replace imported SVG files, plots, graphs, tables and equations with the
+    // shapes they contain, and load the files that code blocks read
+    for scene in &mut scenes {
+        svg::expand_svg_objects(&mut scene.items);
+        axes::expand_axes_objects(&mut scene.items);
+        // Graphs turn changes to their nodes and edges into animations of their parts
+        let mut no_animations = Vec::new();
+        let animations = temp_timelines
+            .iter_mut()
+            .find(|t| t.scene_name == scene.name)
+            .map_or(&mut no_animations, |t| &mut t.animations);
+        graph::expand_graph_objects(&mut scene.items, animations);
+        table::expand_table_objects(&mut scene.items, &camera);
+        math::expand_math_objects(&mut scene.items);
+        code::load_code_sources(&mut scene.items);
+        number_line::check_ranges(&scene.items);
+    }
+
     // Link timelines to scenes
     for temp_timeline in temp_timelines {
         if let Some(scene) = scenes
             .iter_mut()
             .find(|s| s.name == temp_timeline.scene_name)
         {
+            // Transforms become ordinary animations of the objects they match up
+            let mut animations = temp_timeline.animations;
+            for t in &temp_timeline.transforms {
+                animations.extend(transform::transform_animations(&scene.items, t));
+            }
+            chart::fit_value_ranges(&mut scene.items, &animations);
             scene.timeline = Some(Timeline {
-                animations: temp_timeline.animations,
+                animations,
+                markers: temp_timeline.markers,
             });
+            animator::check_animations(scene);
         } else {
             eprintln!(
                 "Warning: Timeline found for non-existent scene '{}'",
@@ -51,32 +147,41 @@ pub fn parse_str(input: &str) -> Result<BeamScript, Box<dyn std::error::Error>>
         }
     }
 
-    Ok(BeamScript { camera, scenes })
+    Ok(BeamScript {
+        camera,
+        scenes,
+        sources,
+    })
 }
 
-fn parse_scene(pair: Pair<Rule>) -> Scene {
+fn parse_scene(pair: Pair<Rule>, fps: f64) -> Result<Scene, String> {
     let mut inner = pair.into_inner();
     let name = parse_string_literal(inner.next().unwrap());
 
     let mut items = Vec::new();
     let mut duration: Option<Duration> = None;
+    let mut layers = Vec::new();
 
     for content in inner {
         match content.as_rule() {
             Rule::object => items.push(parse_object(content)),
             Rule::scene_duration => {
-                duration = Some(parse_time_value(content.into_inner().next().unwrap()));
+                duration = Some(parse_time_value(content.into_inner().next().unwrap(), fps)?);
+            }
+            Rule::scene_layers => {
+                layers = content.into_inner().map(parse_string_literal).collect();
             }
             _ => (), // Skip comments
         }
     }
 
-    Scene {
+    Ok(Scene {
         name,
         items,
         timeline: None,
         duration,
-    }
+        layers,
+    })
 }
 
 fn parse_object(pair: Pair<Rule>) -> Object {
@@ -106,35 +211,126 @@ fn parse_value(pair: Pair<Rule>) -> Value {
     match inner.as_rule() {
         Rule::string_literal => Value::String(parse_string_literal(inner)),
         Rule::number => Value::Number(inner.as_str().parse().unwrap()),
-        Rule::hex_color => Value::Color(inner.as_str().to_string()),
+        Rule::hex_color | Rule::transparent => Value::Color(inner.as_str().to_string()),
         Rule::tuple => {
             let mut inner = inner.into_inner();
             let x = inner.next().unwrap().as_str().parse().unwrap();
             let y = inner.next().unwrap().as_str().parse().unwrap();
             Value::Tuple(x, y)
         }
+        Rule::list => Value::List(inner.into_inner().map(parse_value).collect()),
+        Rule::axes_point => {
+            let mut inner = inner.into_inner();
+            let axes = parse_string_literal(inner.next().unwrap());
+            let x = inner.next().unwrap().as_str().parse().unwrap();
+            let y = inner.next().unwrap().as_str().parse().unwrap();
+            Value::Reference(Reference::AxesPoint { axes, x, y })
+        }
+        Rule::anchor => {
+            let mut inner = inner.into_inner();
+            let object = parse_string_literal(inner.next().unwrap());
+            let anchor = inner.next().unwrap().as_str().to_string();
+            Value::Reference(Reference::Anchor { object, anchor })
+        }
+        Rule::layout_reference => {
+            let mut inner = inner.into_inner();
+            let relation = inner.next().unwrap().as_str().to_string();
+            let target = parse_string_literal(inner.next().unwrap());
+            let gap = inner.next().map(|n| n.as_str().parse().unwrap());
+            Value::Reference(Reference::Layout {
+                relation,
+                target,
+                gap,
+            })
+        }
         _ => unreachable!(),
     }
 }
 
 fn parse_string_literal(pair: Pair<Rule>) -> String {
-    pair.as_str().trim_matches('"').to_string()
+    let s = pair.as_str();
+    dedent(&s[1..s.len() - 1].replace("\\\"", "\""))
+}
+
+// A string that starts on the line after its opening quote, such as a code
+// block's `source`, is laid out with the script: drop that first line break,
+// the indentation its lines share and the indentation before the closing quote.
+fn dedent(text: &str) -> String {
+    let Some(body) = text.strip_prefix('\n').or_else(|| text.strip_prefix("\r\n")) else {
+        return text.to_string();
+    };
+    let mut lines: Vec<&str> = body.lines().collect();
+    if body.ends_with('\n') {
+        lines.push("");
+    }
+    if lines.last().is_some_and(|line| line.trim().is_empty()) {
+        lines.pop();
+    }
+    let indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    lines
+        .iter()
+        .map(|line| line.get(indent..).unwrap_or("").trim_end_matches('\r'))
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
-fn parse_temp_timeline(pair: Pair<Rule>) -> ParsedTimeline {
+fn parse_temp_timeline(pair: Pair<Rule>, fps: f64) -> Result<ParsedTimeline, String> {
     let mut inner = pair.into_inner();
     let scene_name = parse_string_literal(inner.next().unwrap());
-    let animations = inner.map(parse_animation).collect();
-    ParsedTimeline {
+    let mut animations = Vec::new();
+    let mut transforms = Vec::new();
+    let mut markers = Vec::new();
+    for statement in inner {
+        match statement.as_rule() {
+            Rule::animation => animations.push(parse_animation(statement, fps)?),
+            Rule::transform => transforms.push(parse_transform(statement, fps)?),
+            Rule::marker => markers.push(parse_marker(statement, fps)?),
+            _ => unreachable!(),
+        }
+    }
+    Ok(ParsedTimeline {
         scene_name,
         animations,
-    }
+        transforms,
+        markers,
+    })
 }
 
-fn parse_animation(pair: Pair<Rule>) -> Animation {
+fn parse_marker(pair: Pair<Rule>, fps: f64) -> Result<Marker, String> {
+    let mut inner = pair.into_inner();
+    let name = parse_string_literal(inner.next().unwrap());
+    let time = parse_time_value(inner.next().unwrap(), fps)?;
+    Ok(Marker { name, time })
+}
+
+fn parse_transform(pair: Pair<Rule>, fps: f64) -> Result<Transform, String> {
+    let mut inner = pair.into_inner();
+    let from = parse_string_literal(inner.next().unwrap());
+    let to = parse_string_literal(inner.next().unwrap());
+    let start = parse_time_value(inner.next().unwrap(), fps)?;
+    let end = parse_time_value(inner.next().unwrap(), fps)?;
+    let easing = inner.next().map(|p| {
+        p.into_inner().next().unwrap().as_str().to_string()
+    });
+
+    Ok(Transform {
+        start,
+        end,
+        from,
+        to,
+        easing,
+    })
+}
+
+fn parse_animation(pair: Pair<Rule>, fps: f64) -> Result<Animation, String> {
     let mut inner = pair.into_inner();
     let time_pair = inner.next().unwrap();
-    let (start, end) = parse_animation_time(time_pair);
+    let (start, end) = parse_animation_time(time_pair, fps)?;
 
     let target_pair = inner.next().unwrap();
     let (target_object, property) = parse_target_property(target_pair);
@@ -145,44 +341,67 @@ fn parse_animation(pair: Pair<Rule>) -> Animation {
         p.into_inner().next().unwrap().as_str().to_string()
     });
 
-    Animation {
+    Ok(Animation {
         start,
         end,
         target_object,
         property,
         to,
         easing,
-    }
+    })
 }
 
-fn parse_animation_time(pair: Pair<Rule>) -> (Duration, Option<Duration>) {
+fn parse_animation_time(
+    pair: Pair<Rule>,
+    fps: f64,
+) -> Result<(Duration, Option<Duration>), String> {
     let mut inner = pair.into_inner();
     let kind = inner.next().unwrap();
     match kind.as_rule() {
         Rule::animation_instant => {
-            let start = parse_time_value(kind.into_inner().next().unwrap());
-            (start, None)
+            let start = parse_time_value(kind.into_inner().next().unwrap(), fps)?;
+            Ok((start, None))
         }
         Rule::animation_range => {
             let mut inner = kind.into_inner();
-            let start = parse_time_value(inner.next().unwrap());
-            let end = parse_time_value(inner.next().unwrap());
-            (start, Some(end))
+            let start = parse_time_value(inner.next().unwrap(), fps)?;
+            let end = parse_time_value(inner.next().unwrap(), fps)?;
+            Ok((start, Some(end)))
         }
         _ => unreachable!(),
     }
 }
 
-fn parse_time_value(pair: Pair<Rule>) -> Duration {
+/// Parses a time such as `2.5s`, `500ms` or `120f`, with frames counted at
+/// `fps` frames per second.
+pub fn parse_time(input: &str, fps: f64) -> Result<Duration, String> {
+    let error = || format!("Invalid time '{}', expected one such as 2.5s, 500ms or 120f", input);
+    let pair = BeamParser::parse(Rule::time_value, input.trim())
+        .map_err(|_| error())?
+        .next()
+        .unwrap();
+    if pair.as_str().len() != input.trim().len() {
+        return Err(error());
+    }
+    parse_time_value(pair, fps)
+}
+
+// Frames (`f`) are counted at `fps` frames per second. Fails for times too
+// long to be a `Duration`.
+fn parse_time_value(pair: Pair<Rule>, fps: f64) -> Result<Duration, String> {
+    let text = pair.as_str();
     let mut inner = pair.into_inner();
-    let value: u64 = inner.next().unwrap().as_str().parse().unwrap();
+    let value: f64 = inner.next().unwrap().as_str().parse().unwrap();
+    let value = value.max(0.0);
     let unit = inner.next().unwrap().as_str();
 
-    match unit {
-        "s" => Duration::from_secs(value),
-        "ms" => Duration::from_millis(value),
+    let seconds = match unit {
+        "s" => value,
+        "ms" => value / 1000.0,
+        "f" => value / fps,
         _ => unreachable!(),
-    }
+    };
+    Duration::try_from_secs_f64(seconds).map_err(|_| format!("Time '{}' is too long", text))
 }
 
 fn parse_target_property(pair: Pair<Rule>) -> (String, String) {
@@ -235,10 +454,11 @@ mod tests {
                 }],
                 timeline: None,
                 duration: None,
+                layers: vec![],
             }],
             ..Default::default()
         };
-        let ast = parse_str(input).unwrap();
+        let ast = parse_str(input, None).unwrap();
         assert_eq!(ast, expected);
     }
 
@@ -272,12 +492,14 @@ mod tests {
                         to: Value::Color("#FF0000".to_string()),
                         easing: None,
                     }],
+                    markers: vec![],
                 }),
                 duration: None,
+                layers: vec![],
             }],
             ..Default::default()
         };
-        let ast = parse_str(input).unwrap();
+        let ast = parse_str(input, None).unwrap();
         assert_eq!(ast, expected);
     }
 
@@ -309,7 +531,7 @@ mod tests {
             }),
             ..Default::default()
         };
-        let ast = parse_str(input).unwrap();
+        let ast = parse_str(input, None).unwrap();
         assert_eq!(ast, expected);
     }
 
@@ -352,10 +574,11 @@ mod tests {
                 }],
                 timeline: None,
                 duration: None,
+                layers: vec![],
             }],
             ..Default::default()
         };
-        let ast = parse_str(input).unwrap();
+        let ast = parse_str(input, None).unwrap();
         assert_eq!(ast, expected);
     }
 
@@ -370,7 +593,7 @@ mod tests {
             }
         "#;
 
-        let script = parse_str(input).unwrap();
+        let script = parse_str(input, None).unwrap();
         let scene = &script.scenes[0];
         let timeline = scene.timeline.as_ref().unwrap();
         let animation = &timeline.animations[0];
@@ -388,7 +611,7 @@ mod tests {
                 }
             }
         "#;
-        let script = parse_str(input).unwrap();
+        let script = parse_str(input, None).unwrap();
         let scene = &script.scenes[0];
         let object = &scene.items[0];
         let property = &object.properties[0];
@@ -407,7 +630,7 @@ mod tests {
                 }
             }
         "#;
-        let script = parse_str(input).unwrap();
+        let script = parse_str(input, None).unwrap();
         let scene = &script.scenes[0];
         let object = &scene.items[0];
         assert_eq!(object.r#type, "rectangle");
@@ -423,7 +646,7 @@ mod tests {
                 }
             }
         "#;
-        let script = parse_str(input).unwrap();
+        let script = parse_str(input, None).unwrap();
         let scene = &script.scenes[0];
         let object = &scene.items[0];
         assert_eq!(object.r#type, "ellipse");
@@ -439,7 +662,7 @@ mod tests {
                 }
             }
         "#;
-        let script = parse_str(input).unwrap();
+        let script = parse_str(input, None).unwrap();
         let scene = &script.scenes[0];
         let object = &scene.items[0];
         assert_eq!(object.r#type, "line");
@@ -452,7 +675,7 @@ mod tests {
                 arrow "a" {}
             }
         "#;
-        let script = parse_str(input).unwrap();
+        let script = parse_str(input, None).unwrap();
         assert_eq!(script.scenes[0].items[0].r#type, "arrow");
     }
 
@@ -463,7 +686,7 @@ mod tests {
                 double_arrow "da" {}
             }
         "#;
-        let script = parse_str(input).unwrap();
+        let script = parse_str(input, None).unwrap();
         assert_eq!(script.scenes[0].items[0].r#type, "double_arrow");
     }
 
@@ -474,7 +697,7 @@ mod tests {
                 vector "v" {}
             }
         "#;
-        let script = parse_str(input).unwrap();
+        let script = parse_str(input, None).unwrap();
         assert_eq!(script.scenes[0].items[0].r#type, "vector");
     }
 
@@ -483,7 +706,7 @@ mod tests {
         let input = r#"
             scene "EmptyScene" {}
         "#;
-        let script = parse_str(input).unwrap();
+        let script = parse_str(input, None).unwrap();
         assert_eq!(script.scenes.len(), 1);
         assert_eq!(script.scenes[0].name, "EmptyScene");
         assert!(script.scenes[0].items.is_empty());
@@ -497,7 +720,7 @@ mod tests {
                 circle "c" { radius: 25 }
             }
         "#;
-        let script = parse_str(input).unwrap();
+        let script = parse_str(input, None).unwrap();
         assert_eq!(script.scenes[0].duration, Some(Duration::from_secs(5)));
     }
 
@@ -508,7 +731,7 @@ mod tests {
                 duration: 1500ms
             }
         "#;
-        let script = parse_str(input).unwrap();
+        let script = parse_str(input, None).unwrap();
         assert_eq!(script.scenes[0].duration, Some(Duration::from_millis(1500)));
     }
 
@@ -522,7 +745,7 @@ mod tests {
                 at 1s to 3s, "s".position -> (100, 100), with ease_in;
             }
         "#;
-        let script = parse_str(input).unwrap();
+        let script = parse_str(input, None).unwrap();
         let timeline = script.scenes[0].timeline.as_ref().unwrap();
         let animation = &timeline.animations[0];
         
@@ -541,7 +764,7 @@ mod tests {
                 square "s1" { size: 50 }
             }
         "#;
-        let script = parse_str(input).unwrap();
+        let script = parse_str(input, None).unwrap();
         assert_eq!(script.scenes.len(), 2);
         assert_eq!(script.scenes[0].name, "Scene1");
         assert_eq!(script.scenes[1].name, "Scene2");
@@ -550,7 +773,7 @@ mod tests {
     #[test]
     fn test_parse_invalid_input_fails() {
         let input = "invalid beam syntax";
-        let result = parse_str(input);
+        let result = parse_str(input, None);
         assert!(result.is_err());
     }
 
@@ -564,8 +787,356 @@ mod tests {
                 at 1s, "c".radius -> 20;
             }
         "#;
-        let script = parse_str(input).unwrap();
+        let script = parse_str(input, None).unwrap();
         assert_eq!(script.scenes.len(), 1);
         assert!(script.scenes[0].timeline.is_none());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_parse_scene_layers() {
+        let input = r#"
+            scene "Layered" {
+                layers: "background", "main", "overlay"
+                circle "c" { radius: 10, layer: "overlay", z_index: 2 }
+            }
+        "#;
+        let script = parse_str(input, None).unwrap();
+        let scene = &script.scenes[0];
+        assert_eq!(scene.layers, vec!["background", "main", "overlay"]);
+        assert_eq!(scene.items[0].layer(), "overlay");
+        assert_eq!(scene.items[0].z_index(), 2.0);
+    }
+
+    #[test]
+    fn test_parse_list_value() {
+        let input = r#"
+            scene "Test" {
+                polyline "p" {
+                    points: [(0, 0), (10, 20), (30, 5)],
+                }
+            }
+        "#;
+        let script = parse_str(input, None).unwrap();
+        let object = &script.scenes[0].items[0];
+        assert_eq!(object.r#type, "polyline");
+        assert_eq!(
+            object.properties[0].value,
+            Value::List(vec![
+                Value::Tuple(0.0, 0.0),
+                Value::Tuple(10.0, 20.0),
+                Value::Tuple(30.0, 5.0),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_new_shape_types() {
+        let input = r#"
+            scene "Test" {
+                polygon "hex" { sides: 6 }
+                star "s" { point_count: 5 }
+                arc "a" { start_angle: 0, end_angle: 90 }
+                sector "pie" { start_angle: 0, end_angle: 45 }
+            }
+        "#;
+        let script = parse_str(input, None).unwrap();
+        let types: Vec<_> = script.scenes[0].items.iter().map(|o| o.r#type.as_str()).collect();
+        assert_eq!(types, vec!["polygon", "star", "arc", "sector"]);
+    }
+
+    #[test]
+    fn test_parse_math_expands_to_glyphs() {
+        let input = r#"
+            scene "Test" {
+                math "eq" { tex: "e^{i\pi} + 1 = 0", font_size: 48 }
+            }
+        "#;
+        let script = parse_str(input, None).unwrap();
+        let names: Vec<_> = script.scenes[0].items.iter().map(|o| o.name.as_str()).collect();
+        assert_eq!(names, vec!["eq.1", "eq.2", "eq.3", "eq.4", "eq.5", "eq.6", "eq.7"]);
+        assert!(script.scenes[0].items.iter().all(|o| o.r#type == "path"));
+    }
+
+    #[test]
+    fn test_parse_transform_statement() {
+        let input = r#"
+            scene "Test" {
+                math "eq1" { tex: "a^2 + b^2" }
+                math "eq2" { tex: "c^2" }
+            }
+            timeline for "Test" {
+                transform "eq1" -> "eq2" from 1s to 2s, with ease_in_out;
+            }
+        "#;
+        let script = parse_str(input, None).unwrap();
+        let animations = &script.scenes[0].timeline.as_ref().unwrap().animations;
+        let morph = animations.iter().find(|a| a.property == "d").unwrap();
+        assert_eq!(morph.target_object, "eq1.2");
+        assert_eq!(morph.start, Duration::from_secs(1));
+        assert_eq!(morph.end, Some(Duration::from_secs(2)));
+        assert_eq!(morph.easing, Some("ease_in_out".to_string()));
+    }
+
+    #[test]
+    fn test_parse_code_with_escaped_quotes() {
+        let input = r#"
+            scene "Test" {
+                code "snippet" {
+                    language: "rust",
+                    source: "let s = \"hi\";
+println!(\"{}\", s);"
+                }
+            }
+        "#;
+        let script = parse_str(input, None).unwrap();
+        let object = &script.scenes[0].items[0];
+        assert_eq!(object.r#type, "code");
+        let source = object.properties.iter().find(|p| p.name == "source").unwrap();
+        assert_eq!(
+            source.value,
+            Value::String("let s = \"hi\";\nprintln!(\"{}\", s);".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_axes_points() {
+        let input = r#"
+            scene "Test" {
+                axes "ax" { x_range: (0, 10), y_range: (0, 10), width: 100, height: 100, position: (50, 50) }
+                circle "dot" { position: axes "ax".c2p(2, 4) }
+            }
+            timeline for "Test" {
+                at 0s to 1s, "dot".position -> axes "ax".c2p(10, 10);
+            }
+        "#;
+        let script = parse_str(input, None).unwrap();
+        let scene = &script.scenes[0];
+        let dot = scene.items.iter().find(|o| o.name == "dot").unwrap();
+        assert_eq!(dot.properties[0].value, Value::Tuple(20.0, 60.0));
+        let animation = &scene.timeline.as_ref().unwrap().animations[0];
+        assert_eq!(animation.to, Value::Tuple(100.0, 0.0));
+        assert!(scene.items.iter().any(|o| o.name == "ax.x_axis"));
+    }
+
+    #[test]
+    fn test_parse_chart_with_animated_values() {
+        let input = r#"
+            scene "Test" {
+                line_chart "sales" { values: [1, 2, 3], labels: ["Q1", "Q2", "Q3"] }
+            }
+            timeline for "Test" {
+                at 0s to 1s, "sales".values -> [4, 5, 12];
+            }
+        "#;
+        let script = parse_str(input, None).unwrap();
+        let chart = &script.scenes[0].items[0];
+        assert_eq!(chart.r#type, "line_chart");
+        let y_range = chart.properties.iter().find(|p| p.name == "y_range");
+        assert_eq!(y_range.unwrap().value, Value::Tuple(0.0, 15.0));
+    }
+
+    #[test]
+    fn test_parse_graph_changes_become_part_animations() {
+        let input = r#"
+            scene "Test" {
+                graph "net" { layout: "circular", nodes: ["a", "b"], edges: [["a", "b"]] }
+            }
+            timeline for "Test" {
+                at 0s to 1s, "net".nodes -> ["a", "b", "c"];
+            }
+        "#;
+        let script = parse_str(input, None).unwrap();
+        let scene = &script.scenes[0];
+        assert!(scene.items.iter().any(|o| o.name == "net.nodes.c"));
+        let animations = &scene.timeline.as_ref().unwrap().animations;
+        assert!(animations.iter().all(|a| a.target_object != "net"));
+        assert!(animations.iter().any(|a| a.target_object == "net.nodes.c"));
+    }
+
+    #[test]
+    fn test_multiline_strings_are_dedented() {
+        let script = "
+            scene \"A\" {
+                code \"c\" {
+                    source: \"
+                        fn main() {
+                            println!(\\\"Hi\\\");
+
+                        }
+                    \"
+                }
+            }
+        ";
+        let scene = parse_str(script, None).unwrap().scenes.remove(0);
+        assert_eq!(
+            scene.items[0].properties[0].value,
+            Value::String("fn main() {\n    println!(\"Hi\");\n\n}".to_string())
+        );
+        // Strings that start on the line of their quote are kept as written.
+        assert_eq!(dedent("a\n    b"), "a\n    b");
+        assert_eq!(dedent("\n  a\n    b\n  c"), "a\n  b\nc");
+    }
+
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(parse_time("2.5s", 60.0), Ok(Duration::from_millis(2500)));
+        assert_eq!(parse_time("250ms", 60.0), Ok(Duration::from_millis(250)));
+        assert_eq!(parse_time("12f", 24.0), Ok(Duration::from_millis(500)));
+        assert!(parse_time("2.5", 60.0).is_err());
+        assert!(parse_time("2s later", 60.0).is_err());
+        assert!(parse_time("99999999999999999999s", 60.0).is_err());
+        assert!(parse_time("1f", 1e-300).is_err());
+    }
+
+    #[test]
+    fn test_times_too_long_are_errors() {
+        for script in [
+            r#"scene "A" { duration: 99999999999999999999s }"#,
+            r#"scene "A" {} timeline for "A" { marker "M" at 99999999999999999999s; }"#,
+            r#"scene "A" { circle "c" {} }
+               timeline for "A" { at 0s to 99999999999999999999s, "c".radius -> 9; }"#,
+        ] {
+            let error = parse_str(script, None).unwrap_err().to_string();
+            assert!(error.contains("too long"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn test_parse_markers() {
+        let input = r#"
+            camera { fps: 24 }
+            scene "Intro" { duration: 4s }
+            timeline for "Intro" {
+                marker "Title" at 0s;
+                marker "Reveal" at 36f
+            }
+        "#;
+        let script = parse_str(input, None).unwrap();
+        let timeline = script.scenes[0].timeline.as_ref().unwrap();
+        assert!(timeline.animations.is_empty());
+        assert_eq!(
+            timeline.markers,
+            [
+                Marker {
+                    name: "Title".to_string(),
+                    time: Duration::ZERO,
+                },
+                Marker {
+                    name: "Reveal".to_string(),
+                    time: Duration::from_millis(1500),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_transparent_background() {
+        let script = parse_str("camera { background_color: transparent }", None).unwrap();
+        let camera = script.camera.unwrap();
+        assert_eq!(
+            camera.properties[0].value,
+            Value::Color("transparent".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_table_cells() {
+        let input = r#"
+            camera { background_color: #102030 }
+            scene "Test" {
+                table "t" { rows: [["a", "b"], ["c", 4]] }
+            }
+        "#;
+        let script = parse_str(input, None).unwrap();
+        let cells = &script.scenes[0].items;
+        assert_eq!(cells.len(), 4);
+        assert_eq!(cells[3].name, "t[1][1]");
+        assert_eq!(cells[3].r#type, "table_cell");
+        let fill = cells[3].properties.iter().find(|p| p.name == "fill").unwrap();
+        assert_eq!(fill.value, Value::Color("#102030".to_string()));
+    }
+
+    #[test]
+    fn test_parse_layout_references_and_stacks() {
+        let input = r#"
+            scene "Test" {
+                square "box" { position: (400, 300) }
+                math "label" { tex: "x", position: below("box", 10), align: left_of("box") }
+                vstack "menu" { items: ["box", "label"], spacing: 5, align: "left" }
+            }
+        "#;
+        let script = parse_str(input, None).unwrap();
+        let items = &script.scenes[0].items;
+        let below = Value::Reference(Reference::Layout {
+            relation: "below".to_string(),
+            target: "box".to_string(),
+            gap: Some(10.0),
+        });
+        let align = Value::Reference(Reference::Layout {
+            relation: "left_of".to_string(),
+            target: "box".to_string(),
+            gap: None,
+        });
+        // The formula's parts all carry its references, to be laid out as a whole.
+        let parts: Vec<_> = items.iter().filter(|o| o.name.starts_with("label.")).collect();
+        assert!(!parts.is_empty());
+        for part in parts {
+            assert!(part.properties.iter().any(|p| p.name == "position" && p.value == below));
+            assert!(part.properties.iter().any(|p| p.name == "align" && p.value == align));
+        }
+        assert!(items.iter().any(|o| o.r#type == "vstack" && o.name == "menu"));
+    }
+
+    #[test]
+    fn test_parse_anchors() {
+        let input = r#"
+            scene "Test" {
+                square "box" { position: (400, 300) }
+                polyline "trail" { points: ["box".top_left, "box".center] }
+            }
+            timeline for "Test" {
+                at 0s to 1s, "box".position -> "trail".end;
+            }
+        "#;
+        let script = parse_str(input, None).unwrap();
+        let scene = &script.scenes[0];
+        let anchor = |object: &str, anchor: &str| {
+            Value::Reference(Reference::Anchor {
+                object: object.to_string(),
+                anchor: anchor.to_string(),
+            })
+        };
+        assert_eq!(
+            scene.items[1].properties[0].value,
+            Value::List(vec![anchor("box", "top_left"), anchor("box", "center")])
+        );
+        let animation = &scene.timeline.as_ref().unwrap().animations[0];
+        assert_eq!(animation.to, anchor("trail", "end"));
+    }
+
+    #[test]
+    fn test_parse_frame_times_and_fps() {
+        let input = r#"
+            camera { fps: 24 }
+            scene "Test" {
+                duration: 48f
+                circle "c" { radius: 10 }
+            }
+            timeline for "Test" {
+                at 12f to 36f, "c".radius -> 20;
+            }
+        "#;
+        let script = parse_str(input, None).unwrap();
+        assert_eq!(script.fps(), 24.0);
+        assert_eq!(script.scenes[0].duration, Some(Duration::from_secs(2)));
+        let animation = &script.scenes[0].timeline.as_ref().unwrap().animations[0];
+        assert_eq!(animation.start, Duration::from_millis(500));
+        assert_eq!(animation.end, Some(Duration::from_millis(1500)));
+
+        // A rate given on the command line replaces the camera's.
+        let script = parse_str(input, Some(30.0)).unwrap();
+        assert_eq!(script.fps(), 30.0);
+        assert_eq!(script.scenes[0].duration, Some(Duration::from_millis(1600)));
+        assert_eq!(parse_str("scene \"Empty\" {}", None).unwrap().fps(), 60.0);
+    }
+}
@@ -0,0 +1,701 @@
+//! SVG path data support for `path` objects.
+//!
+//! Path data is parsed into absolute segments where every drawing command has
+//! been converted to a cubic Bézier. Keeping a single segment kind makes it easy
+//! to flatten paths for the CPU renderer and to morph between two paths that
+//! have the same structure.
+
+use std::f64::consts::PI;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Segment {
+    MoveTo((f64, f64)),
+    CubicTo((f64, f64), (f64, f64), (f64, f64)),
+    Close,
+}
+
+pub type PathData = Vec<Segment>;
+
+/// A flattened subpath, ready to be filled or stroked as straight lines.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Subpath {
+    pub points: Vec<(f64, f64)>,
+    pub closed: bool,
+}
+
+pub fn parse_path_data(d: &str) -> Result<PathData, String> {
+    let mut tokens = Tokens {
+        bytes: d.as_bytes(),
+        pos: 0,
+    };
+    let mut segments = Vec::new();
+    let mut current = (0.0, 0.0);
+    let mut subpath_start = (0.0, 0.0);
+    let mut last_cubic_ctrl: Option<(f64, f64)> = None;
+    let mut last_quad_ctrl: Option<(f64, f64)> = None;
+    let mut command: Option<u8> = None;
+
+    loop {
+        let cmd = match tokens.next_command() {
+            Some(c) => c,
+            None if tokens.at_end() => break,
+            // Extra coordinates repeat the previous command; a move turns into a line.
+            None => match command {
+                Some(b'M') => b'L',
+                Some(b'm') => b'l',
+                Some(c) if c != b'Z' && c != b'z' => c,
+                _ => return Err(format!("unexpected character at offset {}", tokens.pos)),
+            },
+        };
+        if segments.is_empty() && cmd != b'M' && cmd != b'm' {
+            return Err("path data must start with a move command".to_string());
+        }
+        command = Some(cmd);
+
+        let relative = cmd.is_ascii_lowercase();
+        let resolve = |p: (f64, f64), current: (f64, f64)| {
+            if relative {
+                (current.0 + p.0, current.1 + p.1)
+            } else {
+                p
+            }
+        };
+
+        let mut cubic_ctrl = None;
+        let mut quad_ctrl = None;
+        match cmd.to_ascii_uppercase() {
+            b'M' => {
+                let p = resolve(tokens.point()?, current);
+                segments.push(Segment::MoveTo(p));
+                current = p;
+                subpath_start = p;
+            }
+            b'L' => {
+                let p = resolve(tokens.point()?, current);
+                segments.push(line_to(current, p));
+                current = p;
+            }
+            b'H' => {
+                let x = tokens.number()?;
+                let p = (if relative { current.0 + x } else { x }, current.1);
+                segments.push(line_to(current, p));
+                current = p;
+            }
+            b'V' => {
+                let y = tokens.number()?;
+                let p = (current.0, if relative { current.1 + y } else { y });
+                segments.push(line_to(current, p));
+                current = p;
+            }
+            b'C' => {
+                let c1 = resolve(tokens.point()?, current);
+                let c2 = resolve(tokens.point()?, current);
+                let p = resolve(tokens.point()?, current);
+                segments.push(Segment::CubicTo(c1, c2, p));
+                cubic_ctrl = Some(c2);
+                current = p;
+            }
+            b'S' => {
+                let c1 = reflect(last_cubic_ctrl, current);
+                let c2 = resolve(tokens.point()?, current);
+                let p = resolve(tokens.point()?, current);
+                segments.push(Segment::CubicTo(c1, c2, p));
+                cubic_ctrl = Some(c2);
+                current = p;
+            }
+            b'Q' => {
+                let q = resolve(tokens.point()?, current);
+                let p = resolve(tokens.point()?, current);
+                segments.push(quad_to(current, q, p));
+                quad_ctrl = Some(q);
+                current = p;
+            }
+            b'T' => {
+                let q = reflect(last_quad_ctrl, current);
+                let p = resolve(tokens.point()?, current);
+                segments.push(quad_to(current, q, p));
+                quad_ctrl = Some(q);
+                current = p;
+            }
+            b'A' => {
+                let rx = tokens.number()?;
+                let ry = tokens.number()?;
+                let x_rotation = tokens.number()?;
+                let large_arc = tokens.flag()?;
+                let sweep = tokens.flag()?;
+                let p = resolve(tokens.point()?, current);
+                segments.extend(arc_to(current, (rx, ry), x_rotation, large_arc, sweep, p));
+                current = p;
+            }
+            b'Z' => {
+                segments.push(Segment::Close);
+                current = subpath_start;
+            }
+            other => return Err(format!("unknown path command '{}'", other as char)),
+        }
+        last_cubic_ctrl = cubic_ctrl;
+        last_quad_ctrl = quad_ctrl;
+    }
+
+    Ok(segments)
+}
+
+/// Serializes segments back to absolute SVG path data.
+pub fn to_path_data(path: &PathData) -> String {
+    path.iter()
+        .map(|segment| match segment {
+            Segment::MoveTo(p) => format!("M {} {}", p.0, p.1),
+            Segment::CubicTo(c1, c2, p) => {
+                format!("C {} {} {} {} {} {}", c1.0, c1.1, c2.0, c2.1, p.0, p.1)
+            }
+            Segment::Close => "Z".to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub fn translate(path: &PathData, offset: (f64, f64)) -> PathData {
+    transform(path, [1.0, 0.0, 0.0, 1.0, offset.0, offset.1])
+}
+
+/// Applies an affine transform given as an SVG `matrix(a b c d e f)`.
+pub fn transform(path: &PathData, matrix: [f64; 6]) -> PathData {
+    let [a, b, c, d, e, f] = matrix;
+    let apply = |p: &(f64, f64)| (a * p.0 + c * p.1 + e, b * p.0 + d * p.1 + f);
+    path.iter()
+        .map(|segment| match segment {
+            Segment::MoveTo(p) => Segment::MoveTo(apply(p)),
+            Segment::CubicTo(c1, c2, p) => Segment::CubicTo(apply(c1), apply(c2), apply(p)),
+            Segment::Close => Segment::Close,
+        })
+        .collect()
+}
+
+/// Approximates every curve with straight lines.
+pub fn flatten(path: &PathData) -> Vec<Subpath> {
+    let mut subpaths = Vec::new();
+    let mut points: Vec<(f64, f64)> = Vec::new();
+    let mut current = (0.0, 0.0);
+
+    for segment in path {
+        match *segment {
+            Segment::MoveTo(p) => {
+                if points.len() > 1 {
+                    subpaths.push(Subpath {
+                        points: std::mem::take(&mut points),
+                        closed: false,
+                    });
+                }
+                points = vec![p];
+                current = p;
+            }
+            Segment::CubicTo(c1, c2, p) => {
+                let length = distance(current, c1) + distance(c1, c2) + distance(c2, p);
+                let steps = (length / 4.0).ceil().clamp(1.0, 64.0) as usize;
+                for i in 1..=steps {
+                    points.push(cubic_point(current, c1, c2, p, i as f64 / steps as f64));
+                }
+                current = p;
+            }
+            Segment::Close => {
+                let start = points.first().copied().unwrap_or(current);
+                if points.len() > 1 {
+                    subpaths.push(Subpath {
+                        points: std::mem::take(&mut points),
+                        closed: true,
+                    });
+                }
+                // Drawing may continue from the start of the closed subpath.
+                points = vec![start];
+                current = start;
+            }
+        }
+    }
+    if points.len() > 1 {
+        subpaths.push(Subpath {
+            points,
+            closed: false,
+        });
+    }
+    subpaths
+}
+
+/// The part of the path that comes first, up to `fraction` of its length.
+///
+/// Used to draw paths on over time. A closed subpath stays open until it is
+/// drawn completely.
+pub fn trim(path: &PathData, fraction: f64) -> PathData {
+    if fraction >= 1.0 {
+        return path.clone();
+    }
+    let mut current = (0.0, 0.0);
+    let mut start = (0.0, 0.0);
+    let lengths: Vec<f64> = path
+        .iter()
+        .map(|segment| match *segment {
+            Segment::MoveTo(p) => {
+                (current, start) = (p, p);
+                0.0
+            }
+            Segment::CubicTo(c1, c2, p) => {
+                let length = cubic_length(current, c1, c2, p, 1.0);
+                current = p;
+                length
+            }
+            Segment::Close => {
+                let length = distance(current, start);
+                current = start;
+                length
+            }
+        })
+        .collect();
+
+    let mut remaining = fraction.max(0.0) * lengths.iter().sum::<f64>();
+    let mut trimmed = Vec::new();
+    for (segment, length) in path.iter().zip(lengths) {
+        if length > remaining {
+            if remaining <= 0.0 {
+                break;
+            }
+            match *segment {
+                Segment::CubicTo(c1, c2, p) => {
+                    // Find where along the curve the remaining length runs out.
+                    let (mut low, mut high) = (0.0, 1.0);
+                    for _ in 0..40 {
+                        let t = (low + high) / 2.0;
+                        if cubic_length(current, c1, c2, p, t) < remaining {
+                            low = t;
+                        } else {
+                            high = t;
+                        }
+                    }
+                    trimmed.push(split_cubic(current, c1, c2, p, low));
+                }
+                Segment::Close => {
+                    trimmed.push(line_to(current, lerp_point(current, start, remaining / length)));
+                }
+                Segment::MoveTo(_) => {}
+            }
+            break;
+        }
+        remaining -= length;
+        match *segment {
+            Segment::MoveTo(p) => (current, start) = (p, p),
+            Segment::CubicTo(_, _, p) => current = p,
+            Segment::Close => current = start,
+        }
+        trimmed.push(*segment);
+    }
+    trimmed
+}
+
+// Approximate length of a cubic from its start up to parameter `t`.
+fn cubic_length(p0: (f64, f64), c1: (f64, f64), c2: (f64, f64), p: (f64, f64), t: f64) -> f64 {
+    const STEPS: usize = 16;
+    let mut length = 0.0;
+    let mut previous = p0;
+    for i in 1..=STEPS {
+        let point = cubic_point(p0, c1, c2, p, t * i as f64 / STEPS as f64);
+        length += distance(previous, point);
+        previous = point;
+    }
+    length
+}
+
+// The part of a cubic from its start up to parameter `t`, by de Casteljau's algorithm.
+fn split_cubic(p0: (f64, f64), c1: (f64, f64), c2: (f64, f64), p: (f64, f64), t: f64) -> Segment {
+    let a = lerp_point(p0, c1, t);
+    let b = lerp_point(c1, c2, t);
+    let c = lerp_point(c2, p, t);
+    let ab = lerp_point(a, b, t);
+    let bc = lerp_point(b, c, t);
+    Segment::CubicTo(a, ab, lerp_point(ab, bc, t))
+}
+
+/// Morphs between two paths with the same segment structure.
+///
+/// Returns `None` when the paths cannot be matched segment for segment.
+pub fn interpolate(from: &str, to: &str, factor: f64) -> Option<String> {
+    let from = parse_path_data(from).ok()?;
+    let to = parse_path_data(to).ok()?;
+    if from.len() != to.len() {
+        return None;
+    }
+
+    let mix = |a: (f64, f64), b: (f64, f64)| (a.0 + (b.0 - a.0) * factor, a.1 + (b.1 - a.1) * factor);
+    let morphed = from
+        .iter()
+        .zip(&to)
+        .map(|pair| match pair {
+            (Segment::MoveTo(a), Segment::MoveTo(b)) => Some(Segment::MoveTo(mix(*a, *b))),
+            (Segment::CubicTo(a1, a2, a), Segment::CubicTo(b1, b2, b)) => {
+                Some(Segment::CubicTo(mix(*a1, *b1), mix(*a2, *b2), mix(*a, *b)))
+            }
+            (Segment::Close, Segment::Close) => Some(Segment::Close),
+            _ => None,
+        })
+        .collect::<Option<PathData>>()?;
+    Some(to_path_data(&morphed))
+}
+
+pub fn line_to(from: (f64, f64), to: (f64, f64)) -> Segment {
+    Segment::CubicTo(
+        lerp_point(from, to, 1.0 / 3.0),
+        lerp_point(from, to, 2.0 / 3.0),
+        to,
+    )
+}
+
+/// A closed path through the corners of a polygon.
+pub fn polygon(points: &[(f64, f64)]) -> PathData {
+    let mut data = Vec::with_capacity(points.len() + 2);
+    if let Some(&first) = points.first() {
+        data.push(Segment::MoveTo(first));
+        for pair in points.windows(2) {
+            data.push(line_to(pair[0], pair[1]));
+        }
+        data.push(line_to(*points.last().unwrap(), first));
+        data.push(Segment::Close);
+    }
+    data
+}
+
+pub fn quad_to(from: (f64, f64), ctrl: (f64, f64), to: (f64, f64)) -> Segment {
+    Segment::CubicTo(
+        lerp_point(from, ctrl, 2.0 / 3.0),
+        lerp_point(to, ctrl, 2.0 / 3.0),
+        to,
+    )
+}
+
+fn reflect(ctrl: Option<(f64, f64)>, around: (f64, f64)) -> (f64, f64) {
+    match ctrl {
+        Some(c) => (2.0 * around.0 - c.0, 2.0 * around.1 - c.1),
+        None => around,
+    }
+}
+
+// Converts an SVG elliptical arc to cubic segments, following the
+// endpoint-to-center conversion in the SVG implementation notes.
+fn arc_to(
+    from: (f64, f64),
+    radii: (f64, f64),
+    x_rotation: f64,
+    large_arc: bool,
+    sweep: bool,
+    to: (f64, f64),
+) -> Vec<Segment> {
+    if from == to {
+        return Vec::new();
+    }
+    let (mut rx, mut ry) = (radii.0.abs(), radii.1.abs());
+    if rx == 0.0 || ry == 0.0 {
+        return vec![line_to(from, to)];
+    }
+
+    let (sin_phi, cos_phi) = x_rotation.to_radians().sin_cos();
+    let dx2 = (from.0 - to.0) / 2.0;
+    let dy2 = (from.1 - to.1) / 2.0;
+    let x1p = cos_phi * dx2 + sin_phi * dy2;
+    let y1p = -sin_phi * dx2 + cos_phi * dy2;
+
+    // Scale up radii that are too small to span the endpoints.
+    let lambda = (x1p * x1p) / (rx * rx) + (y1p * y1p) / (ry * ry);
+    if lambda > 1.0 {
+        rx *= lambda.sqrt();
+        ry *= lambda.sqrt();
+    }
+
+    let numerator = rx * rx * ry * ry - rx * rx * y1p * y1p - ry * ry * x1p * x1p;
+    let denominator = rx * rx * y1p * y1p + ry * ry * x1p * x1p;
+    let mut coef = (numerator / denominator).max(0.0).sqrt();
+    if large_arc == sweep {
+        coef = -coef;
+    }
+    let cxp = coef * rx * y1p / ry;
+    let cyp = -coef * ry * x1p / rx;
+    let cx = cos_phi * cxp - sin_phi * cyp + (from.0 + to.0) / 2.0;
+    let cy = sin_phi * cxp + cos_phi * cyp + (from.1 + to.1) / 2.0;
+
+    let angle = |u: (f64, f64), v: (f64, f64)| {
+        let dot = u.0 * v.0 + u.1 * v.1;
+        let len = (u.0 * u.0 + u.1 * u.1).sqrt() * (v.0 * v.0 + v.1 * v.1).sqrt();
+        let a = (dot / len).clamp(-1.0, 1.0).acos();
+        if u.0 * v.1 - u.1 * v.0 < 0.0 {
+            -a
+        } else {
+            a
+        }
+    };
+    let start_vector = ((x1p - cxp) / rx, (y1p - cyp) / ry);
+    let end_vector = ((-x1p - cxp) / rx, (-y1p - cyp) / ry);
+    let theta1 = angle((1.0, 0.0), start_vector);
+    let mut delta_theta = angle(start_vector, end_vector);
+    if !sweep && delta_theta > 0.0 {
+        delta_theta -= 2.0 * PI;
+    } else if sweep && delta_theta < 0.0 {
+        delta_theta += 2.0 * PI;
+    }
+
+    let point_at = |theta: f64| {
+        let (sin, cos) = theta.sin_cos();
+        (
+            cx + rx * cos * cos_phi - ry * sin * sin_phi,
+            cy + rx * cos * sin_phi + ry * sin * cos_phi,
+        )
+    };
+    let derivative_at = |theta: f64| {
+        let (sin, cos) = theta.sin_cos();
+        (
+            -rx * sin * cos_phi - ry * cos * sin_phi,
+            -rx * sin * sin_phi + ry * cos * cos_phi,
+        )
+    };
+
+    // Each cubic covers at most a quarter turn.
+    let count = (delta_theta.abs() / (PI / 2.0)).ceil().max(1.0) as usize;
+    let step = delta_theta / count as f64;
+    let handle = 4.0 / 3.0 * (step / 4.0).tan();
+    (0..count)
+        .map(|i| {
+            let a1 = theta1 + step * i as f64;
+            let a2 = a1 + step;
+            let p1 = point_at(a1);
+            let p2 = if i + 1 == count { to } else { point_at(a2) };
+            let d1 = derivative_at(a1);
+            let d2 = derivative_at(a2);
+            Segment::CubicTo(
+                (p1.0 + handle * d1.0, p1.1 + handle * d1.1),
+                (p2.0 - handle * d2.0, p2.1 - handle * d2.1),
+                p2,
+            )
+        })
+        .collect()
+}
+
+fn cubic_point(p0: (f64, f64), c1: (f64, f64), c2: (f64, f64), p: (f64, f64), t: f64) -> (f64, f64) {
+    let mt = 1.0 - t;
+    let a = mt * mt * mt;
+    let b = 3.0 * mt * mt * t;
+    let c = 3.0 * mt * t * t;
+    let d = t * t * t;
+    (
+        a * p0.0 + b * c1.0 + c * c2.0 + d * p.0,
+        a * p0.1 + b * c1.1 + c * c2.1 + d * p.1,
+    )
+}
+
+fn lerp_point(a: (f64, f64), b: (f64, f64), t: f64) -> (f64, f64) {
+    (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t)
+}
+
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    ((b.0 - a.0).powi(2) + (b.1 - a.1).powi(2)).sqrt()
+}
+
+struct Tokens<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Tokens<'_> {
+    fn skip_separators(&mut self) {
+        while self.pos < self.bytes.len()
+            && (self.bytes[self.pos].is_ascii_whitespace() || self.bytes[self.pos] == b',')
+        {
+            self.pos += 1;
+        }
+    }
+
+    fn at_end(&mut self) -> bool {
+        self.skip_separators();
+        self.pos >= self.bytes.len()
+    }
+
+    fn next_command(&mut self) -> Option<u8> {
+        self.skip_separators();
+        match self.bytes.get(self.pos) {
+            // `e` and `E` only ever appear inside numbers.
+            Some(&c) if c.is_ascii_alphabetic() && c != b'e' && c != b'E' => {
+                self.pos += 1;
+                Some(c)
+            }
+            _ => None,
+        }
+    }
+
+    fn number(&mut self) -> Result<f64, String> {
+        self.skip_separators();
+        let start = self.pos;
+        if matches!(self.bytes.get(self.pos), Some(b'-' | b'+')) {
+            self.pos += 1;
+        }
+        self.skip_digits();
+        if self.bytes.get(self.pos) == Some(&b'.') {
+            self.pos += 1;
+            self.skip_digits();
+        }
+        if matches!(self.bytes.get(self.pos), Some(b'e' | b'E')) {
+            self.pos += 1;
+            if matches!(self.bytes.get(self.pos), Some(b'-' | b'+')) {
+                self.pos += 1;
+            }
+            self.skip_digits();
+        }
+        std::str::from_utf8(&self.bytes[start..self.pos])
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| format!("expected a number at offset {}", start))
+    }
+
+    fn point(&mut self) -> Result<(f64, f64), String> {
+        Ok((self.number()?, self.number()?))
+    }
+
+    // Arc flags may be written without separators, e.g. `a1 1 0 01 5 5`.
+    fn flag(&mut self) -> Result<bool, String> {
+        self.skip_separators();
+        let flag = match self.bytes.get(self.pos) {
+            Some(b'0') => false,
+            Some(b'1') => true,
+            _ => return Err(format!("expected an arc flag at offset {}", self.pos)),
+        };
+        self.pos += 1;
+        Ok(flag)
+    }
+
+    fn skip_digits(&mut self) {
+        while self.pos < self.bytes.len() && self.bytes[self.pos].is_ascii_digit() {
+            self.pos += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn end_point(segment: &Segment) -> (f64, f64) {
+        match segment {
+            Segment::MoveTo(p) | Segment::CubicTo(_, _, p) => *p,
+            Segment::Close => panic!("close has no end point"),
+        }
+    }
+
+    fn assert_close(a: (f64, f64), b: (f64, f64)) {
+        assert!((a.0 - b.0).abs() < 1e-9 && (a.1 - b.1).abs() < 1e-9, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn test_parse_absolute_commands() {
+        let path = parse_path_data("M10 10 C 20 20, 40 20, 50 10 Z").unwrap();
+        assert_eq!(
+            path,
+            vec![
+                Segment::MoveTo((10.0, 10.0)),
+                Segment::CubicTo((20.0, 20.0), (40.0, 20.0), (50.0, 10.0)),
+                Segment::Close,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_relative_and_shorthand_commands() {
+        let path = parse_path_data("m10 10 h 20 v-5 l-20 0z").unwrap();
+        assert_eq!(path.len(), 5);
+        assert_close(end_point(&path[1]), (30.0, 10.0));
+        assert_close(end_point(&path[2]), (30.0, 5.0));
+        assert_close(end_point(&path[3]), (10.0, 5.0));
+    }
+
+    #[test]
+    fn test_implicit_line_after_move() {
+        let path = parse_path_data("M0 0 10 0 10 10").unwrap();
+        assert_eq!(path.len(), 3);
+        assert_close(end_point(&path[2]), (10.0, 10.0));
+    }
+
+    #[test]
+    fn test_parse_compact_numbers() {
+        let path = parse_path_data("M.5.5L-1e1-2").unwrap();
+        assert_close(end_point(&path[0]), (0.5, 0.5));
+        assert_close(end_point(&path[1]), (-10.0, -2.0));
+    }
+
+    #[test]
+    fn test_smooth_cubic_reflects_control_point() {
+        let path = parse_path_data("M0 0 C0 10 10 10 10 0 S20 -10 20 0").unwrap();
+        match path[2] {
+            Segment::CubicTo(c1, _, _) => assert_close(c1, (10.0, -10.0)),
+            _ => panic!("expected a cubic"),
+        }
+    }
+
+    #[test]
+    fn test_arc_ends_at_target() {
+        let path = parse_path_data("M0 0 A10 10 0 0 1 20 0").unwrap();
+        assert_eq!(path.len(), 3);
+        assert_close(end_point(path.last().unwrap()), (20.0, 0.0));
+        // A clockwise half circle from (0, 0) to (20, 0) passes through (10, -10).
+        let flattened = flatten(&path);
+        let top = flattened[0]
+            .points
+            .iter()
+            .fold(f64::MAX, |min, p| min.min(p.1));
+        assert!((top + 10.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_parse_arc_with_compact_flags() {
+        let path = parse_path_data("M0 0a10 10 0 015 5").unwrap();
+        assert_close(end_point(path.last().unwrap()), (5.0, 5.0));
+    }
+
+    #[test]
+    fn test_invalid_path_data() {
+        assert!(parse_path_data("L 10 10").is_err());
+        assert!(parse_path_data("M 10").is_err());
+        assert!(parse_path_data("M 0 0 X 1 1").is_err());
+    }
+
+    #[test]
+    fn test_flatten_marks_closed_subpaths() {
+        let path = parse_path_data("M0 0 L10 0 L10 10 Z M20 20 L30 30").unwrap();
+        let subpaths = flatten(&path);
+        assert_eq!(subpaths.len(), 2);
+        assert!(subpaths[0].closed);
+        assert!(!subpaths[1].closed);
+    }
+
+    #[test]
+    fn test_interpolate_compatible_paths() {
+        let morphed = interpolate("M0 0 L10 0", "M10 10 L30 10", 0.5).unwrap();
+        let path = parse_path_data(&morphed).unwrap();
+        assert_close(end_point(&path[0]), (5.0, 5.0));
+        assert_close(end_point(&path[1]), (20.0, 5.0));
+    }
+
+    #[test]
+    fn test_interpolate_incompatible_paths() {
+        assert_eq!(interpolate("M0 0 L10 0", "M0 0 L10 0 L10 10", 0.5), None);
+    }
+
+    #[test]
+    fn test_trim_by_length() {
+        let path = parse_path_data("M0 0 L10 0 L10 30").unwrap();
+        let trimmed = trim(&path, 0.5);
+        assert_eq!(trimmed.len(), 3);
+        assert_close(end_point(&trimmed[1]), (10.0, 0.0));
+        assert_close(end_point(&trimmed[2]), (10.0, 10.0));
+        assert_eq!(trim(&path, 1.0), path);
+        assert!(trim(&path, 0.0).len() <= 1);
+    }
+
+    #[test]
+    fn test_trim_leaves_partial_subpath_open() {
+        let path = parse_path_data("M0 0 L10 0 L10 10 L0 10 Z").unwrap();
+        let trimmed = trim(&path, 0.9);
+        assert!(!trimmed.contains(&Segment::Close));
+        assert_close(end_point(trimmed.last().unwrap()), (0.0, 4.0));
+    }
+}
@@ -0,0 +1,116 @@
+//! Loading a script again when it or a file it uses changes, for watch mode
+//! and the preview server.
+
+use crate::ast::BeamScript;
+use crate::{assets, parser};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// A script and the files it uses, with when each was last modified.
+#[derive(Debug, Default)]
+pub struct ScriptFiles {
+    /// By path, with its directory made absolute.
+    files: BTreeMap<PathBuf, Option<SystemTime>>,
+}
+
+impl ScriptFiles {
+    /// Parses the script at `path` again, reading the files it uses afresh,
+    /// and reports how that went. After an error the files of the last
+    /// script that parsed are kept, to notice when they change too.
+    pub fn load(&mut self, path: &Path, fps: Option<f64>) -> Result<BeamScript, String> {
+        let modified_before = modified(path);
+        assets::clear_cache();
+        let parsed = fs::read_to_string(path)
+            .map_err(|e| format!("Error reading {}: {}", path.display(), e))
+            .and_then(|text| {
+                parser::parse_str(&text, fps).map_err(|e| format!("Error parsing script: {}", e))
+            });
+        match &parsed {
+            Ok(script) => {
+                self.files = assets::dependencies(script)
+                    .iter()
+                    .map(|file| (normalize(file), modified(file)))
+                    .collect();
+                eprintln!("✅ Parsed successfully!");
+            }
+            Err(e) => eprintln!("{}", e),
+        }
+        self.files.insert(normalize(path), modified_before);
+        parsed
+    }
+
+    /// Whether `path`, with its directory made absolute, is one of the files
+    /// and has changed since it was loaded.
+    pub fn has_changed(&self, path: &Path) -> bool {
+        self.files
+            .get(path)
+            .is_some_and(|seen| modified(path) != *seen)
+    }
+
+    /// Whether any of the files has changed since it was loaded.
+    pub fn any_changed(&self) -> bool {
+        self.files.keys().any(|file| self.has_changed(file))
+    }
+
+    /// The directories the files are in.
+    pub fn dirs(&self) -> BTreeSet<PathBuf> {
+        self.files
+            .keys()
+            .filter_map(|file| file.parent().map(Path::to_path_buf))
+            .collect()
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// `path` with its directory made absolute, to compare with the paths of
+/// changes, even for files that no longer exist.
+pub fn normalize(path: &Path) -> PathBuf {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    match (dir.canonicalize(), path.file_name()) {
+        (Ok(dir), Some(name)) => dir.join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_load_notes_the_files_used() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = dir.path().join("demo.beam");
+        let image = dir.path().join("photo.png");
+        let text = format!(
+            r#"scene "A" {{ image "i" {{ src: "{}" }} }}"#,
+            image.display()
+        );
+        fs::write(&script, &text).unwrap();
+        fs::write(&image, "old").unwrap();
+        let mut files = ScriptFiles::default();
+        assert!(files.load(&script, None).is_ok());
+        assert_eq!(
+            files.dirs(),
+            BTreeSet::from([normalize(&script).parent().unwrap().to_path_buf()])
+        );
+        assert!(!files.any_changed());
+
+        // A broken script still notices changes to the files it used before.
+        std::thread::sleep(Duration::from_millis(20));
+        fs::write(&script, "scene {").unwrap();
+        assert!(files.load(&script, None).is_err());
+        assert!(!files.has_changed(&normalize(&script)));
+        fs::remove_file(&image).unwrap();
+        assert!(files.has_changed(&normalize(&image)));
+        assert!(files.any_changed());
+    }
+}
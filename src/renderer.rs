@@ -1,4 +1,6 @@
-use crate::ast::{Camera, Object, Scene, Value};
+use crate::ast::{Camera, Object, Property, Scene, Value};
+use crate::shapes::{self, Shape};
+use crate::{assets, chart, code, color, font, number_line, path, table};
 use image::{RgbaImage, Rgba};
 use imageproc::drawing::{
     draw_filled_circle_mut, draw_filled_ellipse_mut, draw_filled_rect_mut,
@@ -14,18 +16,28 @@ const DEFAULT_WIDTH: u32 = 1920;
 const DEFAULT_HEIGHT: u32 = 1080;
 const DEFAULT_BG_COLOR: Rgba<u8> = Rgba([25, 25, 25, 255]);
 
-pub fn render_scene(scene: &Scene, camera: &Option<Camera>) -> RgbaImage {
+/// Size in pixels of the frames rendered through `camera`.
+pub fn frame_size(camera: &Option<Camera>) -> (u32, u32) {
     let width = get_camera_property_number(camera, "width")
         .map(|w| w as u32)
         .unwrap_or(DEFAULT_WIDTH);
     let height = get_camera_property_number(camera, "height")
         .map(|h| h as u32)
         .unwrap_or(DEFAULT_HEIGHT);
-    let bg_color = get_camera_property_color(camera, "background_color").unwrap_or(DEFAULT_BG_COLOR);
+    (width, height)
+}
+
+/// The color frames start from, which is see-through for a camera with
+/// `background_color: transparent`.
+pub fn background_color(camera: &Option<Camera>) -> Rgba<u8> {
+    get_camera_property_color(camera, "background_color").unwrap_or(DEFAULT_BG_COLOR)
+}
 
-    let mut image = RgbaImage::from_pixel(width, height, bg_color);
+pub fn render_scene(scene: &Scene, camera: &Option<Camera>) -> RgbaImage {
+    let (width, height) = frame_size(camera);
+    let mut image = RgbaImage::from_pixel(width, height, background_color(camera));
 
-    for item in &scene.items {
+    for item in scene.draw_order() {
         draw_object(&mut image, item);
     }
 
@@ -53,6 +65,22 @@ fn draw_object(image: &mut RgbaImage, object: &Object) {
         "line" => draw_line(&mut object_canvas, &properties),
         "arrow" | "vector" => draw_arrow(&mut object_canvas, &properties, false),
         "double_arrow" => draw_arrow(&mut object_canvas, &properties, true),
+        "polygon" => draw_regular_polygon(&mut object_canvas, &properties),
+        "star" => draw_star(&mut object_canvas, &properties),
+        "arc" => draw_arc(&mut object_canvas, &properties),
+        "sector" => draw_sector(&mut object_canvas, &properties),
+        "polyline" => draw_polyline(&mut object_canvas, &properties),
+        "path" => draw_path(&mut object_canvas, &properties),
+        "image" => draw_image(&mut object_canvas, &properties),
+        "code" => fill_shapes(&mut object_canvas, &code::shapes(&object.properties)),
+        "bar_chart" | "line_chart" | "pie_chart" => fill_shapes(
+            &mut object_canvas,
+            &chart::shapes(&object.r#type, &object.properties),
+        ),
+        "table_cell" => fill_shapes(&mut object_canvas, &table::cell_shapes(&object.properties)),
+        "number_line" => draw_number_line(&mut object_canvas, &properties, &object.properties),
+        // Stacks only arrange other objects.
+        "hstack" | "vstack" => {}
         _ => eprintln!("Warning: Unknown object type '{}'", object.r#type),
     }
 
@@ -87,12 +115,15 @@ fn draw_object(image: &mut RgbaImage, object: &Object) {
         object_canvas = rotated;
     }
 
+    let opacity = get_property_number(&properties, "opacity").unwrap_or(1.0).clamp(0.0, 1.0);
+
     // Overlay the (possibly rotated) object canvas onto the main image
     for y in 0..object_canvas.height() {
         for x in 0..object_canvas.width() {
-            let pixel = object_canvas.get_pixel(x, y);
+            let mut pixel = *object_canvas.get_pixel(x, y);
+            pixel[3] = (pixel[3] as f64 * opacity).round() as u8;
             if pixel[3] > 0 { // if not transparent
-                image.put_pixel(x, y, Rgba([pixel[0], pixel[1], pixel[2], pixel[3]]));
+                blend_pixel(image.get_pixel_mut(x, y), pixel);
             }
         }
     }
@@ -161,6 +192,19 @@ fn draw_square(
     let half_size = size / 2.0;
     let top_left_x = (position.0 - half_size) as i32;
     let top_left_y = (position.1 - half_size) as i32;
+
+    let corner_radius = get_property_number(properties, "corner_radius").unwrap_or(0.0);
+    if corner_radius > 0.0 {
+        let outline = shapes::rounded_rect(
+            (position.0 - half_size, position.1 - half_size),
+            size,
+            size,
+            corner_radius,
+        );
+        draw_outline(image, properties, &outline);
+        return;
+    }
+
     let rect = Rect::at(top_left_x, top_left_y).of_size(size as u32, size as u32);
 
     // Handle fill
@@ -186,6 +230,19 @@ fn draw_rectangle(
     let half_height = height / 2.0;
     let top_left_x = (position.0 - half_width) as i32;
     let top_left_y = (position.1 - half_height) as i32;
+
+    let corner_radius = get_property_number(properties, "corner_radius").unwrap_or(0.0);
+    if corner_radius > 0.0 {
+        let outline = shapes::rounded_rect(
+            (position.0 - half_width, position.1 - half_height),
+            width,
+            height,
+            corner_radius,
+        );
+        draw_outline(image, properties, &outline);
+        return;
+    }
+
     let rect = Rect::at(top_left_x, top_left_y).of_size(width as u32, height as u32);
 
     // Handle fill
@@ -277,6 +334,362 @@ fn draw_arrow(
     }
 }
 
+fn draw_regular_polygon(
+    image: &mut RgbaImage,
+    properties: &HashMap<&str, &Value>,
+) {
+    let position = get_property_tuple(properties, "position").unwrap_or((0.0, 0.0));
+    let radius = get_property_number(properties, "radius").unwrap_or(50.0);
+    let sides = get_property_number(properties, "sides").unwrap_or(6.0);
+
+    let outline = shapes::regular_polygon(position, radius, sides as usize);
+    draw_outline(image, properties, &outline);
+}
+
+fn draw_star(
+    image: &mut RgbaImage,
+    properties: &HashMap<&str, &Value>,
+) {
+    let position = get_property_tuple(properties, "position").unwrap_or((0.0, 0.0));
+    let outer_radius = get_property_number(properties, "outer_radius").unwrap_or(50.0);
+    let inner_radius = get_property_number(properties, "inner_radius").unwrap_or(outer_radius / 2.0);
+    let point_count = get_property_number(properties, "point_count").unwrap_or(5.0);
+
+    let outline = shapes::star(position, outer_radius, inner_radius, point_count as usize);
+    draw_outline(image, properties, &outline);
+}
+
+fn draw_arc(
+    image: &mut RgbaImage,
+    properties: &HashMap<&str, &Value>,
+) {
+    let position = get_property_tuple(properties, "position").unwrap_or((0.0, 0.0));
+    let radius = get_property_number(properties, "radius").unwrap_or(50.0);
+    let start_angle = get_property_number(properties, "start_angle").unwrap_or(0.0);
+    let end_angle = get_property_number(properties, "end_angle").unwrap_or(90.0);
+    let color_hex =
+        get_property_color_str(properties, "border_color").unwrap_or("#FFFFFF".to_string());
+
+    let points = shapes::arc(position, radius, start_angle, end_angle);
+    draw_open_path(image, &points, hex_to_rgba(&color_hex));
+}
+
+fn draw_sector(
+    image: &mut RgbaImage,
+    properties: &HashMap<&str, &Value>,
+) {
+    let position = get_property_tuple(properties, "position").unwrap_or((0.0, 0.0));
+    let radius = get_property_number(properties, "radius").unwrap_or(50.0);
+    let inner_radius = get_property_number(properties, "inner_radius").unwrap_or(0.0);
+    let start_angle = get_property_number(properties, "start_angle").unwrap_or(0.0);
+    let end_angle = get_property_number(properties, "end_angle").unwrap_or(90.0);
+
+    let outline = shapes::sector(position, radius, inner_radius, start_angle, end_angle);
+    draw_outline(image, properties, &outline);
+}
+
+fn draw_polyline(
+    image: &mut RgbaImage,
+    properties: &HashMap<&str, &Value>,
+) {
+    let points = get_property_points(properties, "points");
+    let color_hex =
+        get_property_color_str(properties, "border_color").unwrap_or("#FFFFFF".to_string());
+
+    draw_open_path(image, &points, hex_to_rgba(&color_hex));
+}
+
+fn draw_image(
+    image: &mut RgbaImage,
+    properties: &HashMap<&str, &Value>,
+) {
+    let Some(Value::String(src)) = properties.get("src") else {
+        return;
+    };
+    let Some(source) = assets::load_image(src) else {
+        return;
+    };
+    let position = get_property_tuple(properties, "position").unwrap_or((0.0, 0.0));
+    let scale = get_property_number(properties, "scale").unwrap_or(1.0);
+    let fit = match properties.get("fit") {
+        Some(Value::String(fit)) => fit.as_str(),
+        _ => "contain",
+    };
+    let filter = match properties.get("filter") {
+        Some(Value::String(filter)) => filter.as_str(),
+        _ => "bilinear",
+    };
+
+    let frame = assets::frame_size(
+        source.dimensions(),
+        get_property_number(properties, "width"),
+        get_property_number(properties, "height"),
+    );
+    let placement = assets::fit_image(source.dimensions(), (frame.0 * scale, frame.1 * scale), fit);
+    let width = placement.size.0.round() as u32;
+    let height = placement.size.1.round() as u32;
+    if width == 0 || height == 0 {
+        return;
+    }
+    let Some(resized) = assets::scaled_image(src, placement.crop, (width, height), filter) else {
+        return;
+    };
+
+    // 'position' is the center of the image, as for other shapes.
+    let left = (position.0 - width as f64 / 2.0).round() as i64;
+    let top = (position.1 - height as f64 / 2.0).round() as i64;
+    for (x, y, pixel) in resized.enumerate_pixels() {
+        let (tx, ty) = (left + x as i64, top + y as i64);
+        if tx < 0 || ty < 0 || tx >= image.width() as i64 || ty >= image.height() as i64 {
+            continue;
+        }
+        blend_pixel(image.get_pixel_mut(tx as u32, ty as u32), *pixel);
+    }
+}
+
+// Composites `src` over `dst` using straight (non-premultiplied) alpha.
+fn blend_pixel(dst: &mut Rgba<u8>, src: Rgba<u8>) {
+    let src_alpha = src[3] as f64 / 255.0;
+    if src_alpha >= 1.0 {
+        *dst = src;
+        return;
+    }
+    let dst_alpha = dst[3] as f64 / 255.0;
+    let out_alpha = src_alpha + dst_alpha * (1.0 - src_alpha);
+    if out_alpha <= 0.0 {
+        return;
+    }
+    for c in 0..3 {
+        let blended = (src[c] as f64 * src_alpha + dst[c] as f64 * dst_alpha * (1.0 - src_alpha)) / out_alpha;
+        dst[c] = blended.round() as u8;
+    }
+    dst[3] = (out_alpha * 255.0).round() as u8;
+}
+
+fn draw_path(
+    image: &mut RgbaImage,
+    properties: &HashMap<&str, &Value>,
+) {
+    let Some(Value::String(d)) = properties.get("d") else {
+        return;
+    };
+    let data = match path::parse_path_data(d) {
+        Ok(data) => data,
+        Err(e) => {
+            eprintln!("Warning: Invalid path data '{}': {}", d, e);
+            return;
+        }
+    };
+    let data = path::trim(&data, get_property_number(properties, "draw").unwrap_or(1.0));
+    let position = get_property_tuple(properties, "position").unwrap_or((0.0, 0.0));
+    let subpaths = path::flatten(&path::translate(&data, position));
+
+    // Handle fill
+    if let Some(fill_hex) = get_property_color_str(properties, "fill") {
+        let even_odd = matches!(properties.get("fill_rule"), Some(Value::String(rule)) if rule == "evenodd");
+        let polygons: Vec<Vec<(f64, f64)>> = subpaths.iter().map(|s| s.points.clone()).collect();
+        fill_polygons(image, &polygons, even_odd, hex_to_rgba(&fill_hex));
+    }
+
+    // Handle border
+    if let Some(border_hex) = get_property_color_str(properties, "border_color") {
+        let stroke_width = get_property_number(properties, "stroke_width").unwrap_or(1.0);
+        let color = hex_to_rgba(&border_hex);
+        for subpath in &subpaths {
+            let mut points = subpath.points.clone();
+            if subpath.closed {
+                points.push(points[0]);
+            }
+            if stroke_width > 1.0 {
+                draw_thick_path(image, &points, stroke_width, color);
+            } else {
+                draw_open_path(image, &points, color);
+            }
+        }
+    }
+}
+
+fn fill_shapes(image: &mut RgbaImage, shapes: &[Shape]) {
+    for shape in shapes {
+        let polygons: Vec<Vec<(f64, f64)>> = path::flatten(&shape.data)
+            .into_iter()
+            .map(|s| s.points)
+            .collect();
+        fill_polygons(image, &polygons, false, Rgba(shape.color));
+    }
+}
+
+/// Draws one line of `text` hanging from `top`, such as a caption outside
+/// of any scene.
+pub fn draw_label(image: &mut RgbaImage, text: &str, top: (f64, f64), font_size: f64, color: Rgba<u8>) {
+    let outlines: Vec<Vec<(f64, f64)>> = font::label(text, top, 0.0, font_size)
+        .iter()
+        .flat_map(path::flatten)
+        .map(|s| s.points)
+        .collect();
+    fill_polygons(image, &outlines, false, color);
+}
+
+fn draw_number_line(
+    image: &mut RgbaImage,
+    properties: &HashMap<&str, &Value>,
+    object_properties: &[Property],
+) {
+    let line = number_line::layout(object_properties);
+    let color = Rgba(line.color);
+    draw_thick_path(image, &[line.start, line.end], line.stroke_width, color);
+    for (from, to) in &line.ticks {
+        draw_thick_path(image, &[*from, *to], line.stroke_width, color);
+    }
+    let labels: Vec<Vec<(f64, f64)>> = line
+        .labels
+        .iter()
+        .flat_map(path::flatten)
+        .map(|s| s.points)
+        .collect();
+    fill_polygons(image, &labels, false, color);
+
+    if line.tips.0 {
+        draw_arrowhead(image, line.end, line.start, properties, color);
+    }
+    if line.tips.1 {
+        draw_arrowhead(image, line.start, line.end, properties, color);
+    }
+    if let Some((tail, tip)) = line.pointer {
+        let color = Rgba(line.pointer_color);
+        draw_thick_path(image, &[tail, tip], line.stroke_width, color);
+        draw_arrowhead(image, tail, tip, properties, color);
+    }
+}
+
+// Scanline fill of one or more closed polygons, sampling at pixel centers.
+fn fill_polygons(image: &mut RgbaImage, polygons: &[Vec<(f64, f64)>], even_odd: bool, color: Rgba<u8>) {
+    let edges: Vec<((f64, f64), (f64, f64))> = polygons
+        .iter()
+        .flat_map(|points| {
+            points
+                .iter()
+                .zip(points.iter().cycle().skip(1))
+                .map(|(a, b)| (*a, *b))
+        })
+        .collect();
+    if edges.is_empty() {
+        return;
+    }
+
+    let min_y = edges.iter().map(|(a, b)| a.1.min(b.1)).fold(f64::MAX, f64::min);
+    let max_y = edges.iter().map(|(a, b)| a.1.max(b.1)).fold(f64::MIN, f64::max);
+    let first_row = min_y.floor().max(0.0) as u32;
+    let last_row = max_y.ceil().min(image.height() as f64).max(0.0) as u32;
+
+    let mut crossings: Vec<(f64, i32)> = Vec::new();
+    for y in first_row..last_row {
+        let sample_y = y as f64 + 0.5;
+        crossings.clear();
+        for (a, b) in &edges {
+            if (a.1 <= sample_y && b.1 > sample_y) || (b.1 <= sample_y && a.1 > sample_y) {
+                let t = (sample_y - a.1) / (b.1 - a.1);
+                let direction = if b.1 > a.1 { 1 } else { -1 };
+                crossings.push((a.0 + t * (b.0 - a.0), direction));
+            }
+        }
+        crossings.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+
+        let mut winding = 0;
+        for pair in crossings.windows(2) {
+            winding += pair[0].1;
+            let inside = if even_odd { winding % 2 != 0 } else { winding != 0 };
+            if inside {
+                let x_start = (pair[0].0 - 0.5).ceil().clamp(0.0, image.width() as f64) as u32;
+                let x_end = (pair[1].0 - 0.5).ceil().clamp(0.0, image.width() as f64) as u32;
+                for x in x_start..x_end {
+                    blend_pixel(image.get_pixel_mut(x, y), color);
+                }
+            }
+        }
+    }
+}
+
+// Strokes a polyline wider than one pixel as a quad per segment with round joins.
+fn draw_thick_path(image: &mut RgbaImage, points: &[(f64, f64)], width: f64, color: Rgba<u8>) {
+    let half = width / 2.0;
+    for segment in points.windows(2) {
+        let (a, b) = (segment[0], segment[1]);
+        let length = ((b.0 - a.0).powi(2) + (b.1 - a.1).powi(2)).sqrt();
+        if length == 0.0 {
+            continue;
+        }
+        let normal = (-(b.1 - a.1) / length * half, (b.0 - a.0) / length * half);
+        let quad = vec![
+            (a.0 + normal.0, a.1 + normal.1),
+            (b.0 + normal.0, b.1 + normal.1),
+            (b.0 - normal.0, b.1 - normal.1),
+            (a.0 - normal.0, a.1 - normal.1),
+        ];
+        fill_polygons(image, &[quad], false, color);
+    }
+    // Round joins and caps, skipping points in the middle of straight runs,
+    // where a flattened line would otherwise look beaded.
+    for (i, point) in points.iter().enumerate() {
+        if i > 0 && i + 1 < points.len() {
+            let (a, b) = (points[i - 1], points[i + 1]);
+            let cross = (point.0 - a.0) * (b.1 - point.1) - (point.1 - a.1) * (b.0 - point.0);
+            if cross.abs() < 1e-6 {
+                continue;
+            }
+        }
+        draw_filled_circle_mut(image, (point.0 as i32, point.1 as i32), half as i32, color);
+    }
+}
+
+// Fill and stroke a closed outline using the object's `fill` and `border_color`.
+fn draw_outline(
+    image: &mut RgbaImage,
+    properties: &HashMap<&str, &Value>,
+    outline: &[(f64, f64)],
+) {
+    // Handle fill
+    if let Some(fill_hex) = get_property_color_str(properties, "fill") {
+        let mut points: Vec<Point<i32>> = outline
+            .iter()
+            .map(|(x, y)| Point::new(x.round() as i32, y.round() as i32))
+            .collect();
+        points.dedup();
+        // `draw_polygon_mut` rejects outlines that repeat their first point.
+        while points.len() > 1 && points.first() == points.last() {
+            points.pop();
+        }
+        if points.len() >= 3 {
+            draw_polygon_mut(image, &points, hex_to_rgba(&fill_hex));
+        }
+    }
+
+    // Handle border
+    if let Some(border_hex) = get_property_color_str(properties, "border_color") {
+        let color = hex_to_rgba(&border_hex);
+        draw_open_path(image, outline, color);
+        if let (Some(first), Some(last)) = (outline.first(), outline.last()) {
+            draw_line_segment_mut(
+                image,
+                (last.0 as f32, last.1 as f32),
+                (first.0 as f32, first.1 as f32),
+                color,
+            );
+        }
+    }
+}
+
+fn draw_open_path(image: &mut RgbaImage, points: &[(f64, f64)], color: Rgba<u8>) {
+    for segment in points.windows(2) {
+        draw_line_segment_mut(
+            image,
+            (segment[0].0 as f32, segment[0].1 as f32),
+            (segment[1].0 as f32, segment[1].1 as f32),
+            color,
+        );
+    }
+}
+
 fn draw_arrowhead(
     image: &mut RgbaImage,
     from: (f64, f64),
@@ -339,6 +752,19 @@ fn get_property_tuple(properties: &HashMap<&str, &Value>, name: &str) -> Option<
     })
 }
 
+fn get_property_points(properties: &HashMap<&str, &Value>, name: &str) -> Vec<(f64, f64)> {
+    match properties.get(name) {
+        Some(Value::List(items)) => items
+            .iter()
+            .filter_map(|v| match v {
+                Value::Tuple(x, y) => Some((*x, *y)),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
 fn get_camera_property_color(camera: &Option<Camera>, name: &str) -> Option<Rgba<u8>> {
     camera.as_ref().and_then(|c| {
         c.properties
@@ -358,21 +784,9 @@ fn get_property_color_str<'a>(properties: &'a HashMap<&str, &Value>, name: &str)
     })
 }
 
+// A color property's value, drawn white if it is not a color.
 fn hex_to_rgba(hex: &str) -> Rgba<u8> {
-    let rgb = hex_to_rgb(hex).unwrap_or(Rgba([255, 255, 255, 255]));
-    Rgba([rgb[0], rgb[1], rgb[2], 255])
-}
-
-fn hex_to_rgb(hex: &str) -> Option<Rgba<u8>> {
-    let hex = hex.trim_start_matches('#');
-    if hex.len() == 6 {
-        let r = u8::from_str_radix(&hex[0..2], 16).ok()?;
-        let g = u8::from_str_radix(&hex[2..4], 16).ok()?;
-        let b = u8::from_str_radix(&hex[4..6], 16).ok()?;
-        Some(Rgba([r, g, b, 255]))
-    } else {
-        None
-    }
+    Rgba(color::parse(hex).unwrap_or([255, 255, 255, 255]))
 }
 
 #[cfg(test)]
@@ -418,6 +832,7 @@ mod tests {
             }],
             timeline: None,
             duration: None,
+            layers: vec![],
         };
 
         let image = render_scene(&scene, &camera);
@@ -439,15 +854,8 @@ mod tests {
         assert_eq!(hex_to_rgba("#0000FF"), Rgba([0, 0, 255, 255]));
         assert_eq!(hex_to_rgba("#FFFFFF"), Rgba([255, 255, 255, 255]));
         assert_eq!(hex_to_rgba("#000000"), Rgba([0, 0, 0, 255]));
-    }
-
-    #[test]
-    fn test_hex_to_rgb() {
-        assert_eq!(hex_to_rgb("#FF0000"), Some(Rgba([255, 0, 0, 255])));
-        assert_eq!(hex_to_rgb("#00FF00"), Some(Rgba([0, 255, 0, 255])));
-        assert_eq!(hex_to_rgb("#0000FF"), Some(Rgba([0, 0, 255, 255])));
-        assert_eq!(hex_to_rgb("invalid"), None);
-        assert_eq!(hex_to_rgb("#FFF"), None);
+        assert_eq!(hex_to_rgba("transparent"), Rgba([0, 0, 0, 0]));
+        assert_eq!(hex_to_rgba("invalid"), Rgba([255, 255, 255, 255]));
     }
 
     #[test]
@@ -557,6 +965,7 @@ mod tests {
             items: vec![],
             timeline: None,
             duration: None,
+            layers: vec![],
         };
 
         let image = render_scene(&scene, &camera);
@@ -574,6 +983,7 @@ mod tests {
             items: vec![],
             timeline: None,
             duration: None,
+            layers: vec![],
         };
 
         let image = render_scene(&scene, &None);
@@ -608,6 +1018,7 @@ mod tests {
             }],
             timeline: None,
             duration: None,
+            layers: vec![],
         };
 
         let camera = Some(Camera {
@@ -646,10 +1057,256 @@ mod tests {
             }],
             timeline: None,
             duration: None,
+            layers: vec![],
         };
 
         let image = render_scene(&scene, &None);
         assert_eq!(image.width(), DEFAULT_WIDTH);
         assert_eq!(image.height(), DEFAULT_HEIGHT);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_render_scene_with_sector() {
+        let scene = Scene {
+            name: "TestScene".to_string(),
+            items: vec![Object {
+                r#type: "sector".to_string(),
+                name: "pie".to_string(),
+                properties: vec![
+                    Property {
+                        name: "position".to_string(),
+                        value: Value::Tuple(50.0, 50.0),
+                    },
+                    Property {
+                        name: "radius".to_string(),
+                        value: Value::Number(40.0),
+                    },
+                    Property {
+                        name: "start_angle".to_string(),
+                        value: Value::Number(0.0),
+                    },
+                    Property {
+                        name: "end_angle".to_string(),
+                        value: Value::Number(90.0),
+                    },
+                    Property {
+                        name: "fill".to_string(),
+                        value: Value::Color("#FF0000".to_string()),
+                    },
+                ],
+            }],
+            timeline: None,
+            duration: None,
+            layers: vec![],
+        };
+
+        let camera = Some(Camera {
+            properties: vec![
+                Property {
+                    name: "width".to_string(),
+                    value: Value::Number(100.0),
+                },
+                Property {
+                    name: "height".to_string(),
+                    value: Value::Number(100.0),
+                },
+                Property {
+                    name: "background_color".to_string(),
+                    value: Value::Color("#000000".to_string()),
+                },
+            ],
+        });
+
+        let image = render_scene(&scene, &camera);
+        // Inside the slice (below and to the right of the center)
+        assert_eq!(*image.get_pixel(65, 65), Rgba([255, 0, 0, 255]));
+        // Outside the slice (above the center)
+        assert_eq!(*image.get_pixel(50, 30), Rgba([0, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_get_property_points() {
+        let mut properties = std::collections::HashMap::new();
+        let value = Value::List(vec![Value::Tuple(1.0, 2.0), Value::Tuple(3.0, 4.0)]);
+        properties.insert("points", &value);
+
+        assert_eq!(get_property_points(&properties, "points"), vec![(1.0, 2.0), (3.0, 4.0)]);
+        assert!(get_property_points(&properties, "nonexistent").is_empty());
+    }
+
+    #[test]
+    fn test_fill_polygons_even_odd_leaves_hole() {
+        let outer = vec![(0.0, 0.0), (30.0, 0.0), (30.0, 30.0), (0.0, 30.0)];
+        let inner = vec![(10.0, 10.0), (20.0, 10.0), (20.0, 20.0), (10.0, 20.0)];
+        let color = Rgba([255, 0, 0, 255]);
+
+        let mut even_odd = RgbaImage::new(30, 30);
+        fill_polygons(&mut even_odd, &[outer.clone(), inner.clone()], true, color);
+        assert_eq!(*even_odd.get_pixel(5, 5), color);
+        assert_eq!(*even_odd.get_pixel(15, 15), Rgba([0, 0, 0, 0]));
+
+        let mut non_zero = RgbaImage::new(30, 30);
+        fill_polygons(&mut non_zero, &[outer, inner], false, color);
+        assert_eq!(*non_zero.get_pixel(15, 15), color);
+    }
+
+    #[test]
+    fn test_render_scene_with_path() {
+        let scene = Scene {
+            name: "TestScene".to_string(),
+            items: vec![Object {
+                r#type: "path".to_string(),
+                name: "logo".to_string(),
+                properties: vec![
+                    Property {
+                        name: "d".to_string(),
+                        value: Value::String("M 10 10 H 90 V 90 H 10 Z".to_string()),
+                    },
+                    Property {
+                        name: "fill".to_string(),
+                        value: Value::Color("#00FF00".to_string()),
+                    },
+                ],
+            }],
+            timeline: None,
+            duration: None,
+            layers: vec![],
+        };
+
+        let image = render_scene(&scene, &None);
+        assert_eq!(*image.get_pixel(50, 50), Rgba([0, 255, 0, 255]));
+        assert_eq!(*image.get_pixel(95, 95), DEFAULT_BG_COLOR);
+    }
+
+    #[test]
+    fn test_blend_pixel() {
+        let mut dst = Rgba([0, 0, 0, 255]);
+        blend_pixel(&mut dst, Rgba([255, 255, 255, 128]));
+        assert_eq!(dst, Rgba([128, 128, 128, 255]));
+
+        let mut transparent = Rgba([0, 0, 0, 0]);
+        blend_pixel(&mut transparent, Rgba([255, 0, 0, 128]));
+        assert_eq!(transparent, Rgba([255, 0, 0, 128]));
+    }
+
+    #[test]
+    fn test_render_scene_with_image() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("logo.png");
+        RgbaImage::from_pixel(4, 2, Rgba([0, 0, 255, 255])).save(&src).unwrap();
+
+        let scene = Scene {
+            name: "TestScene".to_string(),
+            items: vec![Object {
+                r#type: "image".to_string(),
+                name: "logo".to_string(),
+                properties: vec![
+                    Property {
+                        name: "src".to_string(),
+                        value: Value::String(src.to_string_lossy().to_string()),
+                    },
+                    Property {
+                        name: "position".to_string(),
+                        value: Value::Tuple(50.0, 50.0),
+                    },
+                    Property {
+                        name: "width".to_string(),
+                        value: Value::Number(40.0),
+                    },
+                ],
+            }],
+            timeline: None,
+            duration: None,
+            layers: vec![],
+        };
+
+        let image = render_scene(&scene, &None);
+        // 40x20 image centered on (50, 50)
+        assert_eq!(*image.get_pixel(50, 50), Rgba([0, 0, 255, 255]));
+        assert_eq!(*image.get_pixel(50, 35), DEFAULT_BG_COLOR);
+    }
+
+    #[test]
+    fn test_render_scene_with_opacity() {
+        let scene = Scene {
+            name: "TestScene".to_string(),
+            items: vec![Object {
+                r#type: "square".to_string(),
+                name: "faded".to_string(),
+                properties: vec![
+                    Property {
+                        name: "position".to_string(),
+                        value: Value::Tuple(50.0, 50.0),
+                    },
+                    Property {
+                        name: "size".to_string(),
+                        value: Value::Number(20.0),
+                    },
+                    Property {
+                        name: "fill".to_string(),
+                        value: Value::Color("#FFFFFF".to_string()),
+                    },
+                    Property {
+                        name: "opacity".to_string(),
+                        value: Value::Number(0.5),
+                    },
+                ],
+            }],
+            timeline: None,
+            duration: None,
+            layers: vec![],
+        };
+
+        let camera = Some(Camera {
+            properties: vec![Property {
+                name: "background_color".to_string(),
+                value: Value::Color("#000000".to_string()),
+            }],
+        });
+
+        let image = render_scene(&scene, &camera);
+        assert_eq!(*image.get_pixel(50, 50), Rgba([128, 128, 128, 255]));
+    }
+
+    #[test]
+    fn test_render_scene_with_transparent_background() {
+        let camera = Some(Camera {
+            properties: vec![Property {
+                name: "background_color".to_string(),
+                value: Value::Color("transparent".to_string()),
+            }],
+        });
+        let scene = Scene {
+            name: "TestScene".to_string(),
+            items: vec![Object {
+                r#type: "square".to_string(),
+                name: "overlay".to_string(),
+                properties: vec![
+                    Property {
+                        name: "position".to_string(),
+                        value: Value::Tuple(50.0, 50.0),
+                    },
+                    Property {
+                        name: "size".to_string(),
+                        value: Value::Number(20.0),
+                    },
+                    Property {
+                        name: "fill".to_string(),
+                        value: Value::Color("#FF0000".to_string()),
+                    },
+                    Property {
+                        name: "opacity".to_string(),
+                        value: Value::Number(0.5),
+                    },
+                ],
+            }],
+            timeline: None,
+            duration: None,
+            layers: vec![],
+        };
+
+        let image = render_scene(&scene, &camera);
+        assert_eq!(*image.get_pixel(50, 50), Rgba([255, 0, 0, 128]));
+        assert_eq!(*image.get_pixel(5, 5), Rgba([0, 0, 0, 0]));
+    }
+}
@@ -0,0 +1,297 @@
+//! A local preview server: a small player page in the browser, with frames
+//! rendered on demand at the time being looked at, which reloads the script
+//! when it or a file it uses changes.
+
+use crate::animator;
+use crate::ast::{BeamScript, Scene};
+use crate::reload::ScriptFiles;
+use crate::renderer;
+use image::ImageFormat;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tiny_http::{Header, Response, Server};
+
+/// The player, which needs nothing but this server.
+const PLAYER: &str = include_str!("player.html");
+
+/// Serves the player for the script at `path` on `port` of this computer
+/// only, until interrupted.
+pub fn serve(path: &Path, fps: Option<f64>, port: u16) -> Result<(), Box<dyn std::error::Error>> {
+    let server = Server::http(("127.0.0.1", port)).map_err(|e| e.to_string())?;
+    let mut session = Session::new(path, fps);
+    eprintln!(
+        "Serving {} at http://127.0.0.1:{}/, press Ctrl-C to stop",
+        path.display(),
+        port
+    );
+    for request in server.incoming_requests() {
+        let reply = session.reply(request.url());
+        let header = Header::from_bytes("Content-Type", reply.content_type)
+            .expect("content types are valid headers");
+        let response = Response::from_data(reply.body)
+            .with_status_code(reply.status)
+            .with_header(header);
+        if let Err(e) = request.respond(response) {
+            eprintln!("Warning: Cannot reply to the browser: {}", e);
+        }
+    }
+    Ok(())
+}
+
+/// An answer to a request from the player.
+#[derive(Debug)]
+struct Reply {
+    status: u16,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl Reply {
+    fn text(status: u16, text: impl Into<String>) -> Reply {
+        Reply {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: text.into().into_bytes(),
+        }
+    }
+}
+
+/// The script being previewed, parsed again whenever it changes.
+struct Session {
+    path: PathBuf,
+    fps: Option<f64>,
+    /// The last script that parsed, kept while the file has errors.
+    script: Option<BeamScript>,
+    error: Option<String>,
+    /// Counts reloads, so that the player knows to draw again.
+    version: u64,
+    files: ScriptFiles,
+}
+
+impl Session {
+    fn new(path: &Path, fps: Option<f64>) -> Session {
+        let mut session = Session {
+            path: path.to_path_buf(),
+            fps,
+            script: None,
+            error: None,
+            version: 0,
+            files: ScriptFiles::default(),
+        };
+        session.load();
+        session
+    }
+
+    fn load(&mut self) {
+        match self.files.load(&self.path, self.fps) {
+            Ok(script) => {
+                self.script = Some(script);
+                self.error = None;
+            }
+            Err(e) => self.error = Some(e),
+        }
+        self.version += 1;
+    }
+
+    // Loads the script again if it or a file it uses has changed.
+    fn refresh(&mut self) {
+        if self.files.any_changed() {
+            self.load();
+        }
+    }
+
+    fn reply(&mut self, url: &str) -> Reply {
+        let (path, query) = url.split_once('?').unwrap_or((url, ""));
+        match path {
+            "/" => Reply {
+                status: 200,
+                content_type: "text/html; charset=utf-8",
+                body: PLAYER.as_bytes().to_vec(),
+            },
+            "/script" => {
+                self.refresh();
+                Reply {
+                    status: 200,
+                    content_type: "application/json",
+                    body: self.info().to_string().into_bytes(),
+                }
+            }
+            "/frame" => {
+                let Some(time) = parameter(query, "t")
+                    .and_then(|t| t.parse::<f64>().ok())
+                    .filter(|t| t.is_finite())
+                else {
+                    return Reply::text(400, "A frame needs a time such as t=1.5");
+                };
+                // Times past the end show the last frame, even those too far
+                // off to be a `Duration`.
+                let time = Duration::try_from_secs_f64(time.max(0.0)).unwrap_or(Duration::MAX);
+                match &self.script {
+                    Some(script) => self.frame(script, time),
+                    None => Reply::text(503, self.error.clone().unwrap_or_default()),
+                }
+            }
+            _ => Reply::text(404, "Not found"),
+        }
+    }
+
+    // What the player needs to know about the script, as JSON.
+    fn info(&self) -> serde_json::Value {
+        let script = self.script.as_ref();
+        let fps = script.map_or(0.0, BeamScript::fps);
+        let (width, height) = script.map_or((0, 0), |s| renderer::frame_size(&s.camera));
+        let mut start = 0;
+        let scenes: Vec<serde_json::Value> = script
+            .map(|script| script.scenes.as_slice())
+            .unwrap_or_default()
+            .iter()
+            .map(|scene| {
+                let frames = animator::scene_frames(scene, fps);
+                let info = serde_json::json!({
+                    "name": scene.name,
+                    "start": start as f64 / fps,
+                    "duration": frames as f64 / fps,
+                });
+                start += frames;
+                info
+            })
+            .collect();
+        serde_json::json!({
+            "version": self.version,
+            "error": self.error,
+            "fps": fps,
+            "width": width,
+            "height": height,
+            "frames": start,
+            "scenes": scenes,
+        })
+    }
+
+    // The frame on screen at `time` as a PNG image.
+    fn frame(&self, script: &BeamScript, time: Duration) -> Reply {
+        let Some((scene, time)) = locate(script, time) else {
+            return Reply::text(404, "The script has no frames");
+        };
+        let frame = animator::frame_scene(scene, time);
+        let image = renderer::render_scene(&frame, &script.camera);
+        let mut png = Cursor::new(Vec::new());
+        match image.write_to(&mut png, ImageFormat::Png) {
+            Ok(()) => Reply {
+                status: 200,
+                content_type: "image/png",
+                body: png.into_inner(),
+            },
+            Err(e) => Reply::text(500, e.to_string()),
+        }
+    }
+}
+
+// The scene shown at `time` from the start of the video, and the time from the
+// start of that scene, holding the last frame after the end.
+fn locate(script: &BeamScript, time: Duration) -> Option<(&Scene, Duration)> {
+    let fps = script.fps();
+    let total: u64 = script
+        .scenes
+        .iter()
+        .map(|scene| animator::scene_frames(scene, fps))
+        .sum();
+    let mut frame = animator::frame_at(time, fps).min(total.checked_sub(1)?);
+    for scene in &script.scenes {
+        let frames = animator::scene_frames(scene, fps);
+        if frame < frames {
+            return Some((scene, Duration::from_secs_f64(frame as f64 / fps)));
+        }
+        frame -= frames;
+    }
+    None
+}
+
+// The value of `name` in a query string such as `t=1.5&v=2`.
+fn parameter<'a>(query: &'a str, name: &str) -> Option<&'a str> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+    use std::fs;
+
+    const SCRIPT: &str = r#"
+        camera { width: 32, height: 18, fps: 10 }
+        scene "A" { duration: 1s }
+        scene "B" {
+            duration: 500ms
+            square "s" { size: 4, position: (0, 9) }
+        }
+        timeline for "B" { at 0s to 500ms, "s".position -> (32, 9); }
+    "#;
+
+    #[test]
+    fn test_parameter() {
+        assert_eq!(parameter("t=1.5&v=2", "t"), Some("1.5"));
+        assert_eq!(parameter("t=1.5&v=2", "v"), Some("2"));
+        assert_eq!(parameter("", "t"), None);
+    }
+
+    #[test]
+    fn test_locate() {
+        let script = parser::parse_str(SCRIPT, None).unwrap();
+        let at = |secs: f64| {
+            let (scene, time) = locate(&script, Duration::from_secs_f64(secs)).unwrap();
+            (scene.name.as_str(), time.as_millis())
+        };
+        assert_eq!(at(0.0), ("A", 0));
+        assert_eq!(at(0.95), ("A", 900));
+        assert_eq!(at(1.2), ("B", 200));
+        // The last frame is held after the end.
+        assert_eq!(at(60.0), ("B", 400));
+        assert_eq!(
+            locate(&script, Duration::MAX).map(|(s, t)| (s.name.as_str(), t.as_millis())),
+            Some(("B", 400))
+        );
+        assert!(locate(&BeamScript::default(), Duration::ZERO).is_none());
+    }
+
+    #[test]
+    fn test_session_replies() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("demo.beam");
+        fs::write(&path, SCRIPT).unwrap();
+        let mut session = Session::new(&path, None);
+
+        let page = session.reply("/");
+        assert_eq!(page.status, 200);
+        assert!(!String::from_utf8(page.body).unwrap().contains("http"));
+
+        let info: serde_json::Value =
+            serde_json::from_slice(&session.reply("/script").body).unwrap();
+        assert_eq!(info["frames"], 15);
+        assert_eq!(info["scenes"][1]["name"], "B");
+        assert_eq!(info["scenes"][1]["start"], 1.0);
+
+        let frame = session.reply("/frame?t=1.2");
+        assert_eq!(frame.content_type, "image/png");
+        let image = image::load_from_memory(&frame.body).unwrap();
+        assert_eq!((image.width(), image.height()), (32, 18));
+        assert_eq!(session.reply("/frame?t=1e30").status, 200);
+        assert_eq!(session.reply("/frame?t=-1e30").status, 200);
+        assert_eq!(session.reply("/frame?t=inf").status, 400);
+        assert_eq!(session.reply("/frame").status, 400);
+        assert_eq!(session.reply("/nothing").status, 404);
+
+        // A broken script is reported while the last one keeps playing.
+        std::thread::sleep(Duration::from_millis(20));
+        fs::write(&path, "scene {").unwrap();
+        let info: serde_json::Value =
+            serde_json::from_slice(&session.reply("/script").body).unwrap();
+        assert_eq!(info["version"], 2);
+        assert!(info["error"].as_str().unwrap().contains("Error parsing"));
+        assert_eq!(session.reply("/frame?t=0").status, 200);
+    }
+}
@@ -0,0 +1,159 @@
+//! Outline geometry for shapes that both renderers build from point lists.
+//!
+//! Angles are in degrees, measured clockwise from the positive x axis, which
+//! matches `rotation` in screen space where y grows downwards.
+
+use crate::path::PathData;
+use std::f64::consts::PI;
+
+/// A filled path in scene coordinates with a straight-alpha RGBA color, for
+/// objects that both renderers draw from the same list of shapes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shape {
+    pub data: PathData,
+    pub color: [u8; 4],
+}
+
+/// Number of straight segments used to approximate a full circle.
+const CIRCLE_SEGMENTS: usize = 96;
+
+pub fn regular_polygon(center: (f64, f64), radius: f64, sides: usize) -> Vec<(f64, f64)> {
+    let sides = sides.max(3);
+    (0..sides)
+        .map(|i| {
+            // Start at the top so that triangles and pentagons point upwards.
+            let angle = -PI / 2.0 + 2.0 * PI * i as f64 / sides as f64;
+            (center.0 + radius * angle.cos(), center.1 + radius * angle.sin())
+        })
+        .collect()
+}
+
+pub fn star(
+    center: (f64, f64),
+    outer_radius: f64,
+    inner_radius: f64,
+    points: usize,
+) -> Vec<(f64, f64)> {
+    let points = points.max(2);
+    (0..points * 2)
+        .map(|i| {
+            let radius = if i % 2 == 0 { outer_radius } else { inner_radius };
+            let angle = -PI / 2.0 + PI * i as f64 / points as f64;
+            (center.0 + radius * angle.cos(), center.1 + radius * angle.sin())
+        })
+        .collect()
+}
+
+/// Points along a circular arc from `start_angle` to `end_angle`, inclusive.
+pub fn arc(center: (f64, f64), radius: f64, start_angle: f64, end_angle: f64) -> Vec<(f64, f64)> {
+    let sweep = end_angle - start_angle;
+    let segments = ((sweep.abs() / 360.0) * CIRCLE_SEGMENTS as f64).ceil().max(1.0) as usize;
+    (0..=segments)
+        .map(|i| {
+            let angle = (start_angle + sweep * i as f64 / segments as f64).to_radians();
+            (center.0 + radius * angle.cos(), center.1 + radius * angle.sin())
+        })
+        .collect()
+}
+
+/// Outline of a pie slice, or of a ring segment when `inner_radius` is positive.
+pub fn sector(
+    center: (f64, f64),
+    radius: f64,
+    inner_radius: f64,
+    start_angle: f64,
+    end_angle: f64,
+) -> Vec<(f64, f64)> {
+    let mut points = arc(center, radius, start_angle, end_angle);
+    if inner_radius > 0.0 {
+        points.extend(arc(center, inner_radius, end_angle, start_angle));
+    } else {
+        points.push(center);
+    }
+    dedup_closing_point(points)
+}
+
+/// Outline of an axis-aligned rectangle with circular corners.
+pub fn rounded_rect(
+    top_left: (f64, f64),
+    width: f64,
+    height: f64,
+    corner_radius: f64,
+) -> Vec<(f64, f64)> {
+    let r = corner_radius.clamp(0.0, width.min(height) / 2.0);
+    let (left, top) = top_left;
+    let (right, bottom) = (left + width, top + height);
+    let mut points = Vec::new();
+    points.extend(arc((right - r, top + r), r, -90.0, 0.0));
+    points.extend(arc((right - r, bottom - r), r, 0.0, 90.0));
+    points.extend(arc((left + r, bottom - r), r, 90.0, 180.0));
+    points.extend(arc((left + r, top + r), r, 180.0, 270.0));
+    points.dedup();
+    dedup_closing_point(points)
+}
+
+// Polygon fills expect an open outline, so drop a last point that repeats the first.
+fn dedup_closing_point(mut points: Vec<(f64, f64)>) -> Vec<(f64, f64)> {
+    if points.len() > 1 && points.first() == points.last() {
+        points.pop();
+    }
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: (f64, f64), b: (f64, f64)) {
+        assert!((a.0 - b.0).abs() < 1e-9 && (a.1 - b.1).abs() < 1e-9, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn test_regular_polygon_starts_at_top() {
+        let points = regular_polygon((0.0, 0.0), 10.0, 4);
+        assert_eq!(points.len(), 4);
+        assert_close(points[0], (0.0, -10.0));
+        assert_close(points[1], (10.0, 0.0));
+    }
+
+    #[test]
+    fn test_regular_polygon_has_at_least_three_sides() {
+        assert_eq!(regular_polygon((0.0, 0.0), 10.0, 1).len(), 3);
+    }
+
+    #[test]
+    fn test_star_alternates_radii() {
+        let points = star((0.0, 0.0), 10.0, 4.0, 5);
+        assert_eq!(points.len(), 10);
+        assert_close(points[0], (0.0, -10.0));
+        let inner = points[1];
+        assert!(((inner.0 * inner.0 + inner.1 * inner.1).sqrt() - 4.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_arc_endpoints() {
+        let points = arc((0.0, 0.0), 10.0, 0.0, 90.0);
+        assert_close(points[0], (10.0, 0.0));
+        assert_close(*points.last().unwrap(), (0.0, 10.0));
+    }
+
+    #[test]
+    fn test_sector_ends_at_center() {
+        let points = sector((5.0, 5.0), 10.0, 0.0, 0.0, 90.0);
+        assert_close(*points.last().unwrap(), (5.0, 5.0));
+    }
+
+    #[test]
+    fn test_full_ring_sector_is_open() {
+        let points = sector((0.0, 0.0), 10.0, 5.0, 0.0, 360.0);
+        assert_ne!(points.first(), points.last());
+    }
+
+    #[test]
+    fn test_rounded_rect_stays_within_bounds() {
+        let points = rounded_rect((0.0, 0.0), 100.0, 50.0, 10.0);
+        assert!(points
+            .iter()
+            .all(|(x, y)| (-1e-9..=100.0 + 1e-9).contains(x) && (-1e-9..=50.0 + 1e-9).contains(y)));
+    }
+}
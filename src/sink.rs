@@ -0,0 +1,522 @@
+//! Destinations for rendered frames, which take them one at a time and in
+//! order, so that no more than a few frames are held at once.
+
+use crate::export::{self, ExportOptions, Format};
+use image::{ImageFormat, RgbaImage};
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, SyncSender};
+use std::thread::{self, JoinHandle};
+
+/// Number of frames queued for an encoder running on its own thread.
+const QUEUED_FRAMES: usize = 4;
+
+/// The frames a sink is about to receive.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VideoInfo {
+    pub width: u32,
+    pub height: u32,
+    pub fps: f64,
+    pub frames: u64,
+}
+
+pub trait FrameSink {
+    /// Prepares for the frames described by `info`, before the first one.
+    fn begin(&mut self, info: &VideoInfo) -> Result<(), Box<dyn Error>>;
+
+    /// Takes the next frame.
+    fn push_frame(&mut self, frame: &RgbaImage) -> Result<(), Box<dyn Error>>;
+
+    /// Completes the output after the last frame.
+    fn finish(&mut self) -> Result<(), Box<dyn Error>>;
+}
+
+/// The sink writing to `path` in the format of `options`. A path of `-`
+/// stands for standard output, which only Y4M can be written to.
+pub fn create(
+    options: &ExportOptions,
+    path: &Path,
+) -> Result<Box<dyn FrameSink>, Box<dyn Error>> {
+    let to_stdout = path == Path::new("-");
+    if to_stdout && options.format != Format::Y4m {
+        return Err("Only y4m can be written to standard output".into());
+    }
+    Ok(match options.format {
+        Format::Mp4 => Box::new(FfmpegSink::new(path, H264)),
+        Format::Webm => Box::new(FfmpegSink::new(path, VP9_WITH_ALPHA)),
+        Format::Prores => Box::new(FfmpegSink::new(path, PRORES_4444)),
+        Format::Png | Format::Qoi => {
+            let pattern = frame_pattern(options).unwrap_or_default();
+            Box::new(ImageSequenceSink::new(path, &pattern)?)
+        }
+        Format::Y4m if to_stdout => Box::new(Y4mSink::new(BufWriter::new(io::stdout()))),
+        Format::Y4m => Box::new(Y4mSink::new(BufWriter::new(File::create(path)?))),
+        Format::Gif | Format::Apng | Format::Webp => Box::new(AnimationSink::new(path, options)),
+        Format::Still => Box::new(StillSink::new(path)),
+    })
+}
+
+/// The pattern the frames are named after when `options` writes them as a
+/// directory of images, such as `frame_%05d.png`.
+pub fn frame_pattern(options: &ExportOptions) -> Option<String> {
+    match (options.format, &options.pattern) {
+        (Format::Png | Format::Qoi, Some(pattern)) => Some(pattern.clone()),
+        (Format::Qoi, None) => Some("frame_%05d.qoi".to_string()),
+        (Format::Png, None) => Some("frame_%05d.png".to_string()),
+        _ => None,
+    }
+}
+
+/// ffmpeg arguments for H.264 video in the pixel format most players expect.
+pub const H264: &[&str] = &["-c:v", "libx264", "-pix_fmt", "yuv420p"];
+/// ffmpeg arguments for VP9 video with alpha. Alternate reference frames are
+/// turned off, as they lose the alpha channel.
+pub const VP9_WITH_ALPHA: &[&str] = &[
+    "-c:v",
+    "libvpx-vp9",
+    "-pix_fmt",
+    "yuva420p",
+    "-auto-alt-ref",
+    "0",
+];
+/// ffmpeg arguments for ProRes 4444 video keeping the alpha channel.
+pub const PRORES_4444: &[&str] = &[
+    "-c:v",
+    "prores_ks",
+    "-profile:v",
+    "4444",
+    "-pix_fmt",
+    "yuva444p10le",
+];
+
+/// Pipes raw RGBA frames into ffmpeg, which encodes them with the given
+/// codec arguments, such as [`H264`].
+pub struct FfmpegSink {
+    path: PathBuf,
+    codec: &'static [&'static str],
+    encoder: Option<FfmpegProcess>,
+}
+
+struct FfmpegProcess {
+    child: Child,
+    stdin: ChildStdin,
+    // ffmpeg blocks once its stderr fills up, so it is read as it comes.
+    stderr: JoinHandle<String>,
+}
+
+impl FfmpegSink {
+    pub fn new(path: &Path, codec: &'static [&'static str]) -> FfmpegSink {
+        FfmpegSink {
+            path: path.to_path_buf(),
+            codec,
+            encoder: None,
+        }
+    }
+}
+
+impl FfmpegProcess {
+    // Waits for ffmpeg to exit, with its output as the error if it failed.
+    fn wait(self) -> Result<(), Box<dyn Error>> {
+        let FfmpegProcess {
+            mut child,
+            stdin,
+            stderr,
+        } = self;
+        drop(stdin);
+        let status = child.wait()?;
+        let stderr = stderr.join().unwrap_or_default();
+        if status.success() {
+            Ok(())
+        } else {
+            Err(format!("ffmpeg error: {}", stderr).into())
+        }
+    }
+}
+
+impl FrameSink for FfmpegSink {
+    fn begin(&mut self, info: &VideoInfo) -> Result<(), Box<dyn Error>> {
+        let mut child = Command::new("ffmpeg")
+            .args(["-f", "rawvideo", "-pix_fmt", "rgba"])
+            .arg("-s")
+            .arg(format!("{}x{}", info.width, info.height))
+            .arg("-r")
+            .arg(ffmpeg_rate(info.fps))
+            .args(["-i", "-"])
+            .args(self.codec)
+            .arg("-y") // Overwrite output file if it exists
+            .arg(&self.path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to execute ffmpeg: {}", e))?;
+        let stdin = child.stdin.take().unwrap();
+        let mut stderr = child.stderr.take().unwrap();
+        let stderr = thread::spawn(move || {
+            let mut output = String::new();
+            let _ = stderr.read_to_string(&mut output);
+            output
+        });
+        self.encoder = Some(FfmpegProcess {
+            child,
+            stdin,
+            stderr,
+        });
+        Ok(())
+    }
+
+    fn push_frame(&mut self, frame: &RgbaImage) -> Result<(), Box<dyn Error>> {
+        let encoder = self.encoder.as_mut().ok_or("ffmpeg is not running")?;
+        if let Err(e) = encoder.stdin.write_all(frame.as_raw()) {
+            // ffmpeg quit early, and its own error explains why.
+            self.encoder.take().unwrap().wait()?;
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Box<dyn Error>> {
+        match self.encoder.take() {
+            Some(encoder) => encoder.wait(),
+            None => Err("ffmpeg is not running".into()),
+        }
+    }
+}
+
+/// Saves frames as numbered PNG or QOI images in a directory, named after a
+/// pattern such as `frame_%05d.png`.
+pub struct ImageSequenceSink {
+    dir: PathBuf,
+    pattern: String,
+    format: ImageFormat,
+    index: u64,
+}
+
+impl ImageSequenceSink {
+    pub fn new(dir: &Path, pattern: &str) -> Result<ImageSequenceSink, Box<dyn Error>> {
+        if frame_name(pattern, 0).is_none() {
+            return Err(format!(
+                "The pattern '{}' needs one frame number, such as %05d",
+                pattern
+            )
+            .into());
+        }
+        let format = match ImageFormat::from_path(pattern) {
+            Ok(format @ (ImageFormat::Png | ImageFormat::Qoi)) => format,
+            _ => {
+                return Err(format!("The pattern '{}' must end in .png or .qoi", pattern).into())
+            }
+        };
+        Ok(ImageSequenceSink {
+            dir: dir.to_path_buf(),
+            pattern: pattern.to_string(),
+            format,
+            index: 0,
+        })
+    }
+}
+
+impl FrameSink for ImageSequenceSink {
+    fn begin(&mut self, _info: &VideoInfo) -> Result<(), Box<dyn Error>> {
+        fs::create_dir_all(&self.dir)?;
+        Ok(())
+    }
+
+    fn push_frame(&mut self, frame: &RgbaImage) -> Result<(), Box<dyn Error>> {
+        let name = frame_name(&self.pattern, self.index).unwrap();
+        frame.save_with_format(self.dir.join(name), self.format)?;
+        self.index += 1;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+}
+
+/// Saves a single frame as a PNG image.
+pub struct StillSink {
+    path: PathBuf,
+    saved: bool,
+}
+
+impl StillSink {
+    pub fn new(path: &Path) -> StillSink {
+        StillSink {
+            path: path.to_path_buf(),
+            saved: false,
+        }
+    }
+}
+
+impl FrameSink for StillSink {
+    fn begin(&mut self, _info: &VideoInfo) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    fn push_frame(&mut self, frame: &RgbaImage) -> Result<(), Box<dyn Error>> {
+        if self.saved {
+            return Err("Only one frame can be saved as a still image".into());
+        }
+        frame.save_with_format(&self.path, ImageFormat::Png)?;
+        self.saved = true;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Box<dyn Error>> {
+        if !self.saved {
+            return Err("No frame to save".into());
+        }
+        Ok(())
+    }
+}
+
+/// The name of frame `index` in a pattern with a single `%d`, or `%05d` for
+/// numbers padded with zeros to five digits.
+pub fn frame_name(pattern: &str, index: u64) -> Option<String> {
+    let (before, rest) = pattern.split_once('%')?;
+    let (spec, after) = rest.split_once('d')?;
+    if after.contains('%') {
+        return None;
+    }
+    let width = match spec {
+        "" => 0,
+        _ => spec.strip_prefix('0')?.parse().ok()?,
+    };
+    Some(format!("{}{:0width$}{}", before, index, after, width = width))
+}
+
+/// Writes uncompressed YUV4MPEG2 video, which any encoder can read from a
+/// pipe. Colors are converted to 4:2:0 BT.601 in the limited range.
+pub struct Y4mSink<W: Write> {
+    out: W,
+}
+
+impl<W: Write> Y4mSink<W> {
+    pub fn new(out: W) -> Y4mSink<W> {
+        Y4mSink { out }
+    }
+}
+
+impl<W: Write> FrameSink for Y4mSink<W> {
+    fn begin(&mut self, info: &VideoInfo) -> Result<(), Box<dyn Error>> {
+        let (numerator, denominator) = rate_fraction(info.fps);
+        writeln!(
+            self.out,
+            "YUV4MPEG2 W{} H{} F{}:{} Ip A1:1 C420jpeg",
+            info.width, info.height, numerator, denominator
+        )?;
+        Ok(())
+    }
+
+    fn push_frame(&mut self, frame: &RgbaImage) -> Result<(), Box<dyn Error>> {
+        self.out.write_all(b"FRAME\n")?;
+        self.out.write_all(&to_yuv420(frame))?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Box<dyn Error>> {
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+// The Y plane of `frame`, followed by its U and V planes at half the width
+// and height, each sample averaging a block of 2x2 pixels.
+fn to_yuv420(frame: &RgbaImage) -> Vec<u8> {
+    let (width, height) = frame.dimensions();
+    let (chroma_width, chroma_height) = (width.div_ceil(2), height.div_ceil(2));
+    let mut y_plane = Vec::with_capacity((width * height) as usize);
+    for pixel in frame.pixels() {
+        let [r, g, b, _] = pixel.0.map(|c| c as f64);
+        y_plane.push((16.0 + (65.481 * r + 128.553 * g + 24.966 * b) / 255.0).round() as u8);
+    }
+    let mut u_plane = Vec::with_capacity((chroma_width * chroma_height) as usize);
+    let mut v_plane = Vec::with_capacity(u_plane.capacity());
+    for y in 0..chroma_height {
+        for x in 0..chroma_width {
+            let mut sum = [0.0; 3];
+            let mut count = 0.0;
+            for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                if let Some(pixel) = frame.get_pixel_checked(x * 2 + dx, y * 2 + dy) {
+                    sum[0] += pixel[0] as f64;
+                    sum[1] += pixel[1] as f64;
+                    sum[2] += pixel[2] as f64;
+                    count += 1.0;
+                }
+            }
+            let [r, g, b] = sum.map(|s| s / count);
+            u_plane.push((128.0 + (-37.797 * r - 74.203 * g + 112.0 * b) / 255.0).round() as u8);
+            v_plane.push((128.0 + (112.0 * r - 93.786 * g - 18.214 * b) / 255.0).round() as u8);
+        }
+    }
+    y_plane.extend(u_plane);
+    y_plane.extend(v_plane);
+    y_plane
+}
+
+/// Encodes a GIF, APNG or WebP animation on a thread of its own, so that
+/// frames are compressed while the next ones render.
+pub struct AnimationSink {
+    path: PathBuf,
+    options: ExportOptions,
+    frames: Option<SyncSender<RgbaImage>>,
+    encoder: Option<JoinHandle<Result<(), String>>>,
+}
+
+impl AnimationSink {
+    pub fn new(path: &Path, options: &ExportOptions) -> AnimationSink {
+        AnimationSink {
+            path: path.to_path_buf(),
+            options: options.clone(),
+            frames: None,
+            encoder: None,
+        }
+    }
+
+    fn join(&mut self) -> Result<(), Box<dyn Error>> {
+        match self.encoder.take().map(JoinHandle::join) {
+            Some(Ok(result)) => Ok(result?),
+            Some(Err(_)) => Err("The encoder panicked".into()),
+            None => Err("The encoder is not running".into()),
+        }
+    }
+}
+
+impl FrameSink for AnimationSink {
+    fn begin(&mut self, info: &VideoInfo) -> Result<(), Box<dyn Error>> {
+        let (frames, queue) = mpsc::sync_channel(QUEUED_FRAMES);
+        let (path, options, fps) = (self.path.clone(), self.options.clone(), info.fps);
+        self.encoder = Some(thread::spawn(move || {
+            export::write_animation(queue.into_iter(), fps, &options, &path)
+                .map_err(|e| e.to_string())
+        }));
+        self.frames = Some(frames);
+        Ok(())
+    }
+
+    fn push_frame(&mut self, frame: &RgbaImage) -> Result<(), Box<dyn Error>> {
+        let frames = self.frames.as_ref().ok_or("The encoder is not running")?;
+        if frames.send(frame.clone()).is_err() {
+            // Sending only fails once the encoder has stopped with an error.
+            self.frames = None;
+            self.join()?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Box<dyn Error>> {
+        // Closing the queue ends the frames the encoder is waiting for.
+        self.frames = None;
+        self.join()
+    }
+}
+
+// Frame rates such as 23.976 and 29.97 are really multiples of 1000/1001,
+// given as their numerator over 1001.
+fn ntsc_rate(fps: f64) -> Option<u64> {
+    let ntsc = (fps * 1.001).round();
+    (fps.fract() != 0.0 && (ntsc * 1000.0 / 1001.0 - fps).abs() < 0.005)
+        .then_some(ntsc as u64 * 1000)
+}
+
+// The frame rate as ffmpeg expects it, with NTSC rates passed on exactly.
+fn ffmpeg_rate(fps: f64) -> String {
+    match ntsc_rate(fps) {
+        Some(numerator) => format!("{}/1001", numerator),
+        None => fps.to_string(),
+    }
+}
+
+// The frame rate as a fraction in lowest terms, to a thousandth of a frame.
+fn rate_fraction(fps: f64) -> (u64, u64) {
+    if let Some(numerator) = ntsc_rate(fps) {
+        return (numerator, 1001);
+    }
+    let (numerator, denominator) = ((fps * 1000.0).round() as u64, 1000);
+    let (mut a, mut b) = (numerator, denominator);
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    (numerator / a, denominator / a)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    fn info(width: u32, height: u32) -> VideoInfo {
+        VideoInfo {
+            width,
+            height,
+            fps: 30.0,
+            frames: 3,
+        }
+    }
+
+    #[test]
+    fn test_ffmpeg_rate() {
+        assert_eq!(ffmpeg_rate(60.0), "60");
+        assert_eq!(ffmpeg_rate(23.976), "24000/1001");
+        assert_eq!(ffmpeg_rate(29.97), "30000/1001");
+        assert_eq!(ffmpeg_rate(12.5), "12.5");
+    }
+
+    #[test]
+    fn test_rate_fraction() {
+        assert_eq!(rate_fraction(60.0), (60, 1));
+        assert_eq!(rate_fraction(29.97), (30000, 1001));
+        assert_eq!(rate_fraction(12.5), (25, 2));
+    }
+
+    #[test]
+    fn test_frame_name() {
+        assert_eq!(frame_name("frame_%05d.png", 42).unwrap(), "frame_00042.png");
+        assert_eq!(frame_name("%d.qoi", 7).unwrap(), "7.qoi");
+        assert_eq!(frame_name("shot.png", 1), None);
+        assert_eq!(frame_name("%05d_%d.png", 1), None);
+        assert_eq!(frame_name("%5d.png", 1), None);
+    }
+
+    #[test]
+    fn test_image_sequence() {
+        let dir = tempfile::tempdir().unwrap();
+        let options = ExportOptions {
+            format: Format::Qoi,
+            pattern: Some("shot_%03d.qoi".to_string()),
+            ..ExportOptions::default()
+        };
+        let mut sink = create(&options, dir.path()).unwrap();
+        sink.begin(&info(4, 4)).unwrap();
+        for shade in [10, 20, 30] {
+            let frame = RgbaImage::from_pixel(4, 4, Rgba([shade, 0, 0, 255]));
+            sink.push_frame(&frame).unwrap();
+        }
+        sink.finish().unwrap();
+        let last = image::open(dir.path().join("shot_002.qoi")).unwrap().to_rgba8();
+        assert_eq!(last.get_pixel(0, 0), &Rgba([30, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_y4m() {
+        let mut sink = Y4mSink::new(Vec::new());
+        sink.begin(&info(3, 2)).unwrap();
+        let mut frame = RgbaImage::from_pixel(3, 2, Rgba([255, 255, 255, 255]));
+        frame.put_pixel(2, 0, Rgba([0, 0, 0, 255]));
+        frame.put_pixel(2, 1, Rgba([0, 0, 0, 255]));
+        sink.push_frame(&frame).unwrap();
+        sink.finish().unwrap();
+
+        let header = b"YUV4MPEG2 W3 H2 F30:1 Ip A1:1 C420jpeg\nFRAME\n";
+        assert_eq!(&sink.out[..header.len()], header);
+        // Six luma samples, then a U and a V for each of the two 2x2 blocks.
+        assert_eq!(
+            &sink.out[header.len()..],
+            &[235, 235, 16, 235, 235, 16, 128, 128, 128, 128]
+        );
+    }
+}
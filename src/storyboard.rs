@@ -0,0 +1,289 @@
+//! Storyboards: a single image of thumbnails taken across a script, each
+//! labeled with its scene and timecode, for reviewing an animation without
+//! playing it.
+
+use crate::animator;
+use crate::ast::{BeamScript, Scene};
+use crate::output::StagedOutput;
+use crate::{font, renderer};
+use image::imageops::{self, FilterType};
+use image::{Rgba, RgbaImage};
+use rayon::prelude::*;
+use std::error::Error;
+use std::path::Path;
+use std::time::Duration;
+
+/// Space around and between thumbnails, in pixels.
+const PADDING: u32 = 16;
+const FONT_SIZE: f64 = 14.0;
+const LINE_HEIGHT: u32 = 20;
+const SHEET_COLOR: Rgba<u8> = Rgba([255, 255, 255, 255]);
+const TEXT_COLOR: Rgba<u8> = Rgba([40, 40, 40, 255]);
+
+/// Which moments a storyboard shows and how it lays them out.
+#[derive(Debug, Clone)]
+pub struct StoryboardOptions {
+    /// Time between thumbnails, or `None` for one at each marker.
+    pub every: Option<Duration>,
+    pub columns: u32,
+    pub thumbnail_width: u32,
+}
+
+/// A moment shown on a storyboard.
+#[derive(Debug)]
+struct Sample<'a> {
+    scene: &'a Scene,
+    /// The frame counted from the start of the scene.
+    frame: u64,
+    /// The frame counted from the start of the video, for the timecode.
+    video_frame: u64,
+    marker: Option<&'a str>,
+}
+
+/// Renders the storyboard of `script` and saves it to `output`, as an image
+/// in the format of its extension.
+pub fn export_storyboard(script: &BeamScript, output: &Path, options: &StoryboardOptions) {
+    let written = storyboard(script, options).and_then(|sheet| {
+        let staged = StagedOutput::new(output)?;
+        sheet.save(staged.path())?;
+        Ok(staged.commit()?)
+    });
+    match written {
+        Ok(()) => eprintln!("✅ Saved to {}", output.display()),
+        Err(e) => eprintln!("Error writing {}: {}", output.display(), e),
+    }
+}
+
+/// The storyboard of `script`: its thumbnails in rows of
+/// `options.columns`, each with the scene name, the marker if there is one,
+/// and the timecode beneath.
+pub fn storyboard(
+    script: &BeamScript,
+    options: &StoryboardOptions,
+) -> Result<RgbaImage, Box<dyn Error>> {
+    let fps = script.fps();
+    let samples = samples(script, options.every)?;
+    eprintln!("Rendering {} thumbnails...", samples.len());
+
+    let (width, height) = renderer::frame_size(&script.camera);
+    let thumbnail_width = options.thumbnail_width.max(1);
+    let thumbnail_height =
+        (u64::from(height) * u64::from(thumbnail_width) / u64::from(width.max(1))).max(1) as u32;
+    let thumbnails: Vec<RgbaImage> = samples
+        .par_iter()
+        .map(|sample| {
+            let time = Duration::from_secs_f64(sample.frame as f64 / fps);
+            let frame = animator::frame_scene(sample.scene, time);
+            let image = renderer::render_scene(&frame, &script.camera);
+            imageops::resize(
+                &image,
+                thumbnail_width,
+                thumbnail_height,
+                FilterType::Triangle,
+            )
+        })
+        .collect();
+
+    let columns = options.columns.clamp(1, samples.len() as u32);
+    let rows = (samples.len() as u32).div_ceil(columns);
+    let cell_width = thumbnail_width + PADDING;
+    let cell_height = thumbnail_height + 2 * LINE_HEIGHT + PADDING;
+    let mut sheet = RgbaImage::from_pixel(
+        columns * cell_width + PADDING,
+        rows * cell_height + PADDING,
+        SHEET_COLOR,
+    );
+    for (i, (sample, thumbnail)) in samples.iter().zip(&thumbnails).enumerate() {
+        let x = PADDING + i as u32 % columns * cell_width;
+        let y = PADDING + i as u32 / columns * cell_height;
+        imageops::overlay(&mut sheet, thumbnail, x.into(), y.into());
+
+        let title = match sample.marker {
+            Some(marker) => format!("{} - {}", sample.scene.name, marker),
+            None => sample.scene.name.clone(),
+        };
+        let lines = [title, timecode(sample.video_frame, fps)];
+        for (line, text) in lines.iter().enumerate() {
+            let top = y + thumbnail_height + 4 + line as u32 * LINE_HEIGHT;
+            let text = fit(text, f64::from(thumbnail_width));
+            renderer::draw_label(
+                &mut sheet,
+                &text,
+                (x.into(), top.into()),
+                FONT_SIZE,
+                TEXT_COLOR,
+            );
+        }
+    }
+    Ok(sheet)
+}
+
+// The moments to show, either every `every` on the clock of the whole video
+// or at each marker in the timelines.
+fn samples(script: &BeamScript, every: Option<Duration>) -> Result<Vec<Sample<'_>>, String> {
+    let fps = script.fps();
+    // Each scene with the frame it starts at and the frame after its end.
+    let mut scenes = Vec::new();
+    let mut offset = 0;
+    for scene in &script.scenes {
+        let length = animator::scene_frames(scene, fps);
+        scenes.push((scene, offset, offset + length));
+        offset += length;
+    }
+
+    let mut samples = Vec::new();
+    match every {
+        Some(every) if every.is_zero() => {
+            return Err("The time between thumbnails must be more than 0".to_string());
+        }
+        Some(every) => {
+            let mut time = Duration::ZERO;
+            loop {
+                let video_frame = animator::frame_at(time, fps);
+                let Some(&(scene, start, _)) = scenes.iter().find(|s| video_frame < s.2) else {
+                    break;
+                };
+                samples.push(Sample {
+                    scene,
+                    frame: video_frame - start,
+                    video_frame,
+                    marker: None,
+                });
+                time += every;
+            }
+        }
+        None => {
+            for (scene, start, end) in scenes {
+                let mut markers: Vec<_> = scene.timeline.iter().flat_map(|t| &t.markers).collect();
+                markers.sort_by_key(|marker| marker.time);
+                for marker in markers {
+                    let frame = animator::frame_at(marker.time, fps);
+                    if start + frame >= end {
+                        eprintln!(
+                            "Warning: Marker '{}' is after the end of scene '{}'",
+                            marker.name, scene.name
+                        );
+                        continue;
+                    }
+                    samples.push(Sample {
+                        scene,
+                        frame,
+                        video_frame: start + frame,
+                        marker: Some(&marker.name),
+                    });
+                }
+            }
+        }
+    }
+
+    if samples.is_empty() {
+        return Err(match every {
+            Some(_) => "No frames to show".to_string(),
+            None => "No markers to show, add some or use --every".to_string(),
+        });
+    }
+    Ok(samples)
+}
+
+/// `frame` as hours, minutes, seconds and frames, such as `00:01:02:15`,
+/// counting whole seconds at `fps` rounded to a whole number like editors
+/// do for non-drop-frame timecode.
+fn timecode(frame: u64, fps: f64) -> String {
+    let rate = (fps.round() as u64).max(1);
+    let seconds = frame / rate;
+    format!(
+        "{:02}:{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+        frame % rate
+    )
+}
+
+// `text` shortened with an ellipsis to fit in `width` pixels.
+fn fit(text: &str, width: f64) -> String {
+    if font::label_width(text, FONT_SIZE) <= width {
+        return text.to_string();
+    }
+    let mut fitted: String = text.to_string();
+    while !fitted.is_empty() && font::label_width(&format!("{}...", fitted), FONT_SIZE) > width {
+        fitted.pop();
+    }
+    format!("{}...", fitted.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_str;
+
+    const SCRIPT: &str = r#"
+        camera { width: 160, height: 90, fps: 10 }
+        scene "Intro" { duration: 1.5s }
+        scene "Main" {
+            duration: 2s
+            circle "dot" { radius: 10, position: (80, 45) }
+        }
+        timeline for "Main" {
+            marker "Reveal" at 5f;
+            marker "Start" at 0s;
+            marker "Late" at 3s;
+        }
+    "#;
+
+    #[test]
+    fn test_samples_every() {
+        let script = parse_str(SCRIPT, None).unwrap();
+        let samples = samples(&script, Some(Duration::from_secs(1))).unwrap();
+        let frames: Vec<_> = samples
+            .iter()
+            .map(|s| (s.scene.name.as_str(), s.frame, s.video_frame))
+            .collect();
+        assert_eq!(
+            frames,
+            [
+                ("Intro", 0, 0),
+                ("Intro", 10, 10),
+                ("Main", 5, 20),
+                ("Main", 15, 30)
+            ]
+        );
+        assert!(super::samples(&script, Some(Duration::ZERO)).is_err());
+    }
+
+    #[test]
+    fn test_samples_at_markers() {
+        let script = parse_str(SCRIPT, None).unwrap();
+        let samples = samples(&script, None).unwrap();
+        let frames: Vec<_> = samples
+            .iter()
+            .map(|s| (s.marker.unwrap(), s.frame, s.video_frame))
+            .collect();
+        assert_eq!(frames, [("Start", 0, 15), ("Reveal", 5, 20)]);
+    }
+
+    #[test]
+    fn test_timecode() {
+        assert_eq!(timecode(0, 24.0), "00:00:00:00");
+        assert_eq!(timecode(24 * 62 + 15, 24.0), "00:01:02:15");
+        assert_eq!(timecode(30 * 3600, 29.97), "01:00:00:00");
+    }
+
+    #[test]
+    fn test_storyboard_layout() {
+        let script = parse_str(SCRIPT, None).unwrap();
+        let options = StoryboardOptions {
+            every: Some(Duration::from_secs(1)),
+            columns: 3,
+            thumbnail_width: 80,
+        };
+        let sheet = storyboard(&script, &options).unwrap();
+        // Two rows of three 80x45 thumbnails, with two lines of labels each.
+        assert_eq!(sheet.width(), 3 * (80 + PADDING) + PADDING);
+        assert_eq!(
+            sheet.height(),
+            2 * (45 + 2 * LINE_HEIGHT + PADDING) + PADDING
+        );
+        assert_eq!(*sheet.get_pixel(0, 0), SHEET_COLOR);
+    }
+}
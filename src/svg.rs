@@ -0,0 +1,479 @@
+//! Import of SVG files as groups of animatable `path` objects.
+//!
+//! Every drawable SVG element becomes its own `path` object named
+//! `"<svg name>.<element id>"`, so the timeline can address it directly.
+//! Elements without an `id` are numbered in document order instead.
+
+use crate::ast::{Object, Property, Value};
+use crate::{assets, color, path};
+use std::fs;
+
+type Matrix = [f64; 6];
+
+const IDENTITY: Matrix = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+/// Presentation attributes inherited down the SVG tree.
+#[derive(Clone)]
+struct Style {
+    fill: Option<String>,
+    stroke: Option<String>,
+    stroke_width: f64,
+    fill_rule: String,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Style {
+            fill: Some("#000000".to_string()),
+            stroke: None,
+            stroke_width: 1.0,
+            fill_rule: "nonzero".to_string(),
+        }
+    }
+}
+
+/// Replaces every `svg` object with the shapes imported from its `src` file.
+pub fn expand_svg_objects(items: &mut Vec<Object>) {
+    if !items.iter().any(|o| o.r#type == "svg") {
+        return;
+    }
+    let mut expanded = Vec::with_capacity(items.len());
+    for object in items.drain(..) {
+        if object.r#type != "svg" {
+            expanded.push(object);
+            continue;
+        }
+        let src = match object.properties.iter().find(|p| p.name == "src") {
+            Some(Property {
+                value: Value::String(src),
+                ..
+            }) => src.clone(),
+            _ => {
+                eprintln!("Warning: svg object '{}' has no 'src'", object.name);
+                continue;
+            }
+        };
+        let path = assets::resolve(&src);
+        match fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| import_svg(&object, &text))
+        {
+            Ok(children) => expanded.extend(children),
+            Err(e) => eprintln!("Warning: Failed to import SVG '{}': {}", path.display(), e),
+        }
+    }
+    *items = expanded;
+}
+
+/// Converts SVG source into `path` objects that belong to `parent`.
+pub fn import_svg(parent: &Object, text: &str) -> Result<Vec<Object>, String> {
+    let document = roxmltree::Document::parse(text).map_err(|e| e.to_string())?;
+    let mut importer = Importer {
+        prefix: parent.name.clone(),
+        inherited: parent.shared_properties(),
+        objects: Vec::new(),
+        unnamed: 0,
+    };
+    importer.visit(document.root_element(), IDENTITY, &Style::default());
+    Ok(importer.objects)
+}
+
+struct Importer {
+    prefix: String,
+    inherited: Vec<Property>,
+    objects: Vec<Object>,
+    unnamed: usize,
+}
+
+impl Importer {
+    fn visit(&mut self, node: roxmltree::Node, parent_matrix: Matrix, parent_style: &Style) {
+        let matrix = match node.attribute("transform") {
+            Some(transform) => multiply(parent_matrix, parse_transform(transform)),
+            None => parent_matrix,
+        };
+        let style = resolve_style(node, parent_style);
+
+        match node.tag_name().name() {
+            "svg" | "g" | "a" => {
+                for child in node.children().filter(|n| n.is_element()) {
+                    self.visit(child, matrix, &style);
+                }
+            }
+            "path" | "rect" | "circle" | "ellipse" | "line" | "polyline" | "polygon" => {
+                let Some(d) = shape_to_path_data(node) else {
+                    return;
+                };
+                match path::parse_path_data(&d) {
+                    Ok(data) => self.push(node, path::transform(&data, matrix), matrix, &style),
+                    Err(e) => eprintln!("Warning: Skipping SVG element with invalid path data: {}", e),
+                }
+            }
+            // Definitions, metadata, text and so on are not drawn.
+            _ => {}
+        }
+    }
+
+    fn push(&mut self, node: roxmltree::Node, data: path::PathData, matrix: Matrix, style: &Style) {
+        let name = match node.attribute("id") {
+            Some(id) => format!("{}.{}", self.prefix, id),
+            None => {
+                self.unnamed += 1;
+                format!("{}.{}", self.prefix, self.unnamed)
+            }
+        };
+        let property = |name: &str, value: Value| Property {
+            name: name.to_string(),
+            value,
+        };
+
+        let mut properties = vec![property("d", Value::String(path::to_path_data(&data)))];
+        if let Some(fill) = &style.fill {
+            properties.push(property("fill", Value::Color(fill.clone())));
+            properties.push(property("fill_rule", Value::String(style.fill_rule.clone())));
+        }
+        if let Some(stroke) = &style.stroke {
+            // Strokes scale with the element, so use the transform's average scale.
+            let scale = ((matrix[0] * matrix[3] - matrix[1] * matrix[2]).abs()).sqrt();
+            properties.push(property("border_color", Value::Color(stroke.clone())));
+            properties.push(property("stroke_width", Value::Number(style.stroke_width * scale)));
+        }
+        properties.extend(self.inherited.iter().cloned());
+
+        self.objects.push(Object {
+            r#type: "path".to_string(),
+            name,
+            properties,
+        });
+    }
+}
+
+fn resolve_style(node: roxmltree::Node, parent: &Style) -> Style {
+    let mut style = parent.clone();
+    let mut declarations: Vec<(&str, &str)> = ["fill", "stroke", "stroke-width", "fill-rule"]
+        .iter()
+        .filter_map(|name| node.attribute(*name).map(|value| (*name, value)))
+        .collect();
+    // Inline styles take precedence over presentation attributes.
+    if let Some(inline) = node.attribute("style") {
+        declarations.extend(inline.split(';').filter_map(|declaration| {
+            let (name, value) = declaration.split_once(':')?;
+            Some((name.trim(), value.trim()))
+        }));
+    }
+
+    for (name, value) in declarations {
+        match name {
+            "fill" => style.fill = parse_paint(value, &style.fill),
+            "stroke" => style.stroke = parse_paint(value, &style.stroke),
+            "stroke-width" => {
+                if let Ok(width) = value.trim_end_matches("px").parse() {
+                    style.stroke_width = width;
+                }
+            }
+            "fill-rule" => style.fill_rule = value.to_string(),
+            _ => {}
+        }
+    }
+    style
+}
+
+// Returns the paint as a color, `None` for no paint.
+fn parse_paint(value: &str, current: &Option<String>) -> Option<String> {
+    let value = value.trim();
+    match value {
+        "none" | "transparent" => None,
+        "inherit" | "currentColor" => current.clone(),
+        _ => parse_color(value).or_else(|| {
+            eprintln!("Warning: Unsupported SVG paint '{}'", value);
+            current.clone()
+        }),
+    }
+}
+
+// Returns the color as `#rrggbb`, or `#rrggbbaa` if it is not opaque.
+fn parse_color(value: &str) -> Option<String> {
+    if let Some(hex) = value.strip_prefix('#') {
+        // `#rgb` and `#rgba` are short for doubling each digit.
+        let hex = match hex.len() {
+            3 | 4 => hex.chars().flat_map(|c| [c, c]).collect(),
+            _ => hex.to_string(),
+        };
+        return color::parse(&format!("#{}", hex)).map(color::format);
+    }
+    if let Some(args) = value.strip_prefix("rgb(").and_then(|v| v.strip_suffix(')')) {
+        let channels: Vec<u8> = args
+            .split(',')
+            .filter_map(|c| {
+                let c = c.trim();
+                match c.strip_suffix('%') {
+                    Some(percent) => percent.parse::<f64>().ok().map(|p| (p * 2.55).round() as u8),
+                    None => c.parse::<f64>().ok().map(|v| v.clamp(0.0, 255.0) as u8),
+                }
+            })
+            .collect();
+        return match channels[..] {
+            [r, g, b] => Some(color::format([r, g, b, 255])),
+            _ => None,
+        };
+    }
+    let rgb = match value.to_ascii_lowercase().as_str() {
+        "black" => [0x00, 0x00, 0x00],
+        "white" => [0xFF, 0xFF, 0xFF],
+        "red" => [0xFF, 0x00, 0x00],
+        "green" => [0x00, 0x80, 0x00],
+        "lime" => [0x00, 0xFF, 0x00],
+        "blue" => [0x00, 0x00, 0xFF],
+        "yellow" => [0xFF, 0xFF, 0x00],
+        "cyan" | "aqua" => [0x00, 0xFF, 0xFF],
+        "magenta" | "fuchsia" => [0xFF, 0x00, 0xFF],
+        "gray" | "grey" => [0x80, 0x80, 0x80],
+        "silver" => [0xC0, 0xC0, 0xC0],
+        "maroon" => [0x80, 0x00, 0x00],
+        "navy" => [0x00, 0x00, 0x80],
+        "olive" => [0x80, 0x80, 0x00],
+        "purple" => [0x80, 0x00, 0x80],
+        "teal" => [0x00, 0x80, 0x80],
+        "orange" => [0xFF, 0xA5, 0x00],
+        _ => return None,
+    };
+    let [r, g, b] = rgb;
+    Some(color::format([r, g, b, 255]))
+}
+
+// Converts a basic shape element to path data.
+fn shape_to_path_data(node: roxmltree::Node) -> Option<String> {
+    let number = |name: &str| {
+        node.attribute(name)
+            .and_then(|v| v.trim_end_matches("px").parse::<f64>().ok())
+            .unwrap_or(0.0)
+    };
+    let d = match node.tag_name().name() {
+        "path" => node.attribute("d")?.to_string(),
+        "rect" => {
+            let (x, y, w, h) = (number("x"), number("y"), number("width"), number("height"));
+            if w <= 0.0 || h <= 0.0 {
+                return None;
+            }
+            // A missing `rx` or `ry` takes the value of the other one.
+            let (rx, ry) = match (node.attribute("rx"), node.attribute("ry")) {
+                (Some(_), Some(_)) => (number("rx"), number("ry")),
+                (Some(_), None) => (number("rx"), number("rx")),
+                (None, Some(_)) => (number("ry"), number("ry")),
+                (None, None) => (0.0, 0.0),
+            };
+            let (rx, ry) = (rx.min(w / 2.0), ry.min(h / 2.0));
+            if rx > 0.0 && ry > 0.0 {
+                format!(
+                    "M{} {} H{} A{} {} 0 0 1 {} {} V{} A{} {} 0 0 1 {} {} H{} A{} {} 0 0 1 {} {} V{} A{} {} 0 0 1 {} {} Z",
+                    x + rx, y, x + w - rx,
+                    rx, ry, x + w, y + ry, y + h - ry,
+                    rx, ry, x + w - rx, y + h, x + rx,
+                    rx, ry, x, y + h - ry, y + ry,
+                    rx, ry, x + rx, y,
+                )
+            } else {
+                format!("M{} {} H{} V{} H{} Z", x, y, x + w, y + h, x)
+            }
+        }
+        "circle" => {
+            let r = number("r");
+            ellipse_path_data(number("cx"), number("cy"), r, r)?
+        }
+        "ellipse" => ellipse_path_data(number("cx"), number("cy"), number("rx"), number("ry"))?,
+        "line" => format!("M{} {} L{} {}", number("x1"), number("y1"), number("x2"), number("y2")),
+        "polyline" | "polygon" => {
+            let points = node.attribute("points")?.trim();
+            if points.is_empty() {
+                return None;
+            }
+            let close = if node.tag_name().name() == "polygon" { " Z" } else { "" };
+            format!("M{}{}", points, close)
+        }
+        _ => return None,
+    };
+    Some(d)
+}
+
+fn ellipse_path_data(cx: f64, cy: f64, rx: f64, ry: f64) -> Option<String> {
+    if rx <= 0.0 || ry <= 0.0 {
+        return None;
+    }
+    Some(format!(
+        "M{} {} A{} {} 0 1 0 {} {} A{} {} 0 1 0 {} {} Z",
+        cx - rx, cy, rx, ry, cx + rx, cy, rx, ry, cx - rx, cy,
+    ))
+}
+
+fn parse_transform(value: &str) -> Matrix {
+    let mut matrix = IDENTITY;
+    for item in value.split(')') {
+        let Some((name, args)) = item.split_once('(') else {
+            continue;
+        };
+        let args: Vec<f64> = args
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|a| !a.is_empty())
+            .filter_map(|a| a.parse().ok())
+            .collect();
+        let next = match (name.trim().trim_start_matches(',').trim(), args.as_slice()) {
+            ("matrix", [a, b, c, d, e, f]) => [*a, *b, *c, *d, *e, *f],
+            ("translate", [x]) => [1.0, 0.0, 0.0, 1.0, *x, 0.0],
+            ("translate", [x, y]) => [1.0, 0.0, 0.0, 1.0, *x, *y],
+            ("scale", [s]) => [*s, 0.0, 0.0, *s, 0.0, 0.0],
+            ("scale", [x, y]) => [*x, 0.0, 0.0, *y, 0.0, 0.0],
+            ("rotate", [angle]) => rotation(*angle),
+            ("rotate", [angle, cx, cy]) => multiply(
+                multiply([1.0, 0.0, 0.0, 1.0, *cx, *cy], rotation(*angle)),
+                [1.0, 0.0, 0.0, 1.0, -cx, -cy],
+            ),
+            ("skewX", [angle]) => [1.0, 0.0, angle.to_radians().tan(), 1.0, 0.0, 0.0],
+            ("skewY", [angle]) => [1.0, angle.to_radians().tan(), 0.0, 1.0, 0.0, 0.0],
+            (other, _) => {
+                eprintln!("Warning: Unsupported SVG transform '{}'", other);
+                IDENTITY
+            }
+        };
+        matrix = multiply(matrix, next);
+    }
+    matrix
+}
+
+fn rotation(degrees: f64) -> Matrix {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    [cos, sin, -sin, cos, 0.0, 0.0]
+}
+
+// Returns the transform that applies `second` first and then `first`.
+fn multiply(first: Matrix, second: Matrix) -> Matrix {
+    let [a1, b1, c1, d1, e1, f1] = first;
+    let [a2, b2, c2, d2, e2, f2] = second;
+    [
+        a1 * a2 + c1 * b2,
+        b1 * a2 + d1 * b2,
+        a1 * c2 + c1 * d2,
+        b1 * c2 + d1 * d2,
+        a1 * e2 + c1 * f2 + e1,
+        b1 * e2 + d1 * f2 + f1,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn svg_object(properties: Vec<Property>) -> Object {
+        Object {
+            r#type: "svg".to_string(),
+            name: "diagram".to_string(),
+            properties,
+        }
+    }
+
+    fn property<'a>(object: &'a Object, name: &str) -> Option<&'a Value> {
+        object.properties.iter().find(|p| p.name == name).map(|p| &p.value)
+    }
+
+    #[test]
+    fn test_import_names_elements_by_id() {
+        let text = r##"
+            <svg xmlns="http://www.w3.org/2000/svg">
+                <rect id="box" x="0" y="0" width="10" height="10" fill="#ff0000"/>
+                <circle cx="5" cy="5" r="2"/>
+                <g id="arrows" stroke="blue" fill="none">
+                    <line id="arrow1" x1="0" y1="0" x2="10" y2="10"/>
+                </g>
+            </svg>
+        "##;
+        let objects = import_svg(&svg_object(vec![]), text).unwrap();
+        let names: Vec<_> = objects.iter().map(|o| o.name.as_str()).collect();
+        assert_eq!(names, vec!["diagram.box", "diagram.1", "diagram.arrow1"]);
+
+        assert_eq!(property(&objects[0], "fill"), Some(&Value::Color("#ff0000".to_string())));
+        assert_eq!(property(&objects[1], "fill"), Some(&Value::Color("#000000".to_string())));
+        assert_eq!(property(&objects[2], "fill"), None);
+        assert_eq!(
+            property(&objects[2], "border_color"),
+            Some(&Value::Color("#0000ff".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_import_applies_transforms() {
+        let text = r#"
+            <svg xmlns="http://www.w3.org/2000/svg">
+                <g transform="translate(10, 20)">
+                    <path id="p" d="M0 0 L5 0" transform="scale(2)"/>
+                </g>
+            </svg>
+        "#;
+        let objects = import_svg(&svg_object(vec![]), text).unwrap();
+        let Some(Value::String(d)) = property(&objects[0], "d") else {
+            panic!("expected path data");
+        };
+        let data = path::parse_path_data(d).unwrap();
+        assert_eq!(data[0], path::Segment::MoveTo((10.0, 20.0)));
+        match data[1] {
+            path::Segment::CubicTo(_, _, end) => assert_eq!(end, (20.0, 20.0)),
+            _ => panic!("expected a cubic"),
+        }
+    }
+
+    #[test]
+    fn test_import_inline_style_and_inherited_properties() {
+        let text = r##"
+            <svg xmlns="http://www.w3.org/2000/svg">
+                <rect id="r" width="4" height="4" fill="red" style="fill: #00f; stroke: rgb(0, 255, 0)"/>
+            </svg>
+        "##;
+        let parent = svg_object(vec![
+            Property {
+                name: "src".to_string(),
+                value: Value::String("diagram.svg".to_string()),
+            },
+            Property {
+                name: "position".to_string(),
+                value: Value::Tuple(100.0, 50.0),
+            },
+        ]);
+        let objects = import_svg(&parent, text).unwrap();
+        assert_eq!(property(&objects[0], "fill"), Some(&Value::Color("#0000ff".to_string())));
+        assert_eq!(
+            property(&objects[0], "border_color"),
+            Some(&Value::Color("#00ff00".to_string()))
+        );
+        assert_eq!(property(&objects[0], "position"), Some(&Value::Tuple(100.0, 50.0)));
+        assert_eq!(property(&objects[0], "src"), None);
+    }
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(parse_color("#abc"), Some("#aabbcc".to_string()));
+        assert_eq!(parse_color("#a0b1c2"), Some("#a0b1c2".to_string()));
+        assert_eq!(parse_color("rgb(100%, 0%, 0%)"), Some("#ff0000".to_string()));
+        assert_eq!(parse_color("white"), Some("#ffffff".to_string()));
+        assert_eq!(parse_color("url(#gradient)"), None);
+        assert_eq!(parse_color("#FF000080"), Some("#ff000080".to_string()));
+        assert_eq!(parse_color("#f008"), Some("#ff000088".to_string()));
+        assert_eq!(parse_color("#FFFFFFFF"), Some("#ffffff".to_string()));
+        assert_eq!(parse_color("#ggg"), None);
+        assert_eq!(parse_color("#12345"), None);
+        assert_eq!(parse_paint("transparent", &Some("#ffffff".to_string())), None);
+        assert_eq!(parse_paint("none", &Some("#ffffff".to_string())), None);
+    }
+
+    #[test]
+    fn test_parse_transform_rotate_about_point() {
+        let matrix = parse_transform("rotate(90 10 10)");
+        let (x, y) = (0.0, 10.0);
+        let mapped = (
+            matrix[0] * x + matrix[2] * y + matrix[4],
+            matrix[1] * x + matrix[3] * y + matrix[5],
+        );
+        assert!((mapped.0 - 10.0).abs() < 1e-9 && (mapped.1 - 0.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_invalid_svg() {
+        assert!(import_svg(&svg_object(vec![]), "<svg").is_err());
+    }
+}
@@ -0,0 +1,350 @@
+//! `table` objects: rows of text in a grid of cells.
+//!
+//! A table is expanded into one `table_cell` object per cell when the script
+//! is parsed, named after the table and the cell's row and column counted
+//! from zero, such as `"t[2][3]"`. `"t[2]"` names the cells of a row and
+//! `"t[*][3]"` those of a column, so highlighting a cell or revealing a row is
+//! an ordinary animation. Every cell shares the table's position, with its
+//! `offset` from it.
+
+use crate::ast::{Camera, Object, Property, Value};
+use crate::axes;
+use crate::color;
+use crate::font;
+use crate::path::{self, PathData};
+use crate::renderer;
+use crate::shapes::Shape;
+
+const DEFAULT_FONT_SIZE: f64 = 24.0;
+const DEFAULT_PADDING: f64 = 12.0;
+const DEFAULT_COLOR: &str = "#FFFFFF";
+
+/// Replaces every `table` object with its cells. Cells without a fill of
+/// their own are filled with the camera's background color, so that animating
+/// a cell's `fill` brings it out of the background.
+pub fn expand_table_objects(items: &mut Vec<Object>, camera: &Option<Camera>) {
+    if !items.iter().any(|o| o.r#type == "table") {
+        return;
+    }
+    let background = color::format(renderer::background_color(camera).0);
+    let mut expanded = Vec::with_capacity(items.len());
+    for object in items.drain(..) {
+        if object.r#type == "table" {
+            expanded.extend(cells(&object, &background));
+        } else {
+            expanded.push(object);
+        }
+    }
+    *items = expanded;
+}
+
+fn cells(table: &Object, background: &str) -> Vec<Object> {
+    let rows: Vec<Vec<String>> = match value(table, "rows") {
+        Some(Value::List(rows)) => rows
+            .iter()
+            .map(|row| match row {
+                Value::List(cells) => cells.iter().map(text).collect(),
+                other => vec![text(other)],
+            })
+            .collect(),
+        _ => {
+            eprintln!("Warning: Table '{}' has no rows", table.name);
+            return Vec::new();
+        }
+    };
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let font_size = number(table, "font_size").unwrap_or(DEFAULT_FONT_SIZE);
+    let padding = number(table, "padding").unwrap_or(DEFAULT_PADDING);
+    let row_height = number(table, "row_height").unwrap_or(font_size + 2.0 * padding);
+    let given_widths = match value(table, "column_widths") {
+        Some(Value::List(widths)) => widths.clone(),
+        _ => Vec::new(),
+    };
+    let widths: Vec<f64> = (0..columns)
+        .map(|c| match given_widths.get(c) {
+            Some(Value::Number(width)) => *width,
+            _ => rows
+                .iter()
+                .filter_map(|row| row.get(c))
+                .map(|cell| font::label_width(cell, font_size) + 2.0 * padding)
+                .fold(0.0, f64::max),
+        })
+        .collect();
+    let (header_row, header_column) = match value(table, "header") {
+        Some(Value::String(header)) => match header.as_str() {
+            "column" => (false, true),
+            "both" => (true, true),
+            "none" => (false, false),
+            _ => (true, false),
+        },
+        _ => (true, false),
+    };
+
+    let fill = color(table, "fill").unwrap_or(background);
+    let text_color = color(table, "color").unwrap_or(DEFAULT_COLOR);
+    let border_color = color(table, "border_color").unwrap_or(DEFAULT_COLOR);
+    let stroke_width = number(table, "stroke_width").unwrap_or(2.0);
+    let align = match value(table, "align") {
+        Some(Value::String(align)) => align.clone(),
+        _ => "center".to_string(),
+    };
+    let cell_fills = match value(table, "cell_fills") {
+        Some(Value::List(rows)) => rows.clone(),
+        _ => Vec::new(),
+    };
+    let cell_fill = |r: usize, c: usize| match cell_fills.get(r) {
+        Some(Value::List(fills)) => match fills.get(c) {
+            Some(Value::Color(fill)) => Some(fill.as_str()),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    let width: f64 = widths.iter().sum();
+    let height = row_height * rows.len() as f64;
+    let mut cells = Vec::with_capacity(rows.len() * columns);
+    for (r, row) in rows.iter().enumerate() {
+        let mut left = -width / 2.0;
+        for (c, &cell_width) in widths.iter().enumerate() {
+            let top = -height / 2.0 + row_height * r as f64;
+            let header = (header_row && r == 0) || (header_column && c == 0);
+            let (fill, text_color) = if header {
+                (
+                    color(table, "header_fill").unwrap_or(fill),
+                    color(table, "header_color").unwrap_or(text_color),
+                )
+            } else {
+                (fill, text_color)
+            };
+            let fill = cell_fill(r, c).unwrap_or(fill);
+
+            let mut properties = vec![
+                property(
+                    "text",
+                    Value::String(row.get(c).cloned().unwrap_or_default()),
+                ),
+                property(
+                    "offset",
+                    Value::Tuple(left + cell_width / 2.0, top + row_height / 2.0),
+                ),
+                property("width", Value::Number(cell_width)),
+                property("height", Value::Number(row_height)),
+                property("padding", Value::Number(padding)),
+                property("font_size", Value::Number(font_size)),
+                property("align", Value::String(align.clone())),
+                property("fill", Value::Color(fill.to_string())),
+                property("color", Value::Color(text_color.to_string())),
+                property("border_color", Value::Color(border_color.to_string())),
+                property("stroke_width", Value::Number(stroke_width)),
+            ];
+            properties.extend(table.shared_properties());
+            cells.push(Object {
+                r#type: "table_cell".to_string(),
+                name: format!("{}[{}][{}]", table.name, r, c),
+                properties,
+            });
+            left += cell_width;
+        }
+    }
+    cells
+}
+
+/// The filled shapes that draw a table cell: its background, its text and
+/// the border around it.
+pub fn cell_shapes(properties: &[Property]) -> Vec<Shape> {
+    let property = |name: &str| properties.iter().find(|p| p.name == name).map(|p| &p.value);
+    let number = |name: &str, default: f64| match property(name) {
+        Some(Value::Number(n)) => *n,
+        _ => default,
+    };
+    let tuple = |name: &str| match property(name) {
+        Some(Value::Tuple(x, y)) => (*x, *y),
+        _ => (0.0, 0.0),
+    };
+    let color = |name: &str| match property(name) {
+        Some(Value::Color(hex)) => color::parse(hex),
+        _ => None,
+    };
+
+    let (position, offset) = (tuple("position"), tuple("offset"));
+    let center = (position.0 + offset.0, position.1 + offset.1);
+    let (width, height) = (number("width", 0.0), number("height", 0.0));
+    let (left, top) = (center.0 - width / 2.0, center.1 - height / 2.0);
+    let (right, bottom) = (left + width, top + height);
+
+    let mut shapes = Vec::new();
+    if let Some(fill) = color("fill") {
+        shapes.push(Shape {
+            data: path::polygon(&[(left, top), (right, top), (right, bottom), (left, bottom)]),
+            color: fill,
+        });
+    }
+
+    if let (Some(Value::String(text)), Some(text_color)) = (property("text"), color("color")) {
+        let font_size = number("font_size", DEFAULT_FONT_SIZE);
+        let padding = number("padding", DEFAULT_PADDING);
+        let (x, align) = match property("align") {
+            Some(Value::String(align)) if align == "left" => (left + padding, 0.0),
+            Some(Value::String(align)) if align == "right" => (right - padding, 1.0),
+            _ => (center.0, 0.5),
+        };
+        let anchor = (x, center.1 - font_size * 0.35);
+        shapes.extend(
+            font::label(text, anchor, align, font_size)
+                .into_iter()
+                .map(|data| Shape {
+                    data,
+                    color: text_color,
+                }),
+        );
+    }
+
+    let stroke_width = number("stroke_width", 2.0);
+    if let Some(border) = color("border_color").filter(|_| stroke_width > 0.0) {
+        // A ring centered on the cell's edge, which runs the other way round
+        // inside so that it is left unfilled.
+        let half = stroke_width / 2.0;
+        let mut data: PathData = path::polygon(&[
+            (left - half, top - half),
+            (right + half, top - half),
+            (right + half, bottom + half),
+            (left - half, bottom + half),
+        ]);
+        data.extend(path::polygon(&[
+            (left + half, top + half),
+            (left + half, bottom - half),
+            (right - half, bottom - half),
+            (right - half, top + half),
+        ]));
+        shapes.push(Shape {
+            data,
+            color: border,
+        });
+    }
+    shapes
+}
+
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Number(n) => axes::format_number(*n),
+        _ => String::new(),
+    }
+}
+
+fn property(name: &str, value: Value) -> Property {
+    Property {
+        name: name.to_string(),
+        value,
+    }
+}
+
+fn value<'a>(object: &'a Object, name: &str) -> Option<&'a Value> {
+    object
+        .properties
+        .iter()
+        .find(|p| p.name == name)
+        .map(|p| &p.value)
+}
+
+fn number(object: &Object, name: &str) -> Option<f64> {
+    match value(object, name) {
+        Some(Value::Number(n)) => Some(*n),
+        _ => None,
+    }
+}
+
+fn color<'a>(object: &'a Object, name: &str) -> Option<&'a str> {
+    match value(object, name) {
+        Some(Value::Color(c)) => Some(c),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(extra: Vec<Property>) -> Object {
+        let row = |cells: &[&str]| {
+            Value::List(cells.iter().map(|c| Value::String(c.to_string())).collect())
+        };
+        let mut properties = vec![
+            property("position", Value::Tuple(500.0, 300.0)),
+            property(
+                "rows",
+                Value::List(vec![row(&["Name", "Score"]), row(&["Ada"])]),
+            ),
+            property(
+                "column_widths",
+                Value::List(vec![Value::Number(200.0), Value::Number(100.0)]),
+            ),
+            property("row_height", Value::Number(40.0)),
+        ];
+        properties.extend(extra);
+        Object {
+            r#type: "table".to_string(),
+            name: "t".to_string(),
+            properties,
+        }
+    }
+
+    #[test]
+    fn test_cells_are_laid_out_in_a_grid() {
+        let mut items = vec![table(vec![property(
+            "header_fill",
+            Value::Color("#333333".to_string()),
+        )])];
+        expand_table_objects(&mut items, &None);
+        let names: Vec<&str> = items.iter().map(|o| o.name.as_str()).collect();
+        assert_eq!(names, ["t[0][0]", "t[0][1]", "t[1][0]", "t[1][1]"]);
+        // The table is 300 by 80, centered on its position.
+        assert_eq!(
+            value(&items[0], "offset"),
+            Some(&Value::Tuple(-50.0, -20.0))
+        );
+        assert_eq!(value(&items[3], "offset"), Some(&Value::Tuple(100.0, 20.0)));
+        assert_eq!(
+            value(&items[3], "text"),
+            Some(&Value::String(String::new()))
+        );
+        assert_eq!(color(&items[1], "fill"), Some("#333333"));
+        assert_eq!(color(&items[2], "fill"), Some("#191919"));
+    }
+
+    #[test]
+    fn test_cell_fills_override_header_and_body() {
+        let fills = Value::List(vec![
+            Value::List(vec![]),
+            Value::List(vec![
+                Value::String(String::new()),
+                Value::Color("#FF0000".to_string()),
+            ]),
+        ]);
+        let mut items = vec![table(vec![property("cell_fills", fills)])];
+        let camera = Some(Camera {
+            properties: vec![property(
+                "background_color",
+                Value::Color("#000000".to_string()),
+            )],
+        });
+        expand_table_objects(&mut items, &camera);
+        assert_eq!(color(&items[2], "fill"), Some("#000000"));
+        assert_eq!(color(&items[3], "fill"), Some("#FF0000"));
+    }
+
+    #[test]
+    fn test_cell_shapes() {
+        let mut items = vec![table(vec![])];
+        expand_table_objects(&mut items, &None);
+        let shapes = cell_shapes(&items[0].properties);
+        // Background, the four glyphs of "Name" and the border.
+        assert_eq!(shapes.len(), 6);
+        assert_eq!(shapes[0].color, [0x19, 0x19, 0x19, 255]);
+        let points: Vec<(f64, f64)> = path::flatten(&shapes[0].data)
+            .into_iter()
+            .flat_map(|s| s.points)
+            .collect();
+        assert!(points.contains(&(350.0, 260.0)) && points.contains(&(550.0, 300.0)));
+    }
+}
@@ -0,0 +1,258 @@
+//! `transform "a" -> "b"` statements, in the spirit of manim's
+//! `TransformMatchingTex`.
+//!
+//! The objects of the two groups are paired up by identity: the `glyph` of a
+//! typeset symbol, or otherwise the part of the name after the group prefix,
+//! such as the element id of an imported SVG. A paired source object morphs
+//! into its target and is swapped for it at the end, while unpaired objects
+//! fade out or in. All of this is expressed as ordinary animations, so the
+//! animator's path morphing and easing apply unchanged.
+
+use crate::animator;
+use crate::ast::{Animation, Object, Transform, Value};
+use crate::path;
+use std::time::Duration;
+
+pub fn transform_animations(items: &[Object], transform: &Transform) -> Vec<Animation> {
+    let sources = members(items, &transform.from);
+    let targets = members(items, &transform.to);
+    if sources.is_empty() || targets.is_empty() {
+        eprintln!(
+            "Warning: Cannot transform '{}' into '{}', both must name objects in the scene",
+            transform.from, transform.to
+        );
+        return Vec::new();
+    }
+
+    let animate =
+        |object: &Object, property: &str, to: Value, start: Duration, end: Option<Duration>| {
+            Animation {
+                start,
+                end,
+                target_object: object.name.clone(),
+                property: property.to_string(),
+                to,
+                easing: transform.easing.clone(),
+            }
+        };
+    let (start, end) = (transform.start, Some(transform.end));
+    let mut animations = Vec::new();
+    let mut used = vec![false; sources.len()];
+
+    for target in &targets {
+        let opacity = Value::Number(number(target, "opacity").unwrap_or(1.0));
+        // Targets stay hidden until the transform reveals them.
+        animations.push(animate(
+            target,
+            "opacity",
+            Value::Number(0.0),
+            Duration::ZERO,
+            None,
+        ));
+
+        let wanted = identity(target, &transform.to);
+        let matched = sources.iter().enumerate().find_map(|(i, source)| {
+            if used[i] || identity(source, &transform.from) != wanted {
+                return None;
+            }
+            morphed_path(source, target).map(|d| (i, source, d))
+        });
+        let Some((i, source, d)) = matched else {
+            animations.push(animate(target, "opacity", opacity, start, end));
+            continue;
+        };
+        used[i] = true;
+
+        animations.push(animate(source, "d", Value::String(d), start, end));
+        if let (Some(from), Some(to)) = (property(source, "fill"), property(target, "fill")) {
+            if from != to {
+                animations.push(animate(source, "fill", to.clone(), start, end));
+            }
+        }
+        // Once the source has taken the target's shape, show the real target.
+        animations.push(animate(
+            source,
+            "opacity",
+            Value::Number(0.0),
+            transform.end,
+            None,
+        ));
+        animations.push(animate(target, "opacity", opacity, transform.end, None));
+    }
+
+    for (source, used) in sources.iter().zip(used) {
+        if !used {
+            animations.push(animate(source, "opacity", Value::Number(0.0), start, end));
+        }
+    }
+    animations
+}
+
+// The objects an animation of `name` applies to, as in the animator.
+fn members<'a>(items: &'a [Object], name: &str) -> Vec<&'a Object> {
+    items
+        .iter()
+        .filter(|o| animator::is_member(&o.name, name))
+        .collect()
+}
+
+fn identity(object: &Object, group: &str) -> String {
+    match property(object, "glyph") {
+        Some(Value::String(glyph)) => glyph.clone(),
+        _ => object
+            .name
+            .strip_prefix(group)
+            .unwrap_or(&object.name)
+            .to_string(),
+    }
+}
+
+fn property<'a>(object: &'a Object, name: &str) -> Option<&'a Value> {
+    object
+        .properties
+        .iter()
+        .find(|p| p.name == name)
+        .map(|p| &p.value)
+}
+
+fn number(object: &Object, name: &str) -> Option<f64> {
+    match property(object, name) {
+        Some(Value::Number(n)) => Some(*n),
+        _ => None,
+    }
+}
+
+fn position(object: &Object) -> (f64, f64) {
+    match property(object, "position") {
+        Some(Value::Tuple(x, y)) => (*x, *y),
+        _ => (0.0, 0.0),
+    }
+}
+
+// The target's path data relative to the source's position, if the two paths
+// are similar enough to morph into each other.
+fn morphed_path(source: &Object, target: &Object) -> Option<String> {
+    let (Some(Value::String(from)), Some(Value::String(to))) =
+        (property(source, "d"), property(target, "d"))
+    else {
+        return None;
+    };
+    let (sx, sy) = position(source);
+    let (tx, ty) = position(target);
+    let to = path::to_path_data(&path::translate(
+        &path::parse_path_data(to).ok()?,
+        (tx - sx, ty - sy),
+    ));
+    path::interpolate(from, &to, 0.0).map(|_| to)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Property;
+    use crate::math;
+
+    fn equation(name: &str, tex: &str, x: f64) -> Vec<Object> {
+        let object = Object {
+            r#type: "math".to_string(),
+            name: name.to_string(),
+            properties: vec![
+                Property {
+                    name: "tex".to_string(),
+                    value: Value::String(tex.to_string()),
+                },
+                Property {
+                    name: "position".to_string(),
+                    value: Value::Tuple(x, 100.0),
+                },
+            ],
+        };
+        math::typeset(&object).unwrap()
+    }
+
+    fn transform() -> Transform {
+        Transform {
+            start: Duration::from_secs(1),
+            end: Duration::from_secs(2),
+            from: "eq1".to_string(),
+            to: "eq2".to_string(),
+            easing: None,
+        }
+    }
+
+    fn find<'a>(animations: &'a [Animation], object: &str, property: &str) -> Vec<&'a Animation> {
+        animations
+            .iter()
+            .filter(|a| a.target_object == object && a.property == property)
+            .collect()
+    }
+
+    #[test]
+    fn test_matching_glyphs_morph_and_others_fade() {
+        // a^2 + b^2 -> c^2: the first 2 is shared, everything else fades.
+        let mut items = equation("eq1", "a^2 + b^2", 100.0);
+        items.extend(equation("eq2", "c^2", 400.0));
+        let animations = transform_animations(&items, &transform());
+
+        let morph = find(&animations, "eq1.2", "d");
+        assert_eq!(morph.len(), 1);
+        assert_eq!(morph[0].end, Some(Duration::from_secs(2)));
+        let swap = find(&animations, "eq1.2", "opacity");
+        assert_eq!(swap[0].start, Duration::from_secs(2));
+        assert_eq!(swap[0].end, None);
+
+        for faded in ["eq1.1", "eq1.3", "eq1.4", "eq1.5"] {
+            let fade = find(&animations, faded, "opacity");
+            assert_eq!(fade.len(), 1, "{}", faded);
+            assert_eq!(fade[0].to, Value::Number(0.0));
+            assert_eq!(fade[0].end, Some(Duration::from_secs(2)));
+            assert!(find(&animations, faded, "d").is_empty());
+        }
+
+        // The unmatched c is hidden at first and fades in.
+        let fade_in = find(&animations, "eq2.1", "opacity");
+        assert_eq!(fade_in[0].start, Duration::ZERO);
+        assert_eq!(fade_in[0].to, Value::Number(0.0));
+        assert_eq!(fade_in[1].to, Value::Number(1.0));
+        assert_eq!(fade_in[1].end, Some(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn test_morph_ends_at_target_location() {
+        let mut items = equation("eq1", "x", 100.0);
+        items.extend(equation("eq2", "x", 400.0));
+        let animations = transform_animations(&items, &transform());
+        let Value::String(d) = &find(&animations, "eq1.1", "d")[0].to else {
+            panic!("expected path data");
+        };
+        let Some(Value::String(target)) = property(&items[1], "d") else {
+            panic!("expected path data");
+        };
+        let shifted = path::translate(&path::parse_path_data(target).unwrap(), (300.0, 0.0));
+        assert_eq!(path::parse_path_data(d).unwrap(), shifted);
+    }
+
+    #[test]
+    fn test_members_match_the_animator() {
+        let items: Vec<Object> = ["eq1.1", "eq10.1", "grid[0][1]", "grid[1][1]", "grid[1][0]"]
+            .iter()
+            .map(|name| Object {
+                r#type: "path".to_string(),
+                name: name.to_string(),
+                properties: vec![],
+            })
+            .collect();
+        let names = |group: &str| -> Vec<String> {
+            members(&items, group).iter().map(|o| o.name.clone()).collect()
+        };
+        assert_eq!(names("eq1"), ["eq1.1"]);
+        assert_eq!(names("grid[*][1]"), ["grid[0][1]", "grid[1][1]"]);
+        assert_eq!(names("grid"), ["grid[0][1]", "grid[1][1]", "grid[1][0]"]);
+    }
+
+    #[test]
+    fn test_missing_group_yields_no_animations() {
+        let items = equation("eq1", "x", 100.0);
+        assert!(transform_animations(&items, &transform()).is_empty());
+    }
+}
@@ -0,0 +1,326 @@
+//! Watch mode: parses a script again whenever it or a file it uses is saved,
+//! and re-renders previews of only the scenes that changed, one file per
+//! scene.
+
+use crate::animator::{self, Selection};
+use crate::assets;
+use crate::ast::{BeamScript, Camera, Property, Scene, Value};
+use crate::export::{ExportOptions, Format};
+use crate::reload::{normalize, ScriptFiles};
+use notify_debouncer_mini::new_debouncer;
+use notify_debouncer_mini::notify::RecursiveMode;
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// How long to wait for more changes after a file is saved, as editors often
+/// write a file in several steps.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// How previews are rendered.
+#[derive(Debug, Clone)]
+pub struct WatchOptions {
+    /// Frames per second to parse the script at, like `render --fps`.
+    pub fps: Option<f64>,
+    /// Frames per second previews are rendered at, if lower than the
+    /// script's, to keep re-rendering quick.
+    pub preview_fps: f64,
+    pub gpu: bool,
+    pub export: ExportOptions,
+}
+
+/// Renders previews of the scenes of `script` into `output_dir`, then keeps
+/// them up to date until interrupted.
+pub fn watch(
+    script: &Path,
+    output_dir: &Path,
+    options: &WatchOptions,
+) -> Result<(), Box<dyn Error>> {
+    let (sender, receiver) = mpsc::channel();
+    let mut debouncer = new_debouncer(DEBOUNCE, sender)?;
+    let mut previews = Previews::default();
+    let mut watched_dirs = BTreeSet::new();
+    let mut changed = BTreeSet::new();
+    loop {
+        previews.update(script, output_dir, options, &changed);
+
+        // Editors often save by replacing a file, which ends a watch on the
+        // file itself, so watch the directories the files are in.
+        let dirs = previews.files.dirs();
+        for dir in watched_dirs.difference(&dirs) {
+            let _ = debouncer.watcher().unwatch(dir);
+        }
+        for dir in dirs.difference(&watched_dirs) {
+            if let Err(e) = debouncer.watcher().watch(dir, RecursiveMode::NonRecursive) {
+                eprintln!("Warning: Cannot watch {}: {}", dir.display(), e);
+            }
+        }
+        watched_dirs = dirs;
+        eprintln!(
+            "Watching {} for changes, press Ctrl-C to stop",
+            script.display()
+        );
+
+        // Reading a file is an event too, so only count those that change it.
+        changed = loop {
+            match receiver.recv()? {
+                Ok(events) => {
+                    let paths: BTreeSet<PathBuf> = events
+                        .into_iter()
+                        .map(|event| normalize(&event.path))
+                        .filter(|path| previews.files.has_changed(path))
+                        .collect();
+                    if !paths.is_empty() {
+                        break paths;
+                    }
+                }
+                Err(e) => eprintln!("Warning: Cannot watch for changes: {}", e),
+            }
+        };
+    }
+}
+
+/// The previews written so far and what they were rendered from.
+#[derive(Debug, Default)]
+struct Previews {
+    camera: Option<Camera>,
+    /// The scene each preview was rendered from, by the path of the preview.
+    scenes: HashMap<PathBuf, Scene>,
+    files: ScriptFiles,
+}
+
+impl Previews {
+    // Parses the script again and re-renders the previews of scenes that
+    // differ from when they were last rendered, or that use one of the
+    // `changed` files. Errors are reported, leaving the previews as they were.
+    fn update(
+        &mut self,
+        path: &Path,
+        output_dir: &Path,
+        options: &WatchOptions,
+        changed: &BTreeSet<PathBuf>,
+    ) {
+        let Ok(mut script) = self.files.load(path, options.fps) else {
+            return;
+        };
+        let sources: Vec<BTreeSet<PathBuf>> = script.scenes.iter().map(sources).collect();
+
+        set_preview_fps(&mut script, options.preview_fps);
+        if script.camera != self.camera {
+            self.scenes.clear();
+            self.camera = script.camera.clone();
+        }
+
+        let paths: Vec<PathBuf> = script
+            .scenes
+            .iter()
+            .enumerate()
+            .map(|(i, scene)| preview_path(output_dir, i, scene, options.export.format))
+            .collect();
+        self.scenes.retain(|old, _| {
+            let kept = paths.contains(old);
+            if !kept {
+                remove(old);
+            }
+            kept
+        });
+        let mut rendered = 0;
+        for ((scene, path), sources) in script.scenes.iter().zip(paths).zip(sources) {
+            if self.scenes.get(&path) == Some(scene) && sources.is_disjoint(changed) {
+                continue;
+            }
+            let preview = BeamScript {
+                camera: script.camera.clone(),
+                scenes: vec![scene.clone()],
+                ..BeamScript::default()
+            };
+            let written = animator::export(
+                &preview,
+                &path,
+                options.gpu,
+                &options.export,
+                &Selection::default(),
+            );
+            match written {
+                Ok(()) => {
+                    self.scenes.insert(path, scene.clone());
+                    rendered += 1;
+                }
+                Err(e) => {
+                    eprintln!("Error writing {}: {}", path.display(), e);
+                    self.scenes.remove(&path);
+                }
+            }
+        }
+        if rendered == 0 {
+            eprintln!("✅ No scenes changed");
+        } else {
+            eprintln!(
+                "✅ Rendered {} of {} scene(s) to {}",
+                rendered,
+                script.scenes.len(),
+                output_dir.display()
+            );
+        }
+    }
+}
+
+// The files `scene` names in `src` properties, such as images.
+fn sources(scene: &Scene) -> BTreeSet<PathBuf> {
+    assets::sources(&scene.items)
+        .iter()
+        .map(|file| normalize(file))
+        .collect()
+}
+
+// Samples the script at `fps` if that is lower than its own rate. Times are
+// already converted to durations, so this only changes how many frames are
+// drawn.
+fn set_preview_fps(script: &mut BeamScript, fps: f64) {
+    if !(fps > 0.0 && fps < script.fps()) {
+        return;
+    }
+    let properties = &mut script.camera.get_or_insert_with(Camera::default).properties;
+    properties.retain(|p| p.name != "fps");
+    properties.push(Property {
+        name: "fps".to_string(),
+        value: Value::Number(fps),
+    });
+}
+
+// Where the preview of the `index`th scene is written, named after the scene.
+fn preview_path(dir: &Path, index: usize, scene: &Scene, format: Format) -> PathBuf {
+    let name: String = scene
+        .name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format.default_output(&dir.join(format!("{:02}_{}", index + 1, name)))
+}
+
+fn remove(path: &Path) {
+    let removed = if path.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    };
+    if let Err(e) = removed {
+        eprintln!("Warning: Cannot remove {}: {}", path.display(), e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+
+    #[test]
+    fn test_preview_path() {
+        let scene = parser::parse_str(r#"scene "Act 1: Intro" {}"#, None)
+            .unwrap()
+            .scenes
+            .remove(0);
+        let dir = Path::new("demo_preview");
+        assert_eq!(
+            preview_path(dir, 0, &scene, Format::Mp4),
+            dir.join("01_Act_1__Intro.mp4")
+        );
+        assert_eq!(
+            preview_path(dir, 11, &scene, Format::Png),
+            dir.join("12_Act_1__Intro_frames")
+        );
+    }
+
+    #[test]
+    fn test_set_preview_fps() {
+        let mut script = parser::parse_str(
+            r#"
+            camera { fps: 60 }
+            scene "A" { duration: 48f }
+            "#,
+            None,
+        )
+        .unwrap();
+        set_preview_fps(&mut script, 15.0);
+        assert_eq!(script.fps(), 15.0);
+        // Frames count at the script's own rate, so the scene keeps its length.
+        assert_eq!(script.scenes[0].duration, Some(Duration::from_millis(800)));
+        set_preview_fps(&mut script, 30.0);
+        assert_eq!(script.fps(), 15.0);
+    }
+
+    #[test]
+    fn test_update_renders_changed_scenes() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = dir.path().join("demo.beam");
+        let output = dir.path().join("demo_preview");
+        let options = WatchOptions {
+            fps: None,
+            preview_fps: 5.0,
+            gpu: false,
+            export: ExportOptions {
+                format: Format::Gif,
+                ..ExportOptions::default()
+            },
+        };
+        let write = |a: &str, b: &str| {
+            let text = format!(
+                r#"
+                camera {{ width: 32, height: 18, fps: 10 }}
+                scene "A" {{ duration: 1s circle "c" {{ radius: 4, fill: {} }} }}
+                scene "{}" {{ duration: 1s }}
+                "#,
+                a, b
+            );
+            fs::write(&script, text).unwrap();
+        };
+        let modified = |name: &str| fs::metadata(output.join(name)).unwrap().modified().unwrap();
+
+        let mut previews = Previews::default();
+        write("#FF0000", "B");
+        previews.update(&script, &output, &options, &BTreeSet::new());
+        let (a, b) = (modified("01_A.gif"), modified("02_B.gif"));
+
+        // Only the scene that was edited is rendered again.
+        std::thread::sleep(Duration::from_millis(20));
+        write("#00FF00", "B");
+        previews.update(
+            &script,
+            &output,
+            &options,
+            &BTreeSet::from([normalize(&script)]),
+        );
+        assert!(modified("01_A.gif") > a);
+        assert_eq!(modified("02_B.gif"), b);
+
+        // A renamed scene replaces its old preview.
+        write("#00FF00", "C");
+        previews.update(
+            &script,
+            &output,
+            &options,
+            &BTreeSet::from([normalize(&script)]),
+        );
+        assert!(!output.join("02_B.gif").exists());
+        assert!(output.join("02_C.gif").exists());
+
+        // Previews are left alone while the script has errors.
+        fs::write(&script, "scene {").unwrap();
+        previews.update(
+            &script,
+            &output,
+            &options,
+            &BTreeSet::from([normalize(&script)]),
+        );
+        assert!(output.join("01_A.gif").exists());
+    }
+}